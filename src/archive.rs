@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf, Component};
+use std::process::Command;
+
+use crate::remote::{Backend, RemoteEntry, split_fields};
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError};
+
+
+// Browses archives like directories, without unpacking them. The listing
+// comes from tar, unzip or 7z, entries are extracted one at a time into a
+// temporary directory and moved to where they should go from there.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Tar,
+    Zip,
+    SevenZip
+}
+
+fn format_of(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+    let tar = [".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tbz2", ".tbz",
+               ".tar.xz", ".txz", ".tar.zst", ".tar.lz", ".tar.lzma"];
+
+    if tar.iter().any(|ext| name.ends_with(ext)) {
+        Some(Format::Tar)
+    } else if name.ends_with(".zip") || name.ends_with(".jar") {
+        Some(Format::Zip)
+    } else if name.ends_with(".7z") {
+        Some(Format::SevenZip)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    format_of(path).is_some()
+}
+
+// Command and arguments to pack names (relative to the cwd) into target
+pub fn pack_args(target: &Path, names: Vec<OsString>) -> HResult<(OsString, Vec<OsString>)> {
+    let name = target.file_name()?.to_string_lossy().to_lowercase();
+
+    let (cmd, mut args) = match format_of(target) {
+        Some(Format::Tar) => {
+            let flags = if name.ends_with(".gz") || name.ends_with(".tgz") {
+                "-czvf"
+            } else if name.ends_with(".bz2") || name.ends_with(".tbz2") || name.ends_with(".tbz") {
+                "-cjvf"
+            } else if name.ends_with(".xz") || name.ends_with(".txz") {
+                "-cJvf"
+            } else if name.ends_with(".zst") {
+                "--zstd -cvf"
+            } else if name.ends_with(".tar") {
+                "-cvf"
+            } else {
+                "-cavf"
+            };
+            ("tar", flags.split(' ').map(OsString::from).collect::<Vec<_>>())
+        }
+        Some(Format::Zip) => ("zip", vec![OsString::from("-r")]),
+        Some(Format::SevenZip) => ("7z", vec![OsString::from("a")]),
+        None => {
+            return Err(HError::Error(format!("Don't know how to make {}, try .tar.gz, .tar.zst or .zip",
+                                             name)));
+        }
+    };
+
+    args.push(target.as_os_str().to_os_string());
+    args.push(OsString::from("--"));
+    args.extend(names);
+
+    Ok((OsString::from(cmd), args))
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+    info: String,
+}
+
+#[derive(Debug)]
+pub struct ArchiveBackend {
+    archive: PathBuf,
+    format: Format,
+    entries: Option<Vec<Entry>>,
+}
+
+fn run(cmd: &str, args: &[&OsString]) -> HResult<String> {
+    let output = Command::new(cmd)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        let err = err.lines().last().unwrap_or("failed").to_string();
+        return Err(HError::Error(format!("{}: {}", cmd, err)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// "drwxr-xr-x root/root 0 2024-01-01 12:00 etc/ssl/"
+fn parse_tar_line(line: &str) -> Option<Entry> {
+    let (fields, name) = split_fields(line, 5)?;
+    let name = match name.find(" -> ").or_else(|| name.find(" link to ")) {
+        Some(pos) => &name[..pos],
+        None => name
+    };
+
+    Some(Entry {
+        path: PathBuf::from(name.trim_start_matches("./").trim_end_matches('/')),
+        is_dir: fields[0].starts_with('d'),
+        size: fields[2].parse().unwrap_or(0),
+        info: format!("{} {} {}", fields[0], fields[3], fields[4])
+    })
+}
+
+// "-rw-r--r--  3.0 unx     1234 tx defN 24-Jan-01 12:00 dir/file"
+fn parse_zip_line(line: &str) -> Option<Entry> {
+    let (fields, name) = split_fields(line, 8)?;
+
+    // Skips the header and the summary at the end
+    if fields[0].len() != 10 { return None; }
+
+    Some(Entry {
+        path: PathBuf::from(name.trim_end_matches('/')),
+        is_dir: fields[0].starts_with('d') || name.ends_with('/'),
+        size: fields[3].parse().unwrap_or(0),
+        info: format!("{} {} {}", fields[0], fields[6], fields[7])
+    })
+}
+
+// 7z -slt has one "Key = Value" block per entry
+fn parse_7z_listing(listing: &str) -> Vec<Entry> {
+    listing.split("\n\n")
+        .filter_map(|block| {
+            let fields = block.lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(2, " = ");
+                    Some((parts.next()?, parts.next()?))
+                })
+                .collect::<HashMap<_, _>>();
+
+            // The archive itself comes first, without attributes
+            let attributes = fields.get("Attributes")?;
+            let path = fields.get("Path")?;
+
+            Some(Entry {
+                path: PathBuf::from(path),
+                is_dir: attributes.starts_with('D'),
+                size: fields.get("Size")
+                    .and_then(|size| size.parse().ok())
+                    .unwrap_or(0),
+                info: fields.get("Modified")
+                    .map(|date| date.to_string())
+                    .unwrap_or_default()
+            })
+        })
+        .collect()
+}
+
+impl ArchiveBackend {
+    pub fn new(archive: &Path) -> HResult<ArchiveBackend> {
+        let format = format_of(archive)
+            .ok_or_else(|| HError::Error(format!("Not an archive: {}",
+                                                 archive.to_string_lossy())))?;
+
+        Ok(ArchiveBackend {
+            archive: archive.to_path_buf(),
+            format: format,
+            entries: None
+        })
+    }
+
+    fn entries(&mut self) -> HResult<&Vec<Entry>> {
+        if self.entries.is_none() {
+            let archive = self.archive.as_os_str().to_os_string();
+            let entries = match self.format {
+                Format::Tar => {
+                    run("tar", &[&OsString::from("-tvf"), &archive])?
+                        .lines()
+                        .filter_map(parse_tar_line)
+                        .collect()
+                }
+                Format::Zip => {
+                    run("unzip", &[&OsString::from("-Z"), &archive])?
+                        .lines()
+                        .filter_map(parse_zip_line)
+                        .collect()
+                }
+                Format::SevenZip => {
+                    let listing = run("7z", &[&OsString::from("l"),
+                                              &OsString::from("-slt"),
+                                              &OsString::from("--"),
+                                              &archive])?;
+                    parse_7z_listing(&listing)
+                }
+            };
+            self.entries = Some(entries);
+        }
+
+        Ok(self.entries.as_ref()?)
+    }
+}
+
+fn relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| c != &Component::RootDir)
+        .collect()
+}
+
+impl Backend for ArchiveBackend {
+    fn name(&self) -> String {
+        format!("archive {}", self.archive.to_string_lossy())
+    }
+
+    fn list(&mut self, path: &Path) -> HResult<Vec<RemoteEntry>> {
+        let dir = relative(path);
+
+        // Parent directories aren't always listed themselves
+        let mut entries: HashMap<String, RemoteEntry> = HashMap::new();
+        for entry in self.entries()? {
+            let rest = match entry.path.strip_prefix(&dir) {
+                Ok(rest) => rest,
+                Err(_) => continue
+            };
+
+            let mut components = rest.components();
+            let name = match components.next() {
+                Some(name) => name.as_os_str().to_string_lossy().to_string(),
+                None => continue
+            };
+
+            match components.next().is_some() || entry.is_dir {
+                true => {
+                    entries.entry(name.clone())
+                        .or_insert_with(|| RemoteEntry::dir(&name));
+                }
+                false => {
+                    let file = RemoteEntry::file(&name, Some(entry.size))
+                        .with_info(entry.info.clone());
+                    entries.insert(name, file);
+                }
+            }
+        }
+
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    fn fetch(&mut self,
+             path: &Path,
+             _is_dir: bool,
+             target: &Path,
+             cwd: &File) -> HResult<Cmd> {
+        let member = relative(path);
+        let target_dir = target.parent()?;
+
+        // $1 archive, $2 member, $3 temporary directory, $4 target
+        let extract = match self.format {
+            Format::Tar => r#"tar -xvf "$1" -C "$3" "$2" || tar -xvf "$1" -C "$3" "./$2""#,
+            Format::Zip => r#"unzip "$1" "$2" "$2/*" -d "$3"; [ -e "$3/$2" ]"#,
+            Format::SevenZip => r#"7z x -o"$3" -- "$1" "$2""#
+        };
+
+        let script = format!(r#"
+tmp=$(mktemp -d "$3/.hunter-extract.XXXXXX") || exit 1
+set -- "$1" "$2" "$tmp" "$4"
+{{ {} ; }} && mv -n "$3/$2" "$4"
+status=$?
+rm -rf "$3"
+exit $status
+"#, extract);
+
+        let short_cmd = format!("extract {} from {}",
+                                member.to_string_lossy(),
+                                self.archive.to_string_lossy());
+
+        let args = vec![OsString::from("-c"),
+                        OsString::from(script),
+                        OsString::from("sh"),
+                        self.archive.as_os_str().to_os_string(),
+                        member.into_os_string(),
+                        target_dir.as_os_str().to_os_string(),
+                        target.as_os_str().to_os_string()];
+
+        Ok(Cmd {
+            cmd: OsString::from("sh"),
+            args: Some(args),
+            vars: None,
+            short_cmd: Some(short_cmd),
+            cwd: cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        })
+    }
+}
@@ -0,0 +1,398 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::widget::{Widget, WidgetCore, Events};
+use crate::coordinates::Coordinates;
+use crate::async_value::Stale;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+
+
+// Plays audio with mpv instead of hunter-media (audio_player=mpv). mpv runs
+// without a window and gets controlled through its JSON IPC socket, the
+// same socket is polled for position and tags to show in the preview.
+
+static NEXT_SOCKET: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct PlayState {
+    position: f64,
+    duration: f64,
+    paused: bool,
+    muted: bool,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+}
+
+pub struct AudioView {
+    core: WidgetCore,
+    file: PathBuf,
+    socket: PathBuf,
+    process: Arc<Mutex<Option<Child>>>,
+    control: Arc<Mutex<Option<UnixStream>>>,
+    state: Arc<Mutex<PlayState>>,
+    stale: Stale,
+}
+
+impl PartialEq for AudioView {
+    fn eq(&self, other: &Self) -> bool {
+        self.core == other.core
+    }
+}
+
+fn socket_path() -> HResult<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => crate::paths::cache_path()?
+    };
+    let n = NEXT_SOCKET.fetch_add(1, Ordering::Relaxed);
+
+    Ok(dir.join(format!("hunter-mpv-{}-{}.sock", std::process::id(), n)))
+}
+
+// Just enough JSON for mpv's answers, like {"data":12.5,"request_id":1,"error":"success"}
+fn json_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let key = format!("\"{}\":", key);
+    let start = line.find(&key)? + key.len();
+    let rest = &line[start..];
+
+    match rest.starts_with('"') {
+        true => {
+            let mut escaped = false;
+            let end = rest[1..].char_indices()
+                .find(|&(_, c)| {
+                    let end = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    end
+                })?.0;
+            Some(&rest[..end+2])
+        }
+        false => {
+            let end = rest.find(|c| c == ',' || c == '}').unwrap_or(rest.len());
+            Some(rest[..end].trim())
+        }
+    }
+}
+
+fn json_string(value: &str) -> Option<String> {
+    if !value.starts_with('"') { return None; }
+
+    let value = &value[1..value.len()-1];
+    Some(value.replace("\\\"", "\"").replace("\\\\", "\\"))
+}
+
+fn send(stream: &mut UnixStream, command: &str) -> HResult<()> {
+    writeln!(stream, "{}", command)?;
+    Ok(stream.flush()?)
+}
+
+// Answers can come after events, those get skipped
+fn get_property(stream: &mut UnixStream,
+                reader: &mut BufReader<UnixStream>,
+                property: &str) -> HResult<String> {
+    let request = format!("{{\"command\":[\"get_property\",\"{}\"],\"request_id\":1}}",
+                          property);
+    send(stream, &request)?;
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(HError::Error(String::from("mpv went away")));
+        }
+
+        if json_value(&line, "request_id") != Some("1") { continue; }
+
+        return match json_value(&line, "data") {
+            Some(data) => Ok(data.to_string()),
+            None => Err(HError::Error(format!("mpv: no {}", property)))
+        };
+    }
+}
+
+fn poll(stream: &mut UnixStream,
+        reader: &mut BufReader<UnixStream>,
+        state: &mut PlayState) {
+    let number = |stream: &mut UnixStream, reader: &mut BufReader<UnixStream>, prop| {
+        get_property(stream, reader, prop)
+            .ok()
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+
+    state.position = number(stream, reader, "time-pos").unwrap_or(0.0);
+    state.duration = number(stream, reader, "duration").unwrap_or(state.duration);
+    state.paused = get_property(stream, reader, "pause")
+        .map(|value| value == "true")
+        .unwrap_or(state.paused);
+    state.muted = get_property(stream, reader, "mute")
+        .map(|value| value == "true")
+        .unwrap_or(state.muted);
+
+    // Tags only need to be read once
+    if state.title.is_none() {
+        let tag = |stream: &mut UnixStream, reader: &mut BufReader<UnixStream>, name| {
+            let prop = format!("metadata/by-key/{}", name);
+            get_property(stream, reader, &prop)
+                .ok()
+                .and_then(|value| json_string(&value))
+        };
+
+        state.title = tag(stream, reader, "title")
+            .or_else(|| get_property(stream, reader, "media-title")
+                     .ok()
+                     .and_then(|value| json_string(&value)));
+        state.artist = tag(stream, reader, "artist");
+        state.album = tag(stream, reader, "album");
+    }
+}
+
+impl AudioView {
+    pub fn new_from_file(core: WidgetCore, file: &Path) -> HResult<AudioView> {
+        let view = AudioView {
+            core: core,
+            file: file.to_path_buf(),
+            socket: socket_path()?,
+            process: Arc::new(Mutex::new(None)),
+            control: Arc::new(Mutex::new(None)),
+            state: Arc::new(Mutex::new(PlayState::default())),
+            stale: Stale::new(),
+        };
+
+        view.start();
+        Ok(view)
+    }
+
+    fn start(&self) {
+        let autoplay = self.core.config().media_autoplay;
+        let mute = self.core.config().media_mute;
+        let file = self.file.clone();
+        let socket = self.socket.clone();
+        let process = self.process.clone();
+        let control = self.control.clone();
+        let state = self.state.clone();
+        let stale = self.stale.clone();
+        let sender = self.core.get_sender();
+        let core = self.core.clone();
+
+        std::thread::spawn(move || -> HResult<()> {
+            // Not for every file scrolled past
+            std::thread::sleep(Duration::from_millis(50));
+            if stale.is_stale()? { return Ok(()); }
+
+            let mut mpv = Command::new("mpv");
+            mpv.arg("--no-video")
+                .arg("--no-terminal")
+                .arg("--idle=no")
+                .arg(format!("--input-ipc-server={}", socket.to_string_lossy()));
+            if !autoplay { mpv.arg("--pause"); }
+            if mute { mpv.arg("--mute=yes"); }
+
+            let child = mpv.arg("--")
+                .arg(&file)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|e| {
+                    let msg = format!("Couldn't run {}mpv{}! Error: {:?}",
+                                      term::color_red(),
+                                      term::normal_color(),
+                                      &e.kind());
+                    core.show_status(&msg).log();
+                    HError::Error(msg)
+                })?;
+            *process.lock() = Some(child);
+
+            // The socket shows up a moment after starting
+            let mut stream = None;
+            for _ in 0..50 {
+                if stale.is_stale()? { return Ok(()); }
+                if let Ok(s) = UnixStream::connect(&socket) {
+                    stream = Some(s);
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            let mut stream = stream?;
+            let mut reader = BufReader::new(stream.try_clone()?);
+            *control.lock() = Some(stream.try_clone()?);
+
+            while !stale.is_stale()? {
+                let mut new_state = state.lock().clone();
+                poll(&mut stream, &mut reader, &mut new_state);
+
+                let changed = {
+                    let mut state = state.lock();
+                    let changed = *state != new_state;
+                    *state = new_state;
+                    changed
+                };
+
+                if changed {
+                    sender.send(Events::WidgetReady).ok();
+                }
+
+                std::thread::sleep(Duration::from_millis(250));
+            }
+
+            Ok(())
+        });
+    }
+
+    fn command(&self, command: &str) -> HResult<()> {
+        let mut control = self.control.lock();
+        let stream = control.as_mut()?;
+        send(stream, &format!("{{\"command\":{}}}", command))
+    }
+
+    pub fn toggle_pause(&self) -> HResult<()> {
+        self.command(r#"["cycle","pause"]"#)
+    }
+
+    pub fn toggle_mute(&self) -> HResult<()> {
+        self.command(r#"["cycle","mute"]"#)
+    }
+
+    pub fn seek_forward(&self) -> HResult<()> {
+        self.command(r#"["seek",5]"#)
+    }
+
+    pub fn seek_backward(&self) -> HResult<()> {
+        self.command(r#"["seek",-5]"#)
+    }
+
+    fn format_secs(secs: f64) -> String {
+        let secs = secs as usize;
+        format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    }
+
+    fn progress_bar(&self, state: &PlayState, width: usize) -> String {
+        let filled = match state.duration > 0.0 {
+            true => ((state.position / state.duration) * width as f64) as usize,
+            false => 0
+        }.min(width);
+
+        format!("{}{}", "|".repeat(filled), " ".repeat(width - filled))
+    }
+
+    fn kill(&self) {
+        let process = self.process.clone();
+        let socket = self.socket.clone();
+
+        std::thread::spawn(move || {
+            if let Some(mut process) = process.lock().take() {
+                process.kill().ok();
+                process.wait().ok();
+            }
+            std::fs::remove_file(socket).ok();
+        });
+    }
+}
+
+impl Widget for AudioView {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        Ok(())
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        Ok(())
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        let (xpos, ypos) = self.core.coordinates.position_u();
+        let xsize = self.core.coordinates.xsize_u();
+        let state = self.state.lock().clone();
+
+        let name = self.file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let title = state.title.clone().unwrap_or(name);
+        let details = match (&state.artist, &state.album) {
+            (Some(artist), Some(album)) => format!("{} - {}", artist, album),
+            (Some(artist), None) => artist.clone(),
+            (None, Some(album)) => album.clone(),
+            (None, None) => String::new()
+        };
+
+        let icons = format!("{}{}",
+                            match state.paused { true => "⏸", false => "▶" },
+                            match state.muted { true => " 🔇", false => "" });
+
+        let progress = format!("{} / {}  {}",
+                               AudioView::format_secs(state.position),
+                               AudioView::format_secs(state.duration),
+                               icons);
+
+        let lines = [format!("{}{}", term::highlight_color(), title),
+                     details,
+                     String::new(),
+                     progress,
+                     self.progress_bar(&state, xsize)];
+
+        let draw = lines.iter()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}{}",
+                                     term::goto_xy_u(xpos, ypos + i),
+                                     term::sized_string_u(line, xsize),
+                                     term::reset()))
+            .collect();
+
+        Ok(draw)
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)
+    }
+}
+
+impl Drop for AudioView {
+    fn drop(&mut self) {
+        self.stale.set_stale().ok();
+        self.kill();
+        self.core.clear().log();
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, MediaAction};
+
+impl Acting for AudioView {
+    type Action = MediaAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.media
+    }
+
+    fn do_action(&mut self, action: &MediaAction) -> HResult<()> {
+        use MediaAction::*;
+
+        match action {
+            SeekForward => self.seek_forward()?,
+            SeekBackward => self.seek_backward()?,
+            TogglePause => self.toggle_pause()?,
+            ToggleMute => self.toggle_mute()?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::fail::{HResult, ErrorLog};
+use crate::proclist::Cmd;
+
+
+// Everything that moved, copied, deleted or otherwise changed files gets a
+// line in ~/.local/share/hunter/audit.log, for when a file went missing
+// and nobody remembers where it went. One line per file, separated by tabs:
+//
+// 2026-10-15 12:34:56	move	/home/me/foo.txt	/home/me/old/foo.txt
+//
+// Lines are appended in a single write, so several instances don't get
+// in each other's way.
+
+// Enough to find recent things in hunter, the file itself keeps everything
+const SHOWN: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub time: String,
+    pub action: String,
+    pub path: PathBuf,
+    pub target: Option<PathBuf>,
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<Entry> {
+        let mut fields = line.split('\t');
+
+        Some(Entry {
+            time: fields.next()?.to_string(),
+            action: fields.next()?.to_string(),
+            path: PathBuf::from(fields.next()?),
+            target: fields.next().map(PathBuf::from)
+        })
+    }
+
+    // Where the file should be now, if it's still around
+    pub fn location(&self) -> &Path {
+        self.target.as_ref().unwrap_or(&self.path)
+    }
+}
+
+pub fn record(action: &str, path: &Path, target: Option<&Path>) {
+    record_all(action, &[(path, target)]);
+}
+
+fn record_all(action: &str, entries: &[(&Path, Option<&Path>)]) {
+    if entries.is_empty() { return; }
+
+    let time = chrono::Local::now().format("%F %T");
+    let lines = entries.iter()
+        .map(|(path, target)| match target {
+            Some(target) => format!("{}\t{}\t{}\t{}\n",
+                                    time,
+                                    action,
+                                    path.to_string_lossy(),
+                                    target.to_string_lossy()),
+            None => format!("{}\t{}\t{}\n", time, action, path.to_string_lossy())
+        })
+        .collect::<String>();
+
+    append(&lines).log();
+}
+
+fn append(lines: &str) -> HResult<()> {
+    // Files moved around in tests don't belong in anyone's log
+    if cfg!(test) { return Ok(()); }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(crate::paths::audit_path()?)?;
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+// For cp and mv the last path is where everything goes, for everything
+// else (rm, chown, tar...) each existing path is changed by the command.
+// Scripts like the ones for batch renames record their moves themselves.
+pub fn record_cmd(cmd: &Cmd) {
+    let name = Path::new(&cmd.cmd)
+        .file_name()
+        .unwrap_or(OsStr::new(""))
+        .to_string_lossy()
+        .to_string();
+    if name == "sh" { return; }
+
+    let paths = cmd.args
+        .iter()
+        .flatten()
+        .filter(|arg| !arg.to_string_lossy().starts_with("-"))
+        .map(|arg| cmd.cwd.path.join(arg))
+        .collect::<Vec<_>>();
+
+    match (name.as_str(), paths.split_last()) {
+        ("cp", Some((target, sources))) |
+        ("mv", Some((target, sources))) => {
+            let targets = sources.iter()
+                .map(|source| match (target.is_dir(), source.file_name()) {
+                    (true, Some(name)) => target.join(name),
+                    _ => target.clone()
+                })
+                .collect::<Vec<_>>();
+            let entries = sources.iter()
+                .zip(targets.iter())
+                .map(|(source, target)| (source.as_path(), Some(target.as_path())))
+                .collect::<Vec<_>>();
+            record_all(&name, &entries);
+        }
+        _ => {
+            let entries = paths.iter()
+                .filter(|path| path.symlink_metadata().is_ok())
+                .map(|path| (path.as_path(), None))
+                .collect::<Vec<_>>();
+            record_all(&name, &entries);
+        }
+    }
+}
+
+pub fn record_moves(action: &str, moves: &[(PathBuf, PathBuf)]) {
+    let entries = moves.iter()
+        .map(|(from, to)| (from.as_path(), Some(to.as_path())))
+        .collect::<Vec<_>>();
+    record_all(action, &entries);
+}
+
+// Newest first
+pub fn recent() -> HResult<Vec<Entry>> {
+    let log = std::fs::read_to_string(crate::paths::audit_path()?)?;
+
+    Ok(log.lines()
+       .rev()
+       .filter_map(Entry::parse)
+       .take(SHOWN)
+       .collect())
+}
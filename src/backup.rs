@@ -0,0 +1,105 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::fail::{HResult, HError};
+use crate::paths;
+
+
+// Everything hunter keeps about its user in one archive, to take a setup
+// to another machine. That's the whole config directory (config, keys,
+// tags, actions, workspaces, history...) as "hunter/" and the bookmarks
+// from the data directory next to it. Importing merges tags and bookmarks
+// with the ones already there, the rest replaces what's there.
+
+fn tar(args: &[&OsStr]) -> HResult<()> {
+    let output = Command::new("tar")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| HError::Error(format!("Couldn't run tar: {}", e)))?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        return Err(HError::Error(format!("tar failed: {}", err.trim())));
+    }
+    Ok(())
+}
+
+pub fn export(target: &Path) -> HResult<()> {
+    let config_dir = paths::hunter_path()?;
+    let config_parent = config_dir.parent()?;
+    let data_dir = paths::data_path()?;
+
+    // Locks and half written files from other instances aren't needed
+    let mut args = vec![OsStr::new("-czf"),
+                        target.as_os_str(),
+                        OsStr::new("--exclude=*.lock"),
+                        OsStr::new("--exclude=*.tmp"),
+                        OsStr::new("-C"),
+                        config_parent.as_os_str(),
+                        OsStr::new("hunter")];
+
+    if paths::bookmark_path()?.exists() {
+        args.extend(&[OsStr::new("-C"),
+                      data_dir.as_os_str(),
+                      OsStr::new("bookmarks")]);
+    }
+
+    tar(&args)
+}
+
+pub fn import(archive: &Path) -> HResult<()> {
+    let staging = paths::cache_path()?
+        .join(format!("import.{}", std::process::id()));
+    std::fs::create_dir_all(&staging)?;
+
+    let result = tar(&[OsStr::new("-xzf"),
+                       archive.as_os_str(),
+                       OsStr::new("-C"),
+                       staging.as_os_str()])
+        .and_then(|_| install(&staging));
+
+    std::fs::remove_dir_all(&staging).ok();
+    result
+}
+
+fn install(staging: &Path) -> HResult<()> {
+    let config_dir = staging.join("hunter");
+    let bookmarks = staging.join("bookmarks");
+
+    if !config_dir.is_dir() && !bookmarks.exists() {
+        return Err(HError::Error(String::from("Not an exported hunter setup")));
+    }
+
+    let tags = config_dir.join("tags");
+    if tags.exists() {
+        crate::files::merge_tags(&tags)?;
+        std::fs::remove_file(&tags)?;
+    }
+
+    if config_dir.is_dir() {
+        copy_dir(&config_dir, &paths::hunter_path()?)?;
+    }
+
+    if bookmarks.exists() {
+        crate::bookmarks::Bookmarks::new().merge(&bookmarks)?;
+    }
+
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> HResult<()> {
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        match entry.file_type()?.is_dir() {
+            true => copy_dir(&entry.path(), &target)?,
+            false => { std::fs::copy(entry.path(), target)?; }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use termion::event::Key;
+use unicode_width::UnicodeWidthStr;
+use parking_lot::Mutex;
+
+use std::sync::Arc;
+
+use crate::listview::{Listable, ListView};
+use crate::widget::{Widget, WidgetCore};
+use crate::coordinates::Coordinates;
+use crate::files::{File, DuOptions};
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+use crate::dirty::Dirtyable;
+
+
+impl Listable for ListView<Vec<File>> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let xsize = self.get_coordinates().unwrap().xsize();
+
+        self.content
+            .iter()
+            .map(|file| {
+                let path = file.short_string();
+                let size = file_size(file)
+                    .map(|size| crate::files::size_string(size))
+                    .unwrap_or_else(|| String::from("DIR"));
+
+                let sized_string = term::sized_string(&path, xsize);
+                let size_pos = xsize.saturating_sub(size.len() as u16);
+                let padding = sized_string.len() - sized_string.width_cjk();
+                let padding = xsize - padding as u16;
+
+                format!("{}{}{:padding$}{}{}{}{}",
+                        termion::cursor::Save,
+                        file.get_color().unwrap_or_else(term::normal_color),
+                        &sized_string,
+                        termion::cursor::Restore,
+                        termion::cursor::Right(size_pos),
+                        term::highlight_color(),
+                        size,
+                        padding = padding as usize)
+            })
+            .collect()
+    }
+
+    fn on_refresh(&mut self) -> HResult<()> {
+        self.core.set_dirty();
+        Ok(())
+    }
+}
+
+// Only plain files, directories would need a full scan
+fn file_size(file: &File) -> Option<u64> {
+    if file.is_dir() { return None; }
+
+    std::fs::symlink_metadata(&file.path)
+        .map(|meta| meta.len())
+        .ok()
+}
+
+
+#[derive(Debug)]
+pub struct Basket {
+    core: WidgetCore,
+    list: ListView<Vec<File>>,
+    pub removed: Vec<File>,
+    pub op: Option<BasketAction>,
+    total_size: Arc<Mutex<Option<u64>>>,
+}
+
+impl Basket {
+    pub fn new(core: &WidgetCore, mut files: Vec<File>) -> Basket {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let list = ListView::new(core, files);
+
+        let mut basket = Basket {
+            core: core.clone(),
+            list: list,
+            removed: vec![],
+            op: None,
+            total_size: Arc::new(Mutex::new(None))
+        };
+
+        basket.count_size();
+        basket
+    }
+
+    // Directories need a full scan, so that happens in the background
+    fn count_size(&mut self) {
+        let paths = self.files()
+            .iter()
+            .map(|f| f.path.clone())
+            .collect::<Vec<_>>();
+        let options = DuOptions::from_config(&self.core.config());
+        let sender = self.core.get_sender();
+
+        let total_size = Arc::new(Mutex::new(None));
+        self.total_size = total_size.clone();
+
+        std::thread::spawn(move || {
+            let size = crate::files::disk_usage(&paths, options);
+            *total_size.lock() = Some(size);
+            sender.send(crate::widget::Events::WidgetReady).ok();
+        });
+    }
+
+    pub fn files(&self) -> &Vec<File> {
+        &self.list.content
+    }
+
+    pub fn total_size(&self) -> Option<u64> {
+        *self.total_size.lock()
+    }
+
+    fn remove_file(&mut self) -> HResult<()> {
+        let selection = self.list.get_selection();
+        if selection >= self.list.len() { return Ok(()); }
+
+        let file = self.list.content.remove(selection);
+        self.removed.push(file);
+        self.count_size();
+
+        if self.list.len() == 0 {
+            return HError::popup_finnished();
+        }
+
+        if self.list.get_selection() >= self.list.len() {
+            self.list.move_up();
+        }
+
+        self.core.clear().log();
+        Ok(())
+    }
+
+    fn finish_with(&mut self, action: BasketAction) -> HResult<()> {
+        self.op = Some(action);
+        HError::popup_finnished()
+    }
+}
+
+impl Widget for Basket {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.list.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        let dirs = self.files()
+            .iter()
+            .filter(|f| f.is_dir())
+            .count();
+        let dirs = match dirs {
+            0 => String::new(),
+            _ => format!(" (+{} directories)", dirs)
+        };
+
+        let size = match self.total_size() {
+            Some(size) => crate::files::size_string(size),
+            None => String::from("counting...")
+        };
+
+        Ok(format!("Basket: {} files, {}{}",
+                   self.list.len(),
+                   size,
+                   dirs))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = "d: remove  c: copy  m: move  D: delete  a: archive  C: clear";
+        Ok(term::sized_string_u(hint, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        self.list.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.list.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, BasketAction, Movement};
+
+impl Acting for Basket {
+    type Action = BasketAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.basket
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        match movement {
+            Up(n) => { for _ in 0..*n { self.list.move_up(); } }
+            Down(n) => { for _ in 0..*n { self.list.move_down(); } }
+            PageUp => self.list.page_up(),
+            PageDown => self.list.page_down(),
+            Top => self.list.move_top(),
+            Bottom => self.list.move_bottom(),
+            Left | Right => {}
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use BasketAction::*;
+
+        match action {
+            Close => HError::popup_finnished()?,
+            Remove => self.remove_file()?,
+            Copy | Move | Delete | Archive | Clear => self.finish_with(*action)?
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,852 @@
+use termion::event::Key;
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::widget::{Widget, WidgetCore};
+use crate::textview::TextView;
+use crate::coordinates::Coordinates;
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+
+
+// First everything gets out of the way under a temporary name, then goes
+// where it should. If that's taken after all, it goes back.
+const MOVE_SCRIPT: &str = r#"
+moves="$1"; shift
+i=0
+while [ $i -lt "$moves" ]; do
+    mv -n "$1" "$2"
+    i=$((i + 1)); shift 2
+done
+i=0
+while [ $i -lt "$moves" ]; do
+    if [ -e "$1" ] || [ -L "$1" ]; then
+        mkdir -p "$(dirname "$2")" && mv -n "$1" "$2"
+        if [ -e "$1" ] || [ -L "$1" ]; then
+            mv -n "$1" "$3"
+            echo "'$2' is taken, kept '$3'"
+        else
+            echo "renamed '$3' -> '$2'"
+        fi
+    fi
+    i=$((i + 1)); shift 3
+done
+for dir in "$@"; do
+    rmdir -v "$dir"
+done
+"#;
+
+// A bunch of moves that can be looked at before they are run as one job.
+// Targets never overwrite anything, colliding names get a number appended.
+// Files moved away by the plan itself make room for others, so chains like
+// 1 -> 2, 2 -> 3 and swaps work out, everything goes through a temporary
+// name first.
+#[derive(Debug, Clone)]
+pub struct MovePlan {
+    pub title: String,
+    pub moves: Vec<(PathBuf, PathBuf)>,
+    // Removed with rmdir after moving, so only if they ended up empty
+    pub remove_dirs: Vec<PathBuf>,
+    claimed: HashSet<PathBuf>,
+    leaving: HashSet<PathBuf>,
+}
+
+impl MovePlan {
+    pub fn new(title: &str) -> MovePlan {
+        MovePlan {
+            title: title.to_string(),
+            moves: vec![],
+            remove_dirs: vec![],
+            claimed: HashSet::new(),
+            leaving: HashSet::new()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty() && self.remove_dirs.is_empty()
+    }
+
+    pub fn add_move(&mut self, from: &Path, to: PathBuf) {
+        if from == to { return; }
+
+        let to = self.unique_target(to);
+        self.claimed.insert(to.clone());
+        self.moves.push((from.to_path_buf(), to));
+    }
+
+    // For renames, where new names can be old names of other files
+    pub fn add_moves(&mut self, moves: Vec<(PathBuf, PathBuf)>) {
+        self.leaving.extend(moves.iter()
+                            .filter(|(from, to)| from != to)
+                            .map(|(from, _)| from.clone()));
+
+        for (from, to) in moves {
+            self.add_move(&from, to);
+        }
+    }
+
+    fn unique_target(&self, target: PathBuf) -> PathBuf {
+        let taken = |path: &PathBuf| {
+            self.claimed.contains(path) ||
+                (path.symlink_metadata().is_ok() && !self.leaving.contains(path))
+        };
+
+        if !taken(&target) { return target; }
+
+        let stem = target.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = target.extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+
+        (1..)
+            .map(|n| target.with_file_name(format!("{}_{}{}", stem, n, ext)))
+            .find(|candidate| !taken(candidate))
+            .unwrap()
+    }
+
+    // Moves everything back. Directories that were removed come back
+    // on their own, since moving recreates missing parents.
+    pub fn undo(&self) -> MovePlan {
+        let mut plan = MovePlan::new(&format!("Undo {}", self.title));
+
+        let moves = self.moves
+            .iter()
+            .rev()
+            .map(|(from, to)| (to.clone(), from.clone()))
+            .collect();
+        plan.add_moves(moves);
+
+        plan
+    }
+
+    pub fn preview_lines(&self, base: &Path) -> Vec<String> {
+        let short = |path: &Path| {
+            path.strip_prefix(base)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        };
+
+        let moves = self.moves
+            .iter()
+            .map(|(from, to)| format!("{} -> {}", short(from), short(to)));
+        let removals = self.remove_dirs
+            .iter()
+            .map(|dir| format!("{}rmdir {}{}",
+                               term::color_red(),
+                               short(dir),
+                               term::normal_color()));
+
+        moves.chain(removals).collect()
+    }
+
+    // Next to from, like ".hunter-move-1234-5-0"
+    fn temp_name(from: &Path, n: usize) -> PathBuf {
+        (0..)
+            .map(|i| from.with_file_name(format!(".hunter-move-{}-{}-{}",
+                                                 std::process::id(),
+                                                 n,
+                                                 i)))
+            .find(|temp| temp.symlink_metadata().is_err())
+            .unwrap()
+    }
+
+    // For sh, paths are passed as arguments, so no quoting is needed
+    fn script_args(&self) -> Vec<OsString> {
+        let temps = self.moves
+            .iter()
+            .enumerate()
+            .map(|(n, (from, _))| MovePlan::temp_name(from, n))
+            .collect::<Vec<_>>();
+
+        let mut args = vec![OsString::from("-c"),
+                            OsString::from(MOVE_SCRIPT),
+                            OsString::from("sh"),
+                            OsString::from(self.moves.len().to_string())];
+
+        for ((from, _), temp) in self.moves.iter().zip(&temps) {
+            args.push(from.clone().into_os_string());
+            args.push(temp.clone().into_os_string());
+        }
+
+        for ((from, to), temp) in self.moves.iter().zip(&temps) {
+            args.push(temp.clone().into_os_string());
+            args.push(to.clone().into_os_string());
+            args.push(from.clone().into_os_string());
+        }
+
+        args.extend(self.remove_dirs.iter().map(|d| d.clone().into_os_string()));
+        args
+    }
+
+    pub fn into_cmd(self, cwd: &File) -> Cmd {
+        let short_cmd = format!("{}: {} moves", self.title, self.moves.len());
+        crate::audit::record_moves(&self.title.to_lowercase(), &self.moves);
+
+        Cmd {
+            cmd: OsString::from("sh"),
+            args: Some(self.script_args()),
+            vars: None,
+            short_cmd: Some(short_cmd),
+            cwd: cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        }
+    }
+}
+
+
+// Pulls every file below dir up into dir itself
+pub fn flatten_plan(dir: &Path) -> HResult<MovePlan> {
+    fn flatten_into(dir: &Path,
+                    target: &Path,
+                    plan: &mut MovePlan) -> HResult<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            match entry.file_type()?.is_dir() {
+                true => flatten_into(&path, target, plan)?,
+                false => plan.add_move(&path, target.join(entry.file_name()))
+            }
+        }
+
+        // Deepest directories come first this way
+        plan.remove_dirs.push(dir.to_path_buf());
+        Ok(())
+    }
+
+    let mut plan = MovePlan::new("Flatten");
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            flatten_into(&entry.path(), dir, &mut plan)?;
+        }
+    }
+
+    Ok(plan)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrganizeBy {
+    Date,
+    Extension
+}
+
+// Sorts files into subdirectories of dir, like 2024/05/ or jpg/
+pub fn organize_plan(files: &[PathBuf], dir: &Path, by: OrganizeBy) -> MovePlan {
+    use chrono::TimeZone;
+    use std::os::unix::fs::MetadataExt;
+
+    let title = match by {
+        OrganizeBy::Date => "Organize by date",
+        OrganizeBy::Extension => "Organize by extension"
+    };
+    let mut plan = MovePlan::new(title);
+
+    for file in files {
+        let meta = match file.symlink_metadata() {
+            Ok(meta) => meta,
+            Err(_) => continue
+        };
+
+        if meta.is_dir() { continue; }
+
+        let subdir = match by {
+            OrganizeBy::Date => {
+                let mtime = chrono::Local.timestamp(meta.mtime(), 0);
+                PathBuf::from(mtime.format("%Y/%m").to_string())
+            }
+            OrganizeBy::Extension => {
+                let ext = file.extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_else(|| String::from("other"));
+                PathBuf::from(ext)
+            }
+        };
+
+        if let Some(name) = file.file_name() {
+            plan.add_move(file, dir.join(subdir).join(name));
+        }
+    }
+
+    plan
+}
+
+
+lazy_static! {
+    static ref PLACEHOLDER: regex::Regex = regex::Regex::new(r"\{(\w+)(?::([^}]*))?\}")
+        .unwrap();
+}
+
+// Checked up front, so typos don't end up in every name. Also used to
+// reject the pattern in the prompt already.
+pub fn check_pattern(pattern: &str) -> Result<(), String> {
+    use chrono::format::{Item, StrftimeItems};
+
+    for caps in PLACEHOLDER.captures_iter(pattern) {
+        match (&caps[1], caps.get(2)) {
+            // chrono would panic on these while formatting
+            ("mtime", Some(spec)) => {
+                if StrftimeItems::new(spec.as_str()).any(|item| item == Item::Error) {
+                    return Err(format!("Invalid time format: {}", spec.as_str()));
+                }
+            }
+            ("n", _) | ("name", _) | ("ext", _) | ("mtime", _) => {}
+            (other, _) => return Err(format!("Unknown placeholder: {{{}}}", other))
+        }
+    }
+
+    Ok(())
+}
+
+// Names files after a pattern, in the order they're given. Placeholders:
+// {n} counts from 1 and takes a width like {n:03}, {name} and {ext} are
+// the old name without and the extension with the dot, and {mtime} is
+// formatted with a strftime string like {mtime:%Y%m%d}.
+pub fn renumber_plan(files: &[PathBuf], pattern: &str) -> HResult<MovePlan> {
+    use chrono::TimeZone;
+    use std::os::unix::fs::MetadataExt;
+
+    check_pattern(pattern).map_err(HError::Error)?;
+
+    let mut plan = MovePlan::new("Renumber");
+    let mut moves = vec![];
+
+    for (i, file) in files.iter().enumerate() {
+        let meta = match file.symlink_metadata() {
+            Ok(meta) => meta,
+            Err(_) => continue
+        };
+
+        // Leaving it out would throw off the numbers
+        if let Some(name) = file.file_name().filter(|name| name.to_str().is_none()) {
+            let name = name.to_string_lossy();
+            return Err(HError::Error(format!("Not a UTF-8 name: {}", name)));
+        }
+
+        let name = file.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let ext = match meta.is_dir() {
+            true => String::new(),
+            false => file.extension()
+                .and_then(|e| e.to_str())
+                .map(|e| format!(".{}", e))
+                .unwrap_or_default()
+        };
+
+        let new_name = PLACEHOLDER.replace_all(pattern, |caps: &regex::Captures| {
+            let spec = caps.get(2).map(|spec| spec.as_str());
+
+            match &caps[1] {
+                "n" => {
+                    let width = spec.and_then(|w| w.parse::<usize>().ok()).unwrap_or(0);
+                    format!("{:0width$}", i + 1, width = width)
+                }
+                "name" => name.clone(),
+                "ext" => ext.clone(),
+                _ => {
+                    let mtime = chrono::Local.timestamp(meta.mtime(), 0);
+                    mtime.format(spec.unwrap_or("%Y-%m-%d")).to_string()
+                }
+            }
+        }).to_string();
+
+        if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains('/') {
+            return Err(HError::Error(format!("Not a valid name: {}", new_name)));
+        }
+
+        moves.push((file.clone(), file.with_file_name(new_name)));
+    }
+
+    plan.add_moves(moves);
+    Ok(plan)
+}
+
+
+// The replacement can refer to capture groups with $1 or ${name}
+pub fn regex_rename_plan(files: &[PathBuf],
+                         pattern: &regex::Regex,
+                         replacement: &str) -> HResult<MovePlan> {
+    let mut plan = MovePlan::new("Regex rename");
+    let mut moves = vec![];
+
+    for file in files {
+        // A pattern can't mean anything for names that aren't UTF-8
+        let name = match file.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue
+        };
+
+        let new_name = pattern.replace_all(name, replacement).to_string();
+
+        if new_name.is_empty() || new_name == "." || new_name == ".." || new_name.contains('/') {
+            return Err(HError::Error(format!("Not a valid name: {}", new_name)));
+        }
+
+        moves.push((file.clone(), file.with_file_name(new_name)));
+    }
+
+    plan.add_moves(moves);
+    Ok(plan)
+}
+
+
+// New names from an editor, one line per file in the same order. Names
+// that clash with others or with existing files are left out and come
+// back as conflicts, the rest can still be renamed. Names of files that
+// are renamed themselves are free to take.
+pub fn bulk_rename_plan(files: &[PathBuf], names: &[&str]) -> (MovePlan, Vec<String>) {
+    let mut plan = MovePlan::new("Bulk rename");
+    let mut moves = vec![];
+    let mut conflicts = vec![];
+    let mut targets = HashSet::new();
+
+    let leaving = files.iter()
+        .zip(names)
+        .filter(|(file, name)| file.file_name() != Some(std::ffi::OsStr::new(name)))
+        .map(|(file, _)| file.clone())
+        .collect::<HashSet<_>>();
+
+    for (file, name) in files.iter().zip(names) {
+        let old_name = file.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if name.is_empty() || name.contains('/') {
+            conflicts.push(format!("{}: not a valid name: \"{}\"", old_name, name));
+            continue;
+        }
+
+        let target = file.with_file_name(name);
+        if &target == file { continue; }
+
+        if !targets.insert(target.clone()) {
+            conflicts.push(format!("{} -> {}: name used twice", old_name, name));
+        } else if target.symlink_metadata().is_ok() && !leaving.contains(&target) {
+            conflicts.push(format!("{} -> {}: already exists", old_name, name));
+        } else {
+            moves.push((file.clone(), target));
+        }
+    }
+
+    plan.add_moves(moves);
+    (plan, conflicts)
+}
+
+// Ways to clean up file names, applied in the order they're given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sanitize {
+    // Runs of whitespace become a single underscore
+    Spaces,
+    // Composed unicode (NFC), names from macOS are often decomposed
+    Normalize,
+    // Accents and other marks are dropped, "é" becomes "e"
+    Ascii,
+    Lowercase,
+    // Anything the shell would treat specially
+    Unsafe,
+}
+
+impl Sanitize {
+    // Like "spaces,normalize,unsafe"
+    pub fn parse(rules: &str) -> HResult<Vec<Sanitize>> {
+        rules.split(',')
+            .map(|rule| rule.trim())
+            .filter(|rule| !rule.is_empty())
+            .map(|rule| match rule {
+                "spaces" => Ok(Sanitize::Spaces),
+                "normalize" => Ok(Sanitize::Normalize),
+                "ascii" => Ok(Sanitize::Ascii),
+                "lowercase" => Ok(Sanitize::Lowercase),
+                "unsafe" => Ok(Sanitize::Unsafe),
+                _ => Err(HError::Error(format!("Unknown rule: {}", rule)))
+            })
+            .collect()
+    }
+
+    pub fn apply(self, name: &str) -> String {
+        use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+        match self {
+            Sanitize::Spaces => {
+                name.split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join("_")
+            }
+            Sanitize::Normalize => name.nfc().collect(),
+            Sanitize::Ascii => {
+                name.nfkd()
+                    .filter(|&c| !is_combining_mark(c))
+                    .collect()
+            }
+            Sanitize::Lowercase => name.to_lowercase(),
+            Sanitize::Unsafe => {
+                let name = name.chars()
+                    .filter(|&c| !c.is_control() && !"'\"`$\\!*?&;|<>(){}[]#~".contains(c))
+                    .collect::<String>();
+
+                // Would be taken for an option
+                match name.starts_with('-') {
+                    true => format!("_{}", &name[1..]),
+                    false => name
+                }
+            }
+        }
+    }
+}
+
+pub fn sanitize_plan(files: &[PathBuf], rules: &[Sanitize]) -> MovePlan {
+    let mut plan = MovePlan::new("Sanitize names");
+    let mut moves = vec![];
+
+    for file in files {
+        let name = match file.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue
+        };
+
+        let clean = rules.iter()
+            .fold(name, |name, rule| rule.apply(&name));
+
+        // Nothing left of it, better keep it as it is
+        if clean.is_empty() || clean == "." || clean == ".." { continue; }
+
+        moves.push((file.clone(), file.with_file_name(clean)));
+    }
+
+    plan.add_moves(moves);
+    plan
+}
+
+
+#[derive(Debug)]
+pub struct BatchPreview {
+    core: WidgetCore,
+    text: TextView,
+    title: String,
+    summary: String,
+    pub confirmed: bool,
+}
+
+impl BatchPreview {
+    pub fn new(core: &WidgetCore, plan: &MovePlan, base: &Path) -> BatchPreview {
+        BatchPreview::from_lines(core,
+                                 &plan.title,
+                                 &format!("{} files will be moved", plan.moves.len()),
+                                 plan.preview_lines(base))
+    }
+
+    // For other changes that should be looked at before running them
+    pub fn from_lines(core: &WidgetCore,
+                      title: &str,
+                      summary: &str,
+                      lines: Vec<String>) -> BatchPreview {
+        let mut text = TextView::new_blank(core);
+        text.set_lines(lines).log();
+
+        BatchPreview {
+            core: core.clone(),
+            text: text,
+            title: title.to_string(),
+            summary: summary.to_string(),
+            confirmed: false
+        }
+    }
+}
+
+impl Widget for BatchPreview {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.text.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        Ok(format!("{}: {}", self.title, self.summary))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = "Enter: run  Esc: cancel";
+        Ok(term::sized_string_u(hint, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        self.text.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.text.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, BatchAction, Movement};
+
+impl Acting for BatchPreview {
+    type Action = BatchAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.batch
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        self.text.movement(movement)
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use BatchAction::*;
+
+        match action {
+            Run => {
+                self.confirmed = true;
+                HError::popup_finnished()?
+            }
+            Cancel => HError::popup_finnished()?
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory with these files in it
+    fn scratch(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("hunter-test-{}-batch-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for file in files {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+
+        dir
+    }
+
+    fn names(plan: &MovePlan) -> Vec<(String, String)> {
+        let name = |path: &PathBuf| path.file_name().unwrap().to_string_lossy().to_string();
+
+        plan.moves
+            .iter()
+            .map(|(from, to)| (name(from), name(to)))
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    fn run(plan: &MovePlan) {
+        for (from, to) in &plan.moves {
+            std::fs::rename(from, to).unwrap();
+        }
+    }
+
+    #[test]
+    fn renumber_in_order() {
+        let dir = scratch("order", &["b.txt", "a.txt"]);
+        let files = vec![dir.join("b.txt"), dir.join("a.txt")];
+
+        let plan = renumber_plan(&files, "{n:02}{ext}").unwrap();
+
+        assert_eq!(names(&plan), pairs(&[("b.txt", "01.txt"), ("a.txt", "02.txt")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn renumber_swaps_names() {
+        let dir = scratch("swap", &["1.txt", "2.txt"]);
+        let files = vec![dir.join("2.txt"), dir.join("1.txt")];
+
+        let plan = renumber_plan(&files, "{n}{ext}").unwrap();
+
+        assert_eq!(names(&plan), pairs(&[("2.txt", "1.txt"), ("1.txt", "2.txt")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn renumber_keeps_clear_of_other_files() {
+        let dir = scratch("taken", &["a.txt", "1.txt"]);
+        let files = vec![dir.join("a.txt")];
+
+        let plan = renumber_plan(&files, "{n}{ext}").unwrap();
+
+        assert_eq!(names(&plan), pairs(&[("a.txt", "1_1.txt")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn renumber_rejects_bad_patterns() {
+        let dir = scratch("bad", &["a.txt"]);
+        let files = vec![dir.join("a.txt")];
+
+        assert!(renumber_plan(&files, "{nmae}").is_err());
+        assert!(renumber_plan(&files, "{mtime:%Q}").is_err());
+        assert!(renumber_plan(&files, "{n}/{ext}").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn renumber_rejects_dots_and_other_encodings() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = scratch("renumber-dots", &["a.txt"]);
+        let files = vec![dir.join("a.txt")];
+
+        assert!(renumber_plan(&files, ".").is_err());
+        assert!(renumber_plan(&files, "..").is_err());
+
+        let latin1 = dir.join(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+        std::fs::write(&latin1, "").unwrap();
+        assert!(renumber_plan(&[latin1], "{n}{ext}").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn undo_moves_back() {
+        let dir = scratch("undo", &["a.txt", "b.txt"]);
+        let files = vec![dir.join("a.txt"), dir.join("b.txt")];
+
+        let plan = renumber_plan(&files, "x{n}{ext}").unwrap();
+        run(&plan);
+
+        assert_eq!(names(&plan.undo()), pairs(&[("x2.txt", "b.txt"), ("x1.txt", "a.txt")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn undo_swaps_back() {
+        let dir = scratch("undo-swap", &["1.txt", "2.txt"]);
+        let files = vec![dir.join("2.txt"), dir.join("1.txt")];
+
+        let plan = renumber_plan(&files, "{n}{ext}").unwrap();
+
+        assert_eq!(names(&plan.undo()), pairs(&[("2.txt", "1.txt"), ("1.txt", "2.txt")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn run_script(plan: &MovePlan) {
+        let status = std::process::Command::new("sh")
+            .args(plan.script_args())
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    fn content(path: PathBuf) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn script_swaps_files() {
+        let dir = scratch("script-swap", &["1.txt", "2.txt"]);
+        let files = vec![dir.join("2.txt"), dir.join("1.txt")];
+
+        run_script(&renumber_plan(&files, "{n}{ext}").unwrap());
+
+        assert_eq!(content(dir.join("1.txt")), "2.txt");
+        assert_eq!(content(dir.join("2.txt")), "1.txt");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn script_moves_taken_files_back() {
+        let dir = scratch("script-taken", &["a.txt"]);
+        let mut plan = MovePlan::new("Test");
+        plan.add_move(&dir.join("a.txt"), dir.join("b.txt"));
+
+        // Shows up after planning
+        std::fs::write(dir.join("b.txt"), "other").unwrap();
+        run_script(&plan);
+
+        assert_eq!(content(dir.join("a.txt")), "a.txt");
+        assert_eq!(content(dir.join("b.txt")), "other");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn regex(pattern: &str) -> regex::Regex {
+        regex::Regex::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn regex_rename_with_groups() {
+        let dir = scratch("regex", &["IMG_001.jpg", "notes.txt"]);
+        let files = vec![dir.join("IMG_001.jpg"), dir.join("notes.txt")];
+
+        let plan = regex_rename_plan(&files, &regex(r"^IMG_(\d+)"), "photo-$1").unwrap();
+
+        assert_eq!(names(&plan), pairs(&[("IMG_001.jpg", "photo-001.jpg")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn regex_rename_chain() {
+        let dir = scratch("regex-chain", &["x", "xx"]);
+        let files = vec![dir.join("x"), dir.join("xx")];
+
+        let plan = regex_rename_plan(&files, &regex("^x"), "xx").unwrap();
+
+        assert_eq!(names(&plan), pairs(&[("x", "xx"), ("xx", "xxx")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn regex_rename_rejects_empty_names() {
+        let dir = scratch("regex-empty", &["a.txt"]);
+        let files = vec![dir.join("a.txt")];
+
+        assert!(regex_rename_plan(&files, &regex(".*"), "").is_err());
+        assert!(regex_rename_plan(&files, &regex("a"), "b/c").is_err());
+        assert!(regex_rename_plan(&files, &regex(".*"), ".").is_err());
+        assert!(regex_rename_plan(&files, &regex(".*"), "..").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn regex_rename_skips_other_encodings() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = scratch("regex-latin1", &["a.txt"]);
+        let latin1 = dir.join(std::ffi::OsStr::from_bytes(b"caf\xe9.txt"));
+        std::fs::write(&latin1, "").unwrap();
+        let files = vec![latin1, dir.join("a.txt")];
+
+        let plan = regex_rename_plan(&files, &regex(r"\.txt$"), ".md").unwrap();
+
+        assert_eq!(names(&plan), pairs(&[("a.txt", "a.md")]));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
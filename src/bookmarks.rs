@@ -10,44 +10,94 @@ use crate::term;
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Bookmarks {
     mapping: HashMap<char, String>,
+    names: HashMap<char, String>,
 }
 
 impl Bookmarks {
     pub fn new() -> Bookmarks {
-        let mut bm = Bookmarks { mapping: HashMap::new() };
+        let mut bm = Bookmarks { mapping: HashMap::new(), names: HashMap::new() };
         bm.load().or_else(|_| HError::log("Couldn't load bookmarks!")).ok();
         bm
     }
     pub fn add(&mut self, key: char, path: &str) -> HResult<()> {
-        self.mapping.insert(key, path.to_string());
-        self.save()?;
-        Ok(())
+        self.change(|bookmarks| {
+            bookmarks.mapping.insert(key, path.to_string());
+            bookmarks.names.remove(&key);
+        })
+    }
+    pub fn set_name(&mut self, key: char, name: &str) -> HResult<()> {
+        self.change(|bookmarks| {
+            match name.trim() {
+                "" => bookmarks.names.remove(&key),
+                name => bookmarks.names.insert(key, name.to_string())
+            };
+        })
+    }
+    pub fn name(&self, key: char) -> Option<&String> {
+        self.names.get(&key)
+    }
+    pub fn remove(&mut self, key: char) -> HResult<()> {
+        self.change(|bookmarks| {
+            bookmarks.mapping.remove(&key);
+            bookmarks.names.remove(&key);
+        })
     }
     pub fn get(&self, key: char) -> HResult<&String> {
         let path = self.mapping.get(&key)?;
         Ok(path)
     }
+    pub fn all(&self) -> Vec<(char, String)> {
+        let mut bookmarks = self.mapping
+            .iter()
+            .map(|(key, path)| (*key, path.clone()))
+            .collect::<Vec<_>>();
+        bookmarks.sort();
+        bookmarks
+    }
     pub fn load(&mut self) -> HResult<()> {
         let bm_file = crate::paths::bookmark_path()?;
 
+        if !bm_file.exists() {
+            self.migrate().log();
+        }
+
         if !bm_file.exists() {
             self.import().log();
         }
 
-        let bm_content = std::fs::read_to_string(bm_file)?;
-        let mapping = bm_content.lines()
-            .fold(HashMap::new(), |mut bm, line| {
+        let bm_content = crate::store::read(&bm_file)?;
+        *self = Bookmarks::parse(&String::from_utf8_lossy(&bm_content));
+        Ok(())
+    }
+    fn parse(bm_content: &str) -> Bookmarks {
+        let mut mapping = HashMap::new();
+        let mut names = HashMap::new();
+
+        // Like "d:/home/user/Downloads", optionally followed by a tab and a name
+        for line in bm_content.lines() {
             let parts = line.splitn(2, ":").collect::<Vec<&str>>();
             if parts.len() == 2 {
                 if let Some(key) = parts[0].chars().next() {
-                    let path = parts[1].to_string();
-                    bm.insert(key, path);
+                    let mut rest = parts[1].splitn(2, '\t');
+                    let path = rest.next().unwrap_or("").to_string();
+                    if let Some(name) = rest.next() {
+                        names.insert(key, name.to_string());
+                    }
+                    mapping.insert(key, path);
                 }
             }
-            bm
-        });
+        }
+
+        Bookmarks { mapping, names }
+    }
+    // Bookmarks used to be kept with the config
+    pub fn migrate(&self) -> HResult<()> {
+        let old_bm_file = crate::paths::old_bookmark_path()?;
 
-        self.mapping = mapping;
+        if old_bm_file.exists() {
+            let bm_file = crate::paths::bookmark_path()?;
+            std::fs::copy(old_bm_file, bm_file)?;
+        }
         Ok(())
     }
     pub fn import(&self) -> HResult<()> {
@@ -60,24 +110,92 @@ impl Bookmarks {
         }
         Ok(())
     }
-    pub fn save(&self) -> HResult<()> {
+    // Imported ones win if both have something on the same key
+    pub fn merge(&mut self, imported: &std::path::Path) -> HResult<()> {
+        let imported = std::fs::read(imported)?;
+        let imported = Bookmarks::parse(&String::from_utf8_lossy(&imported));
+
+        self.change(|bookmarks| {
+            for (key, path) in imported.mapping {
+                bookmarks.mapping.insert(key, path);
+                bookmarks.names.remove(&key);
+            }
+            bookmarks.names.extend(imported.names);
+        })
+    }
+    fn serialize(&self) -> String {
+        self.mapping.iter().map(|(key, path)| {
+            match self.names.get(key) {
+                Some(name) => format!("{}:{}\t{}\n", key, path, name),
+                None => format!("{}:{}\n", key, path)
+            }
+        }).collect::<String>()
+    }
+    // Applied to what's on disk, so bookmarks made by other instances in
+    // the meantime are kept and show up here too
+    fn change<F: FnOnce(&mut Bookmarks)>(&mut self, change: F) -> HResult<()> {
         let bm_file = crate::paths::bookmark_path()?;
-        let bookmarks = self.mapping.iter().map(|(key, path)| {
-            format!("{}:{}\n", key, path)
-        }).collect::<String>();
 
-        std::fs::write(bm_file, bookmarks)?;
+        let content = crate::store::update(&bm_file, |content| {
+            let mut bookmarks = Bookmarks::parse(&String::from_utf8_lossy(content));
+            change(&mut bookmarks);
+            bookmarks.serialize().into_bytes()
+        })?;
 
+        *self = Bookmarks::parse(&String::from_utf8_lossy(&content));
         Ok(())
     }
 }
 
 
+// The standard directories from xdg-user-dirs, like XDG_DOWNLOAD_DIR, with
+// the keys they are given in the user_dirs option. Ones that aren't set up
+// point to $HOME and are left out.
+pub fn user_dirs(keys: &[(char, String)]) -> Vec<(char, String)> {
+    let home = match crate::paths::home_path() {
+        Ok(home) => home.to_string_lossy().trim_end_matches('/').to_string(),
+        Err(_) => return vec![]
+    };
+    let dirs = dirs_2::config_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join("user-dirs.dirs")).ok())
+        .unwrap_or_default();
+
+    // XDG_DOWNLOAD_DIR="$HOME/Downloads"
+    let dirs = dirs.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let parts = line.splitn(2, '=').collect::<Vec<&str>>();
+            let name = parts.get(0)?
+                .trim()
+                .trim_start_matches("XDG_")
+                .trim_end_matches("_DIR");
+            let path = parts.get(1)?
+                .trim()
+                .trim_matches('"')
+                .replace("$HOME", &home);
+            let path = path.trim_end_matches('/').to_string();
+
+            if path == home { return None; }
+            Some((name.to_string(), path))
+        })
+        .collect::<HashMap<_, _>>();
+
+    keys.iter()
+        .filter_map(|(key, name)| Some((*key, dirs.get(name)?.clone())))
+        .collect()
+}
+
+
 pub struct BMPopup {
     core: WidgetCore,
     bookmarks: Bookmarks,
     bookmark_path: Option<String>,
     add_mode: bool,
+    // Key the last bookmark was added on
+    added: Option<char>,
+    user_dirs: Vec<(char, String)>,
+    // Picking from the user dirs, after pressing G
+    user_dir_mode: bool,
 }
 
 impl BMPopup {
@@ -86,13 +204,18 @@ impl BMPopup {
             core: core.clone(),
             bookmarks: Bookmarks::new(),
             bookmark_path: None,
-            add_mode: false
+            add_mode: false,
+            added: None,
+            user_dirs: vec![],
+            user_dir_mode: false
         };
         bmpopup.set_coordinates(&core.coordinates).log();
         bmpopup
     }
 
     pub fn pick(&mut self, cwd: String) -> HResult<String> {
+        // Another instance might have added some
+        self.bookmarks.load().log();
         self.bookmark_path = Some(cwd);
         self.refresh()?;
         match self.popup() {
@@ -108,21 +231,45 @@ impl BMPopup {
         Ok(bookmark?)
     }
 
-    pub fn add(&mut self, path: &str) -> HResult<()> {
+    pub fn set_user_dirs(&mut self, user_dirs: Vec<(char, String)>) {
+        self.user_dirs = user_dirs;
+        self.user_dir_mode = false;
+    }
+
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    pub fn bookmarks_mut(&mut self) -> &mut Bookmarks {
+        &mut self.bookmarks
+    }
+
+    // Gives back the key it was added on, nothing if it was cancelled
+    pub fn add(&mut self, path: &str) -> HResult<Option<char>> {
         self.add_mode = true;
+        self.added = None;
         self.bookmark_path = Some(path.to_string());
         self.refresh()?;
         self.get_core()?.clear()?;
-        self.popup()?;
+        match self.popup() {
+            Ok(_) => {},
+            Err(HError::PopupFinnished) => {},
+            err @ Err(_) => {
+                self.add_mode = false;
+                err?
+            }
+        }
         self.get_core()?.clear()?;
-        Ok(())
+
+        self.add_mode = false;
+        Ok(self.added.take())
     }
 
     fn resize(&mut self) -> HResult<()> {
         HError::terminal_resized()?
     }
 
-    pub fn render_line(&self, n: u16, key: &char, path: &str) -> String {
+    pub fn render_line(&self, n: u16, key: &str, path: &str) -> String {
         let xsize = term::xsize();
         let padding = xsize - 4;
 
@@ -154,7 +301,10 @@ impl Widget for BMPopup {
 
     fn set_coordinates(&mut self, _: &Coordinates) -> HResult<()> {
         let (xsize, ysize) = crate::term::size()?;
-        let len = self.bookmarks.mapping.len();
+        let len = match self.user_dir_mode {
+            true => self.user_dirs.len(),
+            false => self.bookmarks.mapping.len() + self.user_dirs.len()
+        };
         let ysize = ysize.saturating_sub( len + 1 );
 
         self.core.coordinates.set_size_u(xsize.saturating_sub(1), len);
@@ -170,13 +320,37 @@ impl Widget for BMPopup {
 
         if !self.add_mode {
             let cwd = self.bookmark_path.as_ref()?;
-            drawlist += &self.render_line(ypos, &'`', cwd);
+            drawlist += &self.render_line(ypos, "`", cwd);
         }
 
-        let bm_list = self.bookmarks.mapping.iter().enumerate().map(|(i, (key, path))| {
-            let line = i as u16 + ypos + 1;
-            self.render_line(line, key, path)
-        }).collect::<String>();
+        let bookmarks = match self.user_dir_mode || self.add_mode {
+            true => vec![],
+            false => self.bookmarks.all()
+                .into_iter()
+                .map(|(key, path)| match self.bookmarks.name(key) {
+                    Some(name) => (key.to_string(), format!("{} ({})", name, path)),
+                    None => (key.to_string(), path)
+                })
+                .collect()
+        };
+
+        // Shown with the G they're reached with from the bookmarks
+        let prefix = if self.user_dir_mode { "" } else { "G" };
+        let user_dirs = match self.add_mode {
+            true => vec![],
+            false => self.user_dirs
+                .iter()
+                .map(|(key, path)| (format!("{}{}", prefix, key), path.clone()))
+                .collect()
+        };
+
+        let bm_list = bookmarks.iter()
+            .chain(user_dirs.iter())
+            .enumerate()
+            .map(|(i, (key, path))| {
+                let line = i as u16 + ypos + 1;
+                self.render_line(line, key, path)
+            }).collect::<String>();
 
         drawlist += &bm_list;
 
@@ -189,12 +363,27 @@ impl Widget for BMPopup {
                 return HError::popup_finnished()
             },
             Key::Char('`') => return HError::popup_finnished(),
+            Key::Char('\'') if !self.add_mode => return HError::popup_finnished(),
+            Key::Char(key) if self.user_dir_mode => {
+                let path = self.user_dirs.iter().find(|(k, _)| *k == key);
+                if let Some((_, path)) = path {
+                    self.bookmark_path.replace(path.clone());
+                    return HError::popup_finnished();
+                }
+            }
+            // Bookmarks on G still win
+            Key::Char('G') if !self.add_mode &&
+                              self.bookmarks.get('G').is_err() &&
+                              !self.user_dirs.is_empty() => {
+                self.user_dir_mode = true;
+                return HError::widget_resized();
+            }
             Key::Char(key) => {
                 if self.add_mode {
                     let path = self.bookmark_path.take()?;
                     self.bookmarks.add(key, &path)?;
                     self.add_mode = false;
-                    self.bookmarks.save().log();
+                    self.added = Some(key);
                     return HError::popup_finnished();
                 }
                 if let Ok(path) = self.bookmarks.get(key) {
@@ -203,8 +392,7 @@ impl Widget for BMPopup {
                 }
             }
             Key::Alt(key) => {
-                self.bookmarks.mapping.remove(&key);
-                self.bookmarks.save().log();
+                self.bookmarks.remove(key).log();
                 return HError::widget_resized();
             }
             _ => {}
@@ -0,0 +1,150 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use crate::fail::{HResult, HError};
+
+
+// Permission presets like "x:+x" or "p:600/700" from the chmod_presets
+// option, each one bound to a key with Chmod(x). What comes after the
+// slash is used for directories instead.
+
+// What Mode::parse takes, for checking typed modes in the minibuffer
+pub const MODE_PATTERN: &str = r"^([0-7]{1,4}|[ugoa]*[-+=][rwxX]*)(/([0-7]{1,4}|[ugoa]*[-+=][rwxX]*))?$";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Mode {
+    Octal(u32),
+    // Like "go-w" or "a+rX", one clause only
+    Symbolic { who: u32, op: char, perms: String }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub key: char,
+    pub spec: String,
+    file_mode: Mode,
+    dir_mode: Mode,
+}
+
+impl Mode {
+    fn parse(s: &str) -> HResult<Mode> {
+        let invalid = || HError::Error(format!("Invalid mode: {}", s));
+
+        if !s.is_empty() && s.chars().all(|c| c.is_digit(8)) {
+            let mode = u32::from_str_radix(s, 8).map_err(|_| invalid())?;
+            if mode > 0o7777 { return Err(invalid()); }
+            return Ok(Mode::Octal(mode));
+        }
+
+        let op_pos = s.find(|c| c == '+' || c == '-' || c == '=')
+            .ok_or_else(invalid)?;
+        let (who, rest) = s.split_at(op_pos);
+
+        let who = who.chars().try_fold(0, |mask, c| match c {
+            'u' => Some(mask | 0o700),
+            'g' => Some(mask | 0o070),
+            'o' => Some(mask | 0o007),
+            'a' => Some(mask | 0o777),
+            _ => None
+        }).ok_or_else(invalid)?;
+
+        let perms = &rest[1..];
+        if !perms.chars().all(|c| "rwxX".contains(c)) {
+            return Err(invalid());
+        }
+
+        Ok(Mode::Symbolic {
+            // Nobody given means everybody, like chmod without umask
+            who: if who == 0 { 0o777 } else { who },
+            op: rest.chars().next().unwrap_or('+'),
+            perms: perms.to_string()
+        })
+    }
+
+    fn apply(&self, old: u32, is_dir: bool) -> u32 {
+        let old = old & 0o7777;
+
+        match self {
+            Mode::Octal(mode) => *mode,
+            Mode::Symbolic { who, op, perms } => {
+                // X only makes executable what already is for someone
+                let executable = is_dir || old & 0o111 != 0;
+                let bits = perms.chars().fold(0, |bits, c| match c {
+                    'r' => bits | 0o444,
+                    'w' => bits | 0o222,
+                    'x' => bits | 0o111,
+                    'X' if executable => bits | 0o111,
+                    _ => bits
+                }) & who;
+
+                match op {
+                    '+' => old | bits,
+                    '-' => old & !bits,
+                    _ => (old & !who) | bits
+                }
+            }
+        }
+    }
+}
+
+// "x:+x,p:600/700,r:a+r/a+rX"
+pub fn parse_presets(presets: &str) -> HResult<Vec<Preset>> {
+    presets.split(',')
+        .map(|preset| preset.trim())
+        .filter(|preset| !preset.is_empty())
+        .map(|preset| {
+            let mut parts = preset.splitn(2, ':');
+            let key = parts.next().and_then(|key| key.chars().next());
+            let spec = parts.next();
+
+            let (key, spec) = match (key, spec) {
+                (Some(key), Some(spec)) => (key, spec),
+                _ => return Err(HError::Error(format!("Invalid preset: {}", preset)))
+            };
+
+            let mut modes = spec.splitn(2, '/');
+            let file_mode = Mode::parse(modes.next().unwrap_or(""))?;
+            let dir_mode = match modes.next() {
+                Some(mode) => Mode::parse(mode)?,
+                None => file_mode.clone()
+            };
+
+            Ok(Preset {
+                key,
+                spec: spec.to_string(),
+                file_mode,
+                dir_mode
+            })
+        })
+        .collect()
+}
+
+// Returns how many files were changed and the ones that couldn't be
+pub fn apply(preset: &Preset, paths: &[PathBuf]) -> (usize, Vec<PathBuf>) {
+    let mut changed = 0;
+    let mut failed = vec![];
+
+    for path in paths {
+        let result = std::fs::metadata(path).and_then(|meta| {
+            let old = meta.permissions().mode();
+            let mode = match meta.is_dir() {
+                true => preset.dir_mode.apply(old, true),
+                false => preset.file_mode.apply(old, false)
+            };
+
+            if mode == old & 0o7777 { return Ok(false); }
+
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+            crate::audit::record(&format!("chmod {:o}", mode), path, None);
+            Ok(true)
+        });
+
+        match result {
+            Ok(true) => changed += 1,
+            Ok(false) => {}
+            Err(_) => failed.push(path.clone())
+        }
+    }
+
+    (changed, failed)
+}
@@ -0,0 +1,150 @@
+use std::ffi::OsString;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::fail::{HResult, HError};
+
+
+// Changing owner and group, with the names for completion taken from
+// /etc/passwd and /etc/group. Only root can give files away, everybody
+// else goes through sudo_cmd.
+
+// More aren't shown in the dry run
+pub const MAX_PREVIEW: usize = 10000;
+
+fn names_in(file: &str) -> Vec<String> {
+    let mut names = std::fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split(':').next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+pub fn user_names() -> Vec<String> {
+    names_in("/etc/passwd")
+}
+
+pub fn group_names() -> Vec<String> {
+    names_in("/etc/group")
+}
+
+#[derive(Clone, Debug)]
+pub struct ChownPlan {
+    pub paths: Vec<PathBuf>,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub recursive: bool,
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+fn user_name(uid: u32) -> String {
+    users::get_user_by_uid(uid)
+        .map(|user| user.name().to_string_lossy().to_string())
+        .unwrap_or_else(|| uid.to_string())
+}
+
+fn group_name(gid: u32) -> String {
+    users::get_group_by_gid(gid)
+        .map(|group| group.name().to_string_lossy().to_string())
+        .unwrap_or_else(|| gid.to_string())
+}
+
+impl ChownPlan {
+    pub fn new(paths: Vec<PathBuf>,
+               user: Option<String>,
+               group: Option<String>,
+               recursive: bool) -> HResult<ChownPlan> {
+        if user.is_none() && group.is_none() {
+            return Err(HError::Error(String::from("Neither user nor group given")));
+        }
+
+        let uid = match &user {
+            Some(user) => Some(users::get_user_by_name(user)
+                .ok_or_else(|| HError::Error(format!("No such user: {}", user)))?
+                .uid()),
+            None => None
+        };
+        let gid = match &group {
+            Some(group) => Some(users::get_group_by_name(group)
+                .ok_or_else(|| HError::Error(format!("No such group: {}", group)))?
+                .gid()),
+            None => None
+        };
+
+        Ok(ChownPlan { paths, user, group, recursive, uid, gid })
+    }
+
+    // Like chown takes it: "user:group", "user" or ":group"
+    pub fn owner_spec(&self) -> String {
+        match (&self.user, &self.group) {
+            (Some(user), Some(group)) => format!("{}:{}", user, group),
+            (Some(user), None) => user.clone(),
+            (None, Some(group)) => format!(":{}", group),
+            (None, None) => String::new()
+        }
+    }
+
+    fn collect_changes(&self, path: &Path, changes: &mut Vec<String>) {
+        if changes.len() >= MAX_PREVIEW { return; }
+
+        // Symlinks themselves aren't changed, like chown -R without -L
+        let meta = match std::fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(_) => return
+        };
+
+        let (uid, gid) = (meta.uid(), meta.gid());
+        let new_uid = self.uid.unwrap_or(uid);
+        let new_gid = self.gid.unwrap_or(gid);
+
+        if (uid, gid) != (new_uid, new_gid) {
+            changes.push(format!("{}  {}:{} -> {}:{}",
+                                 path.to_string_lossy(),
+                                 user_name(uid),
+                                 group_name(gid),
+                                 user_name(new_uid),
+                                 group_name(new_gid)));
+        }
+
+        if self.recursive && meta.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(path) {
+                let mut entries = entries.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .collect::<Vec<_>>();
+                entries.sort();
+
+                for entry in entries {
+                    self.collect_changes(&entry, changes);
+                }
+            }
+        }
+    }
+
+    // What would change, for the dry run
+    pub fn preview_lines(&self) -> Vec<String> {
+        let mut changes = vec![];
+
+        for path in &self.paths {
+            self.collect_changes(path, &mut changes);
+        }
+
+        changes
+    }
+
+    pub fn chown_args(&self) -> Vec<OsString> {
+        let mut args = vec![OsString::from("chown")];
+        if self.recursive { args.push(OsString::from("-R")); }
+        args.push(OsString::from(self.owner_spec()));
+        args.push(OsString::from("--"));
+        args.extend(self.paths.iter().map(|path| path.as_os_str().to_os_string()));
+        args
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::fail::{HResult, HError};
+
+
+// Talks to the system clipboard through whatever tool is around:
+// wl-clipboard on Wayland, xclip on X11 and pbpaste on macOS. Copying
+// falls back to OSC 52, which also works over ssh and inside tmux.
+
+#[derive(Debug)]
+pub enum ClipContent {
+    Text(Vec<u8>),
+    Image(Vec<u8>)
+}
+
+impl ClipContent {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ClipContent::Text(_) => "txt",
+            ClipContent::Image(_) => "png"
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        match self {
+            ClipContent::Text(data) => data,
+            ClipContent::Image(data) => data
+        }
+    }
+}
+
+fn has_bin(name: &str) -> bool {
+    crate::minibuffer::find_bins(name)
+        .map(|bins| bins.iter().any(|bin| bin == name))
+        .unwrap_or(false)
+}
+
+fn run(cmd: &str, args: &[&str]) -> HResult<Vec<u8>> {
+    let output = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(HError::Error(format!("{} failed", cmd)));
+    }
+
+    Ok(output.stdout)
+}
+
+pub fn paste() -> HResult<ClipContent> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let x11 = std::env::var_os("DISPLAY").is_some();
+
+    let content = if wayland && has_bin("wl-paste") {
+        let types = run("wl-paste", &["--list-types"])?;
+        match String::from_utf8_lossy(&types).lines().any(|t| t == "image/png") {
+            true => ClipContent::Image(run("wl-paste", &["--type", "image/png"])?),
+            false => ClipContent::Text(run("wl-paste", &["--no-newline"])?)
+        }
+    } else if x11 && has_bin("xclip") {
+        let types = run("xclip", &["-selection", "clipboard", "-t", "TARGETS", "-o"])?;
+        match String::from_utf8_lossy(&types).lines().any(|t| t == "image/png") {
+            true => ClipContent::Image(run("xclip", &["-selection", "clipboard",
+                                                     "-t", "image/png", "-o"])?),
+            false => ClipContent::Text(run("xclip", &["-selection", "clipboard", "-o"])?)
+        }
+    } else if has_bin("pbpaste") {
+        ClipContent::Text(run("pbpaste", &[])?)
+    } else {
+        return Err(HError::Error(String::from("No clipboard tool found! \
+                                               Install wl-clipboard or xclip")));
+    };
+
+    if content.data().is_empty() {
+        return Err(HError::Error(String::from("Clipboard is empty!")));
+    }
+
+    Ok(content)
+}
+
+fn run_with_input(cmd: &str, args: &[&str], input: &[u8]) -> HResult<()> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    child.stdin.take()?.write_all(input)?;
+
+    if !child.wait()?.success() {
+        return Err(HError::Error(format!("{} failed", cmd)));
+    }
+
+    Ok(())
+}
+
+// Bytes, so paths can go in as they are
+pub fn copy(text: &[u8]) -> HResult<()> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let x11 = std::env::var_os("DISPLAY").is_some();
+
+    if wayland && has_bin("wl-copy") {
+        run_with_input("wl-copy", &[], text)
+    } else if x11 && has_bin("xclip") {
+        run_with_input("xclip", &["-selection", "clipboard", "-i"], text)
+    } else if has_bin("pbcopy") {
+        run_with_input("pbcopy", &[], text)
+    } else {
+        // Let the terminal deal with it
+        let mut stdout = std::io::stdout();
+        write!(stdout, "{}", crate::term::osc52_copy(text))?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
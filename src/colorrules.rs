@@ -0,0 +1,72 @@
+use termion::color;
+
+use crate::fail::{HResult, HError};
+use crate::files::NameFilter;
+
+
+// Colors by name, asked before LS_COLORS, like "TODO*:yellow+bold,*.bak:dim".
+// Patterns work like in the filter (globs, /regex or part of the name) and
+// the first rule that matches wins. Since only the name is needed, these
+// still work in fast mode.
+
+// Back to normal intensity and no italics, the colors are reset anyway
+pub const STYLE_END: &str = "\x1b[22;23m";
+
+#[derive(Debug, Clone)]
+pub struct ColorRule {
+    filter: NameFilter,
+    style: String,
+}
+
+fn style_code(word: &str) -> HResult<String> {
+    let code = match word {
+        "black" => format!("{}", color::Fg(color::Black)),
+        "red" => format!("{}", color::Fg(color::Red)),
+        "green" => format!("{}", color::Fg(color::Green)),
+        "yellow" => format!("{}", color::Fg(color::Yellow)),
+        "blue" => format!("{}", color::Fg(color::Blue)),
+        "magenta" => format!("{}", color::Fg(color::Magenta)),
+        "cyan" => format!("{}", color::Fg(color::Cyan)),
+        "white" => format!("{}", color::Fg(color::White)),
+        "grey" => format!("{}", color::Fg(color::LightBlack)),
+        "bold" => format!("{}", termion::style::Bold),
+        "dim" => format!("{}", termion::style::Faint),
+        "italic" => format!("{}", termion::style::Italic),
+        // One of the 256 colors
+        _ => match word.parse::<u8>() {
+            Ok(n) => format!("{}", color::Fg(color::AnsiValue(n))),
+            Err(_) => return Err(HError::Error(format!("Unknown style: {}", word)))
+        }
+    };
+    Ok(code)
+}
+
+pub fn parse_rules(rules: &str) -> HResult<Vec<ColorRule>> {
+    rules.split(',')
+        .map(|rule| rule.trim())
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| {
+            // Regexes can have colons too, styles can't
+            let mut parts = rule.rsplitn(2, ':');
+            let (style, pattern) = match (parts.next(), parts.next()) {
+                (Some(style), Some(pattern)) => (style, pattern),
+                _ => return Err(HError::Error(format!("Invalid color rule: {}", rule)))
+            };
+
+            let style = style.split('+')
+                .map(style_code)
+                .collect::<HResult<String>>()?;
+
+            Ok(ColorRule {
+                filter: NameFilter::parse(pattern)?,
+                style
+            })
+        })
+        .collect()
+}
+
+pub fn style_for<'a>(rules: &'a [ColorRule], name: &str) -> Option<&'a str> {
+    rules.iter()
+        .find(|rule| rule.filter.matches(name))
+        .map(|rule| rule.style.as_str())
+}
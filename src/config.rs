@@ -9,6 +9,37 @@ use crate::fail::{HError, HResult, ErrorLog};
 use crate::keybind::KeyBinds;
 
 
+// What to do when files won't fit where they're copied to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpaceCheck {
+    Warn,
+    Refuse,
+    Off
+}
+
+// How directories on screen are kept up to date
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Refresh {
+    // inotify, directories where that doesn't work are polled instead
+    Watch,
+    // Every refresh_interval seconds
+    Poll,
+    // Only with the Rescan action
+    Manual
+}
+
+// What Enter/Right does with the selected file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnterAction {
+    // Go into directories and archives
+    Enter,
+    // With xdg-open
+    Open,
+    // Show the preview fullscreen
+    Preview,
+    Ask
+}
+
 #[derive(Clone)]
 // These are options, so we know if they have been set or not
 struct ArgvConfig {
@@ -85,6 +116,7 @@ pub struct Config {
     pub animation: bool,
     pub animation_refresh_frequency: usize,
     pub show_hidden: bool,
+    pub natural_sort: bool,
     pub select_cmd: String,
     pub cd_cmd: String,
     pub icons: bool,
@@ -93,8 +125,36 @@ pub struct Config {
     pub media_mute: bool,
     pub media_previewer: String,
     pub media_previewer_exists: bool,
+    pub audio_player: String,
     pub ratios: Vec::<usize>,
     pub graphics: String,
+    pub skip_identical: bool,
+    pub verify_moves: bool,
+    pub du_hardlinks_once: bool,
+    pub du_one_file_system: bool,
+    pub du_dereference: bool,
+    pub exclude: Vec<String>,
+    pub transfer_retries: usize,
+    pub remote_cache_ttl: u64,
+    pub stale_after: u64,
+    pub max_fps: usize,
+    pub which_key_delay: Option<u64>,
+    pub fast_mode_threshold: usize,
+    pub flat_depth: usize,
+    pub slideshow_interval: u64,
+    pub thumbnail_count: usize,
+    pub columns: Vec<crate::listview::Column>,
+    pub enter_actions: Vec<(String, EnterAction)>,
+    pub space_check: SpaceCheck,
+    pub sanitize: String,
+    pub alternate_screen: bool,
+    pub user_dirs: Vec<(char, String)>,
+    pub chmod_presets: Vec<crate::chmod::Preset>,
+    pub color_rules: Vec<crate::colorrules::ColorRule>,
+    pub sudo_cmd: String,
+    pub file_manager_cmd: String,
+    pub terminal_cmd: String,
+    pub key_preset: Option<String>,
     pub keybinds: KeyBinds,
 }
 
@@ -111,6 +171,7 @@ impl Config {
             animation: true,
             animation_refresh_frequency: 60,
             show_hidden: false,
+            natural_sort: true,
             select_cmd: "find -type f | fzf -m".to_string(),
             cd_cmd: "find -type d | fzf".to_string(),
             icons: false,
@@ -119,8 +180,40 @@ impl Config {
             media_mute: false,
             media_previewer: "hunter-media".to_string(),
             media_previewer_exists: false,
+            audio_player: String::from("hunter-media"),
             ratios: vec![20,30,49],
             graphics: detect_g_mode(),
+            skip_identical: true,
+            verify_moves: true,
+            du_hardlinks_once: true,
+            du_one_file_system: false,
+            du_dereference: false,
+            exclude: vec![],
+            transfer_retries: 5,
+            remote_cache_ttl: 300,
+            stale_after: 5,
+            max_fps: 60,
+            which_key_delay: Some(500),
+            fast_mode_threshold: 10000,
+            flat_depth: 8,
+            slideshow_interval: 5,
+            thumbnail_count: 12,
+            columns: vec![],
+            enter_actions: parse_enter_actions("dir:enter,archive:enter,*:open")
+                .unwrap_or_default(),
+            space_check: SpaceCheck::Warn,
+            sanitize: String::from("spaces,normalize,unsafe"),
+            alternate_screen: true,
+            user_dirs: parse_user_dirs("d:DOWNLOAD,o:DOCUMENTS,p:PICTURES,m:MUSIC,\
+                                        v:VIDEOS,k:DESKTOP,e:TEMPLATES,s:PUBLICSHARE"),
+            chmod_presets: crate::chmod::parse_presets("x:+x,p:600/700,r:a+r/a+rX")
+                .unwrap_or_default(),
+            color_rules: vec![],
+            sudo_cmd: String::from("sudo"),
+            file_manager_cmd: String::from("xdg-open ."),
+            terminal_cmd: std::env::var("TERMINAL")
+                .unwrap_or_else(|_| String::from("x-terminal-emulator")),
+            key_preset: None,
             keybinds: KeyBinds::default(),
         }
     }
@@ -146,6 +239,8 @@ impl Config {
                 }
                 Ok(("show_hidden", "on")) => config.show_hidden = true,
                 Ok(("show_hidden", "off")) => config.show_hidden = false,
+                Ok(("natural_sort", "on")) => config.natural_sort = true,
+                Ok(("natural_sort", "off")) => config.natural_sort = false,
                 Ok(("icons", "on")) => config.icons = true,
                 Ok(("icons", "off")) => config.icons = false,
                 Ok(("icons_space", "on")) => config.icons_space = true,
@@ -162,10 +257,129 @@ impl Config {
                 Ok(("media_autoplay", "off")) => config.media_autoplay = false,
                 Ok(("media_mute", "on")) => config.media_mute = true,
                 Ok(("media_mute", "off")) => config.media_mute = false,
+                Ok(("skip_identical", "on")) => config.skip_identical = true,
+                Ok(("skip_identical", "off")) => config.skip_identical = false,
+                Ok(("verify_moves", "on")) => config.verify_moves = true,
+                Ok(("verify_moves", "off")) => config.verify_moves = false,
+                Ok(("du_hardlinks_once", "on")) => config.du_hardlinks_once = true,
+                Ok(("du_hardlinks_once", "off")) => config.du_hardlinks_once = false,
+                Ok(("du_one_file_system", "on")) => config.du_one_file_system = true,
+                Ok(("du_one_file_system", "off")) => config.du_one_file_system = false,
+                Ok(("du_dereference", "on")) => config.du_dereference = true,
+                Ok(("du_dereference", "off")) => config.du_dereference = false,
+                Ok(("exclude", patterns)) => {
+                    config.exclude = patterns.split(',')
+                        .map(|p| p.trim().to_string())
+                        .filter(|p| !p.is_empty())
+                        .collect();
+                }
+                Ok(("transfer_retries", retries)) => {
+                    match retries.parse::<usize>() {
+                        Ok(retries) => config.transfer_retries = retries,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("remote_cache_ttl", ttl)) => {
+                    match ttl.parse::<u64>() {
+                        Ok(ttl) => config.remote_cache_ttl = ttl,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("stale_after", minutes)) => {
+                    match minutes.parse::<u64>() {
+                        Ok(minutes) => config.stale_after = minutes,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("max_fps", fps)) => {
+                    match fps.parse::<usize>() {
+                        Ok(fps) => config.max_fps = fps,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("which_key_delay", "off")) => config.which_key_delay = None,
+                Ok(("which_key_delay", delay)) => {
+                    match delay.parse::<u64>() {
+                        Ok(delay) => config.which_key_delay = Some(delay),
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("fast_mode_threshold", entries)) => {
+                    match entries.parse::<usize>() {
+                        Ok(entries) => config.fast_mode_threshold = entries,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("flat_depth", depth)) => {
+                    match depth.parse::<usize>() {
+                        Ok(depth) => config.flat_depth = depth,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("slideshow_interval", seconds)) => {
+                    match seconds.parse::<u64>() {
+                        Ok(seconds) => config.slideshow_interval = seconds,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("thumbnail_count", count)) => {
+                    match count.parse::<usize>() {
+                        Ok(count) => config.thumbnail_count = count,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("columns", columns)) => {
+                    match crate::listview::parse_columns(columns) {
+                        Ok(columns) => config.columns = columns,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("enter_action", actions)) => {
+                    match parse_enter_actions(actions) {
+                        Ok(actions) => config.enter_actions = actions,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("space_check", "warn")) => config.space_check = SpaceCheck::Warn,
+                Ok(("space_check", "refuse")) => config.space_check = SpaceCheck::Refuse,
+                Ok(("space_check", "off")) => config.space_check = SpaceCheck::Off,
+                Ok(("sanitize", rules)) => config.sanitize = rules.to_string(),
+                Ok(("alternate_screen", "on")) => config.alternate_screen = true,
+                Ok(("alternate_screen", "off")) => config.alternate_screen = false,
+                Ok(("user_dirs", dirs)) => config.user_dirs = parse_user_dirs(dirs),
+                Ok(("sudo_cmd", cmd)) => config.sudo_cmd = cmd.to_string(),
+                Ok(("file_manager_cmd", cmd)) => config.file_manager_cmd = cmd.to_string(),
+                Ok(("terminal_cmd", cmd)) => config.terminal_cmd = cmd.to_string(),
+                Ok(("key_preset", "default")) => config.key_preset = None,
+                Ok(("key_preset", preset)) => config.key_preset = Some(preset.to_string()),
+                Ok(("chmod_presets", presets)) => {
+                    match crate::chmod::parse_presets(presets) {
+                        Ok(presets) => config.chmod_presets = presets,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                Ok(("color_rules", rules)) => {
+                    match crate::colorrules::parse_rules(rules) {
+                        Ok(rules) => config.color_rules = rules,
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                }
+                // Only used at startup, see early_option()
+                Ok(("startup_dir", _)) => {}
+                Ok(("single_instance", _)) => {}
+                Ok(("mouse", _)) => {}
+                Ok(("refresh", _)) => {}
+                Ok(("refresh_interval", _)) => {}
                 Ok(("media_previewer", cmd)) => {
                     let cmd = cmd.to_string();
                     config.media_previewer = cmd;
                 },
+                Ok(("audio_player", player)) => {
+                    match player {
+                        "hunter-media" | "mpv" => config.audio_player = player.to_string(),
+                        _ => HError::config_error::<Config>(line.to_string()).log()
+                    }
+                },
                 Ok(("ratios", ratios)) => {
                     let ratios_str = ratios.to_string();
                     if ratios_str.chars().all(|x| x.is_digit(10) || x.is_whitespace()
@@ -189,6 +403,8 @@ impl Config {
                     "sixel")) => config.graphics = "sixel".to_string(),
                 Ok(("graphics",
                     "kitty")) => config.graphics = "kitty".to_string(),
+                Ok(("graphics",
+                    "ueberzug")) => config.graphics = "ueberzug".to_string(),
                 Ok(("graphics",
                     "auto")) => config.graphics = detect_g_mode(),
                 _ => { HError::config_error::<Config>(line.to_string()).log(); }
@@ -205,13 +421,84 @@ impl Config {
         let mut config = infuse_argv_config(config);
 
         //use std::iter::Extend;
-        KeyBinds::load()
+        KeyBinds::load_with_preset(config.key_preset.as_ref().map(|preset| preset.as_str()))
             .map(|kb| config.keybinds = kb)
             .log();
 
         Ok(config)
     }
 
+    // The screen and the first directory are set up before the config is
+    // loaded in the background, so these few are looked up on their own
+    fn early_option(key: &str) -> Option<String> {
+        let config = std::fs::read_to_string(paths::config_path().ok()?).ok()?;
+
+        config.lines()
+            .filter_map(|line| match Config::prep_line(line) {
+                Ok((option, value)) if option == key => Some(value.to_string()),
+                _ => None
+            })
+            .last()
+    }
+
+    pub fn alternate_screen() -> bool {
+        match Config::early_option("alternate_screen").as_deref() {
+            Some("off") => false,
+            _ => true
+        }
+    }
+
+    pub fn mouse() -> bool {
+        match Config::early_option("mouse").as_deref() {
+            Some("off") => false,
+            _ => true
+        }
+    }
+
+    // Only graphics=auto needs to ask the terminal what it can do
+    pub fn probe_graphics() -> bool {
+        match Config::early_option("graphics").as_deref() {
+            None | Some("auto") => true,
+            _ => false
+        }
+    }
+
+    pub fn single_instance() -> bool {
+        Config::early_option("single_instance").as_deref() == Some("on")
+    }
+
+    // These two are needed when the file cache is created
+    pub fn refresh() -> Refresh {
+        match Config::early_option("refresh").as_deref() {
+            Some("poll") => Refresh::Poll,
+            Some("manual") => Refresh::Manual,
+            _ => Refresh::Watch
+        }
+    }
+
+    pub fn refresh_interval() -> std::time::Duration {
+        let secs = Config::early_option("refresh_interval")
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .filter(|&secs| secs > 0)
+            .unwrap_or(5);
+
+        std::time::Duration::from_secs(secs)
+    }
+
+    // "cwd" is where hunter was started from, "last" where it quit last
+    // time, anything else is taken as path
+    pub fn startup_dir() -> Option<std::path::PathBuf> {
+        match Config::early_option("startup_dir")?.as_str() {
+            "cwd" => None,
+            "last" => {
+                let last = std::fs::read_to_string(paths::last_dir_path().ok()?).ok()?;
+                Some(std::path::PathBuf::from(last.trim_end_matches('\n')))
+            }
+            path if path.starts_with("~/") => Some(paths::home_path().ok()?.join(&path[2..])),
+            path => Some(std::path::PathBuf::from(path))
+        }
+    }
+
     fn prep_line<'a>(line: &'a str) -> HResult<(&'a str, &'a str)> {
         let setting = line.split("=").collect::<Vec<&str>>();
         if setting.len() == 2 {
@@ -233,9 +520,61 @@ impl Config {
     pub fn media_available(&self) -> bool {
         self.media_previewer_exists
     }
+
+    // Most specific first: the whole MIME type, then just its first part
+    // like "image", then "*". Directories and archives only go by "dir"
+    // and "archive", since there's nothing else to open them with.
+    pub fn enter_action(&self, categories: &[&str]) -> Option<EnterAction> {
+        categories.iter()
+            .find_map(|category| {
+                self.enter_actions
+                    .iter()
+                    .find(|(rule, _)| rule == category)
+                    .map(|(_, action)| *action)
+            })
+    }
+}
+
+// "dir:enter,image:preview,*:open" -> [("dir", Enter), ("image", Preview), ("*", Open)]
+fn parse_enter_actions(actions: &str) -> HResult<Vec<(String, EnterAction)>> {
+    actions.split(',')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(|rule| {
+            let mut parts = rule.trim().rsplitn(2, ':');
+            let action = match parts.next()?.trim() {
+                "enter" => EnterAction::Enter,
+                "open" => EnterAction::Open,
+                "preview" => EnterAction::Preview,
+                "ask" => EnterAction::Ask,
+                _ => return HError::config_error(rule.to_string())
+            };
+            let category = parts.next()?.trim().to_lowercase();
+            Ok((category, action))
+        })
+        .collect()
+}
+
+// "d:DOWNLOAD,p:PICTURES" -> [('d', "DOWNLOAD"), ('p', "PICTURES")]
+fn parse_user_dirs(dirs: &str) -> Vec<(char, String)> {
+    dirs.split(',')
+        .filter_map(|dir| {
+            let mut parts = dir.trim().splitn(2, ':');
+            let key = parts.next()?.chars().next()?;
+            let name = parts.next()?.trim().to_uppercase();
+            Some((key, name))
+        })
+        .collect()
 }
 
 fn detect_g_mode() -> String {
+    // What the terminal answered is better than guessing from $TERM
+    match crate::term::probed_graphics() {
+        Some("kitty") => return String::from("kitty"),
+        #[cfg(feature = "sixel")]
+        Some("sixel") => return String::from("sixel"),
+        _ => {}
+    }
+
     let term = std::env::var("TERM").unwrap_or(String::new());
     match term.as_str() {
         "xterm-kitty" => "kitty",
@@ -253,3 +592,17 @@ fn has_media_previewer(name: &str) -> bool {
         false => find_bins(name).is_ok()
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_dirs_from_config() {
+        assert_eq!(parse_user_dirs("d:desktop, e: Templates,x,:MUSIC,"),
+                   vec![('d', String::from("DESKTOP")),
+                        ('e', String::from("TEMPLATES"))]);
+        assert!(parse_user_dirs("").is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use parking_lot::Mutex;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::fail::{HResult, HError, ErrorLog};
+
+
+// Passwords for remote backends. With secret-tool around they go into the
+// system keyring (which unlocks itself once per login), either way they're
+// kept in memory until hunter quits, so they only have to be typed once.
+
+lazy_static! {
+    static ref SESSION: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+fn has_keyring() -> bool {
+    crate::minibuffer::find_bins("secret-tool")
+        .map(|bins| bins.iter().any(|bin| bin == "secret-tool"))
+        .unwrap_or(false)
+}
+
+fn keyring_lookup(key: &str) -> Option<String> {
+    let output = Command::new("secret-tool")
+        .args(&["lookup", "application", "hunter", "key", key])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    match output.status.success() && !output.stdout.is_empty() {
+        true => Some(String::from_utf8_lossy(&output.stdout).to_string()),
+        false => None
+    }
+}
+
+fn keyring_store(key: &str, secret: &str) -> HResult<()> {
+    let label = format!("hunter: {}", key);
+    let mut child = Command::new("secret-tool")
+        .args(&["store", "--label", &label, "application", "hunter", "key", key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    child.stdin.take()?.write_all(secret.as_bytes())?;
+
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => Err(HError::Error(String::from("Couldn't save to keyring")))
+    }
+}
+
+pub fn lookup(key: &str) -> Option<String> {
+    if let Some(secret) = SESSION.lock().get(key) {
+        return Some(secret.clone());
+    }
+
+    if !has_keyring() { return None; }
+
+    let secret = keyring_lookup(key)?;
+    SESSION.lock().insert(key.to_string(), secret.clone());
+    Some(secret)
+}
+
+pub fn store(key: &str, secret: &str) {
+    SESSION.lock().insert(key.to_string(), secret.to_string());
+
+    if has_keyring() {
+        keyring_store(key, secret).log();
+    }
+}
+
+// Small script for SSH_ASKPASS and wget --use-askpass, it just prints
+// whatever is in HUNTER_SECRET, so nothing ends up on a command line
+pub fn askpass_path() -> HResult<std::path::PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = crate::paths::cache_path()?.join("askpass.sh");
+
+    if !path.exists() {
+        std::fs::write(&path, "#!/bin/sh\nprintf '%s\\n' \"$HUNTER_SECRET\"\n")?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))?;
+    }
+
+    Ok(path)
+}
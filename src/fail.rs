@@ -41,6 +41,8 @@ pub enum HError {
     StaleError,
     #[fail(display = "Failed: {}", _0)]
     Error(String),
+    #[fail(display = "Connection failed: {}", _0)]
+    ConnectionError(String),
     #[fail(display = "Was None!")]
     NoneError,
     #[fail(display = "Async Error: {}", _0)]
@@ -434,7 +436,9 @@ pub enum KeyBindError {
     #[fail(display = "Couldn't parse as either char or u8: {}", _0)]
     CharOrNumParseError(String),
     #[fail(display = "Wanted {}, but got {}!", _0, _1)]
-    CharOrNumWrongType(String, String)
+    CharOrNumWrongType(String, String),
+    #[fail(display = "Key {} for {} is already used in [{}], ignored", _0, _1, _2)]
+    Shadowed(String, String, String)
 
 }
 
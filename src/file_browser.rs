@@ -1,4 +1,4 @@
-use termion::event::Key;
+use termion::event::{Key, MouseEvent, MouseButton};
 use parking_lot::{Mutex, RwLock};
 
 use pathbuftools::PathBufTools;
@@ -11,9 +11,10 @@ use std::path::PathBuf;
 use std::ffi::OsString;
 use std::os::unix::ffi::OsStringExt;
 use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
 use crate::files::{File, Files};
-use crate::fscache::FsCache;
+use crate::fscache::{FsCache, FsEvent};
 use crate::listview::{ListView, FileSource};
 use crate::hbox::HBox;
 use crate::widget::Widget;
@@ -24,6 +25,11 @@ use crate::fail::{HResult, HError, ErrorLog};
 use crate::widget::{Events, WidgetCore};
 use crate::proclist::ProcView;
 use crate::bookmarks::BMPopup;
+use crate::basket::Basket;
+use crate::picker::{TargetPicker, Target};
+use crate::batch::{BatchPreview, MovePlan};
+use crate::remote::{RemoteBrowser, Backend};
+use crate::minibuffer::{Validator, Wizard};
 use crate::term;
 use crate::term::ScreenExt;
 use crate::foldview::LogView;
@@ -88,12 +94,34 @@ pub struct FileBrowser {
     pub columns: HBox<FileBrowserWidgets>,
     pub cwd: File,
     pub prev_cwd: Option<File>,
+    recent_dirs: Vec<File>,
+    tab_dirs: Vec<File>,
+    defer_job: bool,
     core: WidgetCore,
     proc_view: Arc<Mutex<ProcView>>,
     bookmarks: Arc<Mutex<BMPopup>>,
     log_view: Arc<Mutex<LogView>>,
     fs_cache: FsCache,
-    fs_stat: Arc<RwLock<FsStat>>
+    fs_stat: Arc<RwLock<FsStat>>,
+    // For undoing renames and other batch moves
+    last_batch: Option<MovePlan>,
+    // When and on which file the mouse was clicked, to catch double clicks
+    last_click: Option<(Instant, usize)>
+}
+
+impl TabView<FileBrowser> {
+    fn store_workspace(&self, name: &str) -> HResult<()> {
+        let tabs = self.widgets
+            .iter()
+            .map(|tab| tab.cwd.path.clone())
+            .collect();
+
+        crate::workspaces::save(crate::workspaces::Workspace {
+            name: name.to_string(),
+            active: self.active,
+            tabs
+        })
+    }
 }
 
 impl Tabbable for TabView<FileBrowser> {
@@ -121,22 +149,29 @@ impl Tabbable for TabView<FileBrowser> {
         Ok(())
     }
 
+    // Selections made here have to be saved for the other tabs to see them
+
     fn close_tab(&mut self) -> HResult<()> {
+        self.active_tab_mut().save_tab_settings().log();
         self.close_tab_().log();
+        self.active_tab_mut().restore_selections().log();
         Ok(())
     }
 
     fn next_tab(&mut self) -> HResult<()> {
+        self.active_tab_mut().save_tab_settings().log();
         self.next_tab_();
         Ok(())
     }
 
     fn prev_tab(&mut self) -> HResult<()> {
+        self.active_tab_mut().save_tab_settings().log();
         self.prev_tab_();
         Ok(())
     }
 
     fn goto_tab(&mut self, index: usize) -> HResult<()> {
+        self.active_tab_mut().save_tab_settings().log();
         self.goto_tab_(index)
     }
 
@@ -158,6 +193,7 @@ impl Tabbable for TabView<FileBrowser> {
     }
 
     fn on_tab_switch(&mut self) -> HResult<()> {
+        self.active_tab_mut().restore_selections().log();
         self.active_tab_mut().refresh()
     }
 
@@ -195,6 +231,19 @@ impl Tabbable for TabView<FileBrowser> {
                 dirs
             });
 
+        let tab_dirs = self.widgets
+            .iter()
+            .map(|tab| tab.cwd.clone())
+            .collect::<Vec<_>>();
+        for tab in self.widgets.iter_mut() {
+            tab.tab_dirs = tab_dirs.clone();
+        }
+
+        let tab_paths = tab_dirs.iter()
+            .map(|dir| dir.path.clone())
+            .collect::<Vec<_>>();
+        crate::mounts::release_unused(&tab_paths);
+
         self.active_tab_mut_()
             .fs_cache
             .watch_only(open_dirs)
@@ -207,8 +256,126 @@ impl Tabbable for TabView<FileBrowser> {
         Ok(())
     }
 
+    // Opens a tab in the directory, files get selected there
+    fn on_reveal(&mut self, path: &std::path::Path) -> HResult<()> {
+        let (dir, select) = match path.is_dir() {
+            true => (path, None),
+            false => (path.parent()?, Some(path))
+        };
+
+        let dir = File::new_from_path(dir)?;
+
+        self.new_tab()?;
+        let tab = self.active_tab_mut();
+
+        if let Some(select) = select {
+            let file = File::new_from_path(select)?;
+            tab.fs_cache.set_selection(dir.clone(), file).log();
+        }
+
+        tab.main_widget_goto(&dir)
+    }
+
+    fn save_workspace(&mut self) -> HResult<()> {
+        let current = crate::workspaces::current().unwrap_or_default();
+        let name = self.core.minibuffer_validated("save workspace as",
+                                                  &current,
+                                                  Validator::NonEmpty)?;
+        let name = name.trim().to_string();
+
+        self.store_workspace(&name)?;
+        crate::workspaces::set_current(&name);
+
+        self.core.show_status(&format!("Saved {} tabs as workspace {}",
+                                       self.widgets.len(),
+                                       name))
+    }
+
+    fn switch_workspace(&mut self) -> HResult<()> {
+        let workspaces = crate::workspaces::load()?;
+        if workspaces.is_empty() {
+            return self.core.show_status("No workspaces saved yet");
+        }
+
+        let names = workspaces.iter()
+            .map(|workspace| workspace.name.as_str())
+            .collect::<Vec<_>>();
+        let name = Wizard::new()
+            .step("switch to workspace")
+            .choices(&names)
+            .run(&self.core)?;
+        let workspace = crate::workspaces::get(name.get(0)?)?;
+
+        // Leaving a workspace keeps its tabs the way they are now
+        if let Some(current) = crate::workspaces::current() {
+            self.store_workspace(&current).log();
+        }
+
+        let dirs = workspace.tabs
+            .iter()
+            .filter_map(|path| File::new_from_path(path).ok())
+            .filter(|dir| dir.is_dir())
+            .collect::<Vec<_>>();
+
+        if dirs.is_empty() {
+            return self.core.show_status(&format!("None of the directories in {} exist",
+                                                  workspace.name));
+        }
+
+        // Reuse the open tabs, add missing ones and close the rest
+        self.active = 0;
+        for (i, dir) in dirs.iter().enumerate() {
+            if i == self.widgets.len() {
+                self.new_tab()?;
+            }
+
+            self.active = i;
+            self.active_tab_mut_().main_widget_goto(dir).log();
+        }
+
+        while self.widgets.len() > dirs.len() {
+            self.pop_widget()?;
+        }
+
+        self.active = workspace.active.min(dirs.len() - 1);
+        self.on_tab_switch().log();
+        crate::workspaces::set_current(&workspace.name);
+
+        let missing = workspace.tabs.len() - dirs.len();
+        match missing {
+            0 => self.core.show_status(&format!("Switched to workspace {}",
+                                                workspace.name)),
+            _ => self.core.show_status(&format!("Switched to workspace {}, {} missing dirs skipped",
+                                                workspace.name,
+                                                missing))
+        }
+    }
+
+    // The user dirs, so gd goes to Downloads
+    fn prefix_keys(&self) -> Vec<(String, String)> {
+        crate::bookmarks::user_dirs(&self.core.config().user_dirs)
+            .into_iter()
+            .map(|(key, path)| (key.to_string(), path))
+            .collect()
+    }
+
+    fn on_prefix_key(&mut self, key: Key) -> HResult<()> {
+        match key {
+            Key::Char(c) => self.active_tab_mut().goto_user_dir(c),
+            _ => HError::undefined_key(key)
+        }
+    }
+
     fn on_config_loaded(&mut self) -> HResult<()> {
-        let show_hidden = self.core.config().show_hidden();
+        let config = self.core.config();
+        let show_hidden = config.show_hidden();
+
+        if !config.keybinds.moved.is_empty() {
+            let status = format!("{}Keys file has old keys, moved {}. See \"Changed keys\" in the README",
+                                 term::color_red(),
+                                 config.keybinds.moved.join(", "));
+            self.core.show_status(&status).log();
+        }
 
         for tab in self.widgets.iter_mut() {
             tab.left_async_widget_mut().map(|async_w| {
@@ -336,17 +503,74 @@ impl FileBrowser {
         Ok(FileBrowser { columns: columns,
                          cwd: cwd,
                          prev_cwd: None,
+                         recent_dirs: vec![],
+                         tab_dirs: vec![],
+                         defer_job: false,
                          core: core.clone(),
                          proc_view: Arc::new(Mutex::new(proc_view)),
                          bookmarks: Arc::new(Mutex::new(bookmarks)),
                          log_view: Arc::new(Mutex::new(log_view)),
                          fs_cache: fs_cache,
-                         fs_stat: Arc::new(RwLock::new(fs_stat)) })
+                         fs_stat: Arc::new(RwLock::new(fs_stat)),
+                         last_batch: None,
+                         last_click: None })
     }
 
+    // Enter/Right, what it does depends on the file and enter_action
     pub fn enter_dir(&mut self) -> HResult<()> {
+        use crate::config::EnterAction;
+
         let file = self.selected_file()?;
 
+        let action = match self.enter_action(&file) {
+            EnterAction::Ask => {
+                let answer = Wizard::new()
+                    .step(&file.name)
+                    .choices(&["enter", "open", "preview"])
+                    .run(&self.core)?;
+
+                match answer.get(0)?.as_str() {
+                    "enter" => EnterAction::Enter,
+                    "preview" => EnterAction::Preview,
+                    _ => EnterAction::Open
+                }
+            }
+            action => action
+        };
+
+        match action {
+            EnterAction::Preview => {
+                self.zoom_preview();
+                Ok(())
+            }
+            EnterAction::Open => self.open_file(&file),
+            _ => self.enter_file(file)
+        }
+    }
+
+    fn enter_action(&self, file: &File) -> crate::config::EnterAction {
+        use crate::config::EnterAction;
+
+        let config = self.core.config();
+
+        if file.is_dir() {
+            return config.enter_action(&["dir"]).unwrap_or(EnterAction::Enter);
+        }
+
+        if crate::archive::is_archive(&file.path) {
+            return config.enter_action(&["archive"]).unwrap_or(EnterAction::Enter);
+        }
+
+        let mime = file.get_mime()
+            .map(|mime| mime.to_string())
+            .unwrap_or_default();
+        let top = mime.split('/').next().unwrap_or("").to_string();
+
+        config.enter_action(&[mime.as_str(), top.as_str(), "*"])
+            .unwrap_or(EnterAction::Open)
+    }
+
+    fn enter_file(&mut self, file: File) -> HResult<()> {
         if file.is_dir() {
             let dir = file;
             match dir.is_readable() {
@@ -365,7 +589,7 @@ impl FileBrowser {
             let previewer_files = self.preview_widget_mut()?.take_files().ok();
             let main_files = self.take_main_files().ok();
 
-            self.prev_cwd = Some(self.cwd.clone());
+            self.remember_cwd();
             self.cwd = dir.clone();
 
             let cache = self.fs_cache.clone();
@@ -395,30 +619,45 @@ impl FileBrowser {
                     .with_stale(stale.clone())
                     .build()
                 }).log();
+        } else if crate::archive::is_archive(&file.path) {
+            // Browsed like a remote, entries are extracted from there
+            let backend = crate::archive::ArchiveBackend::new(&file.path)?;
+            self.browse_remote(Box::new(backend))?;
         } else {
-            self.preview_widget_mut().map(|preview| {
-                preview.cancel_animation().log();
-            }).log();
-            self.core.get_sender().send(Events::InputEnabled(false))?;
-            self.core.screen.suspend().log();
+            self.open_file(&file)?;
+        }
+
+        Ok(())
+    }
+
+    fn open_file(&mut self, file: &File) -> HResult<()> {
+        // Nothing else makes sense for a directory
+        if file.is_dir() {
+            return self.enter_file(file.clone());
+        }
 
-            let status = std::process::Command::new("xdg-open")
-                .args(file.path.file_name())
-                .status();
+        self.preview_widget_mut().map(|preview| {
+            preview.cancel_animation().log();
+        }).log();
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+        self.core.screen.suspend().log();
+
+        let status = std::process::Command::new("xdg-open")
+            .args(file.path.file_name())
+            .status();
 
-            self.core.screen.activate().log();
-            self.core.clear().log();
+        self.core.screen.activate().log();
+        self.core.clear().log();
 
-            self.core.get_sender().send(Events::InputEnabled(true))?;
+        self.core.get_sender().send(Events::InputEnabled(true))?;
 
-            match status {
-                Ok(status) =>
-                    self.core.show_status(&format!("\"{}\" exited with {}",
-                                                   "xdg-open", status)).log(),
-                Err(err) =>
-                    self.core.show_status(&format!("Can't run this \"{}\": {}",
-                                                   "xdg-open", err)).log()
-            }
+        match status {
+            Ok(status) =>
+                self.core.show_status(&format!("\"{}\" exited with {}",
+                                               "xdg-open", status)).log(),
+            Err(err) =>
+                self.core.show_status(&format!("Can't run this \"{}\": {}",
+                                               "xdg-open", err)).log()
         }
 
         Ok(())
@@ -498,7 +737,7 @@ impl FileBrowser {
         let dir = dir.clone();
         let cache = self.fs_cache.clone();
 
-        self.prev_cwd = Some(self.cwd.clone());
+        self.remember_cwd();
         self.cwd = dir.clone();
         let file_source = FileSource::Path(self.cwd.clone());
 
@@ -555,7 +794,7 @@ impl FileBrowser {
             let main_selection = self.cwd.clone();
             let preview_files = self.take_main_files();
 
-            self.prev_cwd = Some(self.cwd.clone());
+            self.remember_cwd();
             self.cwd = new_cwd.clone();
 
             let cache = self.fs_cache.clone();
@@ -611,6 +850,15 @@ impl FileBrowser {
         self.refresh()
     }
 
+    fn remember_cwd(&mut self) {
+        let cwd = self.cwd.clone();
+        self.prev_cwd = Some(cwd.clone());
+
+        self.recent_dirs.retain(|dir| dir != &cwd);
+        self.recent_dirs.insert(0, cwd);
+        self.recent_dirs.truncate(10);
+    }
+
     pub fn goto_prev_cwd(&mut self) -> HResult<()> {
         let prev_cwd = self.prev_cwd.take()?;
         self.main_widget_goto(&prev_cwd)?;
@@ -629,6 +877,11 @@ impl FileBrowser {
             None => &self.cwd
         }.path.to_string_lossy().to_string();
 
+        let user_dirs = crate::bookmarks::user_dirs(&self.core.config().user_dirs);
+
+        self.bookmarks
+            .lock()
+            .set_user_dirs(user_dirs);
         self.bookmarks
             .lock()
             .set_coordinates(&self.core.coordinates)
@@ -676,7 +929,140 @@ impl FileBrowser {
         Ok(())
     }
 
+    pub fn goto_user_dir(&mut self, key: char) -> HResult<()> {
+        let (_, path) = crate::bookmarks::user_dirs(&self.core.config().user_dirs)
+            .into_iter()
+            .find(|(k, _)| *k == key)
+            .ok_or(HError::WidgetUndefinedKeyError { key: Key::Char(key) })?;
+
+        let path = File::new_from_path(&PathBuf::from(path))?;
+        self.main_widget_goto(&path)?;
+        Ok(())
+    }
+
+    // With refresh=manual this is the only way to see changes
+    pub fn rescan(&mut self) -> HResult<()> {
+        match self.fs_cache.rescan()? {
+            true => self.refresh(),
+            false => self.core.show_status("Nothing changed")
+        }
+    }
+
+    // Fetches the metadata of the selected files again
+    pub fn restat(&mut self) -> HResult<()> {
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
+
+        self.main_widget()?
+            .content
+            .pending_events
+            .write()?
+            .extend(files.into_iter().map(FsEvent::Change));
+        self.refresh()
+    }
+
+    pub fn chmod_preset(&mut self, key: char) -> HResult<()> {
+        let preset = self.core
+            .config()
+            .chmod_presets
+            .into_iter()
+            .find(|preset| preset.key == key);
+        let preset = match preset {
+            Some(preset) => preset,
+            None => return self.core.show_status(&format!("No chmod preset on {}", key))
+        };
+
+        self.apply_chmod(&preset)
+    }
+
+    // Like a preset, but typed in, "go-w" or "644/755"
+    pub fn chmod_to(&mut self) -> HResult<()> {
+        let pattern = String::from(crate::chmod::MODE_PATTERN);
+        let mode = self.core.minibuffer_validated("chmod", "", Validator::Regex(pattern))?;
+
+        let preset = crate::chmod::parse_presets(&format!("_:{}", mode.trim()))?
+            .pop()?;
+        self.apply_chmod(&preset)
+    }
+
+    fn apply_chmod(&mut self, preset: &crate::chmod::Preset) -> HResult<()> {
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
+        let paths = files.iter()
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+
+        let (changed, failed) = crate::chmod::apply(preset, &paths);
+
+        // Not every directory is watched
+        self.restat().log();
+
+        match failed.len() {
+            0 => self.core.show_status(&format!("chmod {}: changed {} of {} files",
+                                                preset.spec,
+                                                changed,
+                                                paths.len())),
+            _ => self.core.show_status(&format!("{}chmod {}: failed on {} files, first {}",
+                                                term::color_red(),
+                                                preset.spec,
+                                                failed.len(),
+                                                failed[0].to_string_lossy()))
+        }
+    }
+
+    // For slow terminals or connections, until the next start
+    pub fn set_max_fps(&mut self) -> HResult<()> {
+        let fps = self.core.minibuffer_number("max fps (0 draws after every key)",
+                                              Some(0),
+                                              Some(1000))?;
+
+        self.core.change_config(|config| config.max_fps = fps as usize)?;
+
+        let status = match fps {
+            0 => String::from("Drawing after every key"),
+            _ => format!("Drawing at most {} times a second", fps)
+        };
+        self.core.show_status(&format!("{}, max_fps={} in the config keeps it",
+                                       status,
+                                       fps))
+    }
+
+    // Lists the directory again from scratch, unlike Rescan
+    pub fn reload_dir(&mut self) -> HResult<()> {
+        self.save_tab_settings()?;
+        self.fs_cache.forget(&self.cwd)?;
+
+        let cwd = self.cwd.clone();
+        let prev_cwd = self.prev_cwd.clone();
+        self.main_widget_goto(&cwd)?;
+        self.prev_cwd = prev_cwd;
+
+        Ok(())
+    }
+
+    // Everything under the current directory in one list, so the whole
+    // subtree can be sorted by size or mtime
+    pub fn toggle_flat_view(&mut self) -> HResult<()> {
+        if self.get_files()?.flat.is_some() {
+            self.reload_dir()?;
+            return self.core.show_status("Flat view off");
+        }
+
+        let depth = self.core.config().flat_depth;
+        let sender = self.core.get_sender();
+        self.main_widget_mut()?.content.flatten(depth, sender);
+
+        let status = format!("Flat view, {} levels deep", depth);
+        self.core.show_status(&status)
+    }
+
     pub fn add_bookmark(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent;
+
         let cwd = self.cwd.path.to_string_lossy().to_string();
         let coords = &self.core.coordinates;
 
@@ -684,9 +1070,25 @@ impl FileBrowser {
             .lock()
             .set_coordinates(&coords)
             .log();
-        self.bookmarks
+        let key = self.bookmarks
             .lock()
             .add(&cwd)?;
+
+        if let Some(key) = key {
+            let name = match self.core.minibuffer("bookmark name (optional)") {
+                Ok(name) => name,
+                Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => String::new(),
+                Err(HError::MiniBufferEvent(_)) => return Ok(()),
+                err @ Err(_) => err?
+            };
+
+            self.bookmarks
+                .lock()
+                .bookmarks_mut()
+                .set_name(key, &name)?;
+            self.core.show_status(&format!("Bookmarked {} on {}", cwd, key))?;
+        }
+
         Ok(())
     }
 
@@ -694,6 +1096,7 @@ impl FileBrowser {
         let path = self.cwd.short_string();
 
         self.core.screen()?.set_title(&path)?;
+        self.core.screen()?.report_cwd(&self.cwd.path)?;
         Ok(())
     }
 
@@ -749,6 +1152,11 @@ impl FileBrowser {
     }
 
     pub fn take_main_files(&mut self) -> HResult<Files> {
+        // Can't be used for anything else, it's listed again instead
+        if self.get_files()?.flat.is_some() {
+            return Err(HError::Error(String::from("Flat view")));
+        }
+
         let w = self.main_widget_mut()?;
         let files = std::mem::take(&mut w.content);
         w.content.len = 0;
@@ -770,7 +1178,77 @@ impl FileBrowser {
         Ok(&self.left_widget()?.content)
     }
 
+    fn column_at(&self, xpos: u16) -> Option<usize> {
+        if self.columns.zoom_active { return self.columns.active; }
+
+        self.columns
+            .widgets
+            .iter()
+            .position(|widget| {
+                widget.get_coordinates()
+                    .map(|coords| xpos >= coords.xpos() &&
+                                  xpos < coords.xpos() + coords.xsize())
+                    .unwrap_or(false)
+            })
+    }
+
+    // Selects the file, a second click right after enters it
+    fn click_main_column(&mut self, ypos: u16) -> HResult<()> {
+        let index = match self.main_widget()?.index_at(ypos) {
+            Some(index) => index,
+            None => return Ok(())
+        };
+        let selection = self.main_widget()?.get_selection();
+
+        let double_click = self.last_click
+            .take()
+            .map(|(time, last)| last == index &&
+                                time.elapsed() < Duration::from_millis(400))
+            .unwrap_or(false);
+
+        if index > selection {
+            self.movement(&Movement::Down(index - selection))?;
+        } else if index < selection {
+            self.movement(&Movement::Up(selection - index))?;
+        }
+
+        if double_click {
+            return self.enter_dir();
+        }
+
+        self.last_click = Some((Instant::now(), index));
+        Ok(())
+    }
+
+    // Goes into the parent directory, with the clicked file selected
+    fn click_left_column(&mut self, ypos: u16) -> HResult<()> {
+        let file = match self.left_widget()?.file_at(ypos) {
+            Some(file) => file,
+            None => return Ok(())
+        };
+
+        if file == self.cwd { return Ok(()); }
+
+        let dir = file.parent_as_file()?;
+        self.fs_cache.set_selection(dir.clone(), file).log();
+        self.main_widget_goto(&dir)
+    }
+
+    // Goes into the previewed directory, with the clicked file selected
+    fn click_preview(&mut self, ypos: u16) -> HResult<()> {
+        let file = match self.preview_widget()?.file_at(ypos) {
+            Some(file) => file,
+            None => return Ok(())
+        };
+
+        let dir = file.parent_as_file()?;
+        self.fs_cache.set_selection(dir.clone(), file).log();
+        self.main_widget_goto(&dir)
+    }
+
     pub fn save_selected_file(&self) -> HResult<()> {
+        let offset = self.main_widget()?.offset;
+        self.fs_cache.set_offset(self.cwd.clone(), offset)?;
         self.selected_file()
             .map(|f| self.fs_cache.set_selection(self.cwd.clone(),
                                                  f))?
@@ -782,13 +1260,33 @@ impl FileBrowser {
         if self.main_widget()?.content.len() > 0 {
             let files = self.get_files()?;
             let selected_file = self.selected_file().ok();
-            self.fs_cache.save_settings(files, selected_file).log();
+            let offset = self.main_widget()?.offset;
+            self.fs_cache.save_settings(files, selected_file, Some(offset)).log();
         }
 
         Ok(())
     }
 
 
+    // Other tabs might have changed them in the meantime
+    pub fn restore_selections(&mut self) -> HResult<()> {
+        let cache = self.fs_cache.clone();
+
+        if self.main_async_widget_mut()?.ready() {
+            let main = self.main_widget_mut()?;
+            cache.restore_selections(&mut main.content)?;
+            main.refresh().log();
+        }
+
+        if self.left_async_widget_mut()?.ready() {
+            let left = self.left_widget_mut()?;
+            cache.restore_selections(&mut left.content)?;
+            left.refresh().log();
+        }
+
+        Ok(())
+    }
+
     pub fn cwd(&self) -> HResult<&File> {
         Ok(&self.cwd)
     }
@@ -928,6 +1426,157 @@ impl FileBrowser {
             .log();
     }
 
+    // Runs a popup until it's closed, resizing it along with the browser
+    fn show_popup(&mut self, popup: &mut impl Widget) {
+        run_popup(popup, |_| {}, |popup| {
+            self.resize().log();
+            popup.set_coordinates(&self.core.coordinates)
+        });
+    }
+
+    pub fn quick_look(&mut self) -> HResult<()> {
+        let core = self.core.clone();
+        let preview = self.preview_widget_mut()?;
+        let mut quick_look = crate::preview::QuickLook::new(&core, preview);
+
+        // The preview is borrowed, so only the popup itself gets resized
+        run_popup(&mut quick_look,
+                  |_| {},
+                  |quick_look| quick_look.set_coordinates(&core.coordinates));
+
+        drop(quick_look);
+        self.core.clear().log();
+        Ok(())
+    }
+
+    pub fn slideshow(&mut self) -> HResult<()> {
+        let config = self.core.config();
+        if !config.media_available() && config.graphics != "ueberzug" {
+            let status = format!("{}Slideshow needs {} or ueberzug!",
+                                 term::color_red(),
+                                 config.media_previewer);
+            return self.core.show_status(&status);
+        }
+
+        let selected = self.selected_file()?;
+        let images = self.main_widget()?
+            .content
+            .iter_files()
+            .filter(|file| {
+                file.get_mime()
+                    .map(|mime| mime.type_().as_str() == "image")
+                    .unwrap_or(false)
+            })
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+
+        if images.is_empty() {
+            let status = format!("{}No images here!", term::color_red());
+            return self.core.show_status(&status);
+        }
+
+        let start = images.iter()
+            .position(|path| path == &selected.path)
+            .unwrap_or(0);
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut slideshow = crate::slideshow::Slideshow::new(&self.core, images, start)?;
+
+        // Refreshes are mostly the timer
+        let coords = self.core.coordinates.clone();
+        run_popup(&mut slideshow,
+                  |slideshow| slideshow.tick().log(),
+                  |slideshow| slideshow.set_coordinates(&coords));
+
+        drop(slideshow);
+        self.core.clear().log();
+        Ok(())
+    }
+
+    pub fn tree_view(&mut self) -> HResult<()> {
+        let cwd = self.cwd()?.path.clone();
+        let selected = self.selected_file()?.path;
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut tree = crate::treeview::TreeView::new(&self.core, &cwd, &selected);
+
+        self.show_popup(&mut tree);
+
+        let path = tree.selected_path();
+        drop(tree);
+        self.core.clear().log();
+
+        // Takes the selection back to the columns
+        match path {
+            Some(path) if path != selected => self.goto_file(&path),
+            _ => Ok(())
+        }
+    }
+
+    pub fn fuzzy_find(&mut self) -> HResult<()> {
+        let cwd = self.cwd()?.path.clone();
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut finder = crate::fuzzy::FuzzyFinder::new(&self.core, &cwd);
+
+        self.show_popup(&mut finder);
+
+        let picked = finder.picked.take();
+        drop(finder);
+        self.core.clear().log();
+
+        match picked {
+            Some(path) => self.goto_file(&path),
+            None => Ok(())
+        }
+    }
+
+    pub fn scrub_video(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+        let is_video = file.get_mime()
+            .map(|mime| mime.type_().as_str() == "video")
+            .unwrap_or(false);
+
+        if !is_video {
+            return self.core.show_status("Not a video!");
+        }
+
+        let preview = self.preview_widget_mut()?;
+        preview.cancel_animation().log();
+        let coords = preview.get_coordinates()?.clone();
+
+        let mut strip = crate::thumbnails::ThumbStrip::new(&self.core, &file.path)?;
+        strip.set_coordinates(&coords).log();
+
+        // Stays where the preview is
+        run_popup(&mut strip, |_| {}, |strip| {
+            self.resize().log();
+            let coords = self.preview_widget()?.get_coordinates()?.clone();
+            strip.set_coordinates(&coords)
+        });
+
+        drop(strip);
+        self.core.clear().log();
+        Ok(())
+    }
+
+    // Goes to the directory the file is in and selects it there
+    pub fn goto_file(&mut self, path: &std::path::Path) -> HResult<()> {
+        let dir = File::new_from_path(path.parent()?)?;
+        let file = File::new_from_path(path)?;
+
+        if dir.path == self.cwd.path {
+            self.main_widget_mut()?.select_file(&file);
+            return Ok(());
+        }
+
+        self.fs_cache.set_selection(dir.clone(), file).log();
+        self.main_widget_goto(&dir)
+    }
+
     pub fn quit_with_dir(&self) -> HResult<()> {
         let cwd = self.cwd()?.clone().path;
         let selected_file = self.selected_file()?;
@@ -970,7 +1619,7 @@ impl FileBrowser {
             |s: &mut FileBrowser, filter: Option<Option<String>>, file: Option<File>| {
                 s.main_widget_mut()
                  .map(|mw| {
-                     filter.map(|f| mw.set_filter(f));
+                     filter.map(|f| mw.set_filter(f).log());
                      file.map(|f| mw.select_file(&f));
                  }).log();
             };
@@ -1041,7 +1690,8 @@ impl FileBrowser {
                             }
 
                             self.main_widget_mut()?
-                                .set_filter(Some(input));
+                                .set_filter(Some(input))
+                                .log();
                         }
                         // Restore original directory and filter/selection
                         Cancelled => {
@@ -1051,7 +1701,7 @@ impl FileBrowser {
                                 .widget
                                 .on_ready(move |mw,_| {
                                     let mw = mw?;
-                                    mw.set_filter(orig_dir_filter.take());
+                                    mw.set_filter(orig_dir_filter.take()).log();
                                     mw.select_file(&orig_dir_selected_file);
                                     Ok(())
                                 })?;
@@ -1087,6 +1737,7 @@ impl FileBrowser {
                             // Explicitly selected
                             selected_file = Some(self.selected_file()?);
                         }
+                        Back => {}
                     }
                 },
                 _ => {  }
@@ -1306,49 +1957,1582 @@ impl FileBrowser {
         Ok(())
     }
 
-    pub fn run_subshell(&mut self) -> HResult<()> {
-        self.core.get_sender().send(Events::InputEnabled(false))?;
+    pub fn quick_enter(&mut self) -> HResult<()> {
+        // Nothing to do if cancelled or no such label
+        if self.main_widget_mut()?.quick_select().is_err() {
+            return Ok(());
+        }
 
-        self.preview_widget().map(|preview| preview.cancel_animation()).log();
-        self.core.screen.suspend().log();
+        self.save_selected_file()?;
+        self.enter_dir()
+    }
 
-        let shell = std::env::var("SHELL").unwrap_or("bash".into());
-        let status = std::process::Command::new(&shell).status();
+    // GUI programs get their own life, hunter doesn't wait for them
+    fn spawn_detached(&self, cmd: &str) -> HResult<()> {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(cmd)
+            .current_dir(&self.cwd.path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+
+        // Don't leave a zombie behind
+        std::thread::spawn(move || child.wait());
+
+        self.core.show_status(&format!("Started \"{}\" in {}",
+                                       cmd,
+                                       self.cwd.path.short_string()))
+    }
+
+    pub fn open_file_manager(&mut self) -> HResult<()> {
+        let cmd = self.core.config().file_manager_cmd;
+        self.spawn_detached(&cmd)
+    }
+
+    pub fn open_terminal(&mut self) -> HResult<()> {
+        let cmd = self.core.config().terminal_cmd;
+        self.spawn_detached(&cmd)
+    }
+
+    pub fn run_subshell(&mut self) -> HResult<()> {
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+        self.core.screen.suspend().log();
+
+        let shell = std::env::var("SHELL").unwrap_or("bash".into());
+        let status = std::process::Command::new(&shell).status();
+
+        self.core.screen.activate().log();
+
+
+        self.core.get_sender().send(Events::InputEnabled(true))?;
+
+        match status {
+            Ok(status) =>
+                self.core.show_status(&format!("\"{}\" exited with {}",
+                                          shell, status)).log(),
+            Err(err) =>
+                self.core.show_status(&format!("Can't run this \"{}\": {}",
+                                          shell, err)).log()
+        }
+
+
+
+        Ok(())
+    }
+
+    pub fn show_procview(&mut self) -> HResult<()> {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+        let procview = self.proc_view.clone();
+        loop {
+            match procview.lock().popup() {
+                // Ignore refresh
+                Err(HError::RefreshParent) => continue,
+                Err(HError::TerminalResizedError) |
+                Err(HError::WidgetResizedError) => self.resize().log(),
+                _ => break
+            }
+        }
+        Ok(())
+    }
+
+    pub fn show_jobs(&mut self) -> HResult<()> {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut jobs = crate::jobs::JobView::new(&self.core);
+        jobs.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut jobs);
+
+        Ok(())
+    }
+
+    pub fn show_basket(&mut self) -> HResult<()> {
+        self.save_tab_settings()?;
+
+        let files = self.fs_cache.all_selections()?;
+        if files.is_empty() {
+            self.core.show_status("Basket is empty!").log();
+            return Ok(());
+        }
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut basket = Basket::new(&self.core, files);
+        basket.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut basket);
+
+        for file in &basket.removed {
+            self.fs_cache.remove_selection(file).log();
+        }
+
+        // Could be in any column, other tabs catch up when switching to them
+        if !basket.removed.is_empty() {
+            self.restore_selections().log();
+        }
+
+        if let Some(op) = basket.op {
+            self.basket_op(op, basket.files().clone())?;
+        }
+
+        Ok(())
+    }
+
+    fn target_candidates(&self) -> Vec<Target> {
+        let mut targets = vec![Target::new("here", self.cwd.path())];
+
+        for (i, dir) in self.tab_dirs.iter().enumerate() {
+            targets.push(Target::new(&format!("tab {}", i), dir.path()));
+        }
+
+        if let Ok(parent) = self.cwd.parent_as_file() {
+            targets.push(Target::new("parent", parent.path()));
+        }
+
+        if let Some(prev_cwd) = self.prev_cwd.as_ref() {
+            targets.push(Target::new("previous", prev_cwd.path()));
+        }
+
+        for (key, path) in self.bookmarks.lock().bookmarks().all() {
+            targets.push(Target::new(&format!("bookmark {}", key), path));
+        }
+
+        for dir in &self.recent_dirs {
+            targets.push(Target::new("recent", dir.path()));
+        }
+
+        targets
+    }
+
+    // Offers open tabs, bookmarks and recent directories before falling
+    // back to typing out the path
+    pub fn pick_target(&mut self, query: &str) -> HResult<PathBuf> {
+        let targets = self.target_candidates();
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut picker = TargetPicker::new(&self.core, query, targets);
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        if picker.prompt {
+            let cwd = self.cwd.path.to_string_lossy().to_string();
+            let path = self.core.minibuffer_validated(query,
+                                                      &format!("{}/", cwd),
+                                                      Validator::ExistingPath)?;
+            return Ok(PathBuf::from(path));
+        }
+
+        Ok(picker.picked?)
+    }
+
+    fn basket_op(&mut self,
+                 op: crate::keybind::BasketAction,
+                 files: Vec<File>) -> HResult<()> {
+        use crate::keybind::BasketAction::*;
+
+        let paths = files.iter()
+            .map(|f| f.path.clone().into_os_string())
+            .collect::<Vec<OsString>>();
+
+        let (cmd, args) = match op {
+            Copy | Move => {
+                use crate::fileops::OpKind;
+
+                let (kind, query) = match op {
+                    Copy => (OpKind::Copy, "copy to"),
+                    _ => (OpKind::Move, "move to")
+                };
+                let target = self.pick_target(query)?;
+                let sources = files.iter()
+                    .map(|f| f.path.clone())
+                    .collect();
+
+                if !self.start_fileop(kind, sources, target)? { return Ok(()); }
+
+                self.fs_cache.clear_selections().log();
+                self.main_widget_mut()?.clear_selections();
+                return Ok(());
+            }
+            Delete => {
+                let parents = files.iter()
+                    .filter_map(|f| f.path.parent())
+                    .collect::<Vec<_>>();
+                if !self.check_writable(&parents) { return Ok(()); }
+
+                let query = format!("delete {} files?", files.len());
+                let answer = Wizard::new()
+                    .step(&query)
+                    .choices(&["yes", "no"])
+                    .run(&self.core)?;
+
+                if answer.get(0)? != "yes" { return Ok(()); }
+
+                let options = crate::fileops::Options {
+                    when: self.ask_schedule()?,
+                    ..Default::default()
+                };
+                let paths = files.iter()
+                    .map(|f| f.path.clone())
+                    .collect();
+
+                crate::fileops::start(crate::fileops::OpKind::Delete,
+                                      paths,
+                                      None,
+                                      std::collections::HashMap::new(),
+                                      options,
+                                      self.core.get_sender());
+
+                self.fs_cache.clear_selections().log();
+                self.main_widget_mut()?.clear_selections();
+                return Ok(());
+            }
+            Archive => {
+                if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+                let name = self.core.minibuffer_validated("archive name",
+                                                          "",
+                                                          Validator::NonEmpty)?;
+
+                // tar picks the compression from the extension with -a
+                let (cmd, flags) = match name.ends_with(".zip") {
+                    true => ("zip", "-r"),
+                    false => ("tar", "-cavf")
+                };
+
+                let mut args = vec![OsString::from(flags), OsString::from(name)];
+                args.extend(paths);
+                (cmd, args)
+            }
+            Clear => {
+                self.fs_cache.clear_selections().log();
+                self.main_widget_mut()?.clear_selections();
+                return self.core.show_status("Cleared all selections");
+            }
+            Close | Remove => return Ok(())
+        };
+
+        let cmd = crate::proclist::Cmd {
+            cmd: OsString::from(cmd),
+            short_cmd: Some(format!("{} {} files", cmd, files.len())),
+            args: Some(args),
+            vars: None,
+            cwd: self.cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        };
+
+        self.run_job(cmd)?;
+
+        self.fs_cache.clear_selections().log();
+        self.main_widget_mut()?.clear_selections();
+
+        Ok(())
+    }
+
+    pub fn defer_next_job(&mut self) -> HResult<()> {
+        self.defer_job = !self.defer_job;
+
+        let status = match self.defer_job {
+            true => "Next job will be scheduled",
+            false => "Next job will start right away"
+        };
+        self.core.show_status(status)
+    }
+
+    fn run_job(&mut self, cmd: crate::proclist::Cmd) -> HResult<()> {
+        let when = self.ask_schedule()?;
+        crate::audit::record_cmd(&cmd);
+        ProcView::start(&self.proc_view, cmd, when)
+    }
+
+    fn ask_schedule(&mut self) -> HResult<Option<crate::proclist::Schedule>> {
+        use crate::proclist::Schedule;
+
+        if !self.defer_job { return Ok(None); }
+
+        let when = Wizard::new()
+            .step("start job")
+            .choices(&["after running jobs", "at time"])
+            .run(&self.core)?;
+
+        let when = match when.get(0)?.as_str() {
+            "at time" => {
+                let validator = Validator::Regex(String::from(r"^([01]?\d|2[0-3]):[0-5]\d$"));
+                let time = self.core.minibuffer_validated("start at (HH:MM)",
+                                                          "",
+                                                          validator)?;
+                let time = chrono::NaiveTime::parse_from_str(&time, "%H:%M").ok()?;
+                let mut at = chrono::Local::today().and_time(time)?;
+
+                if at <= chrono::Local::now() {
+                    at = at + chrono::Duration::days(1);
+                }
+
+                Schedule::At(at)
+            }
+            _ => Schedule::AfterRunning
+        };
+
+        self.defer_job = false;
+        Ok(Some(when))
+    }
+
+    pub fn paste_files(&mut self) -> HResult<()> {
+        use crate::fileops::OpKind;
+
+        let (kind, sources) = match crate::fileops::register() {
+            Some(register) => register,
+            None => return self.core.show_status("Nothing yanked")
+        };
+        let target = self.cwd.path.clone();
+
+        // Cut files can only be moved once
+        if self.start_fileop(kind, sources, target)? && kind == OpKind::Move {
+            crate::fileops::clear_register();
+        }
+
+        Ok(())
+    }
+
+    // Pasted files and copies or moves from the basket all get asked the
+    // same things before fileops takes over. False when it didn't start.
+    fn start_fileop(&mut self,
+                    kind: crate::fileops::OpKind,
+                    sources: Vec<PathBuf>,
+                    target: PathBuf) -> HResult<bool> {
+        use crate::fileops::{OpKind, Options};
+        use crate::files::Excludes;
+
+        if !target.is_dir() {
+            let status = format!("{}{} isn't a directory",
+                                 term::color_red(),
+                                 target.to_string_lossy());
+            self.core.show_status(&status).log();
+            return Ok(false);
+        }
+
+        let mut dirs = vec![target.as_path()];
+        if kind == OpKind::Move {
+            dirs.extend(sources.iter().filter_map(|source| source.parent()));
+        }
+
+        if !self.check_writable(&dirs) { return Ok(false); }
+
+        let resolutions = match self.ask_conflicts(&sources, &target)? {
+            Some(resolutions) => resolutions,
+            None => return Ok(false)
+        };
+
+        let copying = kind == OpKind::Copy;
+        let needs_space = copying || crate::transfer::crosses_device(&sources, &target);
+        if needs_space && !self.enough_space(&sources, &target)? { return Ok(false); }
+
+        let rules = self.ask_fix_names(&sources, &target, copying)?;
+        let excludes = match copying {
+            true => self.ask_excludes(&sources)?,
+            false => Excludes::default()
+        };
+        let when = self.ask_schedule()?;
+        let config = self.core.config();
+
+        let options = Options {
+            excludes,
+            rules: if copying { rules } else { None },
+            skip_identical: copying && config.skip_identical,
+            verify: !copying && config.verify_moves,
+            when
+        };
+
+        crate::fileops::start(kind,
+                              sources,
+                              Some(target),
+                              resolutions,
+                              options,
+                              self.core.get_sender());
+        Ok(true)
+    }
+
+    // One question for each file that's in the way, unless the answer
+    // goes for the rest too. None when it's cancelled.
+    fn ask_conflicts(&self,
+                     sources: &[PathBuf],
+                     target: &std::path::Path) -> HResult<Option<std::collections::HashMap<PathBuf, crate::fileops::Resolution>>> {
+        use std::collections::HashMap;
+        use crate::fileops::Resolution;
+
+        let conflicts = crate::fileops::conflicts(sources, target);
+        let mut resolutions = HashMap::new();
+
+        for (i, (source, existing)) in conflicts.iter().enumerate() {
+            let age = match crate::fileops::is_newer(source, existing) {
+                true => "newer",
+                false => "not newer"
+            };
+            let query = format!("{} is already here, pasted one is {}",
+                                existing.file_name()?.to_string_lossy(),
+                                age);
+
+            let left = conflicts.len() - i - 1;
+            let mut wizard = Wizard::new()
+                .step(&query)
+                .choices(&["overwrite", "skip", "rename", "if newer", "cancel"]);
+            if left > 0 {
+                wizard = wizard.step(&format!("same for the other {}", left))
+                    .choices(&["no", "yes"]);
+            }
+            let answer = wizard.run(&self.core)?;
+
+            let resolution = match answer.get(0)?.as_str() {
+                "overwrite" => Resolution::Overwrite,
+                "skip" => Resolution::Skip,
+                "rename" => Resolution::Rename,
+                "if newer" => Resolution::IfNewer,
+                _ => return Ok(None)
+            };
+
+            match answer.get(1).map(|all| all == "yes") {
+                Some(true) => {
+                    for (source, _) in &conflicts[i..] {
+                        resolutions.insert(source.clone(), resolution);
+                    }
+                    break;
+                }
+                _ => { resolutions.insert(source.clone(), resolution); }
+            }
+        }
+
+        Ok(Some(resolutions))
+    }
+
+    // Big directories take a while, so they're walked in the background
+    // while the minibuffer waits for Esc to give up
+    fn enough_space(&self, sources: &[PathBuf], target: &std::path::Path) -> HResult<bool> {
+        use crate::files::{disk_usage_cancellable, DuOptions, Ticker};
+        use crate::minibuffer::MiniBufferEvent;
+
+        let stale = Stale::new();
+        let needed = Arc::new(Mutex::new(None));
+
+        let sources = sources.to_vec();
+        let options = DuOptions::from_config(&self.core.config());
+        let walk_stale = stale.clone();
+        let walk_needed = needed.clone();
+
+        std::thread::spawn(move || {
+            if let Ok(size) = disk_usage_cancellable(&sources, options, &walk_stale) {
+                *walk_needed.lock() = Some(size);
+            }
+        });
+
+        // Wakes up the minibuffer to look if it's done
+        let _ticker = Ticker::start_ticking(self.core.get_sender());
+
+        let needed = loop {
+            if let Some(needed) = *needed.lock() { break needed; }
+
+            match self.core.minibuffer("Checking free space, Esc cancels") {
+                Err(HError::MiniBufferEvent(MiniBufferEvent::Cancelled)) => {
+                    stale.set_stale()?;
+                    self.core.show_status("Cancelled").log();
+                    return Ok(false);
+                }
+                Err(HError::RefreshParent) |
+                Err(HError::MiniBufferEvent(_)) |
+                Ok(_) => continue,
+                Err(err) => {
+                    stale.set_stale()?;
+                    return Err(err);
+                }
+            }
+        };
+
+        self.check_space(needed, target)
+    }
+
+    // Depending on space_check, anything that won't fit on the target's
+    // filesystem gets a warning first, or isn't started at all. Archives
+    // can be checked with their unpacked size from stats::unpacked_size.
+    fn check_space(&self, needed: u64, target: &std::path::Path) -> HResult<bool> {
+        use crate::config::SpaceCheck;
+        use crate::files::size_string;
+
+        let config = self.core.config();
+        if config.space_check == SpaceCheck::Off { return Ok(true); }
+
+        // Can't tell, so better not stand in the way
+        let available = match crate::stats::available_space(target) {
+            Ok(available) => available,
+            Err(_) => return Ok(true)
+        };
+
+        if needed <= available { return Ok(true); }
+
+        let msg = format!("Needs {}, but only {} free",
+                          size_string(needed),
+                          size_string(available));
+
+        match config.space_check {
+            SpaceCheck::Refuse => {
+                self.core.show_status(&format!("{}, not starting!", msg))?;
+                Ok(false)
+            }
+            _ => {
+                let answer = Wizard::new()
+                    .step(&format!("{}, continue?", msg))
+                    .choices(&["no", "yes"])
+                    .run(&self.core)?;
+
+                Ok(answer.get(0)? == "yes")
+            }
+        }
+    }
+
+    // Better to know before starting something that can only fail
+    fn check_writable(&self, dirs: &[&std::path::Path]) -> bool {
+        let blocked = dirs.iter()
+            .find_map(|dir| Some((dir, crate::stats::write_blocker(dir)?)));
+
+        match blocked {
+            Some((dir, reason)) => {
+                let status = format!("{}{}: {}",
+                                     term::color_red(),
+                                     dir.to_string_lossy(),
+                                     reason);
+                self.core.show_status(&status).log();
+                false
+            }
+            None => true
+        }
+    }
+
+    // Names that are fine here might not be on a USB stick. Asks whether
+    // to rename them when copying, moves can only go ahead or not.
+    fn ask_fix_names(&self,
+                     sources: &[PathBuf],
+                     target: &std::path::Path,
+                     copying: bool) -> HResult<Option<crate::transfer::NameRules>> {
+        use crate::transfer::{NameRules, check_names};
+        use crate::minibuffer::MiniBufferEvent;
+
+        if !target.is_dir() { return Ok(None); }
+
+        let rules = self.fs_stat
+            .read()
+            .find_fs(target)
+            .ok()
+            .and_then(|fs| NameRules::for_fs(&fs.fs_type));
+        let rules = match rules {
+            Some(rules) => rules,
+            None => return Ok(None)
+        };
+
+        self.core.show_status("Checking names...").log();
+        let fixes = check_names(sources, target, rules)?;
+        self.core.clear_status().log();
+
+        if fixes.collisions == 0 && fixes.invalid == 0 { return Ok(None); }
+
+        let query = format!("{} names clash by case, {} have invalid characters",
+                            fixes.collisions,
+                            fixes.invalid);
+        let choices: &[&str] = match copying {
+            true => &["rename", "copy anyway", "cancel"],
+            false => &["move anyway", "cancel"]
+        };
+
+        let answer = Wizard::new()
+            .step(&query)
+            .choices(choices)
+            .run(&self.core)?;
+
+        match answer.get(0)?.as_str() {
+            "rename" => Ok(Some(rules)),
+            "cancel" => Err(HError::MiniBufferEvent(MiniBufferEvent::Cancelled)),
+            _ => Ok(None)
+        }
+    }
+
+    // Excludes only matter when there are directories involved
+    fn ask_excludes(&self, sources: &[PathBuf]) -> HResult<crate::files::Excludes> {
+        use crate::files::Excludes;
+        use crate::minibuffer::MiniBufferEvent;
+
+        let default = self.core.config().exclude.join(",");
+
+        if !sources.iter().any(|source| source.is_dir()) {
+            return Ok(Excludes::parse(&default));
+        }
+
+        match self.core.minibuffer_prefilled("exclude", &default) {
+            Ok(list) => Ok(Excludes::parse(&list)),
+            Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => Ok(Excludes::default()),
+            Err(err) => Err(err)
+        }
+    }
+
+    pub fn flatten_dir(&mut self) -> HResult<()> {
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let plan = crate::batch::flatten_plan(&self.cwd.path)?;
+        self.run_batch(plan)
+    }
+
+    fn templates(&self) -> Vec<Target> {
+        let dirs = vec![("hunter", crate::paths::templates_path()),
+                        ("xdg", crate::paths::xdg_templates_path())];
+
+        let mut templates = dirs.into_iter()
+            .filter_map(|(kind, dir)| Some((kind, std::fs::read_dir(dir.ok()?).ok()?)))
+            .flat_map(|(kind, entries)| {
+                entries.flatten()
+                    .map(move |entry| Target::new(kind, entry.path()))
+            })
+            .collect::<Vec<_>>();
+
+        templates.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+        templates
+    }
+
+    pub fn new_from_template(&mut self) -> HResult<()> {
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let templates = self.templates();
+
+        if templates.is_empty() {
+            let status = "No templates found, put some in ~/.config/hunter/templates";
+            self.core.show_status(status).log();
+            return Ok(());
+        }
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut picker = TargetPicker::new(&self.core, "new from template", templates);
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        let template = match picker.prompt {
+            true => PathBuf::from(self.core.minibuffer_validated("template",
+                                                                 "",
+                                                                 Validator::ExistingPath)?),
+            false => picker.picked?
+        };
+
+        let name = template.file_name()?.to_string_lossy().to_string();
+        let name = self.core.minibuffer_validated("file name",
+                                                  &name,
+                                                  Validator::NonEmpty)?;
+
+        if name.contains('/') || name == "." || name == ".." {
+            let status = format!("{}Not a valid name: {}", term::color_red(), name);
+            return self.core.show_status(&status);
+        }
+
+        let target = self.cwd.path.join(&name);
+
+        if target.symlink_metadata().is_ok() {
+            let status = format!("{}{} already exists!", term::color_red(), name);
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        match template.is_dir() {
+            true => {
+                let cmd = crate::transfer::plain_cmd("cp",
+                                                     "-r",
+                                                     vec![template],
+                                                     target,
+                                                     &self.cwd);
+                self.run_job(cmd)?;
+            }
+            false => {
+                std::fs::copy(&template, &target)?;
+                crate::audit::record("create", &target, None);
+                self.core.show_status(&format!("Created {}", name)).log();
+            }
+        }
+
+        Ok(())
+    }
+
+    // Text ends up in a .txt, images in a .png
+    pub fn paste_new_file(&mut self) -> HResult<()> {
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let content = match crate::clipboard::paste() {
+            Ok(content) => content,
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status).log();
+                return Ok(());
+            }
+            Err(err) => return Err(err)
+        };
+
+        let name = format!("clipboard.{}", content.extension());
+        let name = self.core.minibuffer_validated("paste as",
+                                                  &name,
+                                                  Validator::NonEmpty)?;
+        let target = self.cwd.path.join(&name);
+
+        if target.symlink_metadata().is_ok() {
+            let status = format!("{}{} already exists!", term::color_red(), name);
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        std::fs::write(&target, content.data())?;
+        crate::audit::record("create", &target, None);
+
+        let status = format!("Saved {} to {}",
+                             crate::files::size_string(content.data().len() as u64),
+                             name);
+        self.core.show_status(&status).log();
+        Ok(())
+    }
+
+    pub fn mount_image(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+
+        if !crate::mounts::is_image(&file.path) {
+            let status = format!("{}{} is not a disk image!",
+                                 term::color_red(),
+                                 file.name);
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        self.core.show_status(&format!("Mounting {}...", file.name)).log();
+
+        match crate::mounts::mount_image(&file.path) {
+            Ok(mountpoint) => {
+                let dir = File::new_from_path(&mountpoint)?;
+                self.main_widget_goto(&dir)?;
+                let status = format!("Mounted {} at {}",
+                                     file.name,
+                                     mountpoint.to_string_lossy());
+                self.core.show_status(&status).log();
+            }
+            Err(HError::Error(msg)) => {
+                let status = format!("{}Can't mount {}: {}",
+                                     term::color_red(),
+                                     file.name,
+                                     msg);
+                self.core.show_status(&status).log();
+            }
+            Err(err) => return Err(err)
+        }
+
+        Ok(())
+    }
+
+    pub fn export_setup(&mut self) -> HResult<()> {
+        let name = self.core.minibuffer_prefilled("export setup to",
+                                                  "hunter-setup.tar.gz")?;
+        let target = self.cwd.path.join(name.trim());
+
+        if target.symlink_metadata().is_ok() {
+            let status = format!("{}{} already exists!",
+                                 term::color_red(),
+                                 target.to_string_lossy());
+            return self.core.show_status(&status);
+        }
+
+        match crate::backup::export(&target) {
+            Ok(()) => {
+                let status = format!("Exported setup to {}", target.to_string_lossy());
+                self.core.show_status(&status)
+            }
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status)
+            }
+            Err(err) => Err(err)
+        }
+    }
+
+    pub fn import_setup(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+        let name = self.core.minibuffer_prefilled("import setup from", &file.name)?;
+        let archive = self.cwd.path.join(name.trim());
+
+        match crate::backup::import(&archive) {
+            // Config and keys are only read on startup
+            Ok(()) => self.core.show_status("Imported setup, restart hunter to use its config and keys"),
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status)
+            }
+            Err(err) => Err(err)
+        }
+    }
+
+    pub fn switch_key_preset(&mut self) -> HResult<()> {
+        let mut names = vec![String::from("default")];
+        names.extend(crate::keypresets::names());
+        let choices = names.iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+
+        let answer = Wizard::new()
+            .step("key preset")
+            .choices(&choices)
+            .run(&self.core)?;
+        let name = answer.get(0)?;
+
+        let preset = match name.as_str() {
+            "default" => None,
+            preset => Some(preset)
+        };
+
+        // Starts over from the keys file, so presets don't pile up
+        let keybinds = match crate::keybind::KeyBinds::load_with_preset(preset) {
+            Ok(keybinds) => keybinds,
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                return self.core.show_status(&status);
+            }
+            Err(err) => return Err(err)
+        };
+
+        self.core.change_config(|config| {
+            config.key_preset = preset.map(String::from);
+            config.keybinds = keybinds;
+        })?;
+
+        let status = format!("Using {} keys, key_preset={} in the config keeps them",
+                             name,
+                             name);
+        self.core.show_status(&status)
+    }
+
+    pub fn pack_archive(&mut self) -> HResult<()> {
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        // Selected files, otherwise tagged ones, otherwise just this one
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files = self.get_files()?
+                .iter_files()
+                .filter(|file| file.is_tagged().unwrap_or(false))
+                .cloned()
+                .collect();
+        }
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
+
+        let default = match files.len() {
+            1 => format!("{}.tar.gz", files[0].name),
+            _ => format!("{}.tar.gz", self.cwd.name)
+        };
+
+        let name = self.core.minibuffer_prefilled("pack into (.tar.gz/.tar.zst/.zip/...)",
+                                                  &default)?;
+        let target = self.cwd.path.join(name.trim());
+
+        if target.symlink_metadata().is_ok() {
+            let status = format!("{}{} already exists!",
+                                 term::color_red(),
+                                 target.to_string_lossy());
+            return self.core.show_status(&status);
+        }
+
+        let names = files.iter()
+            .map(|file| OsString::from(&file.name))
+            .collect();
+
+        let (cmd, args) = match crate::archive::pack_args(&target, names) {
+            Ok(cmd) => cmd,
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                return self.core.show_status(&status);
+            }
+            Err(err) => return Err(err)
+        };
+
+        let short_cmd = format!("pack {} files into {}",
+                                files.len(),
+                                target.file_name()?.to_string_lossy());
+
+        // The listed files in the output and io rate in the process view show how far along it is
+        let cmd = crate::proclist::Cmd {
+            cmd: cmd,
+            args: Some(args),
+            vars: None,
+            short_cmd: Some(short_cmd),
+            cwd: self.cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        };
+
+        self.run_job(cmd)
+    }
+
+    pub fn browse_remote(&mut self, mut backend: Box<dyn Backend>) -> HResult<()> {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        if let Some(key) = backend.credentials_key() {
+            backend.set_secret(crate::credentials::lookup(&key));
+        }
+
+        let uploads = self.selected_files()
+            .unwrap_or(vec![])
+            .into_iter()
+            .map(|file| file.path)
+            .collect();
+
+        let mut browser = RemoteBrowser::new(&self.core, backend, &self.cwd)
+            .with_uploads(uploads);
+        browser.set_coordinates(&self.core.coordinates).log();
+
+        loop {
+            self.show_popup(&mut browser);
+
+            let key = match (browser.login, browser.credentials_key()) {
+                (true, Some(key)) => key,
+                _ => break
+            };
+
+            let query = match key.starts_with("http") {
+                true => format!("user:password for {}", key),
+                false => format!("password for {}", key)
+            };
+
+            match self.core.minibuffer_secret(&query) {
+                Ok(secret) => {
+                    crate::credentials::store(&key, &secret);
+                    browser.set_secret(Some(secret));
+                }
+                Err(_) => browser.login = false
+            }
+        }
+
+        if browser.jobs.is_empty() { return Ok(()); }
+
+        // Transfers are queued, so they don't fight over the connection
+        let when = self.ask_schedule()?;
+        let queued = match when {
+            Some(when) => when,
+            None => crate::proclist::Schedule::AfterRunning
+        };
+
+        let count = browser.jobs.len();
+        for (i, cmd) in browser.jobs.into_iter().enumerate() {
+            match i {
+                0 => ProcView::start(&self.proc_view, cmd, when)?,
+                _ => ProcView::start(&self.proc_view, cmd, Some(queued))?
+            }
+        }
+
+        if count > 1 {
+            self.core.show_status(&format!("Queued {} transfers", count)).log();
+        }
+
+        Ok(())
+    }
+
+    pub fn browse_container_image(&mut self) -> HResult<()> {
+        let images = crate::oci::local_images()
+            .into_iter()
+            .map(|(tool, image)| Target::new(&tool, image))
+            .collect::<Vec<_>>();
+
+        if images.is_empty() {
+            let status = format!("{}No docker or podman images found!",
+                                 term::color_red());
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut picker = TargetPicker::new(&self.core, "image", images.clone());
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        let (tool, image) = match picker.prompt {
+            true => {
+                let image = self.core.minibuffer_validated("image",
+                                                           "",
+                                                           Validator::NonEmpty)?;
+                (images[0].kind.clone(), image)
+            }
+            false => {
+                let picked = picker.picked?;
+                let target = images.iter().find(|target| target.path == picked)?;
+                (target.kind.clone(), picked.to_string_lossy().to_string())
+            }
+        };
+
+        let backend = crate::oci::ImageBackend::new(&tool, &image);
+        self.browse_remote(Box::new(backend))
+    }
+
+    pub fn browse_ssh(&mut self) -> HResult<()> {
+        let location = self.core.minibuffer_validated("ssh host",
+                                                      "",
+                                                      Validator::NonEmpty)?;
+        let backend = crate::ssh::SshBackend::new(&location);
+        self.browse_remote(Box::new(backend))
+    }
+
+    pub fn browse_http(&mut self) -> HResult<()> {
+        let url = self.core.minibuffer_validated("url",
+                                                 "https://",
+                                                 Validator::NonEmpty)?;
+        let backend = crate::http_index::HttpIndexBackend::new(&url)?;
+        self.browse_remote(Box::new(backend))
+    }
+
+    pub fn browse_s3(&mut self) -> HResult<()> {
+        let location = match self.core.minibuffer_prefilled("s3 location", "s3://") {
+            Ok(location) => location,
+            Err(HError::MiniBufferEvent(_)) => String::new(),
+            Err(err) => return Err(err)
+        };
+
+        let backend = crate::s3::S3Backend::new(&location);
+        self.browse_remote(Box::new(backend))
+    }
+
+    // Works on the selected files, or everything in the directory
+    pub fn sanitize_names(&mut self) -> HResult<()> {
+        use crate::batch::{sanitize_plan, Sanitize};
+
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let default = self.core.config().sanitize.clone();
+        let rules = self.core.minibuffer_prefilled("sanitize", &default)?;
+        let rules = match Sanitize::parse(&rules) {
+            Ok(rules) => rules,
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status).log();
+                return Ok(());
+            }
+            Err(err) => return Err(err)
+        };
 
-        self.core.screen.activate().log();
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
 
+        let paths = files.into_iter()
+            .map(|f| f.path)
+            .collect::<Vec<_>>();
 
-        self.core.get_sender().send(Events::InputEnabled(true))?;
+        let plan = sanitize_plan(&paths, &rules);
+        self.run_batch(plan)
+    }
 
-        match status {
-            Ok(status) =>
-                self.core.show_status(&format!("\"{}\" exited with {}",
-                                          shell, status)).log(),
-            Err(err) =>
-                self.core.show_status(&format!("Can't run this \"{}\": {}",
-                                          shell, err)).log()
+    pub fn renumber_files(&mut self) -> HResult<()> {
+        use crate::batch::renumber_plan;
+
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
         }
 
+        let pattern = self.core.minibuffer_validated("rename pattern",
+                                                     "{name}_{n:03}{ext}",
+                                                     Validator::RenamePattern)?;
 
+        let paths = files.into_iter()
+            .map(|f| f.path)
+            .collect::<Vec<_>>();
 
-        Ok(())
+        match renumber_plan(&paths, &pattern) {
+            Ok(plan) => self.run_batch(plan),
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status).log();
+                Ok(())
+            }
+            Err(err) => Err(err)
+        }
     }
 
-    pub fn show_procview(&mut self) -> HResult<()> {
+    pub fn regex_rename(&mut self) -> HResult<()> {
+        use crate::batch::regex_rename_plan;
+        use crate::minibuffer::MiniBufferEvent;
+
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
+
+        let pattern = self.core.minibuffer_validated("rename regex",
+                                                     "",
+                                                     Validator::NonEmpty)?;
+        let pattern = match regex::Regex::new(&pattern) {
+            Ok(pattern) => pattern,
+            Err(err) => {
+                let status = format!("{}{}", term::color_red(), err);
+                self.core.show_status(&status).log();
+                return Ok(());
+            }
+        };
+
+        // Nothing just removes the matches
+        let replacement = match self.core.minibuffer_prefilled("replace with", "") {
+            Ok(replacement) => replacement,
+            Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => String::new(),
+            Err(err) => return Err(err)
+        };
+
+        let paths = files.into_iter()
+            .map(|f| f.path)
+            .collect::<Vec<_>>();
+
+        match regex_rename_plan(&paths, &pattern, &replacement) {
+            Ok(plan) => self.run_batch(plan),
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status).log();
+                Ok(())
+            }
+            Err(err) => Err(err)
+        }
+    }
+
+    pub fn bulk_rename(&mut self) -> HResult<()> {
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let (plan, conflicts) = match self.main_widget_mut()?.edit_names() {
+            Ok(plan) => plan,
+            Err(HError::Error(msg)) => {
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status).log();
+                return Ok(());
+            }
+            Err(err) => return Err(err)
+        };
+
+        if plan.is_empty() && conflicts.is_empty() {
+            self.core.show_status("Nothing to do!").log();
+            return Ok(());
+        }
+
+        let mut lines = plan.preview_lines(&self.cwd.path);
+        lines.extend(conflicts.iter().map(|conflict| format!("{}{}{}",
+                                                             term::color_red(),
+                                                             conflict,
+                                                             term::normal_color())));
+        let summary = format!("{} files will be renamed, {} conflicts left out",
+                              plan.moves.len(),
+                              conflicts.len());
+
+        let preview = BatchPreview::from_lines(&self.core, &plan.title, &summary, lines);
+        if !self.confirm_preview(preview) || plan.is_empty() { return Ok(()); }
+
+        self.last_batch = Some(plan.clone());
+        let cmd = plan.into_cmd(&self.cwd);
+        self.run_job(cmd)
+    }
+
+    // Running the undo again redoes it
+    pub fn undo_batch(&mut self) -> HResult<()> {
+        let undo = match &self.last_batch {
+            Some(plan) => plan.undo(),
+            None => {
+                self.core.show_status("Nothing to undo!").log();
+                return Ok(());
+            }
+        };
+
+        self.run_batch(undo)
+    }
+
+    pub fn organize_files(&mut self) -> HResult<()> {
+        use crate::batch::{organize_plan, OrganizeBy};
+
+        if !self.check_writable(&[self.cwd.path.as_path()]) { return Ok(()); }
+
+        let by = Wizard::new()
+            .step("organize by")
+            .choices(&["date", "extension"])
+            .run(&self.core)?;
+        let by = match by.get(0)?.as_str() {
+            "date" => OrganizeBy::Date,
+            _ => OrganizeBy::Extension
+        };
+
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files = self.main_widget()?
+                .content
+                .iter_files()
+                .cloned()
+                .collect();
+        }
+
+        let paths = files.into_iter()
+            .map(|f| f.path)
+            .collect::<Vec<_>>();
+
+        let plan = organize_plan(&paths, &self.cwd.path, by);
+        self.run_batch(plan)
+    }
+
+    fn run_batch(&mut self, plan: MovePlan) -> HResult<()> {
+        if plan.is_empty() {
+            self.core.show_status("Nothing to do!").log();
+            return Ok(());
+        }
+
+        let preview = BatchPreview::new(&self.core, &plan, &self.cwd.path);
+        if !self.confirm_preview(preview) { return Ok(()); }
+
+        self.last_batch = Some(plan.clone());
+        let cmd = plan.into_cmd(&self.cwd);
+        self.run_job(cmd)
+    }
+
+    // True if Enter was pressed
+    fn confirm_preview(&mut self, mut preview: BatchPreview) -> bool {
         self.preview_widget().map(|preview| preview.cancel_animation()).log();
-        let procview = self.proc_view.clone();
-        loop {
-            match procview.lock().popup() {
-                // Ignore refresh
-                Err(HError::RefreshParent) => continue,
-                Err(HError::TerminalResizedError) |
-                Err(HError::WidgetResizedError) => self.resize().log(),
-                _ => break
+        preview.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut preview);
+
+        preview.confirmed
+    }
+
+    pub fn chown(&mut self) -> HResult<()> {
+        use crate::chown::{ChownPlan, user_names, group_names, MAX_PREVIEW};
+        use crate::minibuffer::MiniBufferEvent;
+
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
+        let paths = files.into_iter()
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+
+        // Leaving one empty keeps it as it is
+        let optional = |answer: HResult<String>| match answer {
+            Ok(name) => Ok(Some(name)),
+            Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => Ok(None),
+            Err(err) => Err(err)
+        };
+        let user = optional(self.core.minibuffer_words("chown user", "", user_names()))?;
+        let group = optional(self.core.minibuffer_words("chown group", "", group_names()))?;
+
+        let recursive = match paths.iter().any(|path| path.is_dir()) {
+            true => {
+                let answer = Wizard::new()
+                    .step("recursive")
+                    .choices(&["no", "yes"])
+                    .run(&self.core)?;
+                answer.get(0)? == "yes"
+            }
+            false => false
+        };
+
+        let plan = ChownPlan::new(paths, user, group, recursive)?;
+
+        self.core.show_status("Looking for files to change...").log();
+        let lines = plan.preview_lines();
+        self.core.clear_status().log();
+
+        if lines.is_empty() {
+            return self.core.show_status("Nothing to change!");
+        }
+
+        let summary = match lines.len() {
+            MAX_PREVIEW => format!("more than {} files will change", MAX_PREVIEW),
+            count => format!("{} files will change", count)
+        };
+        let title = format!("chown {}", plan.owner_spec());
+        let preview = BatchPreview::from_lines(&self.core, &title, &summary, lines);
+        if !self.confirm_preview(preview) { return Ok(()); }
+
+        let mut args = plan.chown_args();
+
+        if nix::unistd::geteuid().is_root() {
+            let cmd = crate::proclist::Cmd {
+                cmd: args.remove(0),
+                args: Some(args),
+                vars: None,
+                short_cmd: Some(title),
+                cwd: self.cwd.clone(),
+                cwd_files: None,
+                tab_files: None,
+                tab_paths: None
+            };
+            return self.run_job(cmd);
+        }
+
+        self.run_as_root(args)
+    }
+
+    // Changes the SELinux context with chcon, or resets it with restorecon
+    pub fn relabel(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent;
+
+        if !crate::selinux::enabled() {
+            return self.core.show_status("SELinux isn't enabled");
+        }
+
+        let mut files = self.selected_files()?;
+        if files.is_empty() {
+            files.push(self.selected_file()?);
+        }
+        let paths = files.into_iter()
+            .map(|file| file.path)
+            .collect::<Vec<_>>();
+
+        let current = crate::selinux::context(paths.get(0)?).unwrap_or_default();
+        let context = match self.core.minibuffer_prefilled("context (empty restores default)",
+                                                           &current) {
+            Ok(context) => context.trim().to_string(),
+            Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => String::new(),
+            Err(err) => return Err(err)
+        };
+
+        let recursive = match paths.iter().any(|path| path.is_dir()) {
+            true => {
+                let answer = Wizard::new()
+                    .step("recursive")
+                    .choices(&["no", "yes"])
+                    .run(&self.core)?;
+                answer.get(0)? == "yes"
+            }
+            false => false
+        };
+
+        let mut args = crate::selinux::relabel_args(&paths, &context, recursive);
+
+        if nix::unistd::geteuid().is_root() {
+            let short_cmd = args.get(0)?.to_string_lossy().to_string();
+            let cmd = crate::proclist::Cmd {
+                cmd: args.remove(0),
+                args: Some(args),
+                vars: None,
+                short_cmd: Some(short_cmd),
+                cwd: self.cwd.clone(),
+                cwd_files: None,
+                tab_files: None,
+                tab_paths: None
+            };
+            return self.run_job(cmd);
+        }
+
+        self.run_as_root(args)
+    }
+
+    pub fn file_history(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+        let current = std::fs::symlink_metadata(&file.path).ok();
+
+        // Snapshots taken since the last change have the same version
+        let versions = crate::snapshots::versions(&file.path)
+            .into_iter()
+            .filter(|version| match &current {
+                Some(meta) => meta.modified().ok() != Some(version.mtime) ||
+                    meta.len() != version.size,
+                None => true
+            })
+            .collect::<Vec<_>>();
+
+        if versions.is_empty() {
+            return self.core.show_status(&format!("No earlier versions of {} in snapshots",
+                                                  file.name));
+        }
+
+        let targets = versions.iter()
+            .map(|version| {
+                let mtime: chrono::DateTime<chrono::Local> = version.mtime.into();
+                let kind = format!("{} {:>6} {}",
+                                   mtime.format("%F %R"),
+                                   crate::files::size_string(version.size),
+                                   version.snapshot);
+                Target::new(&kind, version.path.clone())
+            })
+            .collect();
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let query = format!("history of {}", file.name);
+        let mut picker = TargetPicker::new(&self.core, &query, targets);
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        // Any other file can be compared too
+        let old = match picker.prompt {
+            true => PathBuf::from(self.core.minibuffer_validated("compare with",
+                                                                 "",
+                                                                 Validator::ExistingPath)?),
+            false => picker.picked?
+        };
+
+        let action = Wizard::new()
+            .step("version")
+            .choices(&["diff", "restore"])
+            .run(&self.core)?;
+
+        let title = format!("{} from {}", file.name, old.short_string());
+        let restore = match action.get(0)?.as_str() {
+            "diff" => {
+                let lines = crate::snapshots::diff(&old, &file.path);
+                let summary = match lines.len() {
+                    0 => String::from("no differences, Enter restores it"),
+                    n => format!("{} lines of diff, Enter restores it", n)
+                };
+                let preview = BatchPreview::from_lines(&self.core, &title, &summary, lines);
+                self.confirm_preview(preview)
+            }
+            _ => {
+                let answer = Wizard::new()
+                    .step(&format!("restore {}?", title))
+                    .choices(&["no", "yes"])
+                    .run(&self.core)?;
+                answer.get(0)? == "yes"
+            }
+        };
+
+        if !restore { return Ok(()); }
+
+        // Directories have too much in them that might be newer
+        if old.is_dir() {
+            return self.core.show_status("Only files can be restored");
+        }
+
+        std::fs::copy(&old, &file.path)?;
+        crate::audit::record("restore", &old, Some(&file.path));
+
+        // Not every directory is watched
+        self.restat().log();
+        self.core.show_status(&format!("Restored {}", title))
+    }
+
+    pub fn show_hardlinks(&mut self) -> HResult<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let file = self.selected_file()?;
+        let meta = file.path.symlink_metadata()?;
+
+        if meta.is_dir() || meta.nlink() <= 1 {
+            return self.core.show_status(&format!("{} has no other hardlinks", file.name));
+        }
+
+        let scope = Wizard::new()
+            .step(&format!("{} has {} other links, search", file.name, meta.nlink() - 1))
+            .choices(&["filesystem", "directory", "home"])
+            .run(&self.core)?;
+        let scope = match scope.get(0)?.as_str() {
+            "directory" => self.cwd.path.clone(),
+            "home" => crate::paths::home_path()?,
+            _ => crate::hardlinks::fs_root(&file.path)
+        };
+
+        self.core.show_status(&format!("Searching {}...", scope.short_string())).log();
+        let (others, links) = crate::hardlinks::find_links(&file.path, &scope)?;
+
+        if links.is_empty() {
+            return self.core.show_status(&format!("None of the {} other links are in {}",
+                                                  others,
+                                                  scope.short_string()));
+        }
+
+        let targets = links.into_iter()
+            .map(|link| Target::new("link", link))
+            .collect::<Vec<_>>();
+        let query = format!("{} of {} other links to {}",
+                            targets.len(),
+                            others,
+                            file.name);
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut picker = TargetPicker::new(&self.core, &query, targets);
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        if picker.prompt { return Ok(()); }
+
+        // Show the chosen one where it is
+        let link = File::new_from_path(&picker.picked?)?;
+        let dir = link.parent_as_file()?;
+        self.fs_cache.set_selection(dir.clone(), link).log();
+        self.main_widget_goto(&dir)
+    }
+
+    // Runs a command through sudo_cmd in the terminal, so it can ask for
+    // a password
+    pub fn run_as_root(&mut self, args: Vec<OsString>) -> HResult<()> {
+        let sudo = self.core.config().sudo_cmd;
+        let cmd = args.get(0)?.to_string_lossy().to_string();
+
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+        self.core.screen.suspend().log();
+
+        let status = std::process::Command::new(&sudo)
+            .args(&args)
+            .status();
+
+        self.core.screen.activate().log();
+        self.core.get_sender().send(Events::InputEnabled(true))?;
+
+        // Not every directory is watched
+        self.restat().log();
+
+        match status {
+            Ok(status) if status.success() => {
+                self.core.show_status(&format!("{} done", cmd))
+            }
+            Ok(status) => {
+                self.core.show_status(&format!("{}\"{} {}\" exited with {}",
+                                               term::color_red(),
+                                               sudo,
+                                               cmd,
+                                               status))
+            }
+            Err(err) => {
+                self.core.show_status(&format!("{}Can't run \"{}\": {}",
+                                               term::color_red(),
+                                               sudo,
+                                               err))
             }
         }
+    }
+
+    // Raw mode swallows ctrl-z, so the signal is sent by hand. Stopping
+    // and continuing is taken care of by the signal thread.
+    pub fn suspend_to_shell(&mut self) -> HResult<()> {
+        use nix::sys::signal::{kill, SIGTSTP};
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+        kill(nix::unistd::getpid(), SIGTSTP)?;
         Ok(())
     }
 
+    pub fn trace_redraws(&mut self) -> HResult<()> {
+        match crate::redraw::toggle() {
+            true => self.core.show_status("Tracing redraws, see the log"),
+            false => self.core.show_status("Stopped tracing redraws")
+        }
+    }
+
     pub fn show_log(&mut self) -> HResult<()> {
         self.preview_widget().map(|preview| preview.cancel_animation()).log();
         loop {
@@ -1371,6 +3555,105 @@ impl FileBrowser {
         Ok(())
     }
 
+    // Picking an entry shows the file where it ended up
+    pub fn show_audit_log(&mut self) -> HResult<()> {
+        let entries = crate::audit::recent().unwrap_or_default();
+
+        if entries.is_empty() {
+            return self.core.show_status("Nothing in the audit log yet");
+        }
+
+        let targets = entries.iter()
+            .map(|entry| {
+                let kind = format!("{} {}", entry.time, entry.action);
+                Target::new(&kind, entry.location())
+            })
+            .collect::<Vec<_>>();
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let query = format!("last {} file operations", targets.len());
+        let mut picker = TargetPicker::new(&self.core, &query, targets);
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        if picker.prompt { return Ok(()); }
+        let path = picker.picked?;
+
+        // Deleted, or moved on since then
+        if path.symlink_metadata().is_err() {
+            let dir = File::new_from_path(path.parent()?)?;
+            self.main_widget_goto(&dir)?;
+            let status = format!("{} isn't there anymore", path.short_string());
+            return self.core.show_status(&status);
+        }
+
+        let file = File::new_from_path(&path)?;
+        let dir = file.parent_as_file()?;
+        self.fs_cache.set_selection(dir.clone(), file).log();
+        self.main_widget_goto(&dir)
+    }
+
+    pub fn edit_note(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent;
+
+        let file = self.selected_file()?;
+        let note = crate::notes::get(&file.path).unwrap_or_default();
+
+        let note = match self.core.minibuffer_prefilled("note", &note) {
+            Ok(note) => note,
+            // Emptying it removes the note
+            Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => String::new(),
+            Err(err) => return Err(err)
+        };
+
+        crate::notes::set(&file.path, &note)?;
+        self.core.set_dirty();
+        Ok(())
+    }
+
+    pub fn search_notes(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent;
+
+        let query = match self.core.minibuffer("search notes") {
+            Ok(query) => query,
+            Err(HError::MiniBufferEvent(MiniBufferEvent::Empty)) => String::new(),
+            Err(err) => return Err(err)
+        };
+
+        let found = crate::notes::search(&query);
+        if found.is_empty() {
+            let status = match query.is_empty() {
+                true => String::from("No notes yet"),
+                false => format!("No notes with {}", query)
+            };
+            return self.core.show_status(&status);
+        }
+
+        let targets = found.into_iter()
+            .map(|(path, note)| {
+                let note = term::sized_string_u(&note, 30);
+                Target::new(note.trim_end(), path)
+            })
+            .collect::<Vec<_>>();
+        let query = format!("{} notes", targets.len());
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut picker = TargetPicker::new(&self.core, &query, targets);
+        picker.set_coordinates(&self.core.coordinates).log();
+
+        self.show_popup(&mut picker);
+
+        if picker.prompt { return Ok(()); }
+
+        let file = File::new_from_path(&picker.picked?)?;
+        let dir = file.parent_as_file()?;
+        self.fs_cache.set_selection(dir.clone(), file).log();
+        self.main_widget_goto(&dir)
+    }
+
     pub fn quick_action(&self) -> HResult<()> {
         let files = self.selected_files()?;
         let files = if files.len() > 0 { files }
@@ -1397,6 +3680,9 @@ impl FileBrowser {
         let user = file.pretty_user().unwrap_or("NOUSER".into());
         let group = file.pretty_group().unwrap_or("NOGROUP".into());
         let mtime = file.pretty_mtime().unwrap_or("NOMTIME".into());
+        let context = crate::selinux::context(&file.path)
+            .map(|context| format!(" {}{}", term::normal_color(), context))
+            .unwrap_or_default();
         let target = if let Some(target) = &file.target {
             "--> ".to_string() + &target.short_string()
         } else { "".to_string() };
@@ -1410,7 +3696,7 @@ impl FileBrowser {
                                  selection,
                                  file_count,
                                  digits = digits);
-        let count_xpos = xsize - file_count.len() as u16;
+        let count_xpos = xsize.saturating_sub(file_count.len() as u16);
         let count_ypos = ypos + self.get_coordinates()?.ysize();
 
         let fs = self.fs_stat
@@ -1426,12 +3712,72 @@ impl FileBrowser {
                             free_space,
                             total_space);
 
-        let space_xpos = count_xpos - space.len() as u16 - 5; // - 3;
+        // Shown in red, nothing can be changed in here
+        let blocker = crate::stats::write_blocker(&self.cwd.path);
+        let (space, space_color) = match blocker {
+            Some(reason) => (format!("{} | {}", reason, space), term::color_red()),
+            None => (space, term::color_orange())
+        };
+
+        // Polled directories can be out of date for a while
+        let stale_after = self.core.config().stale_after * 60;
+        let age = self.fs_cache
+            .listed_at(&self.cwd)
+            .and_then(|listed_at| listed_at.elapsed().ok())
+            .filter(|age| stale_after > 0 && age.as_secs() >= stale_after);
+        let (space, space_color) = match age {
+            Some(age) => (format!("listed {}m ago | {}", age.as_secs() / 60, space),
+                          term::color_red()),
+            None => (space, space_color)
+        };
+
+        let space = match main_widget.content.fast {
+            true => format!("fast mode | {}", space),
+            false => space
+        };
+
+        let space = match main_widget.content.reverse {
+            true => format!("sort: {} reversed | {}", main_widget.content.sort, space),
+            false => format!("sort: {} | {}", main_widget.content.sort, space)
+        };
+
+        let space = match main_widget.content.get_filter() {
+            Some(filter) => format!("filter: {} | {}", filter, space),
+            None => space
+        };
+
+        // The rest of huge directories is still coming in
+        let space = match main_widget.content.loading() {
+            Some(loaded) => format!("loading... {} entries | {}", loaded, space),
+            None => space
+        };
+
+        // Lock might be held by the process view, just skip it then
+        let io_rate = self.proc_view
+            .try_lock()
+            .and_then(|proc_view| proc_view.io_rate());
+        let space = match io_rate {
+            Some(io_rate) => format!("{} | {}", io_rate, space),
+            None => space
+        };
+
+        let space = crate::fileops::running()
+            .iter()
+            .fold(space, |space, op| format!("{} | {}", op.status(), space));
+
+        let space = match crate::jobs::active() {
+            0 => space,
+            1 => format!("1 job | {}", space),
+            n => format!("{} jobs | {}", n, space)
+        };
+
+        let (space, space_xpos) = footer_space(&space, count_xpos);
 
-        let status = format!("{} {}:{} {}{} {}{}",
+        let status = format!("{} {}:{}{} {}{} {}{}",
                              permissions,
                              user,
                              group,
+                             context,
                              crate::term::header_color(),
                              mtime,
                              crate::term::color_yellow(),
@@ -1443,7 +3789,7 @@ impl FileBrowser {
                              status,
                              crate::term::header_color(),
                              crate::term::goto_xy(space_xpos, count_ypos),
-                             crate::term::color_orange(),
+                             space_color,
                              space,
                              crate::term::header_color(),
                              file_count);
@@ -1452,6 +3798,33 @@ impl FileBrowser {
     }
 }
 
+// The loop every popup runs in. Refreshes are passed to on_refresh, resizes
+// to on_resize, anything else closes it.
+fn run_popup<W: Widget>(popup: &mut W,
+                        mut on_refresh: impl FnMut(&mut W),
+                        mut on_resize: impl FnMut(&mut W) -> HResult<()>) {
+    loop {
+        match popup.popup() {
+            Err(HError::RefreshParent) => on_refresh(popup),
+            Err(HError::TerminalResizedError) |
+            Err(HError::WidgetResizedError) => on_resize(popup).log(),
+            _ => break
+        }
+    }
+}
+
+// Whatever doesn't fit left of the file count is cut off, along with where
+// it starts. Leaves room for the " | " in front of the count.
+fn footer_space(space: &str, count_xpos: u16) -> (&str, u16) {
+    use crate::unicode_width::UnicodeWidthStr;
+
+    let room = count_xpos.saturating_sub(5);
+    let space = term::sized_string(space, room.saturating_add(1));
+    let xpos = room - space.width() as u16;
+
+    (space, xpos)
+}
+
 impl Widget for FileBrowser {
     fn get_core(&self) -> HResult<&WidgetCore> {
         Ok(&self.core)
@@ -1484,9 +3857,13 @@ impl Widget for FileBrowser {
         let file = self.selected_file()?;
         let name = &file.name;
 
+        let rules = self.core.config().color_rules;
+        let rule_style = crate::colorrules::style_for(&rules, name);
         let fcolor = file.get_color();
 
-        let color = if file.is_dir() {
+        let color = if let Some(style) = rule_style {
+            style.to_string()
+        } else if file.is_dir() {
             crate::term::highlight_color() }
         else {
             match fcolor {
@@ -1503,7 +3880,18 @@ impl Widget for FileBrowser {
         if &path == "/" { path.pop(); }
 
 
-        let pretty_path = format!("{}/{}{}", path, &color, name );
+        // Without a note of its own, the directory's note shows up
+        let note = crate::notes::get(&file.path)
+            .or_else(|| crate::notes::get(&self.cwd.path))
+            .map(|note| format!("  {}{}", term::color_cyan(), note))
+            .unwrap_or_default();
+
+        let style_end = match rule_style {
+            Some(_) => crate::colorrules::STYLE_END,
+            None => ""
+        };
+
+        let pretty_path = format!("{}/{}{}{}{}", path, &color, name, style_end, note);
         let sized_path = crate::term::sized_string(&pretty_path, xsize);
         Ok(sized_path.to_string())
     }
@@ -1567,6 +3955,12 @@ impl Widget for FileBrowser {
                     Ok(_) => {
                         self.save_tab_settings()?;
                     }
+                    // The list leaves pasting to us after the second press
+                    Err(HError::WidgetUndefinedKeyError{..})
+                        if matches!(self.core.config().keybinds.filelist.get(key),
+                                    Some(crate::keybind::FileListAction::Paste)) => {
+                        self.paste_files()?
+                    }
                     Err(HError::WidgetUndefinedKeyError{..}) => {
                         self.preview_widget_mut()?.on_key(key)?
                     }
@@ -1579,6 +3973,37 @@ impl Widget for FileBrowser {
         if !self.columns.zoom_active { self.update_preview().log(); }
         Ok(())
     }
+
+    fn on_mouse(&mut self, event: MouseEvent) -> HResult<()> {
+        let (button, xpos, ypos) = match event {
+            MouseEvent::Press(button, xpos, ypos) => (button, xpos, ypos),
+            _ => return Ok(())
+        };
+
+        match (button, self.columns.active) {
+            // Zoomed preview scrolls by itself
+            (MouseButton::WheelUp, Some(2)) => {
+                for _ in 0..3 { self.columns.active_widget_mut()?.on_key(Key::Up)?; }
+            }
+            (MouseButton::WheelDown, Some(2)) => {
+                for _ in 0..3 { self.columns.active_widget_mut()?.on_key(Key::Down)?; }
+            }
+            (MouseButton::WheelUp, _) => self.movement(&Movement::Up(3))?,
+            (MouseButton::WheelDown, _) => self.movement(&Movement::Down(3))?,
+            (MouseButton::Left, _) => {
+                match self.column_at(xpos) {
+                    Some(0) => self.click_left_column(ypos)?,
+                    Some(1) => self.click_main_column(ypos)?,
+                    Some(2) => self.click_preview(ypos)?,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        if !self.columns.zoom_active { self.update_preview().log(); }
+        Ok(())
+    }
 }
 
 use crate::keybind::{Acting, Bindings, FileBrowserAction, Movement};
@@ -1625,13 +4050,58 @@ impl Acting for FileBrowser {
             ShowBookmarks => self.goto_bookmark()?,
             AddBookmark => self.add_bookmark()?,
             ShowProcesses => self.show_procview()?,
+            ShowJobs => self.show_jobs()?,
             ShowLog => self.show_log()?,
+            TraceRedraws => self.trace_redraws()?,
             ShowQuickActions => self.quick_action()?,
             RunSubshell => self.run_subshell()?,
             ToggleColumns => self.toggle_colums(),
             ZoomPreview => self.zoom_preview(),
+            QuickLook => self.quick_look()?,
+            Slideshow => self.slideshow()?,
+            TreeView => self.tree_view()?,
+            FuzzyFind => self.fuzzy_find()?,
+            ScrubVideo => self.scrub_video()?,
+            FlatView => self.toggle_flat_view()?,
             // Tab implementation needs to call exec_cmd because ALL files are needed
-            ExecCmd => Err(HError::FileBrowserNeedTabFiles)?
+            ExecCmd => Err(HError::FileBrowserNeedTabFiles)?,
+            QuickEnter => self.quick_enter()?,
+            ShowBasket => self.show_basket()?,
+            DeferNextJob => self.defer_next_job()?,
+            Flatten => self.flatten_dir()?,
+            Organize => self.organize_files()?,
+            NewFromTemplate => self.new_from_template()?,
+            PasteNewFile => self.paste_new_file()?,
+            MountImage => self.mount_image()?,
+            PackArchive => self.pack_archive()?,
+            BrowseContainerImage => self.browse_container_image()?,
+            BrowseSsh => self.browse_ssh()?,
+            BrowseHttp => self.browse_http()?,
+            BrowseS3 => self.browse_s3()?,
+            Sanitize => self.sanitize_names()?,
+            Renumber => self.renumber_files()?,
+            UndoBatch => self.undo_batch()?,
+            RegexRename => self.regex_rename()?,
+            Suspend => self.suspend_to_shell()?,
+            Rescan => self.rescan()?,
+            Restat => self.restat()?,
+            ReloadDir => self.reload_dir()?,
+            Chmod(key) => self.chmod_preset(*key)?,
+            Chown => self.chown()?,
+            Relabel => self.relabel()?,
+            FileHistory => self.file_history()?,
+            ShowHardlinks => self.show_hardlinks()?,
+            OpenFileManager => self.open_file_manager()?,
+            OpenTerminal => self.open_terminal()?,
+            BulkRename => self.bulk_rename()?,
+            ExportSetup => self.export_setup()?,
+            ImportSetup => self.import_setup()?,
+            SwitchKeyPreset => self.switch_key_preset()?,
+            ShowAuditLog => self.show_audit_log()?,
+            EditNote => self.edit_note()?,
+            SearchNotes => self.search_notes()?,
+            ChmodTo => self.chmod_to()?,
+            SetMaxFps => self.set_max_fps()?
         }
         Ok(())
     }
@@ -1646,3 +4116,26 @@ impl PartialEq for FileBrowser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footer_space_fits() {
+        assert_eq!(footer_space("10G free", 40), ("10G free", 27));
+    }
+
+    #[test]
+    fn footer_space_is_cut_off() {
+        assert_eq!(footer_space("10G free", 8), ("10G", 0));
+        // Wide characters aren't split
+        assert_eq!(footer_space("日本語", 10), ("日本", 1));
+    }
+
+    #[test]
+    fn footer_space_without_room() {
+        assert_eq!(footer_space("10G free", 5), ("", 0));
+        assert_eq!(footer_space("10G free", 0), ("", 0));
+    }
+}
@@ -0,0 +1,778 @@
+use parking_lot::Mutex;
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+
+use crate::files::{size_string, Excludes};
+use crate::jobs::Job;
+use crate::proclist::Schedule;
+use crate::transfer::NameRules;
+use crate::widget::Events;
+
+
+// Copying, moving and deleting files right here instead of running cp,
+// mv or rm. Each operation gets its own thread and keeps track of how
+// many bytes it got through in its job, so the footer can show how fast it
+// goes. Pausing and cancelling happens through the job too.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OpKind {
+    Copy,
+    Move,
+    Delete
+}
+
+impl std::fmt::Display for OpKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self {
+            OpKind::Copy => "copy",
+            OpKind::Move => "move",
+            OpKind::Delete => "delete"
+        };
+        f.write_str(kind)
+    }
+}
+
+// What to do when a pasted file's name is already taken
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Resolution {
+    Overwrite,
+    Skip,
+    // Goes next to it, with a number added to the name
+    Rename,
+    // Only replaces it when the pasted one was changed more recently
+    IfNewer,
+}
+
+// The same things copies from the basket get asked about
+#[derive(Debug, Default)]
+pub struct Options {
+    pub excludes: Excludes,
+    // Names are fixed for the target's filesystem when set
+    pub rules: Option<NameRules>,
+    pub skip_identical: bool,
+    // Moves to other filesystems compare the copy before removing anything
+    pub verify: bool,
+    pub when: Option<Schedule>,
+}
+
+#[derive(Debug)]
+pub struct FileOp {
+    pub kind: OpKind,
+    pub sources: Vec<PathBuf>,
+    pub target: Option<PathBuf>,
+    // By source, sources in the way of something without one are skipped
+    pub resolutions: HashMap<PathBuf, Resolution>,
+    pub options: Options,
+    pub job: Arc<Job>,
+    pub errors: Mutex<Vec<String>>,
+    // New names from the name rules, by source
+    renames: Mutex<HashMap<PathBuf, OsString>>,
+    skipped: AtomicUsize,
+}
+
+lazy_static! {
+    // Yanked or cut files, waiting to be pasted
+    static ref REGISTER: Mutex<Option<(OpKind, Vec<PathBuf>)>> = Mutex::new(None);
+    static ref OPS: Mutex<Vec<Arc<FileOp>>> = Mutex::new(vec![]);
+}
+
+pub fn yank(kind: OpKind, paths: Vec<PathBuf>) {
+    *REGISTER.lock() = Some((kind, paths));
+}
+
+pub fn register() -> Option<(OpKind, Vec<PathBuf>)> {
+    REGISTER.lock().clone()
+}
+
+pub fn clear_register() {
+    *REGISTER.lock() = None;
+}
+
+// Sources and the files in target they would run into when pasted there
+pub fn conflicts(sources: &[PathBuf], target: &Path) -> Vec<(PathBuf, PathBuf)> {
+    sources.iter()
+        .filter_map(|source| {
+            let existing = target.join(source.file_name()?);
+            match &existing != source && existing.symlink_metadata().is_ok() {
+                true => Some((source.clone(), existing)),
+                false => None
+            }
+        })
+        .collect()
+}
+
+pub fn is_newer(path: &Path, than: &Path) -> bool {
+    let mtime = |path: &Path| path.symlink_metadata().and_then(|meta| meta.modified());
+
+    match (mtime(path), mtime(than)) {
+        (Ok(mtime), Ok(other)) => mtime > other,
+        _ => false
+    }
+}
+
+pub fn running() -> Vec<Arc<FileOp>> {
+    OPS.lock()
+        .iter()
+        .filter(|op| !op.job.is_finished())
+        .cloned()
+        .collect()
+}
+
+impl FileOp {
+    fn new(kind: OpKind,
+           sources: Vec<PathBuf>,
+           target: Option<PathBuf>,
+           resolutions: HashMap<PathBuf, Resolution>,
+           options: Options) -> FileOp {
+        let description = match &target {
+            Some(target) => format!("{} {} files to {}",
+                                    kind,
+                                    sources.len(),
+                                    target.to_string_lossy()),
+            None => format!("{} {} files", kind, sources.len())
+        };
+
+        FileOp {
+            kind,
+            sources,
+            target,
+            resolutions,
+            options,
+            job: crate::jobs::add(description, None),
+            errors: Mutex::new(vec![]),
+            renames: Mutex::new(HashMap::new()),
+            skipped: AtomicUsize::new(0)
+        }
+    }
+
+    // Where source goes in dir, under a fixed name if it needed one
+    fn dest(&self, source: &Path, dir: &Path) -> Option<PathBuf> {
+        let name = match self.renames.lock().get(source) {
+            Some(name) => name.clone(),
+            None => source.file_name()?.to_os_string()
+        };
+        Some(dir.join(name))
+    }
+
+    // Holds off until it's due, unless it's cancelled meanwhile
+    fn wait_until_due(&self) -> bool {
+        loop {
+            let due = match self.options.when {
+                None => true,
+                Some(Schedule::At(time)) => time <= chrono::Local::now(),
+                // Only earlier jobs, so two of these don't wait for each other
+                Some(Schedule::AfterRunning) => {
+                    !crate::jobs::all()
+                        .iter()
+                        .any(|job| job.id < self.job.id && !job.is_finished())
+                }
+            };
+
+            if due { return true; }
+            if self.job.state() == crate::jobs::JobState::Cancelled { return false; }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    }
+
+    fn add_done(&self, bytes: u64) {
+        self.job.done.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // Like "copy 2 files 45% 12M/s 0:42"
+    pub fn status(&self) -> String {
+        let eta = match self.job.eta() {
+            Some(eta) => format!(" {}:{:02}", eta / 60, eta % 60),
+            None => String::new()
+        };
+
+        format!("{} {} files {}% {}/s{}",
+                self.kind,
+                self.sources.len(),
+                self.job.percent(),
+                size_string(self.job.rate()),
+                eta)
+    }
+
+    // Blocks while paused, errors out when cancelled
+    fn proceed(&self) -> std::io::Result<()> {
+        match self.job.proceed() {
+            true => Ok(()),
+            false => Err(std::io::Error::new(std::io::ErrorKind::Interrupted,
+                                             "cancelled"))
+        }
+    }
+
+    fn error(&self, path: &Path, err: std::io::Error) {
+        // Not really an error, and it would be there for every file left
+        if err.kind() == std::io::ErrorKind::Interrupted { return; }
+
+        self.errors
+            .lock()
+            .push(format!("{}: {}", path.to_string_lossy(), err));
+    }
+}
+
+fn total_size(path: &Path) -> u64 {
+    let meta = match path.symlink_metadata() {
+        Ok(meta) => meta,
+        Err(_) => return 0
+    };
+
+    if !meta.is_dir() { return meta.len(); }
+
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .map(|entry| total_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn copy_file(op: &FileOp,
+             source: &Path,
+             target: &Path,
+             meta: &std::fs::Metadata) -> std::io::Result<()> {
+    let mut input = std::fs::File::open(source)?;
+    let mut output = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+    let mut buffer = vec![0; 1 << 20];
+
+    let mut copy = || -> std::io::Result<()> {
+        loop {
+            op.proceed()?;
+            let len = input.read(&mut buffer)?;
+            if len == 0 { break; }
+            output.write_all(&buffer[..len])?;
+            op.add_done(len as u64);
+        }
+
+        output.set_permissions(meta.permissions())
+    };
+
+    // Half a file is worse than none
+    let result = copy();
+    if result.is_err() {
+        std::fs::remove_file(target).ok();
+    }
+    result
+}
+
+fn copy_path(op: &FileOp, source: &Path, target: &Path) -> std::io::Result<()> {
+    op.proceed()?;
+    let meta = source.symlink_metadata()?;
+    let file_type = meta.file_type();
+
+    if file_type.is_dir() {
+        std::fs::create_dir(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            if op.options.excludes.matches(&entry.file_name()) { continue; }
+
+            let path = entry.path();
+            let dest = op.dest(&path, target)
+                .unwrap_or_else(|| target.join(entry.file_name()));
+            match copy_path(op, &path, &dest) {
+                // The rest would only be cancelled too
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => return Err(err),
+                result => result.unwrap_or_else(|err| op.error(&path, err))
+            }
+        }
+        std::fs::set_permissions(target, meta.permissions())
+    } else if file_type.is_symlink() {
+        let link = std::fs::read_link(source)?;
+        std::os::unix::fs::symlink(link, target)
+    } else {
+        copy_file(op, source, target, &meta)
+    }
+}
+
+fn remove_path(op: &FileOp, path: &Path) -> std::io::Result<()> {
+    op.proceed()?;
+    let meta = path.symlink_metadata()?;
+
+    if meta.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            remove_path(op, &entry?.path())?;
+        }
+        std::fs::remove_dir(path)
+    } else {
+        std::fs::remove_file(path)?;
+        op.add_done(meta.len());
+        Ok(())
+    }
+}
+
+fn move_path(op: &FileOp, source: &Path, target: &Path) -> std::io::Result<()> {
+    match std::fs::rename(source, target) {
+        Ok(()) => Ok(()),
+        // Other filesystem, has to be copied
+        Err(ref err) if err.raw_os_error() == Some(libc::EXDEV) => {
+            // Anything there would be taken for a failed copy below
+            if target.symlink_metadata().is_ok() {
+                return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists,
+                                               "already exists"));
+            }
+
+            let errors = op.errors.lock().len();
+
+            let copied = copy_path(op, source, target).and_then(|_| {
+                // Whatever didn't make it over would be gone for good
+                if op.errors.lock().len() > errors {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                   "copy incomplete, left in place"));
+                }
+                op.proceed()?;
+
+                match !op.options.verify || same_tree(source, target) {
+                    true => Ok(()),
+                    false => Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                                     "copy differs, left in place"))
+                }
+            });
+
+            // The source is still all there, so the copy can go
+            if let Err(err) = copied {
+                remove_copy(target);
+                return Err(err);
+            }
+
+            // Nothing to count a second time. The copy is complete, so
+            // whatever can't be removed is just left over.
+            let done = op.job.done.load(Ordering::Relaxed);
+            remove_path(op, source).unwrap_or_else(|err| op.error(source, err));
+            op.job.done.store(done, Ordering::Relaxed);
+            Ok(())
+        }
+        Err(err) => Err(err)
+    }
+}
+
+// Only for what copy_path made, which never starts on anything that exists
+fn remove_copy(path: &Path) {
+    match path.symlink_metadata().map(|meta| meta.is_dir()) {
+        Ok(true) => std::fs::remove_dir_all(path).ok(),
+        Ok(false) => std::fs::remove_file(path).ok(),
+        Err(_) => None
+    };
+}
+
+// Like diff -r, for checking copies before the originals go away
+fn same_tree(source: &Path, target: &Path) -> bool {
+    let (meta, target_meta) = match (source.symlink_metadata(), target.symlink_metadata()) {
+        (Ok(meta), Ok(target_meta)) => (meta, target_meta),
+        _ => return false
+    };
+
+    if meta.is_dir() {
+        let names = |dir: &Path| -> Option<Vec<OsString>> {
+            let mut names = std::fs::read_dir(dir).ok()?
+                .map(|entry| entry.map(|entry| entry.file_name()))
+                .collect::<std::io::Result<Vec<_>>>()
+                .ok()?;
+            names.sort();
+            Some(names)
+        };
+
+        match (target_meta.is_dir(), names(source), names(target)) {
+            (true, Some(names), Some(target_names)) if names == target_names => {
+                names.iter()
+                    .all(|name| same_tree(&source.join(name), &target.join(name)))
+            }
+            _ => false
+        }
+    } else if meta.file_type().is_symlink() {
+        match (std::fs::read_link(source), std::fs::read_link(target)) {
+            (Ok(link), Ok(target_link)) => link == target_link,
+            _ => false
+        }
+    } else {
+        crate::transfer::identical(source, target)
+    }
+}
+
+// Like "notes_1.txt" when "notes.txt" is taken
+pub fn free_name(path: &Path) -> PathBuf {
+    let stem = path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path.extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{}_{}{}", stem, n, ext)))
+        .find(|candidate| candidate.symlink_metadata().is_err())
+        .unwrap()
+}
+
+// Where overwriting files go first, like ".notes.txt.hunter"
+fn temp_name(target: &Path) -> PathBuf {
+    let name = target.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = target.with_file_name(format!(".{}.hunter", name));
+
+    match temp.symlink_metadata() {
+        Ok(_) => free_name(&temp),
+        Err(_) => temp
+    }
+}
+
+// Finds a place for source when target is taken, along with what it
+// replaces once it's all there. None when it should be left alone.
+fn resolve(resolution: Resolution,
+           source: &Path,
+           target: PathBuf) -> std::io::Result<Option<(PathBuf, Option<PathBuf>)>> {
+    match resolution {
+        Resolution::Skip => Ok(None),
+        Resolution::Rename => Ok(Some((free_name(&target), None))),
+        Resolution::IfNewer if !is_newer(source, &target) => Ok(None),
+        // Removing target would take source with it
+        _ if source.starts_with(&target) => {
+            Err(std::io::Error::new(std::io::ErrorKind::Other,
+                                    "can't replace something it's in"))
+        }
+        _ => Ok(Some((temp_name(&target), Some(target))))
+    }
+}
+
+// The old one only goes away when the new one is complete
+fn replace(new: &Path, old: &Path) -> std::io::Result<()> {
+    match old.symlink_metadata()?.is_dir() {
+        true => std::fs::remove_dir_all(old)?,
+        false => std::fs::remove_file(old)?
+    }
+    crate::audit::record("overwrite", old, None);
+    std::fs::rename(new, old)
+}
+
+fn run(op: &FileOp) {
+    for source in &op.sources {
+        if op.proceed().is_err() { break; }
+
+        let result = match (op.kind, &op.target) {
+            (OpKind::Delete, _) => {
+                remove_path(op, source)
+                    .map(|_| crate::audit::record("delete", source, None))
+            }
+            (kind, Some(target)) => {
+                let target = match op.dest(source, target) {
+                    Some(target) => target,
+                    None => continue
+                };
+
+                // Pasting cut files in the same place
+                if &target == source { continue; }
+
+                if target.starts_with(source) {
+                    let err = std::io::Error::new(std::io::ErrorKind::Other,
+                                                  "can't go into itself");
+                    op.error(source, err);
+                    continue;
+                }
+
+                if op.options.skip_identical && crate::transfer::identical(source, &target) {
+                    op.skipped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+
+                // Nothing gets overwritten without asking first
+                let (dest, replaces) = match (target.symlink_metadata(),
+                                              op.resolutions.get(source)) {
+                    (Err(_), _) => (target, None),
+                    (Ok(_), Some(resolution)) => {
+                        match resolve(*resolution, source, target.clone()) {
+                            Ok(Some(place)) => place,
+                            Ok(None) => continue,
+                            Err(err) => {
+                                op.error(&target, err);
+                                continue;
+                            }
+                        }
+                    }
+                    (Ok(_), None) => {
+                        let err = std::io::Error::new(std::io::ErrorKind::AlreadyExists,
+                                                      "already exists");
+                        op.error(&target, err);
+                        continue;
+                    }
+                };
+
+                let result = match kind {
+                    OpKind::Move => move_path(op, source, &dest),
+                    _ => copy_path(op, source, &dest)
+                };
+
+                // A partial copy is no use, failed moves clean up themselves
+                if result.is_err() && kind == OpKind::Copy && replaces.is_some() {
+                    remove_copy(&dest);
+                }
+
+                let target = replaces.as_ref().unwrap_or(&dest);
+                result.and_then(|_| match &replaces {
+                    Some(old) => replace(&dest, old),
+                    None => Ok(())
+                }).map(|_| crate::audit::record(&kind.to_string(), source, Some(target)))
+            }
+            _ => continue
+        };
+
+        result.unwrap_or_else(|err| op.error(source, err));
+    }
+}
+
+pub fn start(kind: OpKind,
+             sources: Vec<PathBuf>,
+             target: Option<PathBuf>,
+             resolutions: HashMap<PathBuf, Resolution>,
+             options: Options,
+             sender: Sender<Events>) -> Arc<FileOp> {
+    let op = Arc::new(FileOp::new(kind, sources, target, resolutions, options));
+
+    {
+        let mut ops = OPS.lock();
+        ops.retain(|op| !op.job.is_finished());
+        ops.push(op.clone());
+    }
+
+    let thread_op = op.clone();
+
+    std::thread::spawn(move || {
+        let op = thread_op;
+
+        if !op.wait_until_due() {
+            op.job.finish(false);
+            return;
+        }
+
+        if let (Some(rules), Some(target)) = (op.options.rules, &op.target) {
+            match crate::transfer::fixed_names(&op.sources, target, rules) {
+                Ok(renames) => *op.renames.lock() = renames,
+                Err(err) => op.errors.lock().push(format!("Checking names: {}", err))
+            }
+        }
+
+        // Keeps the footer up to date
+        crate::files::start_ticking(sender.clone());
+
+        // Moves on the same filesystem are just renames, nothing to count
+        let total = op.sources
+            .iter()
+            .filter(|source| match (op.kind, &op.target) {
+                (OpKind::Move, Some(target)) => {
+                    crate::transfer::crosses_device(&[source.to_path_buf()], target)
+                }
+                _ => true
+            })
+            .map(|source| total_size(source))
+            .sum::<u64>();
+        op.job.total.store(total, Ordering::Relaxed);
+
+        run(&op);
+
+        let errors = op.errors.lock();
+        op.job.finish(errors.is_empty());
+        crate::files::stop_ticking();
+
+        for error in errors.iter() {
+            crate::fail::HError::log::<()>(error).ok();
+        }
+
+        let status = match errors.len() {
+            _ if op.job.state() == crate::jobs::JobState::Cancelled => {
+                format!("{} of {} files cancelled", op.kind, op.sources.len())
+            }
+            0 => format!("{} of {} files done", op.kind, op.sources.len()),
+            n => format!("{} of {} files done, {} errors (see log)",
+                         op.kind,
+                         op.sources.len(),
+                         n)
+        };
+        let status = match op.skipped.load(Ordering::Relaxed) {
+            0 => status,
+            n => format!("{}, {} identical skipped", status, n)
+        };
+        sender.send(Events::Status(status)).ok();
+    });
+
+    op
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory with these files in it, a trailing / makes a directory
+    fn scratch(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("hunter-test-{}-fileops-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for file in files {
+            let path = dir.join(file);
+            match file.ends_with('/') {
+                true => std::fs::create_dir_all(&path).unwrap(),
+                false => {
+                    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    std::fs::write(&path, file).unwrap();
+                }
+            }
+        }
+
+        dir
+    }
+
+    fn content(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap_or_default()
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names = std::fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn paste(kind: OpKind,
+             sources: &[PathBuf],
+             target: &Path,
+             resolution: Option<Resolution>) -> Vec<String> {
+        let resolutions = sources.iter()
+            .filter_map(|source| Some((source.clone(), resolution?)))
+            .collect();
+        let op = FileOp::new(kind,
+                             sources.to_vec(),
+                             Some(target.to_path_buf()),
+                             resolutions,
+                             Options::default());
+
+        run(&op);
+        op.job.finish(true);
+
+        let errors = op.errors.lock().clone();
+        errors
+    }
+
+    #[test]
+    fn free_names() {
+        let dir = scratch("free", &["notes.txt", "notes_1.txt", "dir/"]);
+
+        assert_eq!(free_name(&dir.join("notes.txt")), dir.join("notes_2.txt"));
+        assert_eq!(free_name(&dir.join("dir")), dir.join("dir_1"));
+        assert_eq!(temp_name(&dir.join("notes.txt")), dir.join(".notes.txt.hunter"));
+
+        std::fs::write(dir.join(".notes.txt.hunter"), "").unwrap();
+        assert_eq!(temp_name(&dir.join("notes.txt")), dir.join(".notes.txt_1.hunter"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn conflicts_with_existing_files() {
+        let dir = scratch("conflicts", &["src/a", "src/b", "dst/b", "dst/c"]);
+        let sources = vec![dir.join("src/a"), dir.join("src/b"), dir.join("dst/c")];
+
+        // Pasting something where it already is doesn't count
+        assert_eq!(conflicts(&sources, &dir.join("dst")),
+                   vec![(dir.join("src/b"), dir.join("dst/b"))]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn nothing_overwritten_without_asking() {
+        let dir = scratch("ask", &["src/a", "dst/a"]);
+
+        let errors = paste(OpKind::Move, &[dir.join("src/a")], &dir.join("dst"), None);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(content(&dir.join("src/a")), "src/a");
+        assert_eq!(content(&dir.join("dst/a")), "dst/a");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn overwrite_copies() {
+        let dir = scratch("overwrite", &["src/a", "dst/a"]);
+
+        let errors = paste(OpKind::Copy,
+                           &[dir.join("src/a")],
+                           &dir.join("dst"),
+                           Some(Resolution::Overwrite));
+
+        assert!(errors.is_empty());
+        assert_eq!(content(&dir.join("dst/a")), "src/a");
+        assert_eq!(content(&dir.join("src/a")), "src/a");
+        // Nothing left from the way there
+        assert_eq!(names(&dir.join("dst")), vec!["a"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn overwrite_moves_dirs() {
+        let dir = scratch("overwrite-dir", &["src/d/new", "dst/d/old"]);
+
+        let errors = paste(OpKind::Move,
+                           &[dir.join("src/d")],
+                           &dir.join("dst"),
+                           Some(Resolution::Overwrite));
+
+        assert!(errors.is_empty());
+        assert_eq!(names(&dir.join("dst/d")), vec!["new"]);
+        assert_eq!(names(&dir.join("dst")), vec!["d"]);
+        assert!(names(&dir.join("src")).is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn rename_and_skip() {
+        let dir = scratch("rename", &["src/a.txt", "src/b", "dst/a.txt", "dst/b"]);
+
+        let errors = paste(OpKind::Copy,
+                           &[dir.join("src/a.txt")],
+                           &dir.join("dst"),
+                           Some(Resolution::Rename));
+        assert!(errors.is_empty());
+
+        let errors = paste(OpKind::Move,
+                           &[dir.join("src/b")],
+                           &dir.join("dst"),
+                           Some(Resolution::Skip));
+        assert!(errors.is_empty());
+
+        assert_eq!(names(&dir.join("dst")), vec!["a.txt", "a_1.txt", "b"]);
+        assert_eq!(content(&dir.join("dst/a_1.txt")), "src/a.txt");
+        assert_eq!(content(&dir.join("dst/b")), "dst/b");
+        assert_eq!(content(&dir.join("src/b")), "src/b");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn no_overwriting_what_its_in() {
+        let dir = scratch("inside", &["d/d"]);
+
+        // d/d would replace d and take itself with it
+        let errors = paste(OpKind::Move,
+                           &[dir.join("d/d")],
+                           &dir,
+                           Some(Resolution::Overwrite));
+        assert_eq!(errors.len(), 1);
+
+        // And a directory can't go into itself
+        let errors = paste(OpKind::Copy, &[dir.join("d")], &dir.join("d"), None);
+        assert_eq!(errors.len(), 1);
+
+        assert_eq!(names(&dir.join("d")), vec!["d"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
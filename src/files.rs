@@ -22,7 +22,6 @@ use users::{get_current_username,
 use chrono::TimeZone;
 use failure::Error;
 use rayon::{ThreadPool, ThreadPoolBuilder};
-use natord::compare;
 use mime_guess;
 use rayon::prelude::*;
 use nix::{dir::*,
@@ -56,6 +55,210 @@ pub fn tick_str() -> &'static str {
     }
 }
 
+// Same as sizes in the file list, for anything else showing bytes
+pub fn size_string(size: u64) -> String {
+    let (size, unit) = size_unit(size);
+    format!("{}{}", size, unit)
+}
+
+fn size_unit(mut size: u64) -> (u64, &'static str) {
+    let mut unit = 0;
+    while size > 1024 {
+        size /= 1024;
+        unit += 1;
+    }
+    let unit = match unit {
+        0 => "",
+        1 => " KB",
+        2 => " MB",
+        3 => " GB",
+        4 => " TB",
+        5 => " wtf are you doing",
+        _ => "",
+    };
+
+    (size, unit)
+}
+
+// LS_COLORS for things that aren't a File, like tree view entries
+pub fn color_for_path(path: &Path, meta: &Metadata) -> Option<String> {
+    COLORS.style_for_path_with_metadata(path, Some(meta))?
+        .foreground
+        .as_ref()
+        .map(|c| crate::term::from_lscolor(&c))
+}
+
+fn glob_regex(glob: &str) -> Result<regex::Regex, regex::Error> {
+    let regex = glob.chars()
+        .map(|c| match c {
+            '*' => String::from(".*"),
+            '?' => String::from("."),
+            _ => regex::escape(&c.to_string())
+        })
+        .collect::<String>();
+
+    regex::Regex::new(&format!("^{}$", regex))
+}
+
+// What's typed into the filter: "/..." is a regex, something with * or ?
+// a glob for the whole name and anything else just has to be in the name
+#[derive(Debug, Clone)]
+pub enum NameFilter {
+    Substring(String),
+    Glob(regex::Regex),
+    Regex(regex::Regex)
+}
+
+impl NameFilter {
+    pub fn parse(filter: &str) -> HResult<NameFilter> {
+        let invalid = |err: regex::Error| {
+            HError::Error(format!("Invalid filter {}: {}", filter, err))
+        };
+
+        if filter.starts_with('/') && filter.len() > 1 {
+            let regex = regex::Regex::new(&filter[1..]).map_err(invalid)?;
+            Ok(NameFilter::Regex(regex))
+        } else if filter.contains('*') || filter.contains('?') {
+            Ok(NameFilter::Glob(glob_regex(filter).map_err(invalid)?))
+        } else {
+            Ok(NameFilter::Substring(filter.to_string()))
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NameFilter::Substring(part) => name.contains(part.as_str()),
+            NameFilter::Glob(regex) |
+            NameFilter::Regex(regex) => regex.is_match(name)
+        }
+    }
+}
+
+// Simple globs (only * and ?) matched against file names, used to leave
+// out things like .git or node_modules in recursive operations
+#[derive(Debug, Clone, Default)]
+pub struct Excludes {
+    patterns: Vec<regex::Regex>,
+}
+
+impl Excludes {
+    pub fn new(patterns: &[String]) -> Excludes {
+        let patterns = patterns.iter()
+            .filter_map(|pattern| glob_regex(pattern).ok())
+            .collect();
+
+        Excludes { patterns }
+    }
+
+    // Comma separated, like in the config
+    pub fn parse(list: &str) -> Excludes {
+        let patterns = list.split(',')
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        Excludes::new(&patterns)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+        self.patterns.iter().any(|p| p.is_match(&name))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DuOptions {
+    pub hardlinks_once: bool,
+    pub one_file_system: bool,
+    pub dereference: bool,
+    pub excludes: Excludes,
+}
+
+impl DuOptions {
+    pub fn from_config(config: &crate::config::Config) -> DuOptions {
+        DuOptions {
+            hardlinks_once: config.du_hardlinks_once,
+            one_file_system: config.du_one_file_system,
+            dereference: config.du_dereference,
+            excludes: Excludes::new(&config.exclude)
+        }
+    }
+}
+
+// Recursive size of everything in paths, works like du
+pub fn disk_usage(paths: &[PathBuf], options: DuOptions) -> u64 {
+    disk_usage_cancellable(paths, options, &Stale::new()).unwrap_or(0)
+}
+
+// Gives up once stale is set
+pub fn disk_usage_cancellable(paths: &[PathBuf],
+                              options: DuOptions,
+                              stale: &Stale) -> HResult<u64> {
+    let mut seen = HashSet::new();
+
+    let size = paths.iter()
+        .map(|path| {
+            let dev = match path.metadata() {
+                Ok(meta) => meta.dev(),
+                Err(_) => return 0
+            };
+            du_walk(path, dev, &options, &mut seen, stale)
+        })
+        .sum();
+
+    match stale.is_stale()? {
+        true => HError::stale(),
+        false => Ok(size)
+    }
+}
+
+fn du_walk(path: &Path,
+           root_dev: u64,
+           options: &DuOptions,
+           seen: &mut HashSet<(u64, u64)>,
+           stale: &Stale) -> u64 {
+    if stale.is_stale().unwrap_or(true) { return 0; }
+
+    let meta = match options.dereference {
+        true => std::fs::metadata(path),
+        false => std::fs::symlink_metadata(path)
+    };
+
+    let meta = match meta {
+        Ok(meta) => meta,
+        Err(_) => return 0
+    };
+
+    if options.one_file_system && meta.dev() != root_dev {
+        return 0;
+    }
+
+    // Following symlinks can lead into loops, so directories are tracked too
+    let track = (options.hardlinks_once && meta.nlink() > 1 && !meta.is_dir()) ||
+                (options.dereference && meta.is_dir());
+    if track && !seen.insert((meta.dev(), meta.ino())) {
+        return 0;
+    }
+
+    let mut size = meta.len();
+
+    if meta.is_dir() {
+        if let Ok(entries) = std::fs::read_dir(path) {
+            for entry in entries.flatten() {
+                if options.excludes.matches(&entry.file_name()) { continue; }
+                size += du_walk(&entry.path(), root_dev, options, seen, stale);
+            }
+        }
+    }
+
+    size
+}
+
 pub fn start_ticking(sender: Sender<Events>) {
     use std::time::Duration;
 
@@ -127,11 +330,7 @@ pub fn load_tags() -> HResult<()> {
             import_tags().log();
         }
 
-        let tags = std::fs::read_to_string(tag_path)?;
-        let mut tags = tags.lines()
-                           .map(PathBuf::from)
-                           .collect::<Vec<PathBuf>>();
-        tags.sort();
+        let mut tags = parse_tags(&crate::store::read(&tag_path)?);
         let mut tag_lock = TAGS.write()?;
         tag_lock.0 = true;
         tag_lock.1.append(&mut tags);
@@ -140,6 +339,30 @@ pub fn load_tags() -> HResult<()> {
     Ok(())
 }
 
+// One path per line, sorted for binary_search
+fn parse_tags(content: &[u8]) -> Vec<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut tags = content.split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| PathBuf::from(OsStr::from_bytes(line)))
+        .collect::<Vec<PathBuf>>();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+fn write_tags(tags: &[PathBuf]) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    tags.iter()
+        .fold(Vec::new(), |mut content, tag| {
+            content.extend_from_slice(tag.as_os_str().as_bytes());
+            content.push(b'\n');
+            content
+        })
+}
+
 pub fn import_tags() -> HResult<()> {
     let mut ranger_tags = crate::paths::ranger_path()?;
     ranger_tags.push("tagged");
@@ -151,6 +374,25 @@ pub fn import_tags() -> HResult<()> {
     Ok(())
 }
 
+// Tags from an exported hunter setup are added to the ones already here
+pub fn merge_tags(imported: &Path) -> HResult<()> {
+    let imported = parse_tags(&std::fs::read(imported)?);
+    let tag_path = crate::paths::tagfile_path()?;
+
+    let content = crate::store::update(&tag_path, |content| {
+        let mut tags = parse_tags(content);
+        tags.extend(imported);
+        tags.sort();
+        tags.dedup();
+        write_tags(&tags)
+    })?;
+
+    let mut tag_lock = TAGS.write()?;
+    tag_lock.0 = true;
+    tag_lock.1 = parse_tags(&content);
+    Ok(())
+}
+
 pub fn check_tag(path: &PathBuf) -> HResult<bool> {
     tags_loaded()?;
     let tagged = TAGS.read()?.1.binary_search(path)
@@ -211,6 +453,9 @@ impl RefreshPackage {
         // Save deletions to delete them efficiently later
         let mut deleted_files = HashSet::with_capacity(event_count);
 
+        // Renamed files can end up anywhere, so everything gets sorted again
+        let mut resort = false;
+
         // Stores jobs to asynchronously fetch metadata
         let mut jobs = Vec::with_capacity(event_count);
 
@@ -223,7 +468,7 @@ impl RefreshPackage {
         for event in events.into_iter().stop_stale(stale.clone()) {
             match event {
                 Create(mut file) => {
-                    let job = file.prepare_meta_job(cache);
+                    let job = file.prepare_meta_job(cache, !files.fast);
                     job.map(|j| jobs.push(j));
                     new_files.push(file);
                 }
@@ -239,6 +484,7 @@ impl RefreshPackage {
                         files.files[fpos].rename(&new.path).log();
                         let job = files.files[fpos].refresh_meta_job();
                         jobs.push(job);
+                        resort = true;
                             }
                 }
                 Remove(file) => {
@@ -263,11 +509,16 @@ impl RefreshPackage {
         }
 
         // Finally add all new files
-        files.files.extend(new_files);
+        match resort {
+            true => {
+                files.files.extend(new_files);
+                files.sort();
+            }
+            false => files.merge_sorted(new_files)
+        }
 
         // Files added, removed, renamed to hidden, etc...
         files.recalculate_len();
-        files.sort();
 
         // Need to unpack this to prevent issue with recursive Files type
             // Also, if no files remain add placeholder and set len
@@ -310,9 +561,16 @@ pub struct Files {
     pub sort: SortBy,
     pub dirs_first: bool,
     pub reverse: bool,
+    // file2 before file10, ignoring case
+    pub natural: bool,
     pub show_hidden: bool,
     pub filter: Option<String>,
     pub filter_selected: bool,
+    // Huge directories skip colors and directory sizes, unless asked for
+    pub fast: bool,
+    pub full_meta: bool,
+    // Flat view, with everything in subdirectories this deep too
+    pub flat: Option<usize>,
     pub dirty: DirtyBit,
     #[derivative(Debug="ignore")]
     #[derivative(PartialEq="ignore")]
@@ -325,7 +583,11 @@ pub struct Files {
     #[derivative(Debug="ignore")]
     #[derivative(PartialEq="ignore")]
     #[derivative(Hash="ignore")]
-    pub stale: Option<Stale>
+    pub stale: Option<Stale>,
+    #[derivative(Debug="ignore")]
+    #[derivative(PartialEq="ignore")]
+    #[derivative(Hash="ignore")]
+    pub rest: Option<Arc<DirRest>>
 }
 
 impl Index<usize> for Files {
@@ -364,17 +626,50 @@ impl Default for Files {
             sort: SortBy::Name,
             dirs_first: true,
             reverse: false,
+            natural: true,
             show_hidden: false,
             filter: None,
             filter_selected: false,
+            fast: false,
+            full_meta: false,
+            flat: None,
             dirty: DirtyBit::new(),
             jobs: vec![],
             cache: None,
-            stale: None
+            stale: None,
+            rest: None
+        }
+    }
+}
+
+// What's left of a huge directory after the first batch. The fd is a dup
+// of the one the first batch was read with, so it continues from there.
+pub struct DirRest {
+    fd: i32,
+    path: PathBuf,
+    started: AtomicBool,
+    done: AtomicBool,
+    loaded: AtomicUsize
+}
+
+impl DirRest {
+    fn new(fd: i32, path: &Path, loaded: usize) -> DirRest {
+        DirRest {
+            fd: fd,
+            path: path.to_path_buf(),
+            started: AtomicBool::new(false),
+            done: AtomicBool::new(false),
+            loaded: AtomicUsize::new(loaded)
         }
     }
 }
 
+impl Drop for DirRest {
+    fn drop(&mut self) {
+        nix::unistd::close(self.fd).ok();
+    }
+}
+
 // Stop processing stuff when Files is dropped
 impl Drop for Files {
     fn drop(&mut self) {
@@ -414,8 +709,14 @@ pub struct linux_dirent {
 // TODO: Better handling of file systems/kernels that don't support
 // report the kind of file in d_type. Currently that means calling
 // stat on ALL files and ithrowing away the result. This is wasteful.
+
+// With max_reads it stops early and the fd can be used to read the rest
+// later. The bool says if all entries were read.
 #[cfg(target_os = "linux")]
-pub fn from_getdents(fd: i32, path: &Path, nothidden: &AtomicUsize)  -> Result<Vec<File>, FileError>
+pub fn from_getdents(fd: i32,
+                     path: &Path,
+                     nothidden: &AtomicUsize,
+                     max_reads: Option<usize>)  -> Result<(Vec<File>, bool), FileError>
 {
     use libc::SYS_getdents64;
 
@@ -440,20 +741,32 @@ pub fn from_getdents(fd: i32, path: &Path, nothidden: &AtomicUsize)  -> Result<V
 
 
     let result = crossbeam::scope(|s| {
+        let mut reads = 0;
+        let mut buffer_full = false;
+
         loop {
+            // If the buffer wasn't even full the next read is most
+            // likely the last one, so might as well do it right away
+            if max_reads.map(|max| reads >= max).unwrap_or(false) && buffer_full {
+                break false;
+            }
+            reads += 1;
+
             // Returns number of bytes written to buffer
             let nread = unsafe { libc::syscall(SYS_getdents64, fd, bufptr, BUFFER_SIZE) };
 
             // 0 means done, -1 means an error happened
             if nread == 0 {
-                break;
+                break true;
             } else if nread < 0 {
                 let pathstr = path.to_string_lossy().to_string();
                 HError::log::<()>(&format!("Couldn't read dents from: {}",
                                            &pathstr)).ok();
-                break;
+                break true;
             }
 
+            buffer_full = nread as usize + 4096 > BUFFER_SIZE;
+
             // Clone buffer for parallel processing in another thread
             let mut buf: Vec<u8> = buf.clone();
 
@@ -597,7 +910,7 @@ pub fn from_getdents(fd: i32, path: &Path, nothidden: &AtomicUsize)  -> Result<V
     });
 
     match result {
-        Ok(()) => Ok(std::mem::take(&mut *files.lock().unwrap())),
+        Ok(complete) => Ok((std::mem::take(&mut *files.lock().unwrap()), complete)),
         Err(_) => Err(FileError::GetDents(path.to_string_lossy().to_string()))
     }
 }
@@ -617,7 +930,12 @@ impl Files {
                              Mode::empty())
             .map_err(|e| FileError::OpenDir(e))?;
 
-        let direntries = from_getdents(dir.as_raw_fd(), path, &nonhidden)?;
+        // Only one batch for now, huge directories would freeze
+        // everything. The rest comes in with load_rest()
+        let (direntries, complete) = from_getdents(dir.as_raw_fd(),
+                                                   path,
+                                                   &nonhidden,
+                                                   Some(1))?;
 
         if stale.is_stale()? {
             HError::stale()?;
@@ -631,6 +949,13 @@ impl Files {
         files.len = nonhidden.load(Ordering::Relaxed);
         files.stale = Some(stale);
 
+        if !complete {
+            let fd = nix::unistd::dup(dir.as_raw_fd())
+                .map_err(|e| FileError::OpenDir(e))?;
+            let loaded = files.files.len();
+            files.rest = Some(Arc::new(DirRest::new(fd, path, loaded)));
+        }
+
         Ok(files)
     }
 
@@ -683,13 +1008,14 @@ impl Files {
             Some(cache) => cache,
             None => return
         };
+        let dirsizes = !self.fast;
 
         let mut jobs = self.iter_files_mut()
                            .collect::<Vec<&mut File>>()
                            .into_par_iter()
                            .skip(from)
                            .take(n)
-                           .filter_map(|f| f.prepare_meta_job(&cache))
+                           .filter_map(|f| f.prepare_meta_job(&cache, dirsizes))
                            .collect::<Vec<_>>();
 
         self.jobs.append(&mut jobs);
@@ -740,6 +1066,131 @@ impl Files {
         });
     }
 
+    // Reads the rest of a huge directory in the background. It's added
+    // batch by batch just like newly created files.
+    #[cfg(target_os = "linux")]
+    pub fn load_rest(&self, sender: Sender<Events>) {
+        let rest = match &self.rest {
+            Some(rest) => rest.clone(),
+            None => return
+        };
+
+        if rest.started.swap(true, Ordering::Relaxed) { return; }
+
+        // Stop reading when nobody's looking anymore
+        let pending_events = Arc::downgrade(&self.pending_events);
+
+        std::thread::spawn(move || {
+            loop {
+                let nothidden = AtomicUsize::default();
+                let batch = from_getdents(rest.fd,
+                                          &rest.path,
+                                          &nothidden,
+                                          Some(1));
+
+                let (batch, complete) = match batch {
+                    Ok(batch) => batch,
+                    Err(e) => { e.log(); break; }
+                };
+
+                let pending_events = match pending_events.upgrade() {
+                    Some(pending_events) => pending_events,
+                    None => break
+                };
+
+                rest.loaded.fetch_add(batch.len(), Ordering::Relaxed);
+
+                if batch.len() > 0 {
+                    pending_events.write()
+                        .map(|mut pending| {
+                            pending.extend(batch.into_iter().map(FsEvent::Create))
+                        }).ok();
+                    sender.send(Events::WidgetReady).ok();
+                }
+
+                if complete { break; }
+            }
+
+            rest.done.store(true, Ordering::Relaxed);
+            sender.send(Events::WidgetReady).ok();
+        });
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn load_rest(&self, _sender: Sender<Events>) {}
+
+    // Adds everything in subdirectories up to depth levels down, named by
+    // the path relative to this directory. Comes in batch by batch just
+    // like the rest of a huge directory.
+    pub fn flatten(&mut self, depth: usize, sender: Sender<Events>) {
+        if self.flat.is_some() { return; }
+        self.flat = Some(depth);
+
+        let root = self.directory.path.clone();
+        let subdirs = self.iter_files()
+            .filter(|file| file.is_dir() && file.target.is_none())
+            .map(|file| (file.path.clone(), 1))
+            .collect::<std::collections::VecDeque<_>>();
+
+        // Stop walking when nobody's looking anymore
+        let pending_events = Arc::downgrade(&self.pending_events);
+
+        std::thread::spawn(move || {
+            let mut subdirs = subdirs;
+            let mut batch = vec![];
+
+            while let Some((dir, level)) = subdirs.pop_front() {
+                let entries = match std::fs::read_dir(&dir) {
+                    Ok(entries) => entries,
+                    Err(_) => continue
+                };
+
+                for entry in entries.filter_map(|entry| entry.ok()) {
+                    let path = entry.path();
+                    let name = match path.strip_prefix(&root) {
+                        Ok(name) => name.to_string_lossy().to_string(),
+                        Err(_) => continue
+                    };
+
+                    // Symlinks could go around in circles
+                    let is_dir = entry.file_type()
+                        .map(|kind| kind.is_dir())
+                        .unwrap_or(false);
+                    if is_dir && level < depth {
+                        subdirs.push_back((path.clone(), level + 1));
+                    }
+
+                    let mut file = File::new(&name, path);
+                    file.hidden = name.split('/').any(|part| part.starts_with('.'));
+                    batch.push(file);
+                }
+
+                if batch.len() >= 1000 || subdirs.is_empty() {
+                    let pending_events = match pending_events.upgrade() {
+                        Some(pending_events) => pending_events,
+                        None => return
+                    };
+
+                    pending_events.write()
+                        .map(|mut pending| {
+                            pending.extend(batch.drain(..).map(FsEvent::Create))
+                        }).ok();
+                    sender.send(Events::WidgetReady).ok();
+                }
+            }
+        });
+    }
+
+    // How many entries of the rest were read so far, while still loading
+    pub fn loading(&self) -> Option<usize> {
+        let rest = self.rest.as_ref()?;
+
+        match rest.done.load(Ordering::Relaxed) {
+            true => None,
+            false => Some(rest.loaded.load(Ordering::Relaxed))
+        }
+    }
+
     pub fn recalculate_len(&mut self) {
         self.len = self.par_iter_files().count();
     }
@@ -813,14 +1264,16 @@ impl Files {
 
     #[allow(trivial_bounds)]
     pub fn filter_fn(&self) -> impl Fn(&File) -> bool + 'static {
-        let filter = self.filter.clone();
+        // Invalid ones never get set, see set_filter
+        let filter = self.filter
+            .as_ref()
+            .and_then(|filter| NameFilter::parse(filter).ok());
         let filter_selected = self.filter_selected;
         let show_hidden = self.show_hidden;
 
         move |f| {
             f.kind == Kind::Placeholder ||
-                !(filter.is_some() &&
-                  !f.name.contains(filter.as_ref().unwrap())) &&
+                filter.as_ref().map(|filter| filter.matches(&f.name)).unwrap_or(true) &&
                 (!filter_selected || f.selected) &&
                 !(!show_hidden && f.name.starts_with("."))
         }
@@ -831,6 +1284,7 @@ impl Files {
         use std::cmp::Ordering::*;
 
         let dirs_first = self.dirs_first.clone();
+        let natural = self.natural;
         let sort = self.sort.clone();
 
         let dircmp = move |a: &File, b: &File| {
@@ -849,7 +1303,7 @@ impl Files {
                 false => (a, b),
             };
 
-            compare(&a.name, &b.name)
+            compare(&a.name, &b.name, natural)
         };
 
         let reverse = self.reverse;
@@ -864,7 +1318,7 @@ impl Files {
                     let a_meta = a_meta.as_ref().unwrap();
                     let b_meta = b_meta.as_ref().unwrap();
                     match a_meta.size() == b_meta.size() {
-                        true => compare(&b.name, &a.name),
+                        true => compare(&b.name, &a.name, natural),
                         false => b_meta.size().cmp(&a_meta.size())
                     }
                 }
@@ -884,7 +1338,7 @@ impl Files {
                     let a_meta = a_meta.as_ref().unwrap();
                     let b_meta = b_meta.as_ref().unwrap();
                     match a_meta.mtime() == b_meta.mtime() {
-                        true => compare(&b.name, &a.name),
+                        true => compare(&b.name, &a.name, natural),
                         false => b_meta.mtime().cmp(&a_meta.mtime())
                     }
                 }
@@ -892,6 +1346,67 @@ impl Files {
             }
         };
 
+        // Files without one come first, same extension goes by name
+        let reverse = self.reverse;
+        let extcmp = move |a: &File, b: &File| {
+            let (a, b) = match reverse {
+                true => (b, a),
+                false => (a, b),
+            };
+
+            let ext = |f: &File| {
+                f.path.extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+                    .unwrap_or_default()
+            };
+
+            match ext(a).cmp(&ext(b)) {
+                Equal => compare(&a.name, &b.name, natural),
+                ord @ _ => ord
+            }
+        };
+
+        // By uid, looking up names for every comparison would be too slow
+        let reverse = self.reverse;
+        let ownercmp = move |a: &File, b: &File| {
+            let (a, b) = match reverse {
+                true => (b, a),
+                false => (a, b),
+            };
+
+            match (a.meta(), b.meta()) {
+                (Some(a_meta), Some(b_meta)) => {
+                    let a_meta = a_meta.as_ref().unwrap();
+                    let b_meta = b_meta.as_ref().unwrap();
+                    match a_meta.uid() == b_meta.uid() {
+                        true => compare(&a.name, &b.name, natural),
+                        false => a_meta.uid().cmp(&b_meta.uid())
+                    }
+                }
+                _ => Equal
+            }
+        };
+
+        let reverse = self.reverse;
+        let permcmp = move |a: &File, b: &File| {
+            let (a, b) = match reverse {
+                true => (b, a),
+                false => (a, b),
+            };
+
+            match (a.meta(), b.meta()) {
+                (Some(a_meta), Some(b_meta)) => {
+                    let a_mode = a_meta.as_ref().unwrap().mode() & 0o7777;
+                    let b_mode = b_meta.as_ref().unwrap().mode() & 0o7777;
+                    match a_mode == b_mode {
+                        true => compare(&a.name, &b.name, natural),
+                        false => a_mode.cmp(&b_mode)
+                    }
+                }
+                _ => Equal
+            }
+        };
+
 
         move |a, b| match sort {
             SortBy::Name => {
@@ -912,6 +1427,24 @@ impl Files {
                     ord @ _ => ord
                 }
             }
+            SortBy::Extension => {
+                match dircmp(a, b) {
+                    Equal => extcmp(a, b),
+                    ord @ _ => ord
+                }
+            }
+            SortBy::Owner => {
+                match dircmp(a, b) {
+                    Equal => ownercmp(a, b),
+                    ord @ _ => ord
+                }
+            }
+            SortBy::Permissions => {
+                match dircmp(a, b) {
+                    Equal => permcmp(a, b),
+                    ord @ _ => ord
+                }
+            }
         }
     }
 
@@ -922,11 +1455,47 @@ impl Files {
             .par_sort_unstable_by(sort);
     }
 
+    // The list is sorted already, so only new files need sorting before
+    // they're merged in. Much cheaper than sorting everything again for each
+    // batch coming in from a huge directory.
+    pub fn merge_sorted(&mut self, mut new_files: Vec<File>) {
+        use std::cmp::Ordering::Greater;
+
+        if new_files.is_empty() { return; }
+
+        let sort = self.sorter();
+        new_files.par_sort_unstable_by(&sort);
+
+        let old_files = std::mem::take(&mut self.files);
+        let mut merged = Vec::with_capacity(old_files.len() + new_files.len());
+        let mut old_files = old_files.into_iter().peekable();
+        let mut new_files = new_files.into_iter().peekable();
+
+        loop {
+            let take_old = match (old_files.peek(), new_files.peek()) {
+                (Some(old), Some(new)) => sort(old, new) != Greater,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break
+            };
+
+            match take_old {
+                true => merged.extend(old_files.next()),
+                false => merged.extend(new_files.next())
+            }
+        }
+
+        self.files = merged;
+    }
+
     pub fn cycle_sort(&mut self) {
         self.sort = match self.sort {
             SortBy::Name => SortBy::Size,
             SortBy::Size => SortBy::MTime,
-            SortBy::MTime => SortBy::Name,
+            SortBy::MTime => SortBy::Extension,
+            SortBy::Extension => SortBy::Owner,
+            SortBy::Owner => SortBy::Permissions,
+            SortBy::Permissions => SortBy::Name,
         };
     }
 
@@ -971,7 +1540,12 @@ impl Files {
                 self.stale.as_ref().map(|s| s.set_fresh());
                 refresh.pull_async()?;
                 let mut refresh = refresh.value?;
+
+                // Selections could have changed while refresh was running
+                let marks = self.marks();
                 self.files = refresh.new_files.take()?;
+                self.restore_marks(&marks);
+
                 self.jobs.append(&mut refresh.jobs);
                 if refresh.new_len != self.len() {
                     self.len = refresh.new_len;
@@ -1040,7 +1614,11 @@ impl Files {
         self.iter_files_mut().find(|file| file.path == path)
     }
 
-    pub fn set_filter(&mut self, filter: Option<String>) {
+    pub fn set_filter(&mut self, filter: Option<String>) -> HResult<()> {
+        if let Some(filter) = &filter {
+            NameFilter::parse(filter)?;
+        }
+
         self.filter = filter;
 
         // Do this first, so we know len() == 0 needs a placeholder
@@ -1055,6 +1633,7 @@ impl Files {
         }
 
         self.set_dirty();
+        Ok(())
     }
 
     pub fn get_filter(&self) -> Option<String> {
@@ -1073,6 +1652,172 @@ impl Files {
         self.iter_files()
             .filter(|f| f.is_selected())
     }
+
+    // Selection/tag state by path, survives files being replaced
+    pub fn marks(&self) -> Marks {
+        self.files
+            .iter()
+            .filter(|f| f.selected || f.tag.is_some())
+            .map(|f| (f.path.clone(), (f.selected, f.tag)))
+            .collect()
+    }
+
+    // Just what's in the directory, for another tab to put its own view on
+    pub fn content_copy(&self) -> Files {
+        let mut files = self.clone();
+
+        files.pending_events = Arc::new(RwLock::new(vec![]));
+        files.refresh = None;
+        files.jobs = vec![];
+        files.stale = None;
+        files.remove_placeholder();
+
+        for file in files.files.iter_mut() {
+            file.selected = false;
+        }
+
+        files.recalculate_len();
+        files.set_dirty();
+        files
+    }
+
+    pub fn compact(&self) -> CompactFiles {
+        let mut names = String::new();
+        let mut entries = Vec::with_capacity(self.files.len());
+
+        for file in self.files.iter() {
+            if file.kind == Kind::Placeholder { continue; }
+
+            // Lossy names can't be turned back into the path
+            let path = match file.path.file_name() == Some(OsStr::new(&file.name)) {
+                true => None,
+                false => Some(file.path.clone())
+            };
+
+            entries.push(CompactEntry {
+                name: (names.len() as u32, file.name.len() as u32),
+                path: path,
+                kind: file.kind,
+                target: file.target.clone()
+            });
+            names.push_str(&file.name);
+        }
+
+        names.shrink_to_fit();
+
+        CompactFiles {
+            directory: self.directory.clone(),
+            names: names,
+            entries: entries
+        }
+    }
+
+    pub fn restore_marks(&mut self, marks: &Marks) {
+        if marks.is_empty() { return; }
+
+        for file in self.files.iter_mut() {
+            if let Some((selected, tag)) = marks.get(&file.path) {
+                file.selected = *selected;
+                if tag.is_some() {
+                    file.tag = *tag;
+                }
+            }
+        }
+
+        // Might have changed with selection filter on
+        if self.filter_selected {
+            self.recalculate_len();
+        }
+        self.set_dirty();
+    }
+
+    // Anything that's not in there isn't selected anymore
+    pub fn restore_selections(&mut self, selections: &HashMap<PathBuf, File>) {
+        let mut changed = false;
+
+        for file in self.files.iter_mut() {
+            let selected = selections.contains_key(&file.path);
+            if file.selected != selected {
+                file.selected = selected;
+                changed = true;
+            }
+        }
+
+        if !changed { return; }
+
+        if self.filter_selected {
+            self.recalculate_len();
+        }
+        self.set_dirty();
+    }
+}
+
+fn compare(a: &str, b: &str, natural: bool) -> std::cmp::Ordering {
+    match natural {
+        true => natord::compare_ignore_case(a, b).then_with(|| natord::compare(a, b)),
+        false => a.cmp(b)
+    }
+}
+
+pub type Marks = HashMap<PathBuf, (bool, Option<bool>)>;
+
+// A listing kept around with all names in one String instead of a String
+// and a PathBuf for each file, which adds up with a few huge directories
+// in the cache. Metadata is left out, that's loaded again when shown.
+#[derive(Debug, Clone)]
+pub struct CompactFiles {
+    directory: File,
+    names: String,
+    entries: Vec<CompactEntry>,
+}
+
+#[derive(Debug, Clone)]
+struct CompactEntry {
+    // Start and length in names
+    name: (u32, u32),
+    path: Option<PathBuf>,
+    kind: Kind,
+    target: Option<PathBuf>,
+}
+
+impl CompactFiles {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn expand(&self) -> Files {
+        let dir = &self.directory.path;
+
+        let files = self.entries
+            .iter()
+            .map(|entry| {
+                let (start, len) = (entry.name.0 as usize, entry.name.1 as usize);
+                let name = &self.names[start..start+len];
+                let path = entry.path
+                    .clone()
+                    .unwrap_or_else(|| dir.join(name));
+
+                File {
+                    name: name.to_string(),
+                    hidden: name.starts_with('.'),
+                    kind: entry.kind,
+                    path: path,
+                    dirsize: None,
+                    target: entry.target.clone(),
+                    meta: None,
+                    selected: false,
+                    tag: None,
+                }
+            })
+            .collect();
+
+        // Files has a Drop impl, so no struct update syntax
+        let mut shared = Files::default();
+        shared.directory = self.directory.clone();
+        shared.files = files;
+        shared.recalculate_len();
+        shared
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -1088,6 +1833,9 @@ impl std::fmt::Display for SortBy {
             SortBy::Name => "name",
             SortBy::Size => "size",
             SortBy::MTime => "mtime",
+            SortBy::Extension => "extension",
+            SortBy::Owner => "owner",
+            SortBy::Permissions => "permissions",
         };
         write!(formatter, "{}", text)
     }
@@ -1098,6 +1846,9 @@ pub enum SortBy {
     Name,
     Size,
     MTime,
+    Extension,
+    Owner,
+    Permissions,
 }
 
 
@@ -1269,7 +2020,7 @@ impl File {
         (self.path.clone(), Some(meta), None)
     }
 
-    pub fn prepare_meta_job(&mut self, cache: &FsCache) -> Option<Job> {
+    pub fn prepare_meta_job(&mut self, cache: &FsCache, dirsize: bool) -> Option<Job> {
         let mslot = match self.meta {
             Some(_) => None,
             None => {
@@ -1280,7 +2031,7 @@ impl File {
         };
 
         let dslot = match self.dirsize {
-            None if self.is_dir() => {
+            None if dirsize && self.is_dir() => {
                 let dslot = match cache.get_dirsize(self) {
                     Some(dslot) => dslot,
                     None => cache.make_dirsize(self)
@@ -1346,24 +2097,11 @@ impl File {
         }
 
 
-        let mut unit = 0;
-        let mut size = match self.meta() {
+        let size = match self.meta() {
             Some(meta) => meta.as_ref().unwrap().size(),
             None => return Err(FileError::MetaPending)?
         };
-        while size > 1024 {
-            size /= 1024;
-            unit += 1;
-        }
-        let unit = match unit {
-            0 => "",
-            1 => " KB",
-            2 => " MB",
-            3 => " GB",
-            4 => " TB",
-            5 => " wtf are you doing",
-            _ => "",
-        };
+        let (size, unit) = size_unit(size);
 
         Ok((size as usize, unit))
     }
@@ -1411,9 +2149,9 @@ impl File {
 
     pub fn is_filtered(&self, filter: &str, filter_selected: bool) -> bool {
         self.kind == Kind::Placeholder ||
-            !(// filter.is_some() &&
-              !self.name.contains(filter// .as_ref().unwrap()
-              )) &&
+            NameFilter::parse(filter)
+                .map(|filter| filter.matches(&self.name))
+                .unwrap_or(true) &&
             (!filter_selected || self.selected)
     }
 
@@ -1508,34 +2246,23 @@ impl File {
         let state = self.tag.unwrap();
 
         std::thread::spawn(move || -> HResult<()> {
-            use std::os::unix::ffi::OsStrExt;
-
             let tagfile_path = crate::paths::tagfile_path()?;
             let mut tags = TAGS.write()?;
 
-            match state {
-                true => {
-                    match tags.1.binary_search(&path) {
-                        Ok(_) => {},
-                        Err(inspos) => tags.1.insert(inspos, path)
-                    };
-                },
-                false => {
-                    match tags.1.binary_search(&path) {
-                        Ok(delpos) => { tags.1.remove(delpos); },
-                        Err(_) => {}
-                    };
+            // Other instances might have changed tags since they were loaded
+            let content = crate::store::update(&tagfile_path, |content| {
+                let mut tags = parse_tags(content);
+
+                match (state, tags.binary_search(&path)) {
+                    (true, Err(inspos)) => tags.insert(inspos, path),
+                    (false, Ok(delpos)) => { tags.remove(delpos); }
+                    _ => {}
                 }
-            }
 
-            let tagstr = tags.1.iter()
-                               .fold(std::ffi::OsString::new(), |mut s, f| {
-                                   s.push(f);
-                                   s.push("\n");
-                                   s
-                               });
+                write_tags(&tags)
+            })?;
 
-            std::fs::write(tagfile_path, tagstr.as_bytes())?;
+            tags.1 = parse_tags(&content);
             Ok(())
         });
         Ok(())
@@ -1676,3 +2403,108 @@ impl Drop for Ticker {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(pattern: &str) -> NameFilter {
+        NameFilter::parse(pattern).unwrap()
+    }
+
+    #[test]
+    fn filter_by_part_of_name() {
+        assert!(filter("port").matches("report.pdf"));
+        assert!(!filter("port").matches("PORT.pdf"));
+        assert!(filter("").matches("anything"));
+    }
+
+    #[test]
+    fn filter_by_glob() {
+        assert!(filter("*.rs").matches("main.rs"));
+        assert!(!filter("*.rs").matches("main.rs.bak"));
+        assert!(filter("?.txt").matches("a.txt"));
+        assert!(!filter("?.txt").matches("ab.txt"));
+        // Dots and such are just characters
+        assert!(!filter("a.*").matches("abc"));
+    }
+
+    #[test]
+    fn filter_by_regex() {
+        assert!(filter("/^IMG_\\d+").matches("IMG_0042.jpg"));
+        assert!(!filter("/^IMG_\\d+").matches("old_IMG_0042.jpg"));
+        // A lone slash is just a slash
+        assert!(!filter("/").matches("file"));
+        assert!(NameFilter::parse("/(").is_err());
+    }
+
+    #[test]
+    fn excludes_from_config_list() {
+        let excludes = Excludes::parse(".git, node_modules,, *.o");
+
+        assert!(excludes.matches(OsStr::new(".git")));
+        assert!(excludes.matches(OsStr::new("node_modules")));
+        assert!(excludes.matches(OsStr::new("main.o")));
+        // Whole names only
+        assert!(!excludes.matches(OsStr::new(".gitignore")));
+        assert!(!excludes.matches(OsStr::new("main.ol")));
+
+        assert!(Excludes::parse(" , ").is_empty());
+    }
+
+    fn sorted(names: &[&str], natural: bool) -> Vec<String> {
+        let mut names = names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        names.sort_by(|a, b| compare(a, b, natural));
+        names
+    }
+
+    #[test]
+    fn natural_order() {
+        assert_eq!(sorted(&["file10", "File2", "file1"], true),
+                   vec!["file1", "File2", "file10"]);
+        // Only case left to decide, uppercase first
+        assert_eq!(sorted(&["b", "B"], true), vec!["B", "b"]);
+    }
+
+    #[test]
+    fn plain_order() {
+        assert_eq!(sorted(&["file10", "File2", "file1"], false),
+                   vec!["File2", "file1", "file10"]);
+    }
+
+    #[test]
+    fn compact_listing_expands_again() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = PathBuf::from("/nonexistent/dir");
+        let mut files = Files::default();
+        files.directory = File::new("dir", dir.clone());
+
+        let mut subdir = File::new("sub", dir.join("sub"));
+        subdir.kind = Kind::Directory;
+        let mut link = File::new("link", dir.join("link"));
+        link.target = Some(PathBuf::from("/elsewhere"));
+        // Not UTF-8, the name is lossy but the path isn't
+        let latin1 = dir.join(OsStr::from_bytes(b"caf\xe9"));
+        let lossy = File::new(&latin1.file_name().unwrap().to_string_lossy(), latin1.clone());
+        let mut placeholder = File::new("loading", dir.join("loading"));
+        placeholder.kind = Kind::Placeholder;
+
+        files.files = vec![subdir, link, lossy, placeholder];
+        files.files[1].selected = true;
+
+        let expanded = files.compact().expand();
+
+        assert_eq!(expanded.directory.path, dir);
+        assert_eq!(expanded.len, 3);
+        assert_eq!(expanded.files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+                   vec!["sub", "link", "caf\u{fffd}"]);
+        assert_eq!(expanded.files.iter().map(|f| f.path.clone()).collect::<Vec<_>>(),
+                   vec![dir.join("sub"), dir.join("link"), latin1]);
+        assert_eq!(expanded.files[0].kind, Kind::Directory);
+        assert_eq!(expanded.files[1].target, Some(PathBuf::from("/elsewhere")));
+        // Selections are kept apart and put back with restore_marks
+        assert!(expanded.files.iter().all(|f| !f.selected && f.meta.is_none()));
+    }
+}
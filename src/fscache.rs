@@ -5,52 +5,107 @@ use async_value::{Async, Stale};
 use std::sync::{Arc, RwLock, Weak};
 use std::sync::mpsc::{channel, Sender, Receiver};
 use std::collections::{HashMap, HashSet};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use std::path::{Path, PathBuf};
 
-use crate::files::{Files, File, SortBy};
+use crate::files::{Files, File, SortBy, CompactFiles};
 use crate::widget::Events;
 use crate::fail::{HResult, HError, ErrorLog};
+use crate::config::{Config, Refresh};
 
 pub type CachedFiles = (Option<File>, Async<Files>);
 
+// Size and mtime of everything in a directory, to find changes by polling
+type DirSnapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
 
+// Bigger listings only keep their names while they're shared
+const COMPACT_ABOVE: usize = 10_000;
+
+#[derive(Debug)]
+enum SharedListing {
+    Full(Files),
+    Compact(CompactFiles)
+}
+
+impl SharedListing {
+    fn new(files: &Files) -> SharedListing {
+        match files.files.len() > COMPACT_ABOVE {
+            true => SharedListing::Compact(files.compact()),
+            false => SharedListing::Full(files.content_copy())
+        }
+    }
+
+    fn files(&self) -> Files {
+        match self {
+            SharedListing::Full(files) => files.content_copy(),
+            SharedListing::Compact(files) => files.expand()
+        }
+    }
+}
+
+
+// How a tab left a directory. Files keeps these itself, so they're put
+// back on the copy of a listing another tab already read.
 #[derive(Debug, Clone)]
-pub struct DirSettings {
+pub struct TabSettings {
+    selection: Option<File>,
+    // First line on screen, so coming back shows the same thing
+    offset: Option<usize>,
     sort: SortBy,
     dirs_first: bool,
     reverse: bool,
+    natural: bool,
     show_hidden: bool,
     filter: Option<String>,
     filter_selected: bool
 }
 
-impl DirSettings {
-    fn new() -> DirSettings {
-        DirSettings {
+impl TabSettings {
+    fn new() -> TabSettings {
+        TabSettings {
+            selection: None,
+            offset: None,
             sort: SortBy::Name,
             dirs_first: true,
             reverse: false,
+            natural: true,
             show_hidden: true,
             filter: None,
             filter_selected: false
         }
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct TabSettings {
-    selection: Option<File>,
-    multi_selections: Vec<File>,
-    dir_settings: DirSettings,
-}
-
-impl TabSettings {
-    fn new() -> TabSettings {
+    fn from_files(files: &Files,
+                  selection: Option<File>,
+                  offset: Option<usize>) -> TabSettings {
         TabSettings {
-            selection: None,
-            multi_selections: vec![],
-            dir_settings: DirSettings::new()
+            selection: selection,
+            offset: offset,
+            sort: files.sort,
+            dirs_first: files.dirs_first,
+            reverse: files.reverse,
+            natural: files.natural,
+            show_hidden: files.show_hidden,
+            filter: files.filter.clone(),
+            filter_selected: files.filter_selected
+        }
+    }
+
+    fn apply(&self, files: &mut Files) {
+        let recalculate = files.show_hidden != self.show_hidden ||
+            files.filter != self.filter ||
+            files.filter_selected != self.filter_selected;
+
+        files.sort = self.sort;
+        files.dirs_first = self.dirs_first;
+        files.reverse = self.reverse;
+        files.natural = self.natural;
+        files.show_hidden = self.show_hidden;
+        files.filter = self.filter.clone();
+        files.filter_selected = self.filter_selected;
+
+        if recalculate {
+            files.recalculate_len();
         }
     }
 }
@@ -68,13 +123,16 @@ impl std::fmt::Debug for FsCache {
 
 #[derive(Clone)]
 struct FsEventDispatcher {
-    targets: Arc<RwLock<HashMap<File, Vec<Weak<RwLock<Vec<FsEvent>>>>>>>
+    targets: Arc<RwLock<HashMap<File, Vec<Weak<RwLock<Vec<FsEvent>>>>>>>,
+    // Shared listings, these are out of date as soon as anything changes
+    files: Arc<RwLock<HashMap<File, SharedListing>>>
 }
 
 impl FsEventDispatcher {
-    fn new() -> Self {
+    fn new(files: Arc<RwLock<HashMap<File, SharedListing>>>) -> Self {
         FsEventDispatcher {
-            targets: Arc::new(RwLock::new(HashMap::new()))
+            targets: Arc::new(RwLock::new(HashMap::new())),
+            files: files
         }
     }
 
@@ -106,6 +164,8 @@ impl FsEventDispatcher {
 
     fn dispatch(&self, events: HashMap<File, Vec<FsEvent>>) -> HResult<()> {
         for (dir, events) in events {
+            self.files.write()?.remove(&dir);
+
             for target_dirs in self.targets
                 .read()?
                 .get(&dir) {
@@ -126,11 +186,19 @@ impl FsEventDispatcher {
 use std::sync::atomic::{AtomicBool, AtomicUsize};
 #[derive(Clone)]
 pub struct FsCache {
-    files: Arc<RwLock<HashMap<File, Files>>>,
+    files: Arc<RwLock<HashMap<File, SharedListing>>>,
     dirsizes: Arc<RwLock<HashMap<PathBuf, HashMap<PathBuf,
                                                   Arc<(AtomicBool, AtomicUsize)>>>>>,
     pub tab_settings: Arc<RwLock<HashMap<File, TabSettings>>>,
+    // Selected files from everywhere, by path. Unlike tab_settings this is shared
+    // by all tabs, so selections survive leaving the directory and can be
+    // collected from several directories and tabs.
+    selections: Arc<RwLock<HashMap<PathBuf, File>>>,
     watched_dirs: Arc<RwLock<HashSet<File>>>,
+    // These are listed again instead of being watched, with the time
+    // they were last listed
+    polled_dirs: Arc<RwLock<HashMap<File, (SystemTime, DirSnapshot)>>>,
+    refresh: Refresh,
     watcher: Arc<RwLock<RecommendedWatcher>>,
     fs_event_dispatcher: FsEventDispatcher
 }
@@ -142,15 +210,24 @@ impl FsCache {
                                               Duration::from_secs(2)).unwrap();
 
 
+        let files = Arc::new(RwLock::new(HashMap::new()));
+
         let fs_cache = FsCache {
-            files: Arc::new(RwLock::new(HashMap::new())),
+            files: files.clone(),
             dirsizes: Arc::new(RwLock::new(HashMap::new())),
             tab_settings: Arc::new(RwLock::new(HashMap::new())),
+            selections: Arc::new(RwLock::new(HashMap::new())),
             watched_dirs: Arc::new(RwLock::new(HashSet::new())),
+            polled_dirs: Arc::new(RwLock::new(HashMap::new())),
+            refresh: Config::refresh(),
             watcher: Arc::new(RwLock::new(watcher)),
-            fs_event_dispatcher: FsEventDispatcher::new()
+            fs_event_dispatcher: FsEventDispatcher::new(files)
         };
 
+        if fs_cache.refresh != Refresh::Manual {
+            poll_fs(fs_cache.clone(), sender.clone());
+        }
+
         watch_fs(rx_fs_event,
                  fs_cache.fs_event_dispatcher.clone(),
                  sender);
@@ -175,6 +252,7 @@ impl FsCache {
             let cache = self.clone();
             let files = Async::new(move |_| {
                 let mut files = Files::new_from_path_cancellable(&dir.path, stale)?;
+                cache.share_files(&files).log();
                 cache.add_watch(&dir).log();
                 cache.fs_event_dispatcher.add_target(&dir,
                                                      &files.pending_events).log();
@@ -209,6 +287,13 @@ impl FsCache {
            .clone())
     }
 
+    pub fn has_view(&self, dir: &File) -> bool {
+        self.tab_settings
+            .read()
+            .map(|tab_settings| tab_settings.contains_key(dir))
+            .unwrap_or(false)
+    }
+
     pub fn set_selection(&self, dir: File, selection: File) -> HResult<()> {
         self.tab_settings.write()
             .map(|mut settings| {
@@ -218,10 +303,68 @@ impl FsCache {
         Ok(())
     }
 
-    pub fn save_settings(&self, files: &Files, selection: Option<File>) -> HResult<()> {
+    pub fn get_offset(&self, dir: &File) -> HResult<usize> {
+        Ok(self.tab_settings
+           .read()?
+           .get(&dir)
+           .as_ref()?
+           .offset?)
+    }
+
+    pub fn set_offset(&self, dir: File, offset: usize) -> HResult<()> {
+        self.tab_settings.write()
+            .map(|mut settings| {
+                let setting = settings.entry(dir).or_insert(TabSettings::new());
+                setting.offset = Some(offset);
+            })?;
+        Ok(())
+    }
+
+    pub fn all_selections(&self) -> HResult<Vec<File>> {
+        Ok(self.selections
+           .read()?
+           .values()
+           .cloned()
+           .collect())
+    }
+
+    pub fn remove_selection(&self, file: &File) -> HResult<()> {
+        self.selections.write()?.remove(&file.path);
+        Ok(())
+    }
+
+    pub fn clear_selections(&self) -> HResult<()> {
+        self.selections.write()?.clear();
+        Ok(())
+    }
+
+    // Takes over what was (un)selected in this listing
+    pub fn save_selections(&self, files: &Files) -> HResult<()> {
+        let mut selections = self.selections.write()?;
+
+        for file in files.files.iter() {
+            match file.is_selected() {
+                true => selections.insert(file.path.clone(), file.clone()),
+                false => selections.remove(&file.path)
+            };
+        }
+        Ok(())
+    }
+
+    // Brings a listing up to date with selections made elsewhere
+    pub fn restore_selections(&self, files: &mut Files) -> HResult<()> {
+        files.restore_selections(&*self.selections.read()?);
+        Ok(())
+    }
+
+    pub fn save_settings(&self,
+                         files: &Files,
+                         selection: Option<File>,
+                         offset: Option<usize>) -> HResult<()> {
         let dir = files.directory.clone();
-        let tab_settings = FsCache::extract_tab_settings(&files, selection);
-        self.tab_settings.write()?.insert(dir, tab_settings);
+        let settings = TabSettings::from_files(&files, selection, offset);
+        self.tab_settings.write()?.insert(dir, settings);
+        self.save_selections(files)?;
         Ok(())
     }
 
@@ -306,7 +449,19 @@ impl FsCache {
 
     fn add_watch(&self, dir: &File) -> HResult<()> {
         if !self.watched_dirs.read()?.contains(&dir) {
-            self.watcher.write()?.watch(&dir.path, RecursiveMode::NonRecursive)?;
+            // Out of inotify watches or not supported by the filesystem
+            let watching = self.refresh == Refresh::Watch &&
+                !crate::stats::is_network_fs(&dir.path) &&
+                self.watcher
+                    .write()?
+                    .watch(&dir.path, RecursiveMode::NonRecursive)
+                    .is_ok();
+
+            if !watching {
+                let snapshot = snapshot_dir(&dir.path)?;
+                self.polled_dirs.write()?.insert(dir.clone(), (SystemTime::now(), snapshot));
+            }
+
             self.watched_dirs.write()?.insert(dir.clone());
         }
         Ok(())
@@ -315,43 +470,90 @@ impl FsCache {
     fn remove_watch(&self, dir: &File) -> HResult<()> {
         if self.watched_dirs.read()?.contains(&dir) {
             self.watched_dirs.write()?.remove(dir);
-            self.watcher.write()?.unwatch(&dir.path)?
+            // Nothing would tell when it's out of date
+            self.files.write()?.remove(dir);
+
+            if self.polled_dirs.write()?.remove(dir).is_none() {
+                self.watcher.write()?.unwatch(&dir.path)?
+            }
         }
         Ok(())
     }
 
+    // Only polled directories can be out of date
+    pub fn listed_at(&self, dir: &File) -> Option<SystemTime> {
+        self.polled_dirs
+            .read()
+            .ok()?
+            .get(dir)
+            .map(|(listed_at, _)| *listed_at)
+    }
+
+    // Stops watching so the next listing starts over
+    pub fn forget(&self, dir: &File) -> HResult<()> {
+        self.files.write()?.remove(dir);
+        self.remove_watch(dir)
+    }
+
+    // Lists the polled directories again and passes on what changed.
+    // Returns false if nothing did.
+    pub fn rescan(&self) -> HResult<bool> {
+        let dirs = self.polled_dirs
+            .read()?
+            .keys()
+            .cloned()
+            .collect::<Vec<File>>();
+
+        let mut events = HashMap::new();
+
+        for dir in dirs {
+            // Gone now, the parent will notice
+            let new = match snapshot_dir(&dir.path) {
+                Ok(new) => new,
+                Err(_) => continue
+            };
+
+            let mut polled_dirs = self.polled_dirs.write()?;
+            if let Some((listed_at, old)) = polled_dirs.get_mut(&dir) {
+                let changes = diff_snapshots(old, &new);
+                *listed_at = SystemTime::now();
+                *old = new;
+
+                if !changes.is_empty() {
+                    events.insert(dir, changes);
+                }
+            }
+        }
+
+        let changed = !events.is_empty();
+        self.fs_event_dispatcher.dispatch(events)?;
+
+        Ok(changed)
+    }
+
     fn get_cached_files(&self, dir: &File) -> HResult<CachedFiles> {
-        let tab_settings = match self.tab_settings.read()?.get(&dir) {
-                Some(tab_settings) => tab_settings.clone(),
+        let settings = match self.tab_settings.read()?.get(&dir) {
+                Some(settings) => settings.clone(),
                 None => TabSettings::new()
         };
-        let selection = tab_settings.selection.clone();
-        let file_cache = self.files.clone();
+        let selection = settings.selection.clone();
+        let cache = self.clone();
         let dir = dir.clone();
 
         let files = Async::new(move |_| {
-            let mut files = file_cache.read()
+            let mut files = cache.files
+                .read()
                 .map_err(|e| HError::from(e))?
                 .get(&dir)
                 .ok_or(HError::NoneError)?
-                .clone();
-            let tab_settings = &tab_settings;
-
-            files.sort = tab_settings.dir_settings.sort;
-            files.dirs_first = tab_settings.dir_settings.dirs_first;
-            files.reverse = tab_settings.dir_settings.reverse;
-            files.show_hidden = tab_settings.dir_settings.show_hidden;
-            files.filter = tab_settings.dir_settings.filter.clone();
-
-            if tab_settings.multi_selections.len() > 0 {
-                for file in &mut files.files {
-                    for selected_files in &tab_settings.multi_selections {
-                        if file.path == selected_files.path {
-                            file.selected = true;
-                        }
-                    }
-                }
-            }
+                .files();
+
+            // Gets its own events, the shared copy is just dropped on changes
+            cache.add_watch(&dir).log();
+            cache.fs_event_dispatcher.add_target(&dir,
+                                                 &files.pending_events).log();
+            settings.apply(&mut files);
+            cache.restore_selections(&mut files).log();
 
             let files = FsCache::ensure_not_empty(files)?;
             Ok(files)
@@ -360,40 +562,26 @@ impl FsCache {
         Ok((selection, files))
     }
 
+    // Directories that are still coming in aren't worth sharing yet
+    fn share_files(&self, files: &Files) -> HResult<()> {
+        if files.rest.is_some() { return Ok(()); }
+
+        self.files
+            .write()?
+            .insert(files.directory.clone(), SharedListing::new(files));
+        Ok(())
+    }
 
     pub fn apply_settingss(cache: &FsCache,
                        files: &mut Files)
                        -> HResult<()> {
-        let dir = &files.directory;
-        let tab_settings = cache.tab_settings.read()?.get(&dir).cloned();
-        if tab_settings.is_none() { return Ok(()) }
-        let tab_settings = tab_settings?;
-
-        if files.show_hidden != tab_settings.dir_settings.show_hidden ||
-            files.filter != tab_settings.dir_settings.filter ||
-            files.filter_selected != tab_settings.dir_settings.filter_selected {
-                files.recalculate_len();
-            }
-
-        files.sort = tab_settings.dir_settings.sort;
-        files.dirs_first = tab_settings.dir_settings.dirs_first;
-        files.reverse = tab_settings.dir_settings.reverse;
-        files.show_hidden = tab_settings.dir_settings.show_hidden;
-        files.filter = tab_settings.dir_settings.filter.clone();
-        files.filter_selected = tab_settings.dir_settings.filter_selected;
-
+        cache.restore_selections(files)?;
 
+        let dir = &files.directory;
+        let settings = cache.tab_settings.read()?.get(&dir).cloned();
+        if settings.is_none() { return Ok(()) }
 
-        if tab_settings.multi_selections.len() > 0 {
-            for file in &mut files.files {
-                for selected_files in &tab_settings.multi_selections {
-                    if file.path == selected_files.path {
-                        file.selected = true;
-                    }
-                }
-            }
-        }
-
+        settings?.apply(files);
         Ok(())
     }
 
@@ -406,22 +594,6 @@ impl FsCache {
         }
         Ok(files)
     }
-
-
-    fn extract_tab_settings(files: &Files, selection: Option<File>) -> TabSettings {
-        TabSettings {
-            selection: selection,
-            multi_selections: files.get_selected().into_iter().cloned().collect(),
-            dir_settings: DirSettings {
-                sort: files.sort,
-                dirs_first: files.dirs_first,
-                reverse: files.reverse,
-                show_hidden: files.show_hidden,
-                filter: files.filter.clone(),
-                filter_selected: files.filter_selected
-            }
-        }
-    }
 }
 
 
@@ -488,6 +660,49 @@ impl TryFrom<DebouncedEvent> for FsEvent {
 }
 
 
+fn snapshot_dir(dir: &Path) -> HResult<DirSnapshot> {
+    let snapshot = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let meta = entry.metadata().ok();
+            let mtime = meta.as_ref().and_then(|meta| meta.modified().ok());
+            let size = meta.map(|meta| meta.len()).unwrap_or(0);
+            (entry.path(), (mtime, size))
+        })
+        .collect();
+
+    Ok(snapshot)
+}
+
+fn diff_snapshots(old: &DirSnapshot, new: &DirSnapshot) -> Vec<FsEvent> {
+    let created = new.iter()
+        .filter_map(|(path, stat)| match old.get(path) {
+            None => Some(FsEvent::Create(File::new_from_path(path).ok()?)),
+            Some(old_stat) if old_stat != stat => {
+                Some(FsEvent::Change(File::new_from_path(path).ok()?))
+            }
+            _ => None
+        });
+
+    let removed = old.keys()
+        .filter(|path| !new.contains_key(*path))
+        .filter_map(|path| Some(FsEvent::Remove(File::new_from_path(path).ok()?)));
+
+    created.chain(removed).collect()
+}
+
+fn poll_fs(cache: FsCache, sender: Sender<Events>) {
+    let interval = Config::refresh_interval();
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+
+        if let Ok(true) = cache.rescan().log_and() {
+            sender.send(Events::WidgetReady).ok();
+        }
+    });
+}
+
 fn watch_fs(rx_fs_events: Receiver<DebouncedEvent>,
             fs_event_dispatcher: FsEventDispatcher,
             sender: Sender<Events>) {
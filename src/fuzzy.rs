@@ -0,0 +1,325 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::listview::{Listable, ListView};
+use crate::widget::{Widget, WidgetCore, Events};
+use crate::coordinates::Coordinates;
+use crate::files::Excludes;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+use crate::dirty::Dirtyable;
+
+
+// Like fzf, but without leaving hunter. Everything below the cwd gets
+// collected in the background while typing already narrows it down, the
+// best matches come first. Typing goes into the query, so the keys are
+// fixed here instead of coming from the keys file.
+
+const MAX_RESULTS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FuzzyMatch {
+    pub path: PathBuf,
+    score: i64,
+    // Char positions in the relative path, for highlighting
+    positions: Vec<usize>,
+}
+
+// Subsequence match with bonuses for consecutive chars and for chars at
+// the start of a name or word, which is mostly what fzf does too. Lower
+// case queries match any case.
+pub fn score(path: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() { return Some((0, vec![])); }
+
+    let smart_case = query.chars().any(|c| c.is_uppercase());
+    let fold = |c: char| match smart_case {
+        true => c,
+        false => c.to_ascii_lowercase()
+    };
+
+    let chars = path.chars().collect::<Vec<_>>();
+    let name_start = path.rfind('/').map(|pos| path[..pos].chars().count() + 1).unwrap_or(0);
+
+    let mut positions = Vec::with_capacity(query.len());
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last: Option<usize> = None;
+
+    for q in query.chars().map(fold) {
+        let found = chars[pos..].iter().position(|&c| fold(c) == q)? + pos;
+
+        score += 16;
+        match last {
+            Some(last) if last + 1 == found => score += 24,
+            Some(last) => score -= (found - last - 1).min(16) as i64,
+            None => {}
+        }
+
+        let at_word = found == 0 ||
+            !chars[found-1].is_alphanumeric() ||
+            (chars[found-1].is_lowercase() && chars[found].is_uppercase());
+        if at_word { score += 20; }
+        if found >= name_start { score += 8; }
+
+        positions.push(found);
+        last = Some(found);
+        pos = found + 1;
+    }
+
+    // Shorter paths win ties
+    score -= (chars.len() / 8) as i64;
+
+    Some((score, positions))
+}
+
+impl Listable for ListView<Vec<FuzzyMatch>> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let xsize = self.get_coordinates().unwrap().xsize_u();
+
+        self.content
+            .iter()
+            .map(|m| {
+                let line = m.path
+                    .to_string_lossy()
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| match m.positions.contains(&i) {
+                        true => format!("{}{}{}",
+                                        term::highlight_color(),
+                                        c,
+                                        term::normal_color()),
+                        false => c.to_string()
+                    })
+                    .collect::<String>();
+
+                term::sized_string_u(&line, xsize)
+            })
+            .collect()
+    }
+}
+
+fn index(dir: &Path,
+         relative: &Path,
+         show_hidden: bool,
+         excludes: &Excludes,
+         paths: &Mutex<Vec<PathBuf>>,
+         stop: &AtomicBool) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return
+    };
+
+    let mut batch = vec![];
+    let mut subdirs = vec![];
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if stop.load(Ordering::Relaxed) { return; }
+
+        let name = entry.file_name();
+        if !show_hidden && name.to_string_lossy().starts_with('.') { continue; }
+        if excludes.matches(&name) { continue; }
+
+        let path = relative.join(&name);
+
+        // Doesn't follow symlinks, they could go in circles
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            subdirs.push((entry.path(), path.clone()));
+        }
+        batch.push(path);
+    }
+
+    paths.lock().extend(batch);
+
+    for (dir, relative) in subdirs {
+        index(&dir, &relative, show_hidden, excludes, paths, stop);
+    }
+}
+
+
+#[derive(Debug)]
+pub struct FuzzyFinder {
+    core: WidgetCore,
+    list: ListView<Vec<FuzzyMatch>>,
+    root: PathBuf,
+    query: String,
+    paths: Arc<Mutex<Vec<PathBuf>>>,
+    // How many paths the current results were ranked from
+    ranked: usize,
+    indexing: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    pub picked: Option<PathBuf>,
+}
+
+impl FuzzyFinder {
+    pub fn new(core: &WidgetCore, root: &Path) -> FuzzyFinder {
+        let finder = FuzzyFinder {
+            core: core.clone(),
+            list: ListView::new(core, vec![]),
+            root: root.to_path_buf(),
+            query: String::new(),
+            paths: Arc::new(Mutex::new(vec![])),
+            ranked: 0,
+            indexing: Arc::new(AtomicBool::new(true)),
+            stop: Arc::new(AtomicBool::new(false)),
+            picked: None
+        };
+
+        finder.start_indexing();
+        finder
+    }
+
+    fn start_indexing(&self) {
+        let root = self.root.clone();
+        let show_hidden = self.core.config().show_hidden();
+        let excludes = Excludes::new(&self.core.config().exclude);
+        let paths = self.paths.clone();
+        let indexing = self.indexing.clone();
+        let stop = self.stop.clone();
+        let sender = self.core.get_sender();
+
+        // Lets the list grow on screen while it's still collecting
+        let ticker_indexing = indexing.clone();
+        let ticker_stop = stop.clone();
+        std::thread::spawn(move || {
+            let mut last = Instant::now();
+            while ticker_indexing.load(Ordering::Relaxed) &&
+                !ticker_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(50));
+                if last.elapsed() >= Duration::from_millis(300) {
+                    sender.send(Events::WidgetReady).ok();
+                    last = Instant::now();
+                }
+            }
+            sender.send(Events::WidgetReady).ok();
+        });
+
+        std::thread::spawn(move || {
+            index(&root, Path::new(""), show_hidden, &excludes, &paths, &stop);
+            indexing.store(false, Ordering::Relaxed);
+        });
+    }
+
+    fn rank(&mut self) {
+        let paths = self.paths.lock();
+
+        let mut matches = paths.iter()
+            .filter_map(|path| {
+                let (score, positions) = score(&path.to_string_lossy(), &self.query)?;
+                Some(FuzzyMatch {
+                    path: path.clone(),
+                    score,
+                    positions
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Without a query it's just the order they were found in
+        if !self.query.is_empty() {
+            matches.sort_by(|a, b| b.score.cmp(&a.score));
+        }
+        matches.truncate(MAX_RESULTS);
+
+        self.ranked = paths.len();
+        self.list.content = matches;
+        self.list.move_top();
+        self.core.set_dirty();
+    }
+
+    fn pick_selected(&mut self) -> HResult<()> {
+        let selection = self.list.get_selection();
+        let path = &self.list.content.get(selection)?.path;
+        self.picked = Some(self.root.join(path));
+        HError::popup_finnished()
+    }
+
+    fn edit_query(&mut self, key: Key) -> HResult<()> {
+        match key {
+            Key::Char(c) if !c.is_control() => self.query.push(c),
+            Key::Backspace => { self.query.pop(); }
+            Key::Ctrl('u') => self.query.clear(),
+            _ => return Ok(())
+        }
+
+        self.rank();
+        self.core.clear()
+    }
+}
+
+impl Drop for FuzzyFinder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Widget for FuzzyFinder {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.list.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        Ok(format!("find: {}{}_", self.query, term::highlight_color()))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let status = match self.indexing.load(Ordering::Relaxed) {
+            true => "indexing...",
+            false => "done"
+        };
+        let footer = format!("{} matches of {} files, {}  Enter: go there  Esc: cancel",
+                             self.list.len(),
+                             self.ranked,
+                             status);
+        Ok(term::sized_string_u(&footer, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        // New paths came in
+        if self.paths.lock().len() != self.ranked {
+            let selection = self.list.get_selection();
+            self.rank();
+            if selection < self.list.len() {
+                self.list.set_selection(selection);
+            }
+        }
+        self.list.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.list.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        match key {
+            Key::Esc | Key::Ctrl('c') => HError::popup_finnished()?,
+            Key::Char('\n') => self.pick_selected()?,
+            Key::Up | Key::Ctrl('p') | Key::Ctrl('k') => self.list.move_up(),
+            Key::Down | Key::Ctrl('n') => self.list.move_down(),
+            Key::PageUp => self.list.page_up(),
+            Key::PageDown => self.list.page_down(),
+            key => self.edit_query(key)?
+        }
+
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use std::os::unix::fs::{DirEntryExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use crate::fail::HResult;
+
+
+// Other names of a hardlinked file. The filesystem only knows how many
+// there are, so finding them means walking a directory tree looking for
+// the same inode. Hardlinks can't cross filesystems, so other mounts are
+// skipped, and the walk stops once all of them turned up.
+
+// Where the filesystem path is on starts
+pub fn fs_root(path: &Path) -> PathBuf {
+    let dev = match path.symlink_metadata() {
+        Ok(meta) => meta.dev(),
+        Err(_) => return PathBuf::from("/")
+    };
+
+    path.ancestors()
+        .take_while(|dir| dir.metadata().map(|meta| meta.dev() == dev).unwrap_or(false))
+        .last()
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+// Number of other links, and the ones found in scope
+pub fn find_links(path: &Path, scope: &Path) -> HResult<(u64, Vec<PathBuf>)> {
+    let meta = path.symlink_metadata()?;
+    let others = meta.nlink().saturating_sub(1);
+    let mut links = vec![];
+
+    if others > 0 {
+        walk(scope, path, meta.dev(), meta.ino(), others, &mut links);
+    }
+
+    Ok((others, links))
+}
+
+fn walk(dir: &Path,
+        skip: &Path,
+        dev: u64,
+        ino: u64,
+        wanted: u64,
+        links: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if links.len() as u64 >= wanted { return; }
+
+        let path = entry.path();
+        let is_dir = entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false);
+
+        if is_dir {
+            let same_fs = path.symlink_metadata()
+                .map(|meta| meta.dev() == dev)
+                .unwrap_or(false);
+            if same_fs {
+                walk(&path, skip, dev, ino, wanted, links);
+            }
+        } else if entry.ino() == ino && path != skip {
+            // Inode numbers are only unique per filesystem
+            let same_fs = path.symlink_metadata()
+                .map(|meta| meta.dev() == dev)
+                .unwrap_or(false);
+            if same_fs {
+                links.push(path);
+            }
+        }
+    }
+}
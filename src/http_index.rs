@@ -0,0 +1,245 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf, Component};
+use std::process::Command;
+
+use crate::remote::{Backend, RemoteEntry};
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError};
+
+
+// Directory indexes as served by nginx/apache autoindex and most mirrors.
+// Pages are downloaded with curl and the links on them are the entries,
+// the ones ending in a slash are directories.
+
+#[derive(Debug)]
+pub struct HttpIndexBackend {
+    // Like "https://mirror.example.org"
+    origin: String,
+    start: PathBuf,
+    // As "user:password"
+    secret: Option<String>,
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' |
+            b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b)
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i+1..i+3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => { decoded.push(byte); i += 3; }
+            (byte, _) => { decoded.push(byte); i += 1; }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn unescape_html(s: &str) -> String {
+    s.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+}
+
+impl HttpIndexBackend {
+    pub fn new(url: &str) -> HResult<HttpIndexBackend> {
+        let scheme_end = url.find("://")
+            .ok_or_else(|| HError::Error(format!("Not a URL: {}", url)))?;
+        let path_start = url[scheme_end+3..]
+            .find('/')
+            .map(|pos| pos + scheme_end + 3)
+            .unwrap_or(url.len());
+
+        let start = match &url[path_start..] {
+            "" => "/".to_string(),
+            path => percent_decode(path)
+        };
+
+        Ok(HttpIndexBackend {
+            origin: url[..path_start].to_string(),
+            start: PathBuf::from(start),
+            secret: None
+        })
+    }
+
+    fn url(&self, path: &Path) -> String {
+        let path = path.components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(percent_encode(&name.to_string_lossy())),
+                _ => None
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        format!("{}/{}", self.origin, path)
+    }
+
+    // curl hides the password from process listings itself
+    fn curl_auth(&self) -> Vec<OsString> {
+        match &self.secret {
+            Some(secret) => vec![OsString::from("-u"), OsString::from(secret)],
+            None => vec![]
+        }
+    }
+}
+
+impl Backend for HttpIndexBackend {
+    fn name(&self) -> String {
+        self.origin.clone()
+    }
+
+    fn root(&self) -> PathBuf {
+        self.start.clone()
+    }
+
+    fn credentials_key(&self) -> Option<String> {
+        Some(self.origin.clone())
+    }
+
+    fn set_secret(&mut self, secret: Option<String>) {
+        self.secret = secret;
+    }
+
+    fn list(&mut self, path: &Path) -> HResult<Vec<RemoteEntry>> {
+        let mut url = self.url(path);
+        if !url.ends_with('/') { url.push('/'); }
+
+        let output = Command::new("curl")
+            .args(self.curl_auth())
+            .args(&["-fsSL", "--", &url])
+            .stdin(std::process::Stdio::null())
+            .output()?;
+
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+            // Resolving, connecting, timeouts, and connections that broke off
+            return match output.status.code() {
+                Some(5) | Some(6) | Some(7) | Some(28) |
+                Some(35) | Some(52) | Some(55) | Some(56) => Err(HError::ConnectionError(err)),
+                _ => Err(HError::Error(err))
+            };
+        }
+
+        let page = String::from_utf8_lossy(&output.stdout);
+        let link = r#"(?i)<a\s[^>]*href\s*=\s*["']([^"']+)["'][^>]*>.*?</a>([^<\n]*)"#;
+        let link = regex::Regex::new(link)
+            .map_err(|err| HError::Error(format!("{}", err)))?;
+
+        let entries = link.captures_iter(&page)
+            .filter_map(|caps| {
+                let href = unescape_html(caps.get(1)?.as_str());
+                // Whatever follows the link, usually date and size
+                let info = caps.get(2)
+                    .map(|info| {
+                        info.as_str()
+                            .split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .unwrap_or_default();
+
+                // Sort links, parents and other sites are left out
+                if href.starts_with('?') ||
+                   href.starts_with('/') ||
+                   href.starts_with("..") ||
+                   href.contains("://") {
+                    return None;
+                }
+
+                let is_dir = href.ends_with('/');
+                let name = percent_decode(href.trim_end_matches('/'));
+                if name.is_empty() || name.contains('/') { return None; }
+
+                let entry = match is_dir {
+                    true => RemoteEntry::dir(&name),
+                    false => RemoteEntry::file(&name, None)
+                };
+                Some(entry.with_info(info))
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn fetch(&mut self,
+             path: &Path,
+             is_dir: bool,
+             target: &Path,
+             cwd: &File) -> HResult<Cmd> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let url = self.url(path);
+
+        let mut vars = vec![];
+
+        // wget strips the leading directories, so only this one is left
+        let (cmd, args) = match is_dir {
+            true => {
+                let cut_dirs = path.components().count().saturating_sub(2);
+                let mut args = vec![OsString::from("-r"),
+                                    OsString::from("-c"),
+                                    OsString::from("-np"),
+                                    OsString::from("-nH"),
+                                    OsString::from(format!("--cut-dirs={}", cut_dirs)),
+                                    OsString::from("-R"),
+                                    OsString::from("index.html*"),
+                                    OsString::from("-P"),
+                                    target.parent()?.as_os_str().to_os_string()];
+
+                // The password is handed over by the askpass script
+                if let Some(secret) = &self.secret {
+                    let mut parts = secret.splitn(2, ':');
+                    let user = parts.next().unwrap_or("");
+                    let password = parts.next().unwrap_or("");
+                    let mut askpass = OsString::from("--use-askpass=");
+                    askpass.push(crate::credentials::askpass_path()?);
+
+                    args.push(OsString::from(format!("--user={}", user)));
+                    args.push(askpass);
+                    vars.push((OsString::from("HUNTER_SECRET"), OsString::from(password)));
+                }
+
+                args.push(OsString::from(format!("{}/", url)));
+                ("wget", args)
+            }
+            false => {
+                let mut args = self.curl_auth();
+                args.extend(vec![OsString::from("-fL"),
+                                 OsString::from("-C"),
+                                 OsString::from("-"),
+                                 OsString::from("-o"),
+                                 target.as_os_str().to_os_string(),
+                                 OsString::from(url)]);
+                ("curl", args)
+            }
+        };
+
+        Ok(Cmd {
+            cmd: OsString::from(cmd),
+            args: Some(args),
+            vars: Some(vars),
+            short_cmd: Some(format!("download {}", name)),
+            cwd: cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        })
+    }
+}
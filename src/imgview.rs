@@ -36,12 +36,31 @@ impl ImgView {
 
     pub fn encode_file(&mut self) -> HResult<()> {
         let (xsize, ysize) = self.core.coordinates.size_u();
-        let (xpix, ypix) = self.core.coordinates.size_pixels()?;
+
+        // ueberzug draws it by itself, it only needs to know where
+        if self.core.config().graphics == "ueberzug" {
+            let (xpos, ypos) = self.core.coordinates.position_u();
+            let file = self.file.as_ref()?;
+            crate::ueberzug::show(file, xpos, ypos, xsize + 1, ysize + 1)?;
+            self.buffer = vec![];
+            return Ok(());
+        }
+
+        let file = self.file.as_ref()?;
+        self.buffer = ImgView::encode(&self.core, file)?;
+
+        Ok(())
+    }
+
+    // Just the image data, without showing anything, so it can be done
+    // ahead of time
+    pub fn encode(core: &WidgetCore, file: &Path) -> HResult<Vec<String>> {
+        let (xsize, ysize) = core.coordinates.size_u();
+        let (xpix, ypix) = core.coordinates.size_pixels()?;
         let cell_ratio = crate::term::cell_ratio()?;
 
-        let file = &self.file.as_ref()?;
-        let media_previewer = self.core.config().media_previewer;
-        let g_mode = self.core.config().graphics;
+        let media_previewer = core.config().media_previewer;
+        let g_mode = core.config().graphics;
 
         let mut previewer = Command::new(&media_previewer)
             .arg(format!("{}", (xsize+1)))
@@ -65,7 +84,7 @@ impl ImgView {
                                   crate::term::normal_color(),
                                   &e.kind());
 
-                self.core.show_status(&msg).ok();
+                core.show_status(&msg).ok();
 
                 MediaError::NoPreviewer(msg)
             })?;
@@ -100,13 +119,22 @@ impl ImgView {
             }
         }
 
-        self.buffer = output;
-
-        Ok(())
+        Ok(ImgView::passthrough(core, output))
     }
 
     pub fn set_image_data(&mut self, img_data: Vec<String>) {
-        self.buffer = img_data;
+        self.buffer = ImgView::passthrough(&self.core, img_data);
+    }
+
+    // Inside tmux the whole image has to go through in one piece
+    fn passthrough(core: &WidgetCore, lines: Vec<String>) -> Vec<String> {
+        let g_mode = core.config().graphics;
+        if !crate::term::in_tmux() || lines.is_empty() ||
+            !(g_mode == "kitty" || g_mode == "sixel") {
+            return lines;
+        }
+
+        vec![crate::term::passthrough(&lines.join("\n"))]
     }
 
     pub fn lines(&self) -> usize {
@@ -177,7 +205,10 @@ impl Drop for ImgView {
     fn drop(&mut self) {
         let g_mode = self.core.config().graphics;
         if g_mode == "kitty" || g_mode == "auto" {
-            print!("\x1b_Ga=d\x1b\\");
+            print!("{}", crate::term::passthrough("\x1b_Ga=d\x1b\\"));
+        }
+        if g_mode == "ueberzug" {
+            self.file.as_ref().map(|file| crate::ueberzug::clear(file));
         }
     }
 }
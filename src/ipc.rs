@@ -0,0 +1,126 @@
+use parking_lot::Mutex;
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::widget::Events;
+
+
+// With single_instance turned on, the first hunter listens on a socket and
+// later ones started with a path just hand it over and quit. The running
+// one opens a new tab there. One line per request: "reveal /some/path".
+
+lazy_static! {
+    // Only the instance that created the socket removes it again
+    static ref LISTENING: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+// HUNTER_SESSION can be set to keep separate groups of instances
+fn socket_path() -> HResult<PathBuf> {
+    socket_in(std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from),
+              std::env::var("HUNTER_SESSION").ok(),
+              crate::paths::cache_path)
+}
+
+// Set but empty counts as unset, or the socket would end up in the cwd
+fn socket_in(runtime_dir: Option<PathBuf>,
+             session: Option<String>,
+             fallback: impl FnOnce() -> HResult<PathBuf>) -> HResult<PathBuf> {
+    let dir = match runtime_dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => dir,
+        None => fallback()?
+    };
+    let session = session
+        .filter(|session| !session.is_empty())
+        .unwrap_or_else(|| String::from("default"));
+
+    Ok(dir.join(format!("hunter-{}.sock", session)))
+}
+
+// Ok means the running instance took over
+pub fn forward(path: &Path) -> HResult<()> {
+    let mut stream = UnixStream::connect(socket_path()?)?;
+
+    let mut request = b"reveal ".to_vec();
+    request.extend(path.as_os_str().as_bytes());
+    request.push(b'\n');
+
+    stream.write_all(&request)?;
+    Ok(())
+}
+
+pub fn listen(sender: Sender<Events>) -> HResult<()> {
+    let path = socket_path()?;
+
+    // Left behind by a crashed instance, or another one is still running
+    if path.exists() {
+        match UnixStream::connect(&path) {
+            Ok(_) => return Err(HError::Error(String::from("Another instance is listening"))),
+            Err(_) => std::fs::remove_file(&path)?
+        }
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    *LISTENING.lock() = Some(path);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue
+            };
+
+            for line in BufReader::new(stream).split(b'\n') {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break
+                };
+
+                if line.starts_with(b"reveal ") {
+                    let path = std::ffi::OsString::from_vec(line[7..].to_vec());
+                    sender.send(Events::Reveal(PathBuf::from(path))).ok();
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+pub fn stop_listening() {
+    if let Some(path) = LISTENING.lock().take() {
+        std::fs::remove_file(path)
+            .map_err(HError::from)
+            .log();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> HResult<PathBuf> {
+        Ok(PathBuf::from("/cache"))
+    }
+
+    #[test]
+    fn socket_in_runtime_dir() {
+        assert_eq!(socket_in(Some(PathBuf::from("/run/user/1000")), None, cache).unwrap(),
+                   PathBuf::from("/run/user/1000/hunter-default.sock"));
+        assert_eq!(socket_in(Some(PathBuf::from("/run")), Some("work".to_string()), cache).unwrap(),
+                   PathBuf::from("/run/hunter-work.sock"));
+    }
+
+    #[test]
+    fn socket_falls_back_to_cache() {
+        assert_eq!(socket_in(None, None, cache).unwrap(),
+                   PathBuf::from("/cache/hunter-default.sock"));
+        assert_eq!(socket_in(Some(PathBuf::new()), Some(String::new()), cache).unwrap(),
+                   PathBuf::from("/cache/hunter-default.sock"));
+    }
+}
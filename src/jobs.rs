@@ -0,0 +1,375 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::listview::{Listable, ListView};
+use crate::widget::{Widget, WidgetCore};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+use crate::dirty::Dirtyable;
+
+
+// Everything that runs in the background and takes a while, like copying
+// files or extracting archives, so it can be watched, paused and cancelled
+// in one place. Finished jobs stick around until they're cleared.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JobState {
+    Running,
+    Paused,
+    Cancelled,
+    Done,
+    Failed
+}
+
+impl std::fmt::Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let state = match self {
+            JobState::Running => "running",
+            JobState::Paused => "paused",
+            JobState::Cancelled => "cancelled",
+            JobState::Done => "done",
+            JobState::Failed => "failed"
+        };
+        f.write_str(state)
+    }
+}
+
+#[derive(Debug)]
+pub struct Job {
+    pub id: usize,
+    pub description: String,
+    pub total: AtomicU64,
+    pub done: AtomicU64,
+    pub started: Instant,
+    // Processes get stopped with signals, threads have to check themselves
+    pid: Option<u32>,
+    state: Mutex<JobState>,
+    ended: Mutex<Option<Instant>>,
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+lazy_static! {
+    static ref JOBS: Mutex<Vec<Arc<Job>>> = Mutex::new(vec![]);
+}
+
+pub fn add(description: String, pid: Option<u32>) -> Arc<Job> {
+    let job = Arc::new(Job {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        description,
+        total: AtomicU64::new(0),
+        done: AtomicU64::new(0),
+        started: Instant::now(),
+        pid,
+        state: Mutex::new(JobState::Running),
+        ended: Mutex::new(None)
+    });
+
+    JOBS.lock().push(job.clone());
+    job
+}
+
+pub fn all() -> Vec<Arc<Job>> {
+    JOBS.lock().clone()
+}
+
+// Running or paused
+pub fn active() -> usize {
+    JOBS.lock()
+        .iter()
+        .filter(|job| !job.is_finished())
+        .count()
+}
+
+pub fn clear_finished() {
+    JOBS.lock().retain(|job| !job.is_finished());
+}
+
+impl Job {
+    pub fn state(&self) -> JobState {
+        *self.state.lock()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.ended.lock().is_some()
+    }
+
+    fn signal(&self, signal: Signal) -> HResult<()> {
+        if let Some(pid) = self.pid {
+            kill(Pid::from_raw(pid as i32), signal)?;
+        }
+        Ok(())
+    }
+
+    pub fn toggle_pause(&self) -> HResult<()> {
+        if self.is_finished() { return Ok(()); }
+
+        let mut state = self.state.lock();
+        *state = match *state {
+            JobState::Running => {
+                self.signal(Signal::SIGSTOP)?;
+                JobState::Paused
+            }
+            JobState::Paused => {
+                self.signal(Signal::SIGCONT)?;
+                JobState::Running
+            }
+            other => other
+        };
+        Ok(())
+    }
+
+    pub fn cancel(&self) -> HResult<()> {
+        if self.is_finished() { return Ok(()); }
+
+        *self.state.lock() = JobState::Cancelled;
+        self.signal(Signal::SIGTERM)?;
+        // A stopped process wouldn't notice
+        self.signal(Signal::SIGCONT)
+    }
+
+    // Threads doing the work call this between chunks. It waits while the
+    // job is paused and says if it should go on at all.
+    pub fn proceed(&self) -> bool {
+        loop {
+            match self.state() {
+                JobState::Paused => std::thread::sleep(Duration::from_millis(100)),
+                JobState::Cancelled => return false,
+                _ => return true
+            }
+        }
+    }
+
+    pub fn finish(&self, success: bool) {
+        let mut state = self.state.lock();
+        if *state != JobState::Cancelled {
+            *state = match success {
+                true => JobState::Done,
+                false => JobState::Failed
+            };
+        }
+        *self.ended.lock() = Some(Instant::now());
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match *self.ended.lock() {
+            Some(ended) => ended.duration_since(self.started),
+            None => self.started.elapsed()
+        }
+    }
+
+    // Bytes per second so far
+    pub fn rate(&self) -> u64 {
+        let elapsed = self.elapsed().as_secs_f64();
+        if elapsed < 0.1 { return 0; }
+        (self.done.load(Ordering::Relaxed) as f64 / elapsed) as u64
+    }
+
+    pub fn percent(&self) -> u64 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 { return 0; }
+        (self.done.load(Ordering::Relaxed) * 100 / total).min(100)
+    }
+
+    pub fn eta(&self) -> Option<u64> {
+        let rate = self.rate();
+        if rate == 0 { return None; }
+        let left = self.total
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.done.load(Ordering::Relaxed));
+        Some(left / rate)
+    }
+
+    // Without a total there's nothing to fill up, like with processes
+    fn progress_bar(&self, width: usize) -> String {
+        let total = self.total.load(Ordering::Relaxed);
+
+        let filled = match (total, self.state()) {
+            (_, JobState::Done) => width,
+            (0, _) => 0,
+            _ => self.percent() as usize * width / 100
+        };
+
+        format!("[{}{}]",
+                "#".repeat(filled),
+                "-".repeat(width - filled))
+    }
+
+    fn render(&self, xsize: usize) -> String {
+        let state_color = match self.state() {
+            JobState::Running => term::color_yellow(),
+            JobState::Paused => term::highlight_color(),
+            JobState::Done => term::color_green(),
+            JobState::Cancelled |
+            JobState::Failed => term::color_red()
+        };
+
+        let elapsed = self.elapsed().as_secs();
+        let progress = match self.total.load(Ordering::Relaxed) {
+            0 => format!("{}:{:02}", elapsed / 60, elapsed % 60),
+            _ => format!("{:3}% {}/s",
+                         self.percent(),
+                         crate::files::size_string(self.rate()))
+        };
+
+        let line = format!("{} {} {}{:9}{} {}",
+                           self.progress_bar(20),
+                           progress,
+                           state_color,
+                           self.state(),
+                           term::normal_color(),
+                           self.description);
+
+        term::sized_string_u(&line, xsize)
+    }
+}
+
+
+impl Listable for ListView<Vec<Arc<Job>>> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let xsize = self.get_coordinates().unwrap().xsize_u();
+
+        self.content
+            .iter()
+            .map(|job| job.render(xsize))
+            .collect()
+    }
+
+    fn on_refresh(&mut self) -> HResult<()> {
+        self.content = all();
+        self.core.set_dirty();
+        Ok(())
+    }
+}
+
+
+#[derive(Debug)]
+pub struct JobView {
+    core: WidgetCore,
+    list: ListView<Vec<Arc<Job>>>,
+}
+
+impl JobView {
+    pub fn new(core: &WidgetCore) -> JobView {
+        JobView {
+            core: core.clone(),
+            list: ListView::new(core, all())
+        }
+    }
+
+    fn selected_job(&self) -> Option<&Arc<Job>> {
+        self.list.content.get(self.list.get_selection())
+    }
+
+    fn toggle_pause(&mut self) -> HResult<()> {
+        self.selected_job()?.toggle_pause()
+    }
+
+    fn cancel(&mut self) -> HResult<()> {
+        self.selected_job()?.cancel()
+    }
+
+    fn clear_finished(&mut self) -> HResult<()> {
+        clear_finished();
+        self.list.refresh()?;
+
+        if self.list.get_selection() >= self.list.len() {
+            self.list.move_top();
+        }
+
+        self.core.clear()
+    }
+}
+
+impl Widget for JobView {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.list.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        Ok(format!("Jobs: {} active, {} total",
+                   active(),
+                   self.list.len()))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = "p: pause/resume  c: cancel  d: clear finished";
+        Ok(term::sized_string_u(hint, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        self.list.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.list.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, JobAction, Movement};
+
+impl Acting for JobView {
+    type Action = JobAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.job
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        match movement {
+            Up(n) => { for _ in 0..*n { self.list.move_up(); } }
+            Down(n) => { for _ in 0..*n { self.list.move_down(); } }
+            PageUp => self.list.page_up(),
+            PageDown => self.list.page_down(),
+            Top => self.list.move_top(),
+            Bottom => self.list.move_bottom(),
+            Left | Right => {}
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use JobAction::*;
+
+        match action {
+            Close => HError::popup_finnished()?,
+            TogglePause => self.toggle_pause()?,
+            Cancel => self.cancel()?,
+            ClearFinished => self.clear_finished()?
+        }
+
+        Ok(())
+    }
+}
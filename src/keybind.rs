@@ -2,7 +2,7 @@ use termion::event::Key;
 use ini::Ini;
 use strum::IntoEnumIterator;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
 use std::str::FromStr;
 use std::fmt::{Display, Debug};
@@ -32,6 +32,10 @@ impl<T> Bindings<T> {
     pub fn new() -> Self {
         Bindings(HashMap::new())
     }
+
+    pub fn keys(&self) -> impl Iterator<Item=&AnyKey> {
+        self.0.keys()
+    }
 }
 
 
@@ -99,6 +103,14 @@ pub struct KeyBinds {
     pub fold: Bindings<FoldAction>,
     pub log: Bindings<LogAction>,
     pub quickaction: Bindings<QuickActionAction>,
+    pub basket: Bindings<BasketAction>,
+    pub picker: Bindings<PickerAction>,
+    pub batch: Bindings<BatchAction>,
+    pub remote: Bindings<RemoteAction>,
+    pub job: Bindings<JobAction>,
+    pub tree: Bindings<TreeAction>,
+    // Old keys that were moved when loading, like "ShowLog g -> l"
+    pub moved: Vec<String>,
 }
 
 impl Default for KeyBinds {
@@ -114,17 +126,58 @@ impl Default for KeyBinds {
             minibuffer: Bindings::default(),
             fold: Bindings::default(),
             log: Bindings::default(),
-            quickaction: Bindings::default()
+            quickaction: Bindings::default(),
+            basket: Bindings::default(),
+            picker: Bindings::default(),
+            batch: Bindings::default(),
+            remote: Bindings::default(),
+            job: Bindings::default(),
+            tree: Bindings::default(),
+            moved: vec![]
         }
     }
 }
 
+// Keys that had to make room for new ones, by section and action. Keys
+// files from before still have them, and drop_shadowed would just quietly
+// take them away.
+const MOVED_KEYS: &[(&str, &str, &str, &str)] = &[
+    ("filelist", "ToggleDirsFirst", "d", "o"),
+    ("filebrowser", "ShowLog", "g", "l"),
+    ("log", "Close", "g", "l")
+];
+
+fn move_old_keys(ini: &mut Ini) -> Vec<String> {
+    let mut moved = vec![];
+
+    for (section, action, old, new) in MOVED_KEYS {
+        let keys = match ini.get_from(Some(*section), action) {
+            Some(keys) => keys.to_string(),
+            None => continue
+        };
+
+        if !keys.split(',').any(|key| key.trim() == *old) { continue; }
+
+        let mut keys = keys.split(',')
+            .map(|key| key.trim())
+            .filter(|key| key != old && key != new)
+            .collect::<Vec<_>>();
+        keys.insert(0, new);
+
+        ini.with_section(Some(*section)).set(*action, keys.join(","));
+        moved.push(format!("{} {} -> {}", action, old, new));
+    }
+
+    moved
+}
+
 
 impl KeyBinds {
     pub fn load() -> HResult<KeyBinds> {
         let bindings_path = crate::paths::bindings_path()?;
-        let ini = Ini::load_from_file_noescape(bindings_path)
+        let mut ini = Ini::load_from_file_noescape(bindings_path)
             .map_err(KeyBindError::from)?;
+        let moved = move_old_keys(&mut ini);
 
         let movement = Movement::load_section(&ini);
         let filebrowser = FileBrowserAction::load_section(&ini);
@@ -137,6 +190,12 @@ impl KeyBinds {
         let fold = FoldAction::load_section(&ini);
         let log = LogAction::load_section(&ini);
         let quickaction = QuickActionAction::load_section(&ini);
+        let basket = BasketAction::load_section(&ini);
+        let picker = PickerAction::load_section(&ini);
+        let batch = BatchAction::load_section(&ini);
+        let remote = RemoteAction::load_section(&ini);
+        let job = JobAction::load_section(&ini);
+        let tree = TreeAction::load_section(&ini);
 
         Ok(KeyBinds {
             movement,
@@ -149,11 +208,69 @@ impl KeyBinds {
             minibuffer,
             fold,
             log,
-            quickaction
+            quickaction,
+            basket,
+            picker,
+            batch,
+            remote,
+            job,
+            tree,
+            moved
         })
     }
 }
 
+impl KeyBinds {
+    pub fn load_with_preset(preset: Option<&str>) -> HResult<KeyBinds> {
+        let mut keybinds = KeyBinds::load()?;
+
+        if let Some(preset) = preset {
+            keybinds.apply_preset(preset)?;
+        }
+
+        keybinds.drop_shadowed();
+
+        Ok(keybinds)
+    }
+
+    // A key bound in more than one of these only ever does the first thing,
+    // in the order the file browser asks them. Later ones lose it, so it's
+    // at least clear from the log why a key doesn't do what it says.
+    pub fn drop_shadowed(&mut self) {
+        let mut taken = HashMap::new();
+
+        TabAction::drop_taken(&mut self.tab, &mut taken);
+        Movement::drop_taken(&mut self.movement, &mut taken);
+        FileBrowserAction::drop_taken(&mut self.filebrowser, &mut taken);
+        FileListAction::drop_taken(&mut self.filelist, &mut taken);
+    }
+
+    pub fn apply_preset(&mut self, name: &str) -> HResult<()> {
+        let ini = crate::keypresets::load(name)?;
+
+        let movement = Movement::parse_preset(&ini);
+        let filebrowser = FileBrowserAction::parse_preset(&ini);
+        let filelist = FileListAction::parse_preset(&ini);
+        let tab = TabAction::parse_preset(&ini);
+
+        // All of these get the same keys in the file browser, so a key can
+        // only do one thing across them
+        let taken = movement.1.keys()
+            .chain(filebrowser.1.keys())
+            .chain(filelist.1.keys())
+            .chain(tab.1.keys())
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        Movement::apply_preset(&mut self.movement, movement, &taken);
+        FileBrowserAction::apply_preset(&mut self.filebrowser, filebrowser, &taken);
+        FileListAction::apply_preset(&mut self.filelist, filelist, &taken);
+        TabAction::apply_preset(&mut self.tab, tab, &taken);
+
+        Ok(())
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum AnyKey {
     Key(Key),
@@ -245,8 +362,9 @@ impl FromStr for AnyKey {
         }
 
 
-        if key.starts_with("F") && key.len() == 2 {
-            let chr = key.get(1..2);
+        // F1 up to F12
+        if key.starts_with("F") && (key.len() == 2 || key.len() == 3) {
+            let chr = key.get(1..);
 
             if chr == Some("_") {
                 Ok(AnyF)
@@ -420,6 +538,9 @@ where
             for key_str in keys_str.split(",") {
                 let key_str = key_str.trim();
 
+                // Nothing there leaves the action without keys
+                if key_str.is_empty() { continue; }
+
                 let key = key_str.parse::<AnyKey>()
                     .map_err(|_| KeyBindError::WrongKey(action_str.to_string(),
                                                         key_str.to_string()));
@@ -439,6 +560,62 @@ where
             .log_and()
             .unwrap_or_else(|_| Bindings::default())
     }
+
+    // The actions named in a preset, with params, and their new keys
+    fn parse_preset(ini: &Ini) -> (HashSet<String>, Bindings<Self>) {
+        let section = match ini.section(Some(Self::section())) {
+            Some(section) => section,
+            None => return (HashSet::new(), Bindings::new())
+        };
+
+        let actions = section.iter()
+            .filter_map(|(action_str, _)| {
+                let (action_str, param) = Self::process_action_str(action_str);
+                let action = Self::from_str(action_str).ok()?;
+                let action = match param {
+                    Some(param) => action.insert_config_param(param),
+                    None => action.as_default()
+                };
+                Some(format!("{:?}", action))
+            })
+            .collect();
+
+        let bindings = Self::parse_section(ini)
+            .log_and()
+            .unwrap_or_else(|_| Bindings::new());
+
+        (actions, bindings)
+    }
+
+    // Actions named in the preset only keep the keys given there, and keys
+    // it uses are taken away from whatever they did before
+    fn apply_preset(bindings: &mut Bindings<Self>,
+                    preset: (HashSet<String>, Bindings<Self>),
+                    taken: &HashSet<AnyKey>) {
+        let (actions, preset) = preset;
+
+        bindings.0.retain(|key, action| {
+            !taken.contains(key) && !actions.contains(&format!("{:?}", action))
+        });
+        bindings.0.extend(preset.0);
+    }
+
+    fn drop_taken(bindings: &mut Bindings<Self>,
+                  taken: &mut HashMap<AnyKey, &'static str>) {
+        bindings.0.retain(|key, action| {
+            match taken.get(key) {
+                Some(section) => {
+                    HError::from(KeyBindError::Shadowed(key.to_string(),
+                                                        format!("{:?}", action),
+                                                        section.to_string())).log();
+                    false
+                }
+                None => true
+            }
+        });
+
+        taken.extend(bindings.keys().map(|key| (*key, Self::section())));
+    }
 }
 
 
@@ -477,12 +654,57 @@ pub enum FileBrowserAction {
     ShowBookmarks,
     AddBookmark,
     ShowProcesses,
+    ShowJobs,
     ShowLog,
+    TraceRedraws,
     ShowQuickActions,
     RunSubshell,
     ToggleColumns,
     ZoomPreview,
-    ExecCmd
+    QuickLook,
+    Slideshow,
+    TreeView,
+    FuzzyFind,
+    ScrubVideo,
+    ExecCmd,
+    QuickEnter,
+    ShowBasket,
+    DeferNextJob,
+    Flatten,
+    FlatView,
+    Organize,
+    NewFromTemplate,
+    PasteNewFile,
+    MountImage,
+    PackArchive,
+    BrowseContainerImage,
+    BrowseSsh,
+    BrowseHttp,
+    BrowseS3,
+    Sanitize,
+    Renumber,
+    UndoBatch,
+    RegexRename,
+    Suspend,
+    Rescan,
+    Restat,
+    ReloadDir,
+    Chmod(char),
+    Chown,
+    Relabel,
+    FileHistory,
+    ShowHardlinks,
+    OpenFileManager,
+    OpenTerminal,
+    BulkRename,
+    ExportSetup,
+    ImportSetup,
+    SwitchKeyPreset,
+    ShowAuditLog,
+    EditNote,
+    SearchNotes,
+    ChmodTo,
+    SetMaxFps
 }
 
 
@@ -496,6 +718,11 @@ pub enum FileListAction {
     Select,
     InvertSelection,
     ClearSelection,
+    SelectPattern,
+    UnselectPattern,
+    SetAnchor,
+    SelectRange,
+    VisualMode,
     FilterSelection,
     ToggleTag,
     ToggleHidden,
@@ -504,6 +731,20 @@ pub enum FileListAction {
     ToNextMtime,
     ToPrevMtime,
     ToggleDirsFirst,
+    ToggleNaturalSort,
+    QuickSelect,
+    FullMetadata,
+    Yank,
+    Cut,
+    Paste,
+    DeleteFiles,
+    CopyPath,
+    CopyName,
+    CopyPaths,
+    CopyPathsNul,
+    NewFile,
+    NewDir,
+    Rename,
 }
 
 
@@ -515,6 +756,9 @@ pub enum TabAction {
     NextTab,
     PrevTab,
     GotoTab(usize),
+    SaveWorkspace,
+    SwitchWorkspace,
+    TabPrefix,
 }
 
 
@@ -549,7 +793,10 @@ pub enum ProcessAction {
     ScrollOutputPageDown,
     ScrollOutputPageUp,
     ScrollOutputBottom,
-    ScrollOutputTop
+    ScrollOutputTop,
+    GotoLine,
+    ShowReport,
+    ShowRemoved
 }
 
 
@@ -570,7 +817,8 @@ pub enum MiniBufferAction {
     ClearLine,
     DeleteWord,
     CursorToStart,
-    CursorToEnd
+    CursorToEnd,
+    Back
 }
 
 #[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
@@ -589,6 +837,55 @@ pub enum QuickActionAction {
     SelectOrRun(char)
 }
 
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum BasketAction {
+    Close,
+    Remove,
+    Copy,
+    Move,
+    Delete,
+    Archive,
+    Clear
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum PickerAction {
+    Close,
+    Pick,
+    EnterPath
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum BatchAction {
+    Run,
+    Cancel
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum RemoteAction {
+    Close,
+    Enter,
+    Fetch,
+    Upload,
+    Login,
+    Reload
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum JobAction {
+    Close,
+    TogglePause,
+    Cancel,
+    ClearFinished
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum TreeAction {
+    Close,
+    Toggle,
+    CollapseAll
+}
+
 
 
 
@@ -635,8 +932,8 @@ impl Default for Bindings<Movement> {
             let key = match action {
                 Up(_) => Key::Char('k'),
                 Down(_) => Key::Char('j'),
-                Left => Key::Char('h'),
-                Right => Key::Char('l'),
+                Left => Key::Char('b'),
+                Right => Key::Char('f'),
                 Top => Key::Char('<'),
                 Bottom => Key::Char('>'),
                 PageUp => Key::PageUp,
@@ -681,19 +978,71 @@ impl Default for Bindings<FileBrowserAction> {
                 RunInBackground => Char('F'),
                 GotoPrevCwd => Char('-'),
                 ShowBookmarks => Char('`'),
-                AddBookmark => Char('b'),
+                AddBookmark => Char('m'),
                 ShowProcesses => Char('w'),
+                ShowJobs => Char('W'),
                 ShowLog => Char('l'),
+                TraceRedraws => Alt('D'),
                 ShowQuickActions => Char('a'),
                 RunSubshell => Char('z'),
                 ToggleColumns => Char('c'),
                 ZoomPreview => Char('C'),
-                ExecCmd => Char('!')
+                QuickLook => Char('i'),
+                Slideshow => Char('P'),
+                TreeView => Char('T'),
+                FuzzyFind => Ctrl('g'),
+                ScrubVideo => Alt('k'),
+                ExecCmd => Char('!'),
+                QuickEnter => Alt('J'),
+                ShowBasket => Char('B'),
+                DeferNextJob => Alt('d'),
+                Flatten => Alt('f'),
+                FlatView => Alt('l'),
+                Organize => Alt('o'),
+                NewFromTemplate => Alt('n'),
+                PasteNewFile => Alt('p'),
+                MountImage => Alt('i'),
+                PackArchive => Alt('a'),
+                BrowseContainerImage => Alt('c'),
+                BrowseSsh => Alt('h'),
+                BrowseHttp => Alt('w'),
+                BrowseS3 => Alt('3'),
+                Sanitize => Alt('x'),
+                Renumber => Alt('r'),
+                UndoBatch => Alt('u'),
+                RegexRename => Alt('e'),
+                Suspend => Ctrl('z'),
+                Rescan => Char('R'),
+                Restat => Char('I'),
+                ReloadDir => Ctrl('r'),
+                Chmod(_) => Alt('X'),
+                Chown => Alt('O'),
+                Relabel => Alt('Z'),
+                FileHistory => Alt('H'),
+                ShowHardlinks => Alt('L'),
+                OpenFileManager => Alt('g'),
+                OpenTerminal => Alt('z'),
+                BulkRename => Alt('E'),
+                ExportSetup => Alt('y'),
+                ImportSetup => Alt('I'),
+                SwitchKeyPreset => Alt('K'),
+                ShowAuditLog => Alt('A'),
+                EditNote => Char('A'),
+                SearchNotes => Alt('C'),
+                ChmodTo => Alt('W'),
+                SetMaxFps => Alt('U')
             };
 
             filebrowser.insert(key, action.as_default());
         }
 
+        // Like in vim and ranger
+        filebrowser.insert(Char('\''), ShowBookmarks);
+
+        // The other default chmod presets
+        filebrowser.insert(Alt('P'), Chmod('p'));
+        filebrowser.insert(Alt('R'), Chmod('r'));
+
         filebrowser
     }
 }
@@ -702,6 +1051,24 @@ impl BindingSection for FileBrowserAction {
     fn section() -> &'static str {
         "filebrowser"
     }
+
+    fn insert_config_param(self, param: CharOrNum) -> Self {
+        use FileBrowserAction::*;
+
+        match self {
+            Chmod(_) => Chmod(param.char_or('x')),
+            _ => self
+        }
+    }
+
+    fn as_default(self) -> Self {
+        use FileBrowserAction::*;
+
+        match self {
+            Chmod(_) => Chmod('x'),
+            _ => self
+        }
+    }
 }
 
 impl Default for Bindings<FileListAction> {
@@ -720,14 +1087,33 @@ impl Default for Bindings<FileListAction> {
                 Select => Char(' '),
                 InvertSelection => Char('v'),
                 ClearSelection => Char('V'),
+                SelectPattern => Char('+'),
+                UnselectPattern => Alt('+'),
+                SetAnchor => Alt('B'),
+                SelectRange => Alt('b'),
+                VisualMode => Char('x'),
                 FilterSelection => Alt('V'),
                 ToggleTag => Char('t'),
                 ToggleHidden => Char('h'),
                 ReverseSort => Char('r'),
                 CycleSort => Char('s'),
-                ToNextMtime => Char('K'),
-                ToPrevMtime => Char('k'),
-                ToggleDirsFirst => Char('d')
+                ToNextMtime => Char('}'),
+                ToPrevMtime => Char('{'),
+                ToggleDirsFirst => Char('o'),
+                ToggleNaturalSort => Char('N'),
+                QuickSelect => Alt('j'),
+                FullMetadata => Alt('F'),
+                Yank => Char('y'),
+                Cut => Char('d'),
+                Paste => Char('p'),
+                DeleteFiles => Char('D'),
+                CopyPath => Ctrl('y'),
+                CopyName => Alt('N'),
+                CopyPaths => Alt('Y'),
+                CopyPathsNul => Alt('0'),
+                NewFile => Char('n'),
+                NewDir => Char('M'),
+                Rename => Char('e')
             };
 
             filelist.insert(key, action.as_default());
@@ -756,7 +1142,10 @@ impl Default for Bindings<TabAction> {
                 NextTab => Char('\t').into(),
                 PrevTab => BackTab.into(),
                 CloseTab => Ctrl('w').into(),
-                GotoTab(_) => AnyKey::AnyF
+                GotoTab(_) => AnyKey::AnyF,
+                SaveWorkspace => Alt('T').into(),
+                SwitchWorkspace => Alt('t').into(),
+                TabPrefix => Char('g').into(),
             };
 
             tab.insert(key, action.as_default());
@@ -888,7 +1277,10 @@ impl Default for Bindings<ProcessAction> {
                 ScrollOutputPageDown => Ctrl('v'),
                 ScrollOutputPageUp => Ctrl('V'),
                 ScrollOutputBottom => Char('>'),
-                ScrollOutputTop => Ctrl('<')
+                ScrollOutputTop => Ctrl('<'),
+                GotoLine => Alt('g'),
+                ShowReport => Char('r'),
+                ShowRemoved => Char('H')
             };
 
             process.insert(key, action.as_default());
@@ -929,7 +1321,8 @@ impl Default for Bindings<MiniBufferAction> {
                 ClearLine => Ctrl('u').into(),
                 DeleteWord => Ctrl('h').into(),
                 CursorToStart => Ctrl('a').into(),
-                CursorToEnd => Ctrl('e').into()
+                CursorToEnd => Ctrl('e').into(),
+                Back => BackTab.into()
         };
 
             minibuffer.insert(key, action.as_default());
@@ -1083,10 +1476,198 @@ impl BindingSection for QuickActionAction {
     }
 }
 
+impl Default for Bindings<BasketAction> {
+    fn default() -> Self {
+        use Key::*;
+        use BasketAction::*;
+
+        let mut basket = Bindings::new();
+
+        for action in BasketAction::iter() {
+            let key = match action {
+                Close => Char('B'),
+                Remove => Char('d'),
+                Copy => Char('c'),
+                Move => Char('m'),
+                BasketAction::Delete => Char('D'),
+                Archive => Char('a'),
+                Clear => Char('C')
+            };
+
+            basket.insert(key, action.as_default());
+        }
+
+        basket.insert(Esc, Close);
+        basket.insert(Ctrl('c'), Close);
+
+        basket
+    }
+}
+
+impl BindingSection for BasketAction {
+    fn section() -> &'static str {
+        "basket"
+    }
+}
+
+impl Default for Bindings<PickerAction> {
+    fn default() -> Self {
+        use Key::*;
+        use PickerAction::*;
+
+        let mut picker = Bindings::new();
+
+        for action in PickerAction::iter() {
+            let key = match action {
+                Close => Esc,
+                Pick => Char('\n'),
+                EnterPath => Char('/')
+            };
+
+            picker.insert(key, action.as_default());
+        }
+
+        picker.insert(Ctrl('c'), Close);
+
+        picker
+    }
+}
+
+impl BindingSection for PickerAction {
+    fn section() -> &'static str {
+        "picker"
+    }
+}
+
+impl Default for Bindings<BatchAction> {
+    fn default() -> Self {
+        use Key::*;
+        use BatchAction::*;
+
+        let mut batch = Bindings::new();
+
+        for action in BatchAction::iter() {
+            let key = match action {
+                Run => Char('\n'),
+                Cancel => Esc
+            };
+
+            batch.insert(key, action.as_default());
+        }
+
+        batch.insert(Ctrl('c'), Cancel);
+        batch.insert(Char('q'), Cancel);
+
+        batch
+    }
+}
+
+impl BindingSection for BatchAction {
+    fn section() -> &'static str {
+        "batch"
+    }
+}
+
+impl Default for Bindings<RemoteAction> {
+    fn default() -> Self {
+        use Key::*;
+        use RemoteAction::*;
+
+        let mut remote = Bindings::new();
+
+        for action in RemoteAction::iter() {
+            let key = match action {
+                Close => Esc,
+                Enter => Char('\n'),
+                Fetch => Char('f'),
+                Upload => Char('u'),
+                Login => Char('L'),
+                Reload => Char('r')
+            };
+
+            remote.insert(key, action.as_default());
+        }
+
+        remote.insert(Ctrl('c'), Close);
+        remote.insert(Char('q'), Close);
+
+        remote
+    }
+}
+
+impl BindingSection for RemoteAction {
+    fn section() -> &'static str {
+        "remote"
+    }
+}
+
+impl Default for Bindings<JobAction> {
+    fn default() -> Self {
+        use Key::*;
+        use JobAction::*;
+
+        let mut job = Bindings::new();
+
+        for action in JobAction::iter() {
+            let key = match action {
+                Close => Char('W'),
+                TogglePause => Char('p'),
+                Cancel => Char('c'),
+                ClearFinished => Char('d')
+            };
+
+            job.insert(key, action.as_default());
+        }
+
+        job.insert(Esc, Close);
+        job.insert(Ctrl('c'), Close);
+
+        job
+    }
+}
+
+impl BindingSection for JobAction {
+    fn section() -> &'static str {
+        "job"
+    }
+}
+
+impl Default for Bindings<TreeAction> {
+    fn default() -> Self {
+        use Key::*;
+        use TreeAction::*;
+
+        let mut tree = Bindings::new();
+
+        for action in TreeAction::iter() {
+            let key = match action {
+                Close => Char('T'),
+                Toggle => Char(' '),
+                CollapseAll => Char('c')
+            };
+
+            tree.insert(key, action.as_default());
+        }
+
+        tree.insert(Esc, Close);
+        tree.insert(Ctrl('c'), Close);
+        tree.insert(Char('\n'), Close);
+        tree.insert(Char('\t'), Toggle);
+
+        tree
+    }
+}
+
+impl BindingSection for TreeAction {
+    fn section() -> &'static str {
+        "tree"
+    }
+}
+
 
 #[test]
 fn test_keyparse() {
-    let keys = ["C-a", "A-_", "Delete", "a", "F9", "C-_"];
+    let keys = ["C-a", "A-_", "Delete", "a", "F9", "F10", "C-_"];
 
     for key in keys.iter() {
         let parsed = key.parse::<AnyKey>();
@@ -1094,3 +1675,53 @@ fn test_keyparse() {
         dbg!(parsed).ok();
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_dont_shadow_each_other() {
+        let count = |keybinds: &KeyBinds| {
+            keybinds.tab.keys().count() +
+                keybinds.movement.keys().count() +
+                keybinds.filebrowser.keys().count() +
+                keybinds.filelist.keys().count()
+        };
+
+        let mut keybinds = KeyBinds::default();
+        let before = count(&keybinds);
+
+        keybinds.drop_shadowed();
+
+        assert_eq!(count(&keybinds), before);
+    }
+
+    #[test]
+    fn later_sections_lose_shared_keys() {
+        let mut keybinds = KeyBinds::default();
+        keybinds.filelist.insert(Key::Char('j'), FileListAction::Search);
+        keybinds.filelist.insert(Key::Char('y'), FileListAction::Search);
+
+        keybinds.drop_shadowed();
+
+        assert!(keybinds.movement.get(Key::Char('j')).is_some());
+        assert!(keybinds.filelist.get(Key::Char('j')).is_none());
+        assert!(keybinds.filelist.get(Key::Char('y')).is_some());
+    }
+
+    #[test]
+    fn old_keys_move() {
+        let mut ini = Ini::load_from_str("[filelist]\nToggleDirsFirst=d\n\
+                                          [filebrowser]\nShowLog=o\n\
+                                          [log]\nClose=g, Esc\n").unwrap();
+
+        let moved = move_old_keys(&mut ini);
+
+        assert_eq!(moved, vec!["ToggleDirsFirst d -> o", "Close g -> l"]);
+        assert_eq!(ini.get_from(Some("filelist"), "ToggleDirsFirst"), Some("o"));
+        assert_eq!(ini.get_from(Some("filebrowser"), "ShowLog"), Some("o"));
+        assert_eq!(ini.get_from(Some("log"), "Close"), Some("l,Esc"));
+    }
+}
@@ -0,0 +1,149 @@
+use ini::Ini;
+
+use crate::fail::{HResult, HError, KeyBindError};
+
+
+// Keys like in other file managers, put on top of the keys file. Only the
+// actions of the file browser itself are changed, popups keep their keys.
+// More presets can be put into ~/.config/hunter/presets, written like the
+// keys file. Those win over the built in ones with the same name.
+
+const RANGER: &str = "
+[movement]
+Up(1)=k, Up
+Down(1)=j, Down
+Left=h, Left
+Right=l, Right
+Bottom=G, End
+Up(10)=K, C-u
+Down(10)=J, C-d
+PageUp=C-b, PageUp
+PageDown=C-f, PageDown
+
+[filebrowser]
+ShowBookmarks=`, '
+AddBookmark=m
+RunSubshell=S
+ExecCmd=!
+ShowProcesses=w
+
+[filelist]
+Search=/
+SearchNext=n
+SearchPrev=N
+Filter=f
+Select=Space
+InvertSelection=v
+ClearSelection=V
+ToggleHidden=Backspace
+ToggleTag=t
+CycleSort=o
+
+[tab]
+NewTab=C-n
+CloseTab=C-w
+NextTab=Tab
+PrevTab=BackTab
+";
+
+const VIM: &str = "
+[movement]
+Up(1)=k, Up
+Down(1)=j, Down
+Left=h, Left
+Right=l, Right
+Bottom=G, End
+Up(10)=C-u
+Down(10)=C-d
+PageUp=C-b, PageUp
+PageDown=C-f, PageDown
+
+[filebrowser]
+ExecCmd=:
+ShowBookmarks=`, '
+AddBookmark=m
+
+[filelist]
+Search=/
+SearchNext=n
+SearchPrev=N
+VisualMode=V
+ClearSelection=x
+Select=Space
+Yank=y
+Cut=d
+Paste=p
+DeleteFiles=D
+
+[tab]
+NextTab=Tab
+PrevTab=BackTab
+";
+
+// mc copies and moves to the other panel right away, here F5/F6 yank or
+// cut and p pastes them where they should go
+const MC: &str = "
+[movement]
+Top=Home
+Bottom=End
+
+[filebrowser]
+QuickLook=F3
+RunSubshell=C-o
+Quit=F10
+
+[filelist]
+Select=Insert, C-t
+SelectPattern=+
+InvertSelection=*
+ToggleHidden=M-.
+Yank=F5
+Cut=F6
+DeleteFiles=F8, Delete
+
+[tab]
+GotoTab=
+NextTab=Tab
+";
+
+const BUILTIN: &[(&str, &str)] = &[("ranger", RANGER),
+                                   ("vim", VIM),
+                                   ("mc", MC)];
+
+pub fn names() -> Vec<String> {
+    let mut names = BUILTIN.iter()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<_>>();
+
+    let user_presets = crate::paths::presets_path()
+        .and_then(|path| Ok(std::fs::read_dir(path)?));
+
+    if let Ok(entries) = user_presets {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    names
+}
+
+pub fn load(name: &str) -> HResult<Ini> {
+    let user_preset = crate::paths::presets_path()?.join(name);
+
+    if user_preset.exists() {
+        let ini = Ini::load_from_file_noescape(user_preset)
+            .map_err(KeyBindError::from)?;
+        return Ok(ini);
+    }
+
+    let preset = BUILTIN.iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, preset)| preset)
+        .ok_or_else(|| HError::Error(format!("No key preset called {}", name)))?;
+
+    Ini::load_from_str_noescape(preset)
+        .map_err(|e| HError::Error(format!("Key preset {} is broken: {}", name, e)))
+}
@@ -1,5 +1,6 @@
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 
 use termion::event::Key;
 use unicode_width::UnicodeWidthStr;
@@ -7,12 +8,13 @@ use rayon::prelude::*;
 
 use async_value::Stale;
 
-use crate::files::{File, Files};
+use crate::files::{File, Files, NameFilter};
 use crate::fail::{HResult, HError, ErrorLog};
 use crate::term;
-use crate::widget::{Widget, WidgetCore};
+use crate::widget::{Events, Widget, WidgetCore};
 use crate::dirty::Dirtyable;
 use crate::fscache::FsCache;
+use crate::batch::MovePlan;
 
 
 pub trait Listable {
@@ -26,6 +28,103 @@ pub trait Listable {
     fn on_key(&mut self, _key: Key) -> HResult<()> { Ok(()) }
 }
 
+// Extra metadata shown between name and size, set with columns=
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Column {
+    Permissions,
+    Owner,
+    MTime,
+    Links,
+}
+
+// The name keeps at least this much when columns are added
+const MIN_NAME_WIDTH: u16 = 20;
+// Room for the size and a link arrow, so columns line up
+const SIZE_WIDTH: u16 = 10;
+
+impl Column {
+    fn width(&self) -> u16 {
+        match self {
+            Column::Permissions => 10,
+            Column::Owner => 17,
+            Column::MTime => 16,
+            Column::Links => 4,
+        }
+    }
+
+    fn render(&self, file: &File) -> String {
+        use std::os::unix::fs::MetadataExt;
+
+        let meta = match file.meta() {
+            Some(meta) => meta,
+            None => return String::new()
+        };
+        let meta = match meta.as_ref() {
+            Some(meta) => meta,
+            None => return String::new()
+        };
+
+        match self {
+            Column::Permissions => {
+                let mode = meta.mode();
+                let kind = match meta.is_dir() {
+                    true => 'd',
+                    false if meta.file_type().is_symlink() => 'l',
+                    false => '-'
+                };
+                let bits = "rwxrwxrwx"
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| match mode & (0o400 >> i) {
+                        0 => '-',
+                        _ => c
+                    });
+                std::iter::once(kind).chain(bits).collect()
+            }
+            Column::Owner => {
+                let user = users::get_user_by_uid(meta.uid())
+                    .map(|user| user.name().to_string_lossy().to_string())
+                    .unwrap_or_else(|| meta.uid().to_string());
+                let group = users::get_group_by_gid(meta.gid())
+                    .map(|group| group.name().to_string_lossy().to_string())
+                    .unwrap_or_else(|| meta.gid().to_string());
+                format!("{}:{}", user, group)
+            }
+            Column::MTime => file.pretty_mtime().unwrap_or_default(),
+            Column::Links => meta.nlink().to_string()
+        }
+    }
+}
+
+pub fn parse_columns(columns: &str) -> HResult<Vec<Column>> {
+    columns.split(',')
+        .map(|column| column.trim())
+        .filter(|column| !column.is_empty())
+        .map(|column| match column {
+            "permissions" => Ok(Column::Permissions),
+            "owner" => Ok(Column::Owner),
+            "mtime" => Ok(Column::MTime),
+            "links" => Ok(Column::Links),
+            _ => Err(HError::Error(format!("Unknown column: {}", column)))
+        })
+        .collect()
+}
+
+// As many as fit next to the name, in the order they were given
+fn fitting_columns(columns: &[Column], xsize: u16) -> Vec<Column> {
+    let mut room = xsize.saturating_sub(MIN_NAME_WIDTH + SIZE_WIDTH);
+
+    columns.iter()
+        .take_while(|column| {
+            let width = column.width() + 1;
+            let fits = width <= room;
+            if fits { room -= width; }
+            fits
+        })
+        .cloned()
+        .collect()
+}
+
 use crate::keybind::{Acting, Bindings, FileListAction, Movement};
 
 
@@ -69,6 +168,11 @@ impl Acting for ListView<Files> {
             Select => self.multi_select_file(),
             InvertSelection => self.invert_selection(),
             ClearSelection => self.clear_selections(),
+            SelectPattern => self.select_pattern(true)?,
+            UnselectPattern => self.select_pattern(false)?,
+            SetAnchor => self.set_anchor(),
+            VisualMode => self.toggle_visual()?,
+            SelectRange => self.select_range()?,
             FilterSelection => self.toggle_filter_selected(),
             ToggleTag => self.toggle_tag()?,
             ToggleHidden => self.toggle_hidden(),
@@ -77,6 +181,16 @@ impl Acting for ListView<Files> {
             ToNextMtime => self.select_next_mtime(),
             ToPrevMtime => self.select_prev_mtime(),
             ToggleDirsFirst => self.toggle_dirs_first(),
+            ToggleNaturalSort => self.toggle_natural_sort(),
+            QuickSelect => { self.quick_select().ok(); }
+            FullMetadata => self.full_metadata(),
+            Yank | Cut | DeleteFiles => self.file_op(*action)?,
+            // Handled by the file browser, see on_key
+            Paste => {}
+            CopyPath | CopyName | CopyPaths | CopyPathsNul => self.copy_paths(*action)?,
+            NewFile => self.create(false)?,
+            NewDir => self.create(true)?,
+            Rename => self.rename()?
         }
 
         Ok(())
@@ -108,6 +222,11 @@ impl Listable for ListView<Files> {
             self.content.len = 1;
         }
 
+        let threshold = self.core.config().fast_mode_threshold;
+        self.content.fast = threshold > 0 &&
+                            self.content.len() > threshold &&
+                            !self.content.full_meta;
+
         let meta_upto = self.content.meta_upto.unwrap_or(0);
         let ysize = self.core.coordinates.ysize_u();
 
@@ -119,17 +238,74 @@ impl Listable for ListView<Files> {
             self.content.run_jobs(sender);
         }
 
+        self.content.load_rest(self.core.get_sender());
         self.refresh_files().log();
 
         if self.content.is_dirty() {
             self.content.set_clean();
             self.core.set_dirty();
+            crate::redraw::mark("ListView<Files>",
+                                format!("{} changed",
+                                        self.content.directory.name));
         }
 
         Ok(())
     }
 
     fn on_key(&mut self, key: Key) -> HResult<()> {
+        use FileListAction::*;
+
+        if self.visual.is_some() {
+            let action = self.search_in().get(key).cloned();
+
+            match (key, action) {
+                (Key::Esc, _) |
+                (_, Some(VisualMode)) => return self.toggle_visual(),
+                // Only needs one press in here, like in vim
+                (_, Some(action @ Yank)) |
+                (_, Some(action @ Cut)) |
+                (_, Some(action @ DeleteFiles)) |
+                (_, Some(action @ ToggleTag)) |
+                (_, Some(action @ Select)) => return self.visual_action(action),
+                (Key::Char('\n'), _) => return self.visual_action(Select),
+                _ => return self.do_key(key)
+            }
+        }
+
+        let pending = self.pending_op.take();
+        if pending.is_some() {
+            crate::whichkey::done(&self.core);
+        }
+        let file_op = match self.search_in().get(key) {
+            Some(action @ Yank) |
+            Some(action @ Cut) |
+            Some(action @ Paste) |
+            Some(action @ DeleteFiles) => Some(*action),
+            _ => None
+        };
+
+        if let Some(file_op) = file_op {
+            if pending != Some(key) {
+                self.pending_op = Some(key);
+                let key = match key {
+                    Key::Char(c) => c.to_string(),
+                    _ => format!("{:?}", key)
+                };
+
+                let keys = vec![(key.clone(), file_op.to_string())];
+                crate::whichkey::start(&self.core, &key, keys);
+
+                return self.core.show_status(&format!("{}-", key));
+            }
+
+            self.core.clear_status().log();
+
+            // The file browser knows more about where things can go
+            if let Paste = file_op { return HError::undefined_key(key); }
+
+            return self.do_action(&file_op);
+        }
+
         self.do_key(key)
     }
 }
@@ -146,6 +322,15 @@ where
     pub core: WidgetCore,
     seeking: bool,
     searching: Option<String>,
+    labeling: bool,
+    // Yank, Cut and co. need their key twice, like yy in vim
+    pending_op: Option<Key>,
+    // Where a range selection starts
+    anchor: Option<<ListView<T> as Listable>::Item>,
+    // Visual mode, where the highlighted region started
+    visual: Option<usize>,
+    // Something just created, selected as soon as it shows up
+    select_new: Option<PathBuf>,
 }
 
 impl<T> ListView<T>
@@ -161,7 +346,12 @@ where
             offset: 0,
             core: core.clone(),
             seeking: false,
-            searching: None
+            searching: None,
+            labeling: false,
+            pending_op: None,
+            anchor: None,
+            visual: None,
+            select_new: None
         };
         view.on_new().log();
         view
@@ -224,6 +414,36 @@ where
         self.selection
     }
 
+    // First and last line of the visual mode region
+    pub fn visual_range(&self) -> Option<(usize, usize)> {
+        Some(region(self.visual?, self.selection))
+    }
+
+    pub fn restore_offset(&mut self, offset: usize) {
+        let ysize = match self.get_coordinates() {
+            Ok(coords) => coords.ysize() as usize,
+            Err(_) => return
+        };
+
+        if offset <= self.selection && self.selection < offset + ysize {
+            self.offset = offset;
+        }
+    }
+
+    // Item on screen line ypos, like where the mouse was clicked
+    pub fn index_at(&self, ypos: u16) -> Option<usize> {
+        let coords = self.get_coordinates().ok()?;
+        let row = ypos.checked_sub(coords.ypos())? as usize;
+
+        if row >= coords.ysize() as usize { return None; }
+
+        let index = self.offset + row;
+        match index < self.len() {
+            true => Some(index),
+            false => None
+        }
+    }
+
     pub fn set_selection(&mut self, position: usize) {
         let ysize = self.get_coordinates().unwrap().ysize() as usize;
         let mut offset = 0;
@@ -327,6 +547,14 @@ impl FileListBuilder {
             files.recalculate_len();
         }
 
+        // Until it's toggled for this directory
+        let has_view = c.as_ref()
+            .map(|c| c.has_view(&files.directory))
+            .unwrap_or(false);
+        if !has_view {
+            files.natural = cfg.natural_sort;
+        }
+
         // TODO: Fix sorting so it works with lazy/partial sorting
         if !nosort {
             files.sort();
@@ -339,6 +567,11 @@ impl FileListBuilder {
                      .and_then(|c| c.get_selection(&view.content.directory).ok()))
             .map(|f| view.select_file(&f));
 
+        // Scrolled just like it was, as long as the selection is still visible
+        c.as_ref()
+            .and_then(|c| c.get_offset(&view.content.directory).ok())
+            .map(|offset| view.restore_offset(offset));
+
         self.stale.map(|s| view.content.stale = Some(s));
         self.cache.map(|c| view.content.cache = Some(c));
         view.content.set_clean();
@@ -405,6 +638,11 @@ impl ListView<Files>
         }
     }
 
+    pub fn file_at(&self, ypos: u16) -> Option<File> {
+        let index = self.index_at(ypos)?;
+        self.content.iter_files().nth(index).cloned()
+    }
+
     pub fn clone_selected_file(&self) -> File {
         let file = self.selected_file().clone();
         file
@@ -531,6 +769,23 @@ impl ListView<Files>
                                         self.content.show_hidden)).log();
     }
 
+    // Colors and directory sizes in huge directories, or back to fast mode
+    fn full_metadata(&mut self) {
+        self.content.full_meta = !self.content.full_meta;
+
+        // Directory sizes still have to be counted for what's already loaded
+        if self.content.full_meta {
+            self.content.meta_upto = None;
+        }
+
+        let status = match self.content.full_meta {
+            true => "Showing everything",
+            false => "Fast mode for huge directories"
+        };
+        self.core.show_status(status).log();
+        self.refresh().log();
+    }
+
     fn toggle_dirs_first(&mut self) {
         let file = self.clone_selected_file();
         self.content.dirs_first = !self.content.dirs_first;
@@ -541,6 +796,292 @@ impl ListView<Files>
                                         self.content.dirs_first)).log();
     }
 
+    fn toggle_natural_sort(&mut self) {
+        let file = self.clone_selected_file();
+        self.content.natural = !self.content.natural;
+        self.content.sort();
+        self.select_file(&file);
+        self.refresh().log();
+        self.core.show_status(&format!("Natural sorting: {}",
+                                       self.content.natural)).log();
+    }
+
+    // Like touch and mkdir -p, names like "src/lib.rs" make the
+    // directories in between too
+    fn create(&mut self, dir: bool) -> HResult<()> {
+        use crate::minibuffer::Validator;
+
+        let query = match dir {
+            true => "mkdir",
+            false => "touch"
+        };
+        let name = self.core.minibuffer_validated(query, "", Validator::NonEmpty)?;
+
+        // a/b is fine, but nothing that ends up outside of here
+        let inside = Path::new(&name)
+            .components()
+            .all(|part| match part {
+                Component::Normal(_) => true,
+                _ => false
+            });
+        if !inside {
+            let status = format!("{}Not a valid name: {}", term::color_red(), name);
+            return self.core.show_status(&status);
+        }
+
+        let path = self.content.directory.path.join(&name);
+
+        if path.symlink_metadata().is_ok() {
+            let status = format!("{}{} already exists!", term::color_red(), name);
+            return self.core.show_status(&status);
+        }
+
+        match dir {
+            true => std::fs::create_dir_all(&path)?,
+            false => {
+                std::fs::create_dir_all(path.parent()?)?;
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+            }
+        }
+        crate::audit::record(query, &path, None);
+
+        // What shows up in here is the first part of the name
+        if let Some(Component::Normal(first)) = Path::new(&name).components().next() {
+            let hidden = first.to_string_lossy().starts_with(".");
+            if !hidden || self.content.show_hidden {
+                self.select_new = Some(self.content.directory.path.join(first));
+            }
+        }
+
+        self.core.show_status(&format!("Created {}", name))
+    }
+
+    // The cursor starts before the extension, that's rarely what changes
+    fn rename(&mut self) -> HResult<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let file = self.clone_selected_file();
+        let position = match (file.is_dir(), file.name.rfind('.')) {
+            (false, Some(pos)) if pos > 0 => pos,
+            _ => file.name.len()
+        };
+
+        let name = self.core.minibuffer_prefilled_at("rename", &file.name, position)?;
+        if name == file.name { return Ok(()); }
+
+        if name.contains('/') || name == "." || name == ".." {
+            let status = format!("{}Not a valid name: {}", term::color_red(), name);
+            return self.core.show_status(&status);
+        }
+
+        let target = file.path.with_file_name(&name);
+
+        // On case insensitive filesystems changing the case finds the file itself
+        if let Ok(existing) = target.symlink_metadata() {
+            let meta = file.path.symlink_metadata()?;
+            if existing.dev() != meta.dev() || existing.ino() != meta.ino() {
+                let status = format!("{}{} already exists!", term::color_red(), name);
+                return self.core.show_status(&status);
+            }
+        }
+
+        std::fs::rename(&file.path, &target)?;
+        crate::audit::record("rename", &file.path, Some(&target));
+
+        if !name.starts_with(".") || self.content.show_hidden {
+            self.select_new = Some(target);
+        }
+
+        self.core.show_status(&format!("Renamed {} to {}", file.name, name))
+    }
+
+    // To the system clipboard, for pasting into other programs
+    fn copy_paths(&mut self, action: FileListAction) -> HResult<()> {
+        use std::os::unix::ffi::OsStrExt;
+        use FileListAction::*;
+
+        let file = self.clone_selected_file();
+
+        let (content, what) = match action {
+            CopyPath => (file.path.as_os_str().as_bytes().to_vec(),
+                         file.path.to_string_lossy().to_string()),
+            CopyName => (file.path.file_name()?.as_bytes().to_vec(),
+                         file.name.clone()),
+            _ => {
+                let mut files = match self.visual_files() {
+                    Some(files) => files,
+                    None => self.content.get_selected().cloned().collect()
+                };
+                if files.is_empty() {
+                    files.push(file);
+                }
+
+                let separator = match action {
+                    CopyPathsNul => b'\0',
+                    _ => b'\n'
+                };
+                let content = files.iter()
+                    .map(|file| file.path.as_os_str().as_bytes())
+                    .collect::<Vec<_>>()
+                    .join(&separator);
+
+                (content, format!("{} paths", files.len()))
+            }
+        };
+
+        match crate::clipboard::copy(&content) {
+            Ok(()) => self.core.show_status(&format!("Copied {}", what)),
+            Err(HError::Error(msg)) => {
+                self.core.show_status(&format!("{}{}", term::color_red(), msg))
+            }
+            Err(err) => Err(err)
+        }
+    }
+
+    fn file_op(&mut self, action: FileListAction) -> HResult<()> {
+        use crate::fileops::OpKind;
+        use FileListAction::*;
+
+        let mut paths = match self.visual_files() {
+            Some(files) => files.into_iter().map(|file| file.path).collect(),
+            None => self.content
+                .get_selected()
+                .map(|file| file.path.clone())
+                .collect::<Vec<_>>()
+        };
+        if paths.is_empty() {
+            paths.push(self.clone_selected_file().path);
+        }
+
+        match action {
+            Yank | Cut => {
+                let kind = match action {
+                    Yank => OpKind::Copy,
+                    _ => OpKind::Move
+                };
+                let status = format!("{} {} files", kind, paths.len());
+                crate::fileops::yank(kind, paths);
+                self.core.show_status(&status)
+            }
+            _ => {
+                let answer = crate::minibuffer::Wizard::new()
+                    .step(&format!("delete {} files?", paths.len()))
+                    .choices(&["no", "yes"])
+                    .run(&self.core)?;
+                if answer.get(0)? != "yes" { return Ok(()); }
+
+                let blocked = paths.iter()
+                    .filter_map(|path| path.parent())
+                    .find_map(|dir| Some((dir, crate::stats::write_blocker(dir)?)));
+                if let Some((dir, reason)) = blocked {
+                    let status = format!("{}{}: {}",
+                                         term::color_red(),
+                                         dir.to_string_lossy(),
+                                         reason);
+                    return self.core.show_status(&status);
+                }
+
+                crate::fileops::start(OpKind::Delete,
+                                      paths,
+                                      None,
+                                      HashMap::new(),
+                                      Default::default(),
+                                      self.core.get_sender());
+                Ok(())
+            }
+        }
+    }
+
+    // The names of the selected files go into a temporary file, one per
+    // line, and whatever is left there after $EDITOR quits are the new ones
+    // In its own directory only we can get into, so nobody else can swap
+    // the file out from under the editor
+    fn rename_file(names: &str) -> HResult<(PathBuf, PathBuf)> {
+        use std::io::Write;
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+        let dir = (0..100)
+            .map(|i| std::env::temp_dir()
+                 .join(format!("hunter-rename-{}-{}", std::process::id(), i)))
+            .find(|dir| std::fs::DirBuilder::new()
+                  .mode(0o700)
+                  .create(dir)
+                  .is_ok())
+            .ok_or_else(|| HError::Error(String::from("Can't create a temporary directory")))?;
+        let tmp = dir.join("names");
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmp)?;
+        file.write_all(format!("{}\n", names).as_bytes())?;
+
+        Ok((dir, tmp))
+    }
+
+    pub fn edit_names(&mut self) -> HResult<(MovePlan, Vec<String>)> {
+        use crate::term::ScreenExt;
+
+        let mut paths = self.content
+            .get_selected()
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            paths.push(self.clone_selected_file().path);
+        }
+
+        let names = paths.iter()
+            .map(|path| path.file_name()
+                 .map(|name| name.to_string_lossy().to_string())
+                 .unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        if names.iter().any(|name| name.contains('\n')) {
+            return Err(HError::Error(String::from("Can't edit names with newlines in them")));
+        }
+
+        let (dir, tmp) = Self::rename_file(&names.join("\n"))?;
+
+        let editor = std::env::var("EDITOR").unwrap_or_else(|_| String::from("vi"));
+
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+        self.core.screen.suspend().log();
+
+        // $EDITOR can come with arguments
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", editor))
+            .arg("sh")
+            .arg(&tmp)
+            .status();
+
+        self.core.screen.activate().log();
+        self.core.get_sender().send(Events::InputEnabled(true))?;
+
+        let edited = std::fs::read_to_string(&tmp);
+        std::fs::remove_dir_all(&dir).ok();
+
+        if !status?.success() {
+            return Err(HError::Error(format!("{} failed, nothing renamed", editor)));
+        }
+
+        let edited = edited?;
+        let new_names = edited.lines().collect::<Vec<_>>();
+
+        // Lines have to stay where they are to know which file is which
+        if new_names.len() != names.len() {
+            return Err(HError::Error(format!("Expected {} names, got {}, nothing renamed",
+                                             names.len(),
+                                             new_names.len())));
+        }
+
+        Ok(crate::batch::bulk_rename_plan(&paths, &new_names))
+    }
+
     fn multi_select_file(&mut self) {
         self.selected_file_mut().toggle_selection();
 
@@ -563,6 +1104,105 @@ impl ListView<Files>
         }
     }
 
+    fn toggle_visual(&mut self) -> HResult<()> {
+        self.visual = match self.visual {
+            Some(_) => None,
+            None => Some(self.get_selection())
+        };
+        self.core.set_dirty();
+
+        match self.visual {
+            Some(_) => self.core.show_status("-- VISUAL --"),
+            None => self.core.clear_status()
+        }
+    }
+
+    fn visual_files(&self) -> Option<Vec<File>> {
+        let (start, end) = self.visual_range()?;
+
+        let files = self.content
+            .iter_files()
+            .skip(start)
+            .take(end - start + 1)
+            .cloned()
+            .collect();
+        Some(files)
+    }
+
+    // Runs action on the whole region and leaves visual mode
+    fn visual_action(&mut self, action: FileListAction) -> HResult<()> {
+        use FileListAction::*;
+
+        let (start, end) = self.visual_range()?;
+
+        match action {
+            ToggleTag => {
+                for file in self.content.iter_files_mut().skip(start).take(end - start + 1) {
+                    file.toggle_tag()?;
+                }
+            }
+            Select => toggle_region(&mut self.content, start, end),
+            _ => self.file_op(action)?
+        }
+
+        self.visual = None;
+        self.core.set_dirty();
+
+        if self.content.filter_selected {
+            self.content.set_dirty();
+            self.refresh().log();
+        }
+
+        Ok(())
+    }
+
+    fn set_anchor(&mut self) {
+        let file = self.clone_selected_file();
+        let status = format!("Anchor set at {}", file.name);
+        self.anchor = Some(file);
+        self.core.show_status(&status).log();
+    }
+
+    // Everything between anchor and cursor gets selected, or unselected
+    // if it already was
+    fn select_range(&mut self) -> HResult<()> {
+        let anchor = match self.anchor.as_ref() {
+            Some(anchor) => anchor,
+            None => return self.core.show_status("No anchor set!")
+        };
+
+        let anchor_pos = self.content
+            .iter_files()
+            .position(|file| file == anchor);
+        let anchor_pos = match anchor_pos {
+            Some(pos) => pos,
+            None => {
+                self.anchor = None;
+                return self.core.show_status("Anchor is gone!");
+            }
+        };
+
+        let (start, end) = region(anchor_pos, self.get_selection());
+        let count = end - start + 1;
+        let select = select_region(&mut self.content, start, end);
+
+        // Only the lines in the range look different now, unless some
+        // of them just disappeared from the selection filter
+        match self.content.filter_selected && !select {
+            true => {
+                self.content.set_dirty();
+                self.refresh().log();
+            }
+            false => self.core.set_dirty()
+        }
+
+        let status = match select {
+            true => format!("Selected {} files", count),
+            false => format!("Unselected {} files", count)
+        };
+        self.core.show_status(&status)
+    }
+
     pub fn invert_selection(&mut self) {
         for file in self.content.iter_files_mut() {
             file.toggle_selection();
@@ -585,6 +1225,91 @@ impl ListView<Files>
         self.refresh().log();
     }
 
+    // Patterns work like in the filter, *.log, /regex or part of the name
+    fn select_pattern(&mut self, select: bool) -> HResult<()> {
+        let query = match select {
+            true => "select",
+            false => "unselect"
+        };
+        let pattern = self.core.minibuffer(query)?;
+
+        let filter = match NameFilter::parse(&pattern) {
+            Ok(filter) => filter,
+            Err(err) => {
+                self.show_filter_error(err);
+                return Ok(());
+            }
+        };
+
+        let mut count = 0;
+        for file in self.content.iter_files_mut() {
+            if file.selected != select && filter.matches(&file.name) {
+                file.selected = select;
+                count += 1;
+            }
+        }
+
+        if self.content.filter_selected && self.content.len() == 0 {
+                self.content.toggle_filter_selected();
+                self.core.show_status("Disabled selection filter!").log();
+        }
+
+        self.content.set_dirty();
+        self.refresh().log();
+
+        let msg = format!("{}ed {} files", query, count);
+        self.core.show_status(&msg)
+    }
+
+    pub fn quick_select(&mut self) -> HResult<File> {
+        use crate::minibuffer::MiniBufferEvent::*;
+
+        self.labeling = true;
+        self.core.set_dirty();
+        self.draw().log();
+
+        let file = loop {
+            let input = match self.core.minibuffer_continuous("label") {
+                Ok(input) => input,
+                Err(HError::MiniBufferEvent(NewInput(input))) => input,
+                Err(HError::MiniBufferEvent(Back)) => continue,
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                    continue;
+                }
+                Err(_) => break None
+            };
+
+            if input.chars().count() < 2 { continue; }
+
+            let ysize = self.get_coordinates()?.ysize_u();
+            let file = label_index(&input)
+                .filter(|&i| i <= ysize)
+                .and_then(|i| self.content.iter_files().nth(self.offset + i))
+                .cloned();
+
+            if file.is_none() {
+                self.core.show_status(&format!("No such label: {}", input)).log();
+            }
+
+            break file;
+        };
+
+        self.labeling = false;
+        self.core.minibuffer_clear().log();
+        self.core.set_dirty();
+
+        let file = file?;
+
+        // Don't scroll, the file is already visible
+        let offset = self.offset;
+        self.select_file(&file);
+        self.offset = offset;
+
+        Ok(file)
+    }
+
     fn toggle_tag(&mut self) -> HResult<()> {
         self.selected_file_mut().toggle_tag()?;
 
@@ -641,6 +1366,7 @@ impl ListView<Files>
                         CyclePrev => {
                             self.search_prev().log();
                         }
+                        Back => continue
                     }
                 }
                 _ => {  }
@@ -714,22 +1440,34 @@ impl ListView<Files>
         Ok(())
     }
 
-    pub fn set_filter(&mut self, filter: Option<String>) {
+    pub fn set_filter(&mut self, filter: Option<String>) -> HResult<()> {
+        if self.content.get_filter() == filter { return Ok(()); }
+
         let prev_len = self.len();
         let selected_file = self.clone_selected_file();
 
-        self.content.set_filter(filter);
+        self.content.set_filter(filter)?;
 
-        // Only do something if filter changed something
-        if self.len() != prev_len {
-            self.refresh().ok();
-            self.select_file(&selected_file);
-            // Clear away that wouldn't get drawn over
-            if self.len() < prev_len {
-                self.core.clear().ok();
-            }
-            self.draw().ok();
+        // Runs on every key typed into the filter, so the list has to be
+        // redrawn even when just as many files are left over
+        self.refresh().ok();
+
+        // Might be filtered out now, then the first one is as good as any
+        let still_there = self.content
+            .iter_files()
+            .any(|file| file == &selected_file);
+        match still_there {
+            true => self.select_file(&selected_file),
+            false => self.move_top()
         }
+
+        // Clear away that wouldn't get drawn over
+        if self.len() < prev_len {
+            self.core.clear().ok();
+        }
+        self.draw().ok();
+
+        Ok(())
     }
 
     fn filter(&mut self) -> HResult<()> {
@@ -745,25 +1483,43 @@ impl ListView<Files>
                 Err(HError::MiniBufferEvent(event)) => {
                     match event {
                         Done(filter) => {
-                            self.core.show_status(&format!("Filtering with: \"{}\"",
-                                                           &filter)).log();
-
-                            self.set_filter(Some(filter));
+                            match self.set_filter(Some(filter.clone())) {
+                                Ok(()) => {
+                                    let msg = format!("Filtering with: \"{}\"", &filter);
+                                    self.core.show_status(&msg).log();
+                                }
+                                Err(err) => {
+                                    self.set_filter(prev_filter.take()).log();
+                                    self.select_file(&selected_file);
+                                    self.show_filter_error(err);
+                                }
+                            }
                         }
                         NewInput(input) => {
-                            self.set_filter(Some(input.clone()));
+                            // Half typed patterns are often invalid, the
+                            // last good one stays until it's fixed
+                            if let Err(err) = self.set_filter(Some(input.clone())) {
+                                self.show_filter_error(err);
+                            }
                             continue;
                         }
                         Empty => {
-                            self.set_filter(None);
+                            self.set_filter(None).log();
                         }
                         Cancelled => {
-                            self.set_filter(prev_filter.take());
+                            self.set_filter(prev_filter.take()).log();
                             self.select_file(&selected_file);
                         }
+                        Back => continue,
                         _ => {}
                     }
                 }
+                // Directory changed while typing
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                    continue;
+                }
                 _ => {}
             }
 
@@ -774,6 +1530,14 @@ impl ListView<Files>
         Ok(())
     }
 
+    fn show_filter_error(&self, err: HError) {
+        let msg = match err {
+            HError::Error(msg) => msg,
+            err => format!("{}", err)
+        };
+        self.core.show_status(&format!("{}{}", term::color_red(), msg)).log();
+    }
+
     fn toggle_filter_selected(&mut self) {
         self.content.toggle_filter_selected();
 
@@ -799,6 +1563,12 @@ impl ListView<Files>
         let config = self.core.config();
         let icons = config.icons;
         let icons_space = config.icons_space;
+        let fast = self.content.fast;
+        let color_rules = config.color_rules.clone();
+        let columns = fitting_columns(&config.columns, xsize);
+        let columns_width = columns.iter()
+            .map(|column| column.width() + 1)
+            .sum::<u16>();
 
         move |file| -> String {
             let mut line = String::with_capacity(500);
@@ -813,6 +1583,10 @@ impl ListView<Files>
 
             let size = file.calculate_size();
             let (size, unit) = match size {
+                // Not counted in fast mode
+                Ok(_) if fast && file.is_dir() && file.dirsize.is_none() => {
+                    (String::new(), "")
+                }
                 Ok((size, unit)) => (size.to_string(), unit),
                 Err(HError::FileError(FileError::MetaPending)) => {
                     let ticks = crate::files::tick_str();
@@ -849,7 +1623,12 @@ impl ListView<Files>
                                                .unwrap_or("");
             let link_indicator_len = link_indicator_len.unwrap_or(0);
 
-            let sized_string = term::sized_string(&name, xsize);
+            // Columns get drawn over the end of long names otherwise
+            let name_width = match columns_width {
+                0 => xsize,
+                _ => xsize - columns_width - SIZE_WIDTH
+            };
+            let sized_string = term::sized_string(&name, name_width);
 
             let size = size.to_string();
             let size_pos = xsize - (size.len() as u16 +
@@ -865,9 +1644,20 @@ impl ListView<Files>
 
             write!(&mut line, "{}", termion::cursor::Save).unwrap();
 
-            match file.get_color() {
+            let rule_style = crate::colorrules::style_for(&color_rules, name);
+            let color = match (rule_style, fast) {
+                (Some(style), _) => Some(style.to_string()),
+                (None, true) => None,
+                (None, false) => file.get_color()
+            };
+            let style_end = match rule_style {
+                Some(_) => crate::colorrules::STYLE_END,
+                None => ""
+            };
+
+            match color {
                 Some(color) => write!(&mut line,
-                                      "{}{}{}{}{}{}{:padding$}{}",
+                                      "{}{}{}{}{}{}{:padding$}{}{}",
                                       tag,
                                       &color,
                                       selection_color,
@@ -875,6 +1665,7 @@ impl ListView<Files>
                                       icon,
                                       icon_space,
                                       &sized_string,
+                                      style_end,
                                       term::normal_color(),
                                       padding = padding as usize),
                 _ => write!(&mut line,
@@ -890,6 +1681,24 @@ impl ListView<Files>
                                padding = padding as usize),
             }.unwrap();
 
+            if columns_width > 0 {
+                let text = columns.iter()
+                    .map(|column| {
+                        let width = column.width() as usize;
+                        let text = column.render(file);
+                        format!(" {:>width$}", term::sized_string(&text, width as u16),
+                                width = width)
+                    })
+                    .collect::<String>();
+
+                write!(&mut line,
+                       "{}{}{}{}",
+                       termion::cursor::Restore,
+                       termion::cursor::Right(xsize - columns_width - SIZE_WIDTH),
+                       term::normal_color(),
+                       text).unwrap();
+            }
+
             write!(&mut line,
                    "{}{}{}{}{}{}",
                    termion::cursor::Restore,
@@ -910,11 +1719,26 @@ impl ListView<Files>
         let ysize = self.get_coordinates().unwrap().ysize_u();
         let files_above_selection = self.get_selection() - self.offset;
         let selected_file = self.selected_file();
+        let labeling = self.labeling;
 
         self.content
             .iter_files_from(selected_file, files_above_selection)
             .take(ysize+1)
-            .map(|file| render_fn(file))
+            .enumerate()
+            .map(|(i, file)| {
+                let line = render_fn(file);
+
+                // Draw label over the start of the line
+                match labeling {
+                    true => format!("{}{}{}{}{}",
+                                    line,
+                                    termion::cursor::Restore,
+                                    term::color_red(),
+                                    index_label(i),
+                                    term::normal_color()),
+                    false => line
+                }
+            })
             .collect()
     }
 
@@ -922,6 +1746,18 @@ impl ListView<Files>
         let file = self.clone_selected_file();
 
         if let Ok(Some(_)) = self.content.get_refresh() {
+            // Something new might be there now
+            let file = match self.select_new.take() {
+                Some(path) => match self.content.find_file_with_path(&path) {
+                    Some(new) => new.clone(),
+                    None => {
+                        self.select_new = Some(path);
+                        file
+                    }
+                },
+                None => file
+            };
+
             // Positions might change when files are added/removed/renamed
             self.select_file(&file);
             self.content.run_jobs(self.core.get_sender());
@@ -936,6 +1772,52 @@ impl ListView<Files>
 }
 
 
+const LABEL_CHARS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+fn index_label(i: usize) -> String {
+    let chars = LABEL_CHARS.chars().collect::<Vec<_>>();
+    let len = chars.len();
+
+    format!("{}{}", chars[(i / len) % len], chars[i % len])
+}
+
+fn label_index(label: &str) -> Option<usize> {
+    let mut chars = label.chars();
+    let first = LABEL_CHARS.find(chars.next()?)?;
+    let second = LABEL_CHARS.find(chars.next()?)?;
+
+    Some(first * LABEL_CHARS.len() + second)
+}
+
+// First and last line from one to the other, whichever comes first
+fn region(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+// Every file shown from line start to end flips its selection
+fn toggle_region(files: &mut Files, start: usize, end: usize) {
+    for file in files.iter_files_mut().skip(start).take(end - start + 1) {
+        file.toggle_selection();
+    }
+}
+
+// Selects the files from line start to end, or unselects them if they all
+// were already. True if they're selected now.
+fn select_region(files: &mut Files, start: usize, end: usize) -> bool {
+    let count = end - start + 1;
+    let select = !files.iter_files()
+        .skip(start)
+        .take(count)
+        .all(|file| file.is_selected());
+
+    for file in files.iter_files_mut().skip(start).take(count) {
+        file.selected = select;
+    }
+
+    select
+}
+
+
 impl<T> Widget for ListView<T>
 where
     ListView<T>: Listable
@@ -977,7 +1859,11 @@ where
                 let mut output = term::normal_color();
 
                 // i counts from the offset, while selection counts from 0
-                if i + self.offset == self.selection {
+                let in_visual = self.visual_range()
+                    .map(|(start, end)| start <= i + self.offset && i + self.offset <= end)
+                    .unwrap_or(false);
+
+                if i + self.offset == self.selection || in_visual {
                     output += &term::invert();
                 }
 
@@ -1000,3 +1886,110 @@ where
         Listable::on_key(self, key)
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(names: &[&str]) -> Files {
+        let mut files = Files::default();
+        files.files = names.iter()
+            .map(|name| File::new(name, PathBuf::from("/nonexistent").join(name)))
+            .collect();
+        files.recalculate_len();
+        files
+    }
+
+    fn selected(files: &Files) -> Vec<&str> {
+        files.files
+            .iter()
+            .filter(|file| file.is_selected())
+            .map(|file| file.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn visual_region_either_way() {
+        assert_eq!(region(2, 5), (2, 5));
+        assert_eq!(region(5, 2), (2, 5));
+        assert_eq!(region(3, 3), (3, 3));
+    }
+
+    #[test]
+    fn visual_select_toggles_each_file() {
+        let mut files = listing(&["a", "b", "c", "d"]);
+        files.files[1].selected = true;
+
+        toggle_region(&mut files, 0, 2);
+
+        assert_eq!(selected(&files), vec!["a", "c"]);
+    }
+
+    #[test]
+    fn visual_select_counts_shown_files() {
+        let mut files = listing(&["a", ".hidden", "b", "c"]);
+
+        toggle_region(&mut files, 1, 2);
+
+        assert_eq!(selected(&files), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn range_selects_between_anchor_and_cursor() {
+        let mut files = listing(&["a", "b", "c", "d"]);
+        files.files[2].selected = true;
+
+        assert!(select_region(&mut files, 1, 2));
+        assert_eq!(selected(&files), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn range_unselects_when_all_selected() {
+        let mut files = listing(&["a", "b", "c", "d"]);
+        for file in &mut files.files {
+            file.selected = true;
+        }
+
+        assert!(!select_region(&mut files, 0, 1));
+        assert_eq!(selected(&files), vec!["c", "d"]);
+    }
+
+    #[test]
+    fn range_counts_shown_files() {
+        let mut files = listing(&[".hidden", "a", "b", "c"]);
+
+        assert!(select_region(&mut files, 0, 1));
+        assert_eq!(selected(&files), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn parse_columns_in_order() {
+        use Column::*;
+
+        assert_eq!(parse_columns("mtime, owner").unwrap(), vec![MTime, Owner]);
+        assert_eq!(parse_columns("links,,permissions,").unwrap(), vec![Links, Permissions]);
+        assert_eq!(parse_columns("").unwrap(), vec![]);
+        assert!(parse_columns("mtime,size").is_err());
+    }
+
+    #[test]
+    fn fitting_columns_leave_room_for_the_name() {
+        use Column::*;
+        let columns = [Permissions, Owner, Links];
+
+        assert_eq!(fitting_columns(&columns, 20), vec![]);
+        assert_eq!(fitting_columns(&columns, 30), vec![]);
+        assert_eq!(fitting_columns(&columns, 41), vec![Permissions]);
+        assert_eq!(fitting_columns(&columns, 59), vec![Permissions, Owner]);
+        assert_eq!(fitting_columns(&columns, 200), columns.to_vec());
+    }
+
+    #[test]
+    fn fitting_columns_stop_at_the_first_too_wide() {
+        use Column::*;
+
+        // Links would fit, but comes after the owner
+        assert_eq!(fitting_columns(&[Owner, Links], 45), vec![]);
+    }
+}
@@ -69,7 +69,43 @@ mod trait_ext;
 mod config_installer;
 mod imgview;
 mod mediaview;
+mod audioview;
 mod keybind;
+mod keypresets;
+mod whichkey;
+mod basket;
+mod picker;
+mod transfer;
+mod batch;
+mod clipboard;
+mod mounts;
+mod remote;
+mod oci;
+mod ssh;
+mod http_index;
+mod s3;
+mod credentials;
+mod ipc;
+mod workspaces;
+mod chmod;
+mod colorrules;
+mod chown;
+mod selinux;
+mod snapshots;
+mod fileops;
+mod hardlinks;
+mod ueberzug;
+mod archive;
+mod jobs;
+mod slideshow;
+mod treeview;
+mod fuzzy;
+mod thumbnails;
+mod redraw;
+mod store;
+mod backup;
+mod audit;
+mod notes;
 
 
 
@@ -101,17 +137,30 @@ fn die_gracefully(core: &WidgetCore) {
 fn main() -> HResult<()> {
     let args = parse_args();
 
+    // Another hunter is already running, that one can show it
+    if crate::config::Config::single_instance() &&
+       !args.is_present("mime") &&
+       !args.is_present("update") {
+        let path = args.value_of("select")
+            .or(args.value_of("path"))
+            .and_then(|path| Some(std::env::current_dir().ok()?.join(path)));
+
+        if let Some(path) = path {
+            if crate::ipc::forward(&path).is_ok() { return Ok(()); }
+        }
+    }
+
     // do this early so it might be ready when needed
     crate::files::load_tags().ok();
 
     let mut core = WidgetCore::new().expect("Can't create WidgetCore!");
 
-    process_args(args, core.clone());
+    let select = process_args(args, core.clone());
 
     // Resets terminal when hunter crashes :(
     die_gracefully(&core);
 
-    match run(core.clone()) {
+    match run(core.clone(), select) {
         Ok(_) | Err(HError::Quit) => reset_screen(&mut core),
         Err(err) => {
             reset_screen(&mut core)?;
@@ -121,7 +170,7 @@ fn main() -> HResult<()> {
     }
 }
 
-fn run(mut core: WidgetCore) -> HResult<()> {
+fn run(mut core: WidgetCore, select: Option<std::path::PathBuf>) -> HResult<()> {
     core.screen.clear()?;
 
     let core2 = core.clone();
@@ -131,11 +180,46 @@ fn run(mut core: WidgetCore) -> HResult<()> {
         crate::config_installer::ensure_config(core2).log();
     });
 
-    let filebrowser = FileBrowser::new(&core, None)?;
+    // The file list picks up its selection from the cache once it's loaded
+    let fs_cache = crate::fscache::FsCache::new(core.get_sender());
+    if let Some(select) = select {
+        use crate::files::File;
+
+        let dir = select.parent().and_then(|dir| File::new_from_path(dir).ok());
+        let file = File::new_from_path(&select).ok();
+        if let (Some(dir), Some(file)) = (dir, file) {
+            fs_cache.set_selection(dir, file).log();
+        }
+    }
+
+    let filebrowser = FileBrowser::new(&core, Some(fs_cache))?;
     let mut tabview = TabView::new(&core);
     tabview.push_widget(filebrowser)?;
 
-    tabview.handle_input()?;
+    // Fails quietly when another instance has the socket already
+    if crate::config::Config::single_instance() {
+        crate::ipc::listen(core.get_sender()).ok();
+    }
+
+    let result = tabview.handle_input();
+
+    crate::ipc::stop_listening();
+
+    // For startup_dir=last
+    if let Ok(path) = crate::paths::last_dir_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let last_dir = &tabview.active_tab_().cwd.path;
+        std::fs::write(path, last_dir.as_os_str().as_bytes())
+            .map_err(HError::from)
+            .log();
+    }
+
+    // Don't leave loop devices lying around
+    crate::mounts::release_all();
+    crate::ueberzug::quit();
+
+    result?;
 
     // core.screen.cursor_show()?;
     // core.screen.flush()?;
@@ -179,7 +263,7 @@ fn parse_args() -> clap::ArgMatches<'static> {
             Arg::with_name("graphics")
                 .short("g")
                 .long("graphics")
-                .help("Show HQ graphics using sixel/kitty")
+                .help("Show HQ graphics using sixel/kitty/ueberzug")
                 .takes_value(true))
         // For "Add Action" action
         .arg(
@@ -188,6 +272,13 @@ fn parse_args() -> clap::ArgMatches<'static> {
                 .long("mime")
                 .help("Print MIME type of file")
                 .takes_value(false))
+        .arg(
+            Arg::with_name("select")
+                .short("s")
+                .long("select")
+                .help("Start with <file> selected in its directory")
+                .value_name("file")
+                .takes_value(true))
         .arg(
             Arg::with_name("path")
                 .index(1)
@@ -197,7 +288,9 @@ fn parse_args() -> clap::ArgMatches<'static> {
 
 
 
-fn process_args(args: clap::ArgMatches, core: WidgetCore) {
+// Returns the file to select, if there is one
+fn process_args(args: clap::ArgMatches,
+                core: WidgetCore) -> Option<std::path::PathBuf> {
     let path = args.value_of("path");
 
     // Just print MIME and quit
@@ -213,13 +306,26 @@ fn process_args(args: clap::ArgMatches, core: WidgetCore) {
         crate::config_installer::update_config(core, true).log();
     }
 
-    if let Some(path) = path {
-        std::env::set_current_dir(&path)
+    // Files given as path are selected too, for "reveal in file manager"
+    let select = args.value_of("select")
+        .or(path.filter(|path| std::path::Path::new(path).is_file()))
+        .and_then(|file| Some(std::env::current_dir().ok()?.join(file)));
+
+    let start_dir = match (&select, path) {
+        (Some(file), _) => file.parent().map(|dir| dir.to_path_buf()),
+        (None, Some(path)) => Some(std::path::PathBuf::from(path)),
+        (None, None) => crate::config::Config::startup_dir()
+    };
+
+    if let Some(dir) = start_dir {
+        std::env::set_current_dir(&dir)
             .map_err(HError::from)
             .log();
     }
 
     crate::config::set_argv_config(args).log();
+
+    select
 }
 
 
@@ -135,7 +135,66 @@ pub enum MiniBufferEvent {
     Empty,
     Cancelled,
     CycleNext,
-    CyclePrev
+    CyclePrev,
+    Back
+}
+
+#[derive(Clone, Debug)]
+pub enum Validator {
+    NonEmpty,
+    Integer(Option<isize>, Option<isize>),
+    ExistingPath,
+    Regex(String),
+    // One of these or nothing
+    Known(Vec<String>),
+    // Placeholders for renumbering, see batch::renumber_plan
+    RenamePattern
+}
+
+impl Validator {
+    pub fn validate(&self, input: &str) -> Result<(), String> {
+        use Validator::*;
+
+        match self {
+            NonEmpty => {
+                if input.trim().is_empty() {
+                    return Err("Input can't be empty!".to_string());
+                }
+            }
+            Integer(min, max) => {
+                let num = input.trim()
+                    .parse::<isize>()
+                    .map_err(|_| format!("Not a number: {}", input))?;
+
+                if let Some(min) = min {
+                    if num < *min { return Err(format!("Must be at least {}", min)); }
+                }
+                if let Some(max) = max {
+                    if num > *max { return Err(format!("Must be at most {}", max)); }
+                }
+            }
+            ExistingPath => {
+                if !std::path::Path::new(input).exists() {
+                    return Err(format!("No such file or directory: {}", input));
+                }
+            }
+            Regex(pattern) => {
+                let re = regex::Regex::new(pattern)
+                    .map_err(|e| format!("Bad pattern: {}", e))?;
+                if !re.is_match(input) {
+                    return Err(format!("Input doesn't match: {}", pattern));
+                }
+            }
+            Known(names) => {
+                if !input.is_empty() && !names.iter().any(|name| name == input) {
+                    return Err(format!("Unknown: {}", input));
+                }
+            }
+            RenamePattern => crate::batch::check_pattern(input)?,
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -147,7 +206,12 @@ pub struct MiniBuffer {
     history: History,
     completions: Vec<OsString>,
     last_completion: Option<String>,
-    continuous: bool
+    continuous: bool,
+    validator: Option<Validator>,
+    // Completed from these instead of files and commands when set
+    words: Vec<String>,
+    // Input is shown as stars and kept out of the history
+    secret: bool
 }
 
 impl MiniBuffer {
@@ -165,7 +229,10 @@ impl MiniBuffer {
             history: History::new(),
             completions: vec![],
             last_completion: None,
-            continuous: false
+            continuous: false,
+            validator: None,
+            words: vec![],
+            secret: false
         }
     }
 
@@ -178,6 +245,59 @@ impl MiniBuffer {
             self.clear();
         }
 
+        self.run_query()
+    }
+
+    pub fn query_prefilled(&mut self, query: &str, input: &str) -> HResult<String> {
+        self.query_prefilled_at(query, input, input.len())
+    }
+
+    // With the cursor somewhere in the middle, like before an extension
+    pub fn query_prefilled_at(&mut self,
+                              query: &str,
+                              input: &str,
+                              position: usize) -> HResult<String> {
+        self.continuous = false;
+        self.query = query.to_string();
+
+        self.clear();
+        self.input = input.to_string();
+        self.position = position.min(self.input.len());
+
+        self.run_query()
+    }
+
+    pub fn query_validated(&mut self,
+                           query: &str,
+                           input: &str,
+                           validator: Validator) -> HResult<String> {
+        self.validator = Some(validator);
+        let answer = self.query_prefilled(query, input);
+        self.validator = None;
+        answer
+    }
+
+    pub fn query_words(&mut self,
+                       query: &str,
+                       input: &str,
+                       words: Vec<String>) -> HResult<String> {
+        self.validator = Some(Validator::Known(words.clone()));
+        self.words = words;
+        let answer = self.query_prefilled(query, input);
+        self.validator = None;
+        self.words.clear();
+        answer
+    }
+
+    pub fn query_secret(&mut self, query: &str) -> HResult<String> {
+        self.secret = true;
+        let answer = self.query_prefilled(query, "");
+        self.secret = false;
+        self.clear();
+        answer
+    }
+
+    fn run_query(&mut self) -> HResult<String> {
         self.core.screen()?.cursor_hide().log();
 
         match self.popup() {
@@ -201,6 +321,10 @@ impl MiniBuffer {
     }
 
     pub fn complete(&mut self) -> HResult<()> {
+        if !self.words.is_empty() {
+            return self.complete_word();
+        }
+
         if !self.input.ends_with(" ") {
             if !self.completions.is_empty() {
                 self.cycle_completions()?;
@@ -248,6 +372,32 @@ impl MiniBuffer {
         Ok(())
     }
 
+    // The whole input is one word here
+    fn complete_word(&mut self) -> HResult<()> {
+        if !self.completions.is_empty() {
+            return self.cycle_completions();
+        }
+
+        let mut completions = self.words
+            .iter()
+            .filter(|word| word.starts_with(&self.input))
+            .rev()
+            .map(OsString::from)
+            .collect::<Vec<_>>();
+
+        let completion = completions.pop()
+            .ok_or(HError::NoCompletionsError)?
+            .to_string_lossy()
+            .to_string();
+
+        self.input = completion.clone();
+        self.position = self.input.len();
+        self.last_completion = Some(completion);
+        self.completions = completions;
+
+        Ok(())
+    }
+
     pub fn cycle_completions(&mut self) -> HResult<()> {
         let last_comp = self.last_completion.as_ref()?;
         let last_len = last_comp.len();
@@ -354,6 +504,15 @@ impl MiniBuffer {
     }
 
     pub fn input_finnished(&self) -> HResult<()> {
+        if let Some(validator) = &self.validator {
+            if let Err(msg) = validator.validate(&self.input) {
+                // Stay in the minibuffer until the input is fixed
+                let msg = format!("{}{}", crate::term::color_red(), msg);
+                self.core.show_status(&msg).log();
+                return Ok(());
+            }
+        }
+
         return HError::popup_finnished()
     }
 
@@ -366,12 +525,173 @@ impl MiniBuffer {
         return Err(MiniBufferEvent::NewInput(self.input.clone()))?;
     }
 
+    pub fn input_back(&self) -> HResult<()> {
+        return Err(MiniBufferEvent::Back)?;
+    }
+
     pub fn input_empty(&self) -> HResult<()> {
         self.core.show_status("Empty!").log();
         return Err(MiniBufferEvent::Empty)?;
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct WizardStep {
+    pub query: String,
+    pub choices: Vec<String>,
+    pub default: Option<String>,
+    pub optional: bool,
+    pub validator: Option<Validator>
+}
+
+impl WizardStep {
+    fn prompt(&self) -> String {
+        let mut prompt = self.query.clone();
+
+        if !self.choices.is_empty() {
+            prompt += &format!(" [{}]", self.choices.join("/"));
+        }
+        if let Some(default) = &self.default {
+            prompt += &format!(" ({})", default);
+        }
+
+        prompt
+    }
+
+    // Choices can be abbreviated, as long as the prefix is unique
+    fn check(&self, input: String) -> Result<String, String> {
+        if self.choices.is_empty() { return Ok(input); }
+
+        let matches = self.choices
+            .iter()
+            .filter(|c| c.starts_with(&input))
+            .collect::<Vec<_>>();
+
+        match matches.as_slice() {
+            [choice] => Ok(choice.to_string()),
+            _ if self.choices.contains(&input) => Ok(input),
+            _ => Err(format!("Choose one of: {}", self.choices.join(", ")))
+        }
+    }
+}
+
+// Asks a series of questions one after another. BackTab goes back to
+// the previous question, cancelling anywhere aborts the whole thing.
+#[derive(Clone, Debug)]
+pub struct Wizard {
+    steps: Vec<WizardStep>
+}
+
+impl Wizard {
+    pub fn new() -> Wizard {
+        Wizard { steps: vec![] }
+    }
+
+    pub fn step(mut self, query: &str) -> Wizard {
+        self.steps.push(WizardStep {
+            query: query.to_string(),
+            choices: vec![],
+            default: None,
+            optional: false,
+            validator: None
+        });
+        self
+    }
+
+    // These modify the last added step
+
+    pub fn choices(mut self, choices: &[&str]) -> Wizard {
+        if let Some(step) = self.steps.last_mut() {
+            step.choices = choices.iter().map(|c| c.to_string()).collect();
+        }
+        self
+    }
+
+    pub fn with_default(mut self, default: &str) -> Wizard {
+        if let Some(step) = self.steps.last_mut() {
+            step.default = Some(default.to_string());
+        }
+        self
+    }
+
+    pub fn optional(mut self) -> Wizard {
+        if let Some(step) = self.steps.last_mut() {
+            step.optional = true;
+        }
+        self
+    }
+
+    pub fn validate(mut self, validator: Validator) -> Wizard {
+        if let Some(step) = self.steps.last_mut() {
+            step.validator = Some(validator);
+        }
+        self
+    }
+
+    pub fn run(&self, core: &WidgetCore) -> HResult<Vec<String>> {
+        use MiniBufferEvent::*;
+
+        let mut answers: Vec<Option<String>> = vec![None; self.steps.len()];
+        let mut current = 0;
+        let mut prefill = String::new();
+
+        while current < self.steps.len() {
+            let step = &self.steps[current];
+
+            let input = match &step.validator {
+                Some(validator) => core.minibuffer_validated(&step.prompt(),
+                                                             &prefill,
+                                                             validator.clone()),
+                None => core.minibuffer_prefilled(&step.prompt(), &prefill)
+            };
+
+            match input {
+                Ok(input) => match step.check(input) {
+                    Ok(answer) => {
+                        answers[current] = Some(answer);
+                        current += 1;
+                    }
+                    Err(msg) => {
+                        core.show_status(&msg).log();
+                        continue;
+                    }
+                }
+                Err(HError::MiniBufferEvent(Empty)) => {
+                    if let Some(default) = &step.default {
+                        answers[current] = Some(default.clone());
+                        current += 1;
+                    } else if step.optional {
+                        answers[current] = Some(String::new());
+                        current += 1;
+                    }
+                }
+                Err(HError::MiniBufferEvent(Back)) => {
+                    current = current.saturating_sub(1);
+                }
+                // Don't lose what was typed so far
+                Err(HError::RefreshParent) => {
+                    prefill = core.minibuffer
+                        .lock()
+                        .as_ref()
+                        .map(|mb| mb.input.clone())
+                        .unwrap_or_default();
+                    continue;
+                }
+                Err(err) => return Err(err)
+            }
+
+            prefill = answers.get(current)
+                .cloned()
+                .flatten()
+                .unwrap_or_default();
+        }
+
+        Ok(answers.into_iter()
+           .map(|answer| answer.unwrap_or_default())
+           .collect())
+    }
+}
+
 pub fn find_bins(comp_name: &str) -> HResult<Vec<OsString>> {
     use osstrtools::OsStrTools;
 
@@ -470,12 +790,16 @@ impl Widget for MiniBuffer {
 
     fn get_drawlist(&self) -> HResult<String> {
         let (xpos, ypos) = self.get_coordinates()?.u16position();
+        let input = match self.secret {
+            true => "*".repeat(self.input.chars().count()),
+            false => self.input.clone()
+        };
         Ok(format!("{}{}{}{}: {}",
                 crate::term::goto_xy(xpos, ypos),
                 termion::clear::CurrentLine,
                 crate::term::header_color(),
                 self.query,
-                self.input))
+                input))
     }
 
     fn on_key(&mut self, key: Key) -> HResult<()> {
@@ -528,8 +852,9 @@ impl Acting for MiniBuffer {
                 self.position += 2;
             }
             Cancel => { self.clear(); self.input_cancelled()? }
+            Back => self.input_back()?,
             Finish => {
-                if self.input != "" {
+                if self.input != "" && !self.secret {
                     self.history.add(&self.query, &self.input);
                 }
                 self.input_finnished()?
@@ -566,3 +891,60 @@ impl Acting for MiniBuffer {
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::Validator;
+
+    #[test]
+    fn non_empty() {
+        assert!(Validator::NonEmpty.validate("a").is_ok());
+        assert!(Validator::NonEmpty.validate("").is_err());
+        assert!(Validator::NonEmpty.validate("  ").is_err());
+    }
+
+    #[test]
+    fn integer_in_range() {
+        let validator = Validator::Integer(Some(1), Some(10));
+
+        assert!(validator.validate("1").is_ok());
+        assert!(validator.validate(" 10 ").is_ok());
+        assert!(validator.validate("0").is_err());
+        assert!(validator.validate("11").is_err());
+        assert!(validator.validate("ten").is_err());
+        assert!(Validator::Integer(None, None).validate("-5").is_ok());
+    }
+
+    #[test]
+    fn existing_path() {
+        assert!(Validator::ExistingPath.validate("/").is_ok());
+        assert!(Validator::ExistingPath.validate("/no/such/hunter/path").is_err());
+    }
+
+    #[test]
+    fn regex() {
+        let validator = Validator::Regex(String::from("^[0-9]{2}:[0-9]{2}$"));
+
+        assert!(validator.validate("12:30").is_ok());
+        assert!(validator.validate("12:3").is_err());
+        assert!(Validator::Regex(String::from("(")).validate("x").is_err());
+    }
+
+    #[test]
+    fn known() {
+        let validator = Validator::Known(vec![String::from("foo"), String::from("bar")]);
+
+        assert!(validator.validate("foo").is_ok());
+        assert!(validator.validate("").is_ok());
+        assert!(validator.validate("baz").is_err());
+    }
+
+    #[test]
+    fn rename_pattern() {
+        assert!(Validator::RenamePattern.validate("{name}_{n:03}{ext}").is_ok());
+        assert!(Validator::RenamePattern.validate("{mtime:%Y%m%d}").is_ok());
+        assert!(Validator::RenamePattern.validate("{mtime:%Q}").is_err());
+        assert!(Validator::RenamePattern.validate("{nmae}").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use parking_lot::Mutex;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::fail::{HResult, HError, ErrorLog};
+
+
+// Disk images are loop-mounted read-only through udisks, so no root is
+// needed. Every mount is remembered and released again once no tab is
+// inside it anymore.
+
+lazy_static! {
+    static ref MOUNTS: Mutex<Vec<ImageMount>> = Mutex::new(vec![]);
+}
+
+#[derive(Debug, Clone)]
+struct ImageMount {
+    image: PathBuf,
+    device: String,
+    mountpoint: PathBuf,
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .map(|ext| match ext.as_str() {
+            "iso" | "img" | "squashfs" | "sfs" => true,
+            _ => false
+        })
+        .unwrap_or(false)
+}
+
+fn udisksctl(args: &[&str]) -> HResult<String> {
+    let output = Command::new("udisksctl")
+        .args(args)
+        .arg("--no-user-interaction")
+        .stdin(std::process::Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        let err = err.lines()
+            .last()
+            .unwrap_or("udisksctl failed")
+            .to_string();
+        return Err(HError::Error(err));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Takes the last word, like "/dev/loop0" from "Mapped file x.iso as /dev/loop0."
+fn last_word(output: &str) -> HResult<String> {
+    let word = output.split_whitespace().last()?;
+    Ok(word.trim_end_matches('.').to_string())
+}
+
+// Some desktops mount new loop devices on their own, so check there first
+fn find_mountpoint(device: &str) -> Option<PathBuf> {
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+
+    mounts.lines()
+        .map(|line| line.split(' ').collect::<Vec<_>>())
+        .find(|fields| fields.len() > 1 && fields[0] == device)
+        .map(|fields| PathBuf::from(fields[1].replace("\\040", " ")))
+}
+
+pub fn mount_image(image: &Path) -> HResult<PathBuf> {
+    let image = image.canonicalize()?;
+
+    if let Some(mount) = MOUNTS.lock().iter().find(|m| m.image == image) {
+        return Ok(mount.mountpoint.clone());
+    }
+
+    let image_str = image.to_string_lossy();
+    let output = udisksctl(&["loop-setup", "--read-only", "-f", &image_str])?;
+    let device = last_word(&output)?;
+
+    let mountpoint = match find_mountpoint(&device) {
+        Some(mountpoint) => mountpoint,
+        None => {
+            let output = udisksctl(&["mount", "-b", &device, "-o", "ro"])
+                .map_err(|err| {
+                    udisksctl(&["loop-delete", "-b", &device]).log();
+                    err
+                })?;
+            PathBuf::from(last_word(&output)?)
+        }
+    };
+
+    MOUNTS.lock().push(ImageMount {
+        image: image,
+        device: device,
+        mountpoint: mountpoint.clone()
+    });
+
+    Ok(mountpoint)
+}
+
+fn release(mount: &ImageMount) -> HResult<()> {
+    udisksctl(&["unmount", "-b", &mount.device])?;
+    udisksctl(&["loop-delete", "-b", &mount.device])?;
+    Ok(())
+}
+
+// Called with the directories of all tabs whenever they're refreshed
+pub fn release_unused(tab_dirs: &[PathBuf]) {
+    let unused = {
+        let mut mounts = MOUNTS.lock();
+        let (used, unused) = mounts
+            .drain(..)
+            .partition::<Vec<_>, _>(|mount| {
+                tab_dirs.iter().any(|dir| dir.starts_with(&mount.mountpoint))
+            });
+        *mounts = used;
+        unused
+    };
+
+    if unused.is_empty() { return; }
+
+    std::thread::spawn(move || {
+        for mount in unused {
+            release(&mount).log();
+        }
+    });
+}
+
+pub fn release_all() {
+    let mounts = std::mem::replace(&mut *MOUNTS.lock(), vec![]);
+
+    for mount in mounts {
+        release(&mount).log();
+    }
+}
@@ -0,0 +1,94 @@
+use parking_lot::RwLock;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::fail::{HResult, ErrorLog};
+
+
+// Short notes on files and directories, like "backup from the old laptop,
+// don't touch". They live in ~/.config/hunter/notes next to the tags, one
+// per line as path and note separated by a tab. Shared with other running
+// instances the same way the tags are.
+
+lazy_static! {
+    static ref NOTES: RwLock<Option<HashMap<PathBuf, String>>> = RwLock::new(None);
+}
+
+fn parse(content: &[u8]) -> HashMap<PathBuf, String> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(2, '\t');
+            let path = fields.next()?;
+            let note = fields.next()?;
+            Some((PathBuf::from(path), note.to_string()))
+        })
+        .collect()
+}
+
+fn serialize(notes: &HashMap<PathBuf, String>) -> Vec<u8> {
+    let mut notes = notes.iter().collect::<Vec<_>>();
+    notes.sort();
+
+    notes.into_iter()
+        .map(|(path, note)| format!("{}\t{}\n", path.to_string_lossy(), note))
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn loaded<F, T>(read: F) -> T
+where F: FnOnce(&HashMap<PathBuf, String>) -> T {
+    if NOTES.read().is_none() {
+        let notes = crate::paths::notes_path()
+            .and_then(|path| crate::store::read(&path))
+            .map(|content| parse(&content))
+            .log_and()
+            .unwrap_or_default();
+        *NOTES.write() = Some(notes);
+    }
+
+    read(NOTES.read().as_ref().unwrap())
+}
+
+pub fn get(path: &Path) -> Option<String> {
+    loaded(|notes| notes.get(path).cloned())
+}
+
+// An empty note removes it
+pub fn set(path: &Path, note: &str) -> HResult<()> {
+    // Has to stay on one line
+    let note = note.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let path = path.to_path_buf();
+
+    let content = crate::store::update(&crate::paths::notes_path()?, |content| {
+        let mut notes = parse(content);
+        match note.is_empty() {
+            true => { notes.remove(&path); }
+            false => { notes.insert(path, note); }
+        }
+        serialize(&notes)
+    })?;
+
+    *NOTES.write() = Some(parse(&content));
+    Ok(())
+}
+
+// Notes or paths containing the query, ignoring case
+pub fn search(query: &str) -> Vec<(PathBuf, String)> {
+    let query = query.to_lowercase();
+
+    let mut found = loaded(|notes| {
+        notes.iter()
+            .filter(|(path, note)| {
+                note.to_lowercase().contains(&query) ||
+                    path.to_string_lossy().to_lowercase().contains(&query)
+            })
+            .map(|(path, note)| (path.clone(), note.clone()))
+            .collect::<Vec<_>>()
+    });
+    found.sort();
+    found
+}
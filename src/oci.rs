@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf, Component};
+use std::process::Command;
+
+use crate::remote::{Backend, RemoteEntry, split_fields};
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError};
+
+
+// Browses the layers of a local docker/podman image. The image is saved
+// to a tarball in the cache once, the layers inside are listed with tar.
+// The top level has one directory per layer, in the order they're applied.
+
+#[derive(Debug, Clone)]
+struct LayerEntry {
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+    info: String,
+}
+
+#[derive(Debug)]
+pub struct ImageBackend {
+    tool: String,
+    image: String,
+    archive: Option<PathBuf>,
+    // Directory name -> path of the layer inside the archive
+    layers: Vec<(String, String)>,
+    contents: HashMap<String, Vec<LayerEntry>>,
+}
+
+// Images from docker and podman, as (tool, image)
+pub fn local_images() -> Vec<(String, String)> {
+    ["docker", "podman"]
+        .iter()
+        .filter_map(|tool| {
+            let output = Command::new(tool)
+                .args(&["images", "--format", "{{.Repository}}:{{.Tag}}"])
+                .stderr(std::process::Stdio::null())
+                .output()
+                .ok()?;
+            let images = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|image| !image.contains("<none>"))
+                .map(|image| (tool.to_string(), image.to_string()))
+                .collect::<Vec<_>>();
+            Some(images)
+        })
+        .flatten()
+        .collect()
+}
+
+fn run(cmd: &str, args: &[&str]) -> HResult<String> {
+    let output = Command::new(cmd)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        let err = err.lines().last().unwrap_or("failed").to_string();
+        return Err(HError::Error(format!("{}: {}", cmd, err)));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+impl ImageBackend {
+    pub fn new(tool: &str, image: &str) -> ImageBackend {
+        ImageBackend {
+            tool: tool.to_string(),
+            image: image.to_string(),
+            archive: None,
+            layers: vec![],
+            contents: HashMap::new()
+        }
+    }
+
+    fn archive(&mut self) -> HResult<PathBuf> {
+        if let Some(archive) = &self.archive {
+            return Ok(archive.clone());
+        }
+
+        let name = self.image.replace(|c: char| !c.is_alphanumeric(), "_");
+        let archive = crate::paths::cache_path()?
+            .join(format!("{}-{}.tar", self.tool, name));
+        let archive_str = archive.to_string_lossy().to_string();
+
+        run(&self.tool, &["save", "-o", &archive_str, &self.image])?;
+
+        // Both docker and podman write a manifest.json listing the layers
+        let manifest = run("tar", &["-xOf", &archive_str, "manifest.json"])?;
+        let layers = regex::Regex::new(r#""Layers"\s*:\s*\[([^\]]*)\]"#)
+            .map_err(|err| HError::Error(format!("{}", err)))?
+            .captures(&manifest)
+            .and_then(|caps| caps.get(1))
+            .map(|layers| layers.as_str())
+            .unwrap_or("");
+
+        self.layers = layers
+            .split(',')
+            .map(|layer| layer.trim().trim_matches('"'))
+            .filter(|layer| !layer.is_empty())
+            .enumerate()
+            .map(|(i, layer)| {
+                let id = layer.trim_end_matches("/layer.tar")
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(layer);
+                let id = id.get(..12).unwrap_or(id);
+                (format!("{:02}-{}", i + 1, id), layer.to_string())
+            })
+            .collect();
+
+        self.archive = Some(archive.clone());
+        Ok(archive)
+    }
+
+    fn layer_contents(&mut self, layer: &str) -> HResult<&Vec<LayerEntry>> {
+        if !self.contents.contains_key(layer) {
+            let archive = self.archive()?.to_string_lossy().to_string();
+            let script = r#"tar -xOf "$1" "$2" | tar -tvf -"#;
+            let listing = run("sh", &["-c", script, "sh", &archive, layer])?;
+
+            let entries = listing.lines().filter_map(parse_tar_line).collect();
+            self.contents.insert(layer.to_string(), entries);
+        }
+
+        Ok(self.contents.get(layer)?)
+    }
+
+    fn layer_path(&self, dir_name: &str) -> HResult<String> {
+        self.layers
+            .iter()
+            .find(|(name, _)| name == dir_name)
+            .map(|(_, layer)| layer.clone())
+            .ok_or_else(|| HError::Error(format!("No layer {}", dir_name)))
+    }
+}
+
+// Lines look like "drwxr-xr-x root/root 0 2024-01-01 12:00 etc/ssl/"
+fn parse_tar_line(line: &str) -> Option<LayerEntry> {
+    let (fields, name) = split_fields(line, 5)?;
+
+    let mode = fields[0];
+    let size = fields[2].parse().unwrap_or(0);
+    let (name, link) = match name.find(" -> ").or_else(|| name.find(" link to ")) {
+        Some(pos) => (&name[..pos], Some(name[pos..].trim())),
+        None => (name, None)
+    };
+
+    let file_name = name.trim_end_matches('/').rsplit('/').next()?;
+    let info = match (file_name.starts_with(".wh."), link) {
+        (true, _) => String::from("deleted"),
+        (false, Some(link)) => format!("{} {}", mode, link),
+        (false, None) => format!("{} {} {}", mode, fields[3], fields[4])
+    };
+
+    Some(LayerEntry {
+        path: PathBuf::from(name.trim_start_matches("./")),
+        is_dir: mode.starts_with('d'),
+        size: size,
+        info: info
+    })
+}
+
+// Splits "/03-abc/etc/ssl" into ("03-abc", "etc/ssl")
+fn split_path(path: &Path) -> Option<(String, PathBuf)> {
+    let mut components = path.components()
+        .filter(|c| c != &Component::RootDir);
+    let layer = components.next()?.as_os_str().to_string_lossy().to_string();
+    let rest = components.collect::<PathBuf>();
+    Some((layer, rest))
+}
+
+impl Backend for ImageBackend {
+    fn name(&self) -> String {
+        format!("{} {}", self.tool, self.image)
+    }
+
+    fn list(&mut self, path: &Path) -> HResult<Vec<RemoteEntry>> {
+        self.archive()?;
+
+        let (dir_name, dir) = match split_path(path) {
+            Some(split) => split,
+            None => {
+                let layers = self.layers
+                    .iter()
+                    .map(|(name, _)| RemoteEntry::dir(name).with_info("layer"))
+                    .collect();
+                return Ok(layers);
+            }
+        };
+
+        let layer = self.layer_path(&dir_name)?;
+        let contents = self.layer_contents(&layer)?;
+
+        // Parent directories aren't always in the layer themselves
+        let mut entries: HashMap<String, RemoteEntry> = HashMap::new();
+        for entry in contents {
+            let rest = match entry.path.strip_prefix(&dir) {
+                Ok(rest) => rest,
+                Err(_) => continue
+            };
+
+            let mut components = rest.components();
+            let name = match components.next() {
+                Some(name) => name.as_os_str().to_string_lossy().to_string(),
+                None => continue
+            };
+
+            match components.next().is_some() || entry.is_dir {
+                true => {
+                    entries.entry(name.clone())
+                        .or_insert_with(|| RemoteEntry::dir(&name));
+                }
+                false => {
+                    let file = RemoteEntry::file(&name, Some(entry.size))
+                        .with_info(entry.info.clone());
+                    entries.insert(name, file);
+                }
+            }
+        }
+
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    fn fetch(&mut self,
+             path: &Path,
+             _is_dir: bool,
+             target: &Path,
+             cwd: &File) -> HResult<Cmd> {
+        let archive = self.archive()?;
+        let (dir_name, member) = split_path(path)?;
+        let layer = self.layer_path(&dir_name)?;
+        let target_dir = target.parent()?;
+
+        // Leading directories are stripped, so only the entry itself ends up
+        // here. Without a member the whole layer goes into its own directory.
+        let strip = member.components().count().saturating_sub(1);
+
+        let script = r#"
+if [ -z "$5" ]; then
+    mkdir -p "$6" && tar -xOf "$1" "$2" | tar -xvf - -C "$6"
+else
+    tar -xOf "$1" "$2" | tar -xvf - -C "$3" --strip-components="$4" "$5" ||
+    tar -xOf "$1" "$2" | tar -xvf - -C "$3" --strip-components="$4" "./$5"
+fi
+"#;
+
+        let short_cmd = format!("extract {} from {}",
+                                member.to_string_lossy(),
+                                self.image);
+
+        let args = vec![OsString::from("-c"),
+                        OsString::from(script),
+                        OsString::from("sh"),
+                        archive.into_os_string(),
+                        OsString::from(layer),
+                        target_dir.as_os_str().to_os_string(),
+                        OsString::from(strip.to_string()),
+                        member.into_os_string(),
+                        target.as_os_str().to_os_string()];
+
+        Ok(Cmd {
+            cmd: OsString::from("sh"),
+            args: Some(args),
+            vars: None,
+            short_cmd: Some(short_cmd),
+            cwd: cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        })
+    }
+}
@@ -42,7 +42,20 @@ pub fn bindings_path() -> HResult<PathBuf> {
     Ok(config_path)
 }
 
+pub fn data_path() -> HResult<PathBuf> {
+    let mut data_path = dirs_2::data_dir()?;
+    data_path.push("hunter");
+    std::fs::create_dir_all(&data_path)?;
+    Ok(data_path)
+}
+
 pub fn bookmark_path() -> HResult<PathBuf> {
+    let mut bookmark_path = data_path()?;
+    bookmark_path.push("bookmarks");
+    Ok(bookmark_path)
+}
+
+pub fn old_bookmark_path() -> HResult<PathBuf> {
     let mut bookmark_path = hunter_path()?;
     bookmark_path.push("bookmarks");
     Ok(bookmark_path)
@@ -54,20 +67,68 @@ pub fn tagfile_path() -> HResult<PathBuf> {
     Ok(tagfile_path)
 }
 
+pub fn notes_path() -> HResult<PathBuf> {
+    let mut notes_path = hunter_path()?;
+    notes_path.push("notes");
+    Ok(notes_path)
+}
+
 pub fn history_path() -> HResult<PathBuf> {
     let mut history_path = hunter_path()?;
     history_path.push("history");
     Ok(history_path)
 }
 
+pub fn last_dir_path() -> HResult<PathBuf> {
+    let mut last_dir_path = hunter_path()?;
+    last_dir_path.push("last_dir");
+    Ok(last_dir_path)
+}
+
+pub fn workspaces_path() -> HResult<PathBuf> {
+    let mut workspaces_path = hunter_path()?;
+    workspaces_path.push("workspaces");
+    Ok(workspaces_path)
+}
+
 pub fn actions_path() -> HResult<PathBuf> {
     let mut actions_path = hunter_path()?;
     actions_path.push("actions");
     Ok(actions_path)
 }
 
+pub fn templates_path() -> HResult<PathBuf> {
+    let mut templates_path = hunter_path()?;
+    templates_path.push("templates");
+    Ok(templates_path)
+}
+
+pub fn xdg_templates_path() -> HResult<PathBuf> {
+    let templates_path = dirs_2::template_dir()?;
+    Ok(templates_path)
+}
+
 pub fn previewers_path() -> HResult<PathBuf> {
     let mut previewers_path = hunter_path()?;
     previewers_path.push("previewers");
     Ok(previewers_path)
 }
+
+pub fn presets_path() -> HResult<PathBuf> {
+    let mut presets_path = hunter_path()?;
+    presets_path.push("presets");
+    Ok(presets_path)
+}
+
+pub fn cache_path() -> HResult<PathBuf> {
+    let mut cache_path = dirs_2::cache_dir()?;
+    cache_path.push("hunter");
+    std::fs::create_dir_all(&cache_path)?;
+    Ok(cache_path)
+}
+
+pub fn audit_path() -> HResult<PathBuf> {
+    let mut audit_path = data_path()?;
+    audit_path.push("audit.log");
+    Ok(audit_path)
+}
@@ -0,0 +1,181 @@
+use termion::event::Key;
+
+use std::path::PathBuf;
+
+use crate::listview::{Listable, ListView};
+use crate::widget::{Widget, WidgetCore};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+use crate::dirty::Dirtyable;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub kind: String,
+    pub path: PathBuf,
+}
+
+impl Target {
+    pub fn new(kind: &str, path: impl Into<PathBuf>) -> Target {
+        Target {
+            kind: kind.to_string(),
+            path: path.into()
+        }
+    }
+}
+
+impl Listable for ListView<Vec<Target>> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let xsize = self.get_coordinates().unwrap().xsize_u();
+        let kind_len = self.content
+            .iter()
+            .map(|t| t.kind.len())
+            .max()
+            .unwrap_or(0);
+
+        self.content
+            .iter()
+            .map(|target| {
+                use pathbuftools::PathBufTools;
+
+                let path = target.path.short_string();
+                let path_len = xsize.saturating_sub(kind_len + 2);
+
+                format!("{}{:kind_len$}  {}{}",
+                        term::highlight_color(),
+                        target.kind,
+                        term::normal_color(),
+                        term::sized_string_u(&path, path_len),
+                        kind_len = kind_len)
+            })
+            .collect()
+    }
+
+    fn on_refresh(&mut self) -> HResult<()> {
+        self.core.set_dirty();
+        Ok(())
+    }
+}
+
+
+// Lets the user choose a destination from a list of likely targets,
+// with a path prompt for everything else
+#[derive(Debug)]
+pub struct TargetPicker {
+    core: WidgetCore,
+    list: ListView<Vec<Target>>,
+    query: String,
+    pub picked: Option<PathBuf>,
+    pub prompt: bool,
+}
+
+impl TargetPicker {
+    pub fn new(core: &WidgetCore,
+               query: &str,
+               mut targets: Vec<Target>) -> TargetPicker {
+        let mut seen = std::collections::HashSet::new();
+        targets.retain(|t| seen.insert(t.path.clone()));
+
+        TargetPicker {
+            core: core.clone(),
+            list: ListView::new(core, targets),
+            query: query.to_string(),
+            picked: None,
+            prompt: false
+        }
+    }
+
+    fn pick_selected(&mut self) -> HResult<()> {
+        let selection = self.list.get_selection();
+        self.picked = Some(self.list.content.get(selection)?.path.clone());
+        HError::popup_finnished()
+    }
+}
+
+impl Widget for TargetPicker {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.list.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        Ok(format!("{}:", self.query))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = "Enter: choose  /: type path  Esc: cancel";
+        Ok(term::sized_string_u(hint, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        self.list.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.list.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, PickerAction, Movement};
+
+impl Acting for TargetPicker {
+    type Action = PickerAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.picker
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        match movement {
+            Up(n) => { for _ in 0..*n { self.list.move_up(); } }
+            Down(n) => { for _ in 0..*n { self.list.move_down(); } }
+            PageUp => self.list.page_up(),
+            PageDown => self.list.page_down(),
+            Top => self.list.move_top(),
+            Bottom => self.list.move_bottom(),
+            Right => self.pick_selected()?,
+            Left => {}
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use PickerAction::*;
+
+        match action {
+            Close => HError::popup_finnished()?,
+            Pick => self.pick_selected()?,
+            EnterPath => {
+                self.prompt = true;
+                HError::popup_finnished()?
+            }
+        }
+
+        Ok(())
+    }
+}
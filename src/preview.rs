@@ -15,6 +15,7 @@ use crate::coordinates::Coordinates;
 use crate::fail::{HResult, HError, ErrorLog};
 use crate::imgview::ImgView;
 use crate::mediaview::MediaView;
+use crate::audioview::AudioView;
 
 
 pub type AsyncWidgetFn<W> = dyn FnOnce(&Stale, WidgetCore)
@@ -224,7 +225,8 @@ enum PreviewWidget {
     FileList(ListView<Files>),
     TextView(TextView),
     ImgView(ImgView),
-    MediaView(MediaView)
+    MediaView(MediaView),
+    AudioView(AudioView)
 }
 
 enum ExtPreviewer {
@@ -324,6 +326,14 @@ impl Previewer {
         Ok(self.animator.set_stale()?)
     }
 
+    // File shown on line ypos when previewing a directory
+    pub fn file_at(&self, ypos: u16) -> Option<File> {
+        match self.widget.widget() {
+            Ok(PreviewWidget::FileList(file_list)) => file_list.file_at(ypos),
+            _ => None
+        }
+    }
+
     pub fn take_files(&mut self) -> HResult<Files> {
         match self.widget.widget_mut() {
             Ok(PreviewWidget::FileList(file_list)) => {
@@ -406,6 +416,8 @@ impl Previewer {
                     let mime_type = mime.type_().as_str();
                     let is_gif = mime.subtype() == "gif";
                     let has_media = core.config().media_available();
+                    // Doesn't need hunter-media for pictures
+                    let has_ueberzug = core.config().graphics == "ueberzug";
 
                     match mime_type {
                         _ if mime_type == "video" || is_gif && has_media => {
@@ -415,13 +427,18 @@ impl Previewer {
                                                                      media_type)?;
                             return Ok(PreviewWidget::MediaView(mediaview));
                         }
-                        "image" if has_media => {
+                        "image" if has_media || has_ueberzug => {
                             // Show animation while image is loading, Drop stops it automatically
                             Ticker::start_ticking(core.get_sender());
                             let imgview = ImgView::new_from_file(core.clone(),
                                                                  &file.path())?;
                             return Ok(PreviewWidget::ImgView(imgview));
                         }
+                        "audio" if core.config().audio_player == "mpv" => {
+                            let audioview = AudioView::new_from_file(core.clone(),
+                                                                     &file.path)?;
+                            return Ok(PreviewWidget::AudioView(audioview));
+                        }
                         "audio" if has_media => {
                             let media_type = crate::mediaview::MediaType::Audio;
                             let mediaview = MediaView::new_from_file(core.clone(),
@@ -671,7 +688,8 @@ impl Widget for PreviewWidget {
             PreviewWidget::FileList(widget) => widget.get_core(),
             PreviewWidget::TextView(widget) => widget.get_core(),
             PreviewWidget::ImgView(widget) => widget.get_core(),
-            PreviewWidget::MediaView(widget) => widget.get_core()
+            PreviewWidget::MediaView(widget) => widget.get_core(),
+            PreviewWidget::AudioView(widget) => widget.get_core()
         }
     }
     fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
@@ -679,7 +697,8 @@ impl Widget for PreviewWidget {
             PreviewWidget::FileList(widget) => widget.get_core_mut(),
             PreviewWidget::TextView(widget) => widget.get_core_mut(),
             PreviewWidget::ImgView(widget) => widget.get_core_mut(),
-            PreviewWidget::MediaView(widget) => widget.get_core_mut()
+            PreviewWidget::MediaView(widget) => widget.get_core_mut(),
+            PreviewWidget::AudioView(widget) => widget.get_core_mut()
         }
     }
     fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
@@ -688,6 +707,7 @@ impl Widget for PreviewWidget {
             PreviewWidget::TextView(widget) => widget.set_coordinates(coordinates),
             PreviewWidget::ImgView(widget) => widget.set_coordinates(coordinates),
             PreviewWidget::MediaView(widget) => widget.set_coordinates(coordinates),
+            PreviewWidget::AudioView(widget) => widget.set_coordinates(coordinates),
         }
     }
     fn refresh(&mut self) -> HResult<()> {
@@ -695,7 +715,8 @@ impl Widget for PreviewWidget {
             PreviewWidget::FileList(widget) => widget.refresh(),
             PreviewWidget::TextView(widget) => widget.refresh(),
             PreviewWidget::ImgView(widget) => widget.refresh(),
-            PreviewWidget::MediaView(widget) => widget.refresh()
+            PreviewWidget::MediaView(widget) => widget.refresh(),
+            PreviewWidget::AudioView(widget) => widget.refresh()
         }
     }
     fn get_drawlist(&self) -> HResult<String> {
@@ -703,7 +724,8 @@ impl Widget for PreviewWidget {
             PreviewWidget::FileList(widget) => widget.get_drawlist(),
             PreviewWidget::TextView(widget) => widget.get_drawlist(),
             PreviewWidget::ImgView(widget) => widget.get_drawlist(),
-            PreviewWidget::MediaView(widget) => widget.get_drawlist()
+            PreviewWidget::MediaView(widget) => widget.get_drawlist(),
+            PreviewWidget::AudioView(widget) => widget.get_drawlist()
         }
     }
 
@@ -712,7 +734,8 @@ impl Widget for PreviewWidget {
             PreviewWidget::FileList(widget) => widget.render_footer(),
             PreviewWidget::TextView(widget) => widget.render_footer(),
             PreviewWidget::ImgView(widget) => widget.render_footer(),
-            PreviewWidget::MediaView(widget) => widget.render_footer()
+            PreviewWidget::MediaView(widget) => widget.render_footer(),
+            PreviewWidget::AudioView(widget) => widget.render_footer()
         }
     }
 
@@ -721,7 +744,8 @@ impl Widget for PreviewWidget {
             PreviewWidget::FileList(widget) => widget.on_key(key),
             PreviewWidget::TextView(widget) => widget.on_key(key),
             PreviewWidget::ImgView(widget) => widget.on_key(key),
-            PreviewWidget::MediaView(widget) => widget.on_key(key)
+            PreviewWidget::MediaView(widget) => widget.on_key(key),
+            PreviewWidget::AudioView(widget) => widget.on_key(key)
         }
     }
 }
@@ -744,3 +768,74 @@ impl<T> Widget for Box<T> where T: Widget + ?Sized {
         (**self).get_drawlist()
     }
 }
+
+
+// The preview over everything else for a moment, the file browser stays
+// just like it is underneath. Closes with the key that opened it.
+pub struct QuickLook<'a> {
+    core: WidgetCore,
+    preview: &'a mut Previewer,
+}
+
+impl<'a> QuickLook<'a> {
+    pub fn new(core: &WidgetCore, preview: &'a mut Previewer) -> QuickLook<'a> {
+        preview.cancel_animation().log();
+        preview.reload_text();
+        // Only the widget inside, the previewer remembers where it was
+        preview.widget.set_coordinates(&core.coordinates).log();
+
+        QuickLook {
+            core: core.clone(),
+            preview
+        }
+    }
+}
+
+impl<'a> Drop for QuickLook<'a> {
+    fn drop(&mut self) {
+        // Back to its place in the columns
+        let coords = self.preview.core.coordinates.clone();
+        self.preview.widget.set_coordinates(&coords).log();
+    }
+}
+
+impl<'a> Widget for QuickLook<'a> {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.preview.widget.set_coordinates(coordinates)
+    }
+    fn render_header(&self) -> HResult<String> {
+        let path = self.preview
+            .get_file()
+            .map(|file| file.path.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(path)
+    }
+    fn render_footer(&self) -> HResult<String> {
+        self.preview.render_footer()
+    }
+    fn refresh(&mut self) -> HResult<()> {
+        self.preview.refresh()
+    }
+    fn get_drawlist(&self) -> HResult<String> {
+        self.preview.get_drawlist()
+    }
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        use crate::keybind::FileBrowserAction;
+
+        let bindings = self.core.config().keybinds.filebrowser;
+
+        match (key, bindings.get(key)) {
+            (Key::Esc, _) |
+            (Key::Char('q'), _) |
+            (_, Some(FileBrowserAction::QuickLook)) => HError::popup_finnished(),
+            _ => self.preview.on_key(key)
+        }
+    }
+}
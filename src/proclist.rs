@@ -11,6 +11,7 @@ use unicode_width::UnicodeWidthStr;
 use osstrtools::{OsStringTools, OsStrTools, OsStrConcat};
 use async_value::Stale;
 use parking_lot::Mutex;
+use chrono::{DateTime, Local};
 
 use crate::listview::{Listable, ListView};
 use crate::textview::TextView;
@@ -30,10 +31,14 @@ struct Process {
     output: Arc<Mutex<String>>,
     status: Arc<Mutex<Option<i32>>>,
     success: Arc<Mutex<Option<bool>>>,
+    started: DateTime<Local>,
+    runtime: Arc<Mutex<Option<std::time::Duration>>>,
+    io: Arc<Mutex<IoStats>>,
     sender: Sender<Events>
 
 }
 
+#[derive(Debug)]
 pub struct Cmd {
     pub cmd: OsString,
     pub args: Option<Vec<OsString>>,
@@ -120,6 +125,86 @@ impl Cmd {
     }
 }
 
+// Read/write counters from /proc/<pid>/io, sampled once a second
+#[derive(Debug, Default)]
+struct IoStats {
+    // None until the first sample, which only gives the starting point
+    total: Option<(u64, u64)>,
+    rate: (u64, u64),
+    history: std::collections::VecDeque<u64>,
+}
+
+impl IoStats {
+    const HISTORY_LEN: usize = 30;
+
+    fn sample(&mut self, pid: u32) -> HResult<()> {
+        let io = std::fs::read_to_string(format!("/proc/{}/io", pid))?;
+
+        let field = |name: &str| -> Option<u64> {
+            io.lines()
+                .find(|line| line.starts_with(name))?
+                .split(':')
+                .nth(1)?
+                .trim()
+                .parse()
+                .ok()
+        };
+
+        let total = (field("read_bytes")?, field("write_bytes")?);
+        let last = match self.total.replace(total) {
+            Some(last) => last,
+            None => return Ok(())
+        };
+
+        self.rate = (total.0.saturating_sub(last.0),
+                     total.1.saturating_sub(last.1));
+
+        self.history.push_back(self.rate.0 + self.rate.1);
+        if self.history.len() > IoStats::HISTORY_LEN {
+            self.history.pop_front();
+        }
+
+        Ok(())
+    }
+
+    fn rate_string(rate: (u64, u64)) -> String {
+        format!("r {}/s w {}/s",
+                crate::files::size_string(rate.0),
+                crate::files::size_string(rate.1))
+    }
+
+    fn sparkline(&self) -> String {
+        const BARS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        let max = self.history.iter().max().cloned().unwrap_or(0).max(1);
+
+        self.history
+            .iter()
+            .map(|rate| BARS[(rate * (BARS.len() as u64 - 1) / max) as usize])
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    AfterRunning,
+    At(DateTime<Local>)
+}
+
+struct ScheduledJob {
+    cmd: Cmd,
+    when: Schedule
+}
+
+impl std::fmt::Debug for ScheduledJob {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let cmd = self.cmd.short_cmd
+            .clone()
+            .unwrap_or_else(|| self.cmd.cmd.to_string_lossy().to_string());
+        formatter.write_str(&format!("{} ({:?})", cmd, self.when))
+    }
+}
+
 impl PartialEq for Process {
     fn eq(&self, other: &Process) -> bool {
         self.cmd == other.cmd
@@ -135,6 +220,19 @@ impl Process {
         let sender = self.sender.clone();
         let cmd = self.cmd.clone();
         let pid = self.handle.lock().id();
+        let runtime = self.runtime.clone();
+        let started = std::time::Instant::now();
+        let job = crate::jobs::add(cmd.clone(), Some(pid));
+
+        let io = self.io.clone();
+        let io_status = self.status.clone();
+        std::thread::spawn(move || {
+            // Gone or not readable, either way there's nothing more to see
+            while io_status.lock().is_none() {
+                if io.lock().sample(pid).is_err() { break; }
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+        });
 
         std::thread::spawn(move || -> HResult<()> {
             let stdout = handle.lock().stdout.take()?;
@@ -160,7 +258,10 @@ impl Process {
             };
             processor(&cmd, &sender).log();
 
-            if let Ok(proc_status) = handle.lock().wait() {
+            let proc_status = handle.lock().wait();
+            job.finish(proc_status.as_ref().map(|s| s.success()).unwrap_or(false));
+
+            if let Ok(proc_status) = proc_status {
                 let proc_success = proc_status.success();
                 let proc_status = match proc_status.code() {
                     Some(status) => status,
@@ -169,6 +270,7 @@ impl Process {
 
                 *success.lock() = Some(proc_success);
                 *status.lock() = Some(proc_status);
+                *runtime.lock() = Some(started.elapsed());
 
                 let color_success =
                     if proc_success {
@@ -197,6 +299,65 @@ impl Process {
 
         Ok(())
     }
+
+    // Lines that look like something went wrong or was left out
+    fn problems(&self) -> Vec<String> {
+        const MARKERS: &[&str] = &["error", "warning", "cannot", "can't",
+                                   "failed", "skipping", "skipped",
+                                   "denied", "no such file", "not overwriting"];
+
+        self.output
+            .lock()
+            .lines()
+            .filter(|line| {
+                let line = line.to_lowercase();
+                MARKERS.iter().any(|marker| line.contains(marker))
+            })
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    fn report(&self) -> String {
+        let lines = self.output.lock().lines().count();
+        let problems = self.problems();
+        let io = self.io.lock();
+        let (read, written) = io.total.unwrap_or((0, 0));
+
+        let runtime = match *self.runtime.lock() {
+            Some(runtime) => format!("{}s", runtime.as_secs()),
+            None => String::from("still running")
+        };
+
+        let status = match (*self.status.lock(), *self.success.lock()) {
+            (Some(status), Some(true)) => format!("{} (success)", status),
+            (Some(status), _) => format!("{} (failure)", status),
+            _ => String::from("-")
+        };
+
+        let mut report = format!("Command:  {}\n\
+                                  Started:  {}\n\
+                                  Runtime:  {}\n\
+                                  Status:   {}\n\
+                                  Output:   {} lines\n\
+                                  IO:       read {}, written {} {}\n\
+                                  Problems: {}\n",
+                                 self.cmd,
+                                 self.started.format("%F %T"),
+                                 runtime,
+                                 status,
+                                 lines,
+                                 crate::files::size_string(read),
+                                 crate::files::size_string(written),
+                                 io.sparkline(),
+                                 problems.len());
+
+        if !problems.is_empty() {
+            report += "\n";
+            report += &problems.join("\n");
+        }
+
+        report
+    }
 }
 
 impl Listable for ListView<Vec<Process>> {
@@ -294,6 +455,9 @@ impl ListView<Vec<Process>> {
             output: Arc::new(Mutex::new(String::new())),
             status: Arc::new(Mutex::new(None)),
             success: Arc::new(Mutex::new(None)),
+            started: Local::now(),
+            runtime: Arc::new(Mutex::new(None)),
+            io: Arc::new(Mutex::new(IoStats::default())),
             sender: self.get_core()?.get_sender()
         };
         proc.read_proc()?;
@@ -368,11 +532,16 @@ impl ListView<Vec<Process>> {
         Ok(())
     }
 
-    fn remove_proc(&mut self) -> HResult<()> {
+    fn remove_proc(&mut self) -> HResult<Process> {
         self.kill_proc().ok();
         let selection = self.get_selection();
-        self.content.remove(selection);
-        Ok(())
+        let proc = self.content.remove(selection);
+
+        if selection >= self.content.len() && selection > 0 {
+            self.move_up();
+        }
+
+        Ok(proc)
     }
 
     fn selected_proc(&mut self) -> Option<&mut Process> {
@@ -451,7 +620,13 @@ pub struct ProcView {
     core: WidgetCore,
     hbox: HBox<ProcViewWidgets>,
     viewing: Option<usize>,
-    animator: Stale
+    animator: Stale,
+    // Removed jobs stay around until hunter quits
+    history: Vec<Process>,
+    showing_history: bool,
+    showing_report: bool,
+    scheduled: Vec<ScheduledJob>,
+    scheduler_running: bool,
 }
 
 impl HBox<ProcViewWidgets> {
@@ -492,7 +667,12 @@ impl ProcView {
             core: core.clone(),
             hbox: hbox,
             viewing: None,
-            animator: Stale::new()
+            animator: Stale::new(),
+            history: vec![],
+            showing_history: false,
+            showing_report: false,
+            scheduled: vec![],
+            scheduler_running: false
         }
     }
 
@@ -509,18 +689,128 @@ impl ProcView {
     }
 
     pub fn run_proc_subshell(&mut self, cmd: Cmd) -> HResult<()> {
+        if self.showing_history { self.toggle_history()?; }
         self.get_listview_mut().run_proc_subshell(cmd)?;
         Ok(())
     }
 
     pub fn run_proc_raw(&mut self, cmd: Cmd) -> HResult<()> {
+        if self.showing_history { self.toggle_history()?; }
         self.get_listview_mut().run_proc_raw(cmd)?;
         Ok(())
     }
 
+    pub fn start(proc_view: &Arc<Mutex<ProcView>>,
+                 cmd: Cmd,
+                 when: Option<Schedule>) -> HResult<()> {
+        match when {
+            Some(when) => ProcView::schedule(proc_view, cmd, when),
+            None => proc_view.lock().run_proc_raw(cmd)
+        }
+    }
+
+    // Jobs are started from a background thread polling once a second,
+    // or from refresh() while the process view itself holds the lock
+    pub fn schedule(proc_view: &Arc<Mutex<ProcView>>,
+                    cmd: Cmd,
+                    when: Schedule) -> HResult<()> {
+        let mut view = proc_view.lock();
+
+        let status = match when {
+            Schedule::AfterRunning => String::from("after running jobs"),
+            Schedule::At(time) => format!("at {}", time.format("%F %R"))
+        };
+        view.core.show_status(&format!("Scheduled job to start {}", status)).log();
+
+        view.scheduled.push(ScheduledJob { cmd, when });
+
+        if view.scheduler_running { return Ok(()); }
+        view.scheduler_running = true;
+
+        let proc_view = proc_view.clone();
+
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(std::time::Duration::from_secs(1));
+
+                if let Some(mut view) = proc_view.try_lock() {
+                    view.start_due_jobs().log();
+
+                    if view.scheduled.is_empty() {
+                        view.scheduler_running = false;
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn start_due_jobs(&mut self) -> HResult<()> {
+        if self.scheduled.is_empty() { return Ok(()); }
+
+        let now = Local::now();
+        let mut running = self.running_procs();
+        let mut waiting = vec![];
+
+        for job in std::mem::replace(&mut self.scheduled, vec![]) {
+            let due = match job.when {
+                Schedule::At(time) => time <= now,
+                // Start these one by one, each waits for the previous
+                Schedule::AfterRunning => running == 0
+            };
+
+            match due {
+                true => {
+                    self.run_proc_raw(job.cmd).log();
+                    running += 1;
+                }
+                false => waiting.push(job)
+            }
+        }
+
+        self.scheduled = waiting;
+        Ok(())
+    }
+
+    fn running_procs(&self) -> usize {
+        // Removed processes might still be running when history is shown
+        let listview = self.get_listview();
+        listview.content
+            .iter()
+            .chain(self.history.iter())
+            .filter(|proc| proc.status.lock().is_none())
+            .count()
+    }
+
+    // Combined throughput of all running jobs, if there are any
+    pub fn io_rate(&self) -> Option<String> {
+        let listview = self.get_listview();
+        let running = listview.content
+            .iter()
+            .chain(self.history.iter())
+            .filter(|proc| proc.status.lock().is_none())
+            .collect::<Vec<_>>();
+
+        if running.is_empty() { return None; }
+
+        let rate = running.iter()
+            .map(|proc| proc.io.lock().rate)
+            .fold((0, 0), |(r, w), (pr, pw)| (r + pr, w + pw));
+
+        Some(IoStats::rate_string(rate))
+    }
+
     pub fn remove_proc(&mut self) -> HResult<()> {
         if self.get_listview_mut().content.len() == 0 { return Ok(()) }
-        self.get_listview_mut().remove_proc()?;
+        let proc = self.get_listview_mut().remove_proc()?;
+
+        // Removing from the history gets rid of the job for good
+        if !self.showing_history {
+            self.history.push(proc);
+        }
+
         self.get_textview().get_core()?.clear().log();
         self.get_textview().widget_mut()?.set_text("").log();
         self.viewing = None;
@@ -531,7 +821,10 @@ impl ProcView {
         if Some(self.get_listview_mut().get_selection()) == self.viewing {
             return Ok(());
         }
-        let output = self.get_listview_mut().selected_proc()?.output.lock().clone();
+        let output = match self.showing_report {
+            true => self.get_listview_mut().selected_proc()?.report(),
+            false => self.get_listview_mut().selected_proc()?.output.lock().clone()
+        };
 
         let animator = self.animator.clone();
         animator.set_fresh().log();
@@ -547,6 +840,27 @@ impl ProcView {
         Ok(())
     }
 
+    pub fn toggle_report(&mut self) -> HResult<()> {
+        self.showing_report = !self.showing_report;
+        self.viewing = None;
+        Ok(())
+    }
+
+    pub fn toggle_history(&mut self) -> HResult<()> {
+        std::mem::swap(&mut self.hbox.get_listview_mut().content,
+                       &mut self.history);
+        self.showing_history = !self.showing_history;
+
+        let listview = self.get_listview_mut();
+        listview.move_top();
+        listview.core.clear().log();
+
+        self.get_textview().get_core()?.clear().log();
+        self.get_textview().widget_mut()?.set_text("").log();
+        self.viewing = None;
+        Ok(())
+    }
+
     pub fn toggle_follow(&mut self) -> HResult<()> {
         self.get_textview().widget_mut()?.toggle_follow();
         Ok(())
@@ -581,6 +895,12 @@ impl ProcView {
         self.get_textview().widget_mut()?.scroll_bottom();
         Ok(())
     }
+
+    pub fn goto_line(&mut self) -> HResult<()> {
+        let line = self.core.minibuffer_number("line", Some(1), None)?;
+        self.get_textview().widget_mut()?.goto_line(line as usize);
+        Ok(())
+    }
 }
 
 impl Widget for ProcView {
@@ -605,9 +925,19 @@ impl Widget for ProcView {
             .filter(|proc| proc.status.lock().is_none())
             .count();
 
-        let header = format!("Running processes: {} / {}",
-                             procs_running,
-                             procs_num);
+        if self.showing_history {
+            return Ok(format!("Removed processes: {}", procs_num));
+        }
+
+        let header = match self.scheduled.len() {
+            0 => format!("Running processes: {} / {}",
+                         procs_running,
+                         procs_num),
+            scheduled => format!("Running processes: {} / {} ({} scheduled)",
+                                 procs_running,
+                                 procs_num,
+                                 scheduled)
+        };
         Ok(header)
     }
 
@@ -647,7 +977,12 @@ impl Widget for ProcView {
                                      term::status_bg(),
                                      color_status);
                 procinfo
-            } else { "still running".to_string() };
+            } else {
+                let io = proc.io.lock();
+                format!("still running {} {}",
+                        IoStats::rate_string(io.rate),
+                        io.sparkline())
+            };
 
             let footer = term::sized_string_u(&procinfo, xsize);
 
@@ -656,6 +991,7 @@ impl Widget for ProcView {
     }
 
     fn refresh(&mut self) -> HResult<()> {
+        self.start_due_jobs().log();
         self.hbox.refresh().log();
 
         if self.get_listview().len() > 0 {
@@ -708,7 +1044,10 @@ impl Acting for ProcView {
             ScrollOutputPageDown => self.page_down()?,
             ScrollOutputPageUp => self.page_up()?,
             ScrollOutputBottom => self.scroll_bottom()?,
-            ScrollOutputTop => self.scroll_top()?
+            ScrollOutputTop => self.scroll_top()?,
+            GotoLine => self.goto_line()?,
+            ShowReport => self.toggle_report()?,
+            ShowRemoved => self.toggle_history()?
         }
 
         Ok(())
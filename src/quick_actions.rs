@@ -16,6 +16,7 @@ use crate::widget::{Widget, WidgetCore, Events};
 use crate::foldview::{Foldable, FoldableWidgetExt, ActingExt};
 use crate::listview::ListView;
 use crate::proclist::ProcView;
+use crate::minibuffer::Wizard;
 use crate::files::File;
 use crate::paths;
 use crate::term;
@@ -362,33 +363,14 @@ impl QuickAction {
            files: Vec<File>,
            core: &WidgetCore,
            proc_view: Arc<Mutex<ProcView>>) -> HResult<()> {
-        use crate::minibuffer::MiniBufferEvent::*;;
-
         let answers = self.queries
             .iter()
-            .fold(Ok(vec![]), |mut acc, query| {
-                // If error occured/input was cancelled just skip querying
-                // Turn into try_fold?
-                if acc.is_err() { return acc; }
-
-                match core.minibuffer(query) {
-                    Err(HError::MiniBufferEvent(Empty)) => {
-                        acc.as_mut()
-                            .map(|acc| acc.push((OsString::from(query),
-                                                 OsString::from(""))))
-                            .ok();
-                        acc
-                    }
-                    Ok(input) => {
-                        acc.as_mut()
-                            .map(|acc| acc.push((OsString::from(query),
-                                                 OsString::from(input))))
-                            .ok();
-                        acc
-                    }
-                    Err(err) => Err(err)
-                }
-            })?;
+            .fold(Wizard::new(), |wizard, query| wizard.step(query).optional())
+            .run(core)?
+            .into_iter()
+            .zip(self.queries.iter())
+            .map(|(answer, query)| (OsString::from(query), OsString::from(answer)))
+            .collect::<Vec<_>>();
 
         let cwd = files.get(0)?.parent_as_file()?;
 
@@ -0,0 +1,109 @@
+use parking_lot::Mutex;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::fail::HError;
+
+
+// For hunting down flicker and needless redraws. While TraceRedraws is on,
+// every frame ends up in the log with what caused it, which widgets got
+// marked dirty and why, what was cleared and how much got written. Off it
+// costs one atomic load at each of those places.
+
+static TRACING: AtomicBool = AtomicBool::new(false);
+static FRAME: AtomicUsize = AtomicUsize::new(0);
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Default)]
+struct Frame {
+    triggers: Vec<String>,
+    marks: Vec<String>,
+    clears: Vec<String>,
+}
+
+lazy_static! {
+    static ref CURRENT: Mutex<Frame> = Mutex::new(Frame::default());
+}
+
+pub fn tracing() -> bool {
+    TRACING.load(Ordering::Relaxed)
+}
+
+pub fn toggle() -> bool {
+    let tracing = !TRACING.fetch_xor(true, Ordering::Relaxed);
+
+    // Leftovers from before would end up in the first frame
+    *CURRENT.lock() = Frame::default();
+    WRITTEN.store(0, Ordering::Relaxed);
+
+    tracing
+}
+
+// Whatever woke up the main loop, a key, a widget being ready...
+pub fn trigger(what: impl Into<String>) {
+    if !tracing() { return; }
+    CURRENT.lock().triggers.push(what.into());
+}
+
+pub fn mark(widget: &str, why: impl Into<String>) {
+    if !tracing() { return; }
+    let widget = short_name(widget);
+    CURRENT.lock().marks.push(format!("{}: {}", widget, why.into()));
+}
+
+pub fn clear(area: String) {
+    if !tracing() { return; }
+    CURRENT.lock().clears.push(area);
+}
+
+pub fn written(bytes: usize) {
+    if !tracing() { return; }
+    WRITTEN.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn frame_done(refresh: Duration, draw: Duration) {
+    if !tracing() { return; }
+
+    let frame = std::mem::take(&mut *CURRENT.lock());
+    let written = WRITTEN.swap(0, Ordering::Relaxed);
+    let n = FRAME.fetch_add(1, Ordering::Relaxed);
+
+    let list = |items: &[String]| match items.is_empty() {
+        true => String::from("-"),
+        false => items.join(", ")
+    };
+
+    let msg = format!("frame {}: refresh {}ms, draw {}ms, {} bytes\n  \
+                       because: {}\n  \
+                       dirty: {}\n  \
+                       cleared: {}",
+                      n,
+                      refresh.as_millis(),
+                      draw.as_millis(),
+                      written,
+                      list(&frame.triggers),
+                      list(&frame.marks),
+                      list(&frame.clears));
+    HError::log::<()>(&msg).ok();
+}
+
+// hunter::listview::ListView<hunter::files::Files> -> ListView<Files>
+pub fn short_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut word = String::new();
+
+    for c in name.chars() {
+        match c {
+            ':' => word.clear(),
+            '<' | '>' | ',' | ' ' | '(' | ')' => {
+                short.push_str(&word);
+                short.push(c);
+                word.clear();
+            }
+            _ => word.push(c)
+        }
+    }
+
+    short + &word
+}
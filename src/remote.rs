@@ -0,0 +1,650 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+
+use std::path::{Path, PathBuf};
+use std::ffi::OsString;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::listview::{Listable, ListView};
+use crate::widget::{Widget, WidgetCore, Events};
+use crate::coordinates::Coordinates;
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+use crate::dirty::Dirtyable;
+
+
+// Things that aren't on the local filesystem, but can still be listed
+// like directories. Files aren't opened directly, but fetched into the
+// current directory by a job.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    // Whatever else is worth showing, like dates or permissions
+    pub info: String,
+}
+
+impl RemoteEntry {
+    pub fn dir(name: &str) -> RemoteEntry {
+        RemoteEntry {
+            name: name.to_string(),
+            is_dir: true,
+            size: None,
+            info: String::new()
+        }
+    }
+
+    pub fn file(name: &str, size: Option<u64>) -> RemoteEntry {
+        RemoteEntry {
+            name: name.to_string(),
+            is_dir: false,
+            size: size,
+            info: String::new()
+        }
+    }
+
+    pub fn with_info(mut self, info: impl Into<String>) -> RemoteEntry {
+        self.info = info.into();
+        self
+    }
+}
+
+pub trait Backend: Send + std::fmt::Debug {
+    // Shown in the header, like "docker alpine:latest"
+    fn name(&self) -> String;
+    // Where browsing starts, paths are always absolute
+    fn root(&self) -> PathBuf {
+        PathBuf::from("/")
+    }
+    fn list(&mut self, path: &Path) -> HResult<Vec<RemoteEntry>>;
+    fn fetch(&mut self,
+             path: &Path,
+             is_dir: bool,
+             target: &Path,
+             cwd: &File) -> HResult<Cmd>;
+    fn upload(&mut self,
+              _sources: &[PathBuf],
+              _path: &Path,
+              _cwd: &File) -> HResult<Cmd> {
+        Err(HError::Error(String::from("Uploading isn't supported here")))
+    }
+    // Backends that can log in with a password name the login here,
+    // like "ssh:user@host". That's what it's saved under.
+    fn credentials_key(&self) -> Option<String> {
+        None
+    }
+    fn set_secret(&mut self, _secret: Option<String>) {}
+}
+
+pub type SharedBackend = Arc<Mutex<Box<dyn Backend>>>;
+
+// Runs a transfer again when it fails, waiting 2, 4, 8... seconds in between.
+// The commands from the backends continue partial downloads where they can.
+pub fn retrying(cmd: Cmd, attempts: usize) -> Cmd {
+    let script = r#"
+attempts="$1"; shift
+n=1
+until "$@"; do
+    if [ "$n" -ge "$attempts" ]; then
+        echo "giving up after $n attempts"; exit 1
+    fi
+    pause=$((1 << n))
+    echo "attempt $n failed, retrying in ${pause}s"
+    sleep "$pause"; n=$((n + 1))
+done
+"#;
+
+    let mut args = vec![OsString::from("-c"),
+                        OsString::from(script),
+                        OsString::from("sh"),
+                        OsString::from(attempts.max(1).to_string()),
+                        cmd.cmd];
+    args.extend(cmd.args.unwrap_or(vec![]));
+
+    Cmd {
+        cmd: OsString::from("sh"),
+        args: Some(args),
+        ..cmd
+    }
+}
+
+// Splits off n whitespace separated fields, the rest is returned as is,
+// since that's usually a file name that can contain spaces itself
+pub fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = vec![];
+    let mut rest = line;
+
+    for _ in 0..n {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    Some((fields, rest.trim_start()))
+}
+
+
+impl Listable for ListView<Vec<RemoteEntry>> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let xsize = self.get_coordinates().unwrap().xsize_u();
+
+        self.content
+            .iter()
+            .map(|entry| {
+                let size = entry.size
+                    .map(|size| crate::files::size_string(size))
+                    .unwrap_or_default();
+                let info = format!("{:>8}  {}", size, entry.info);
+                let name_len = xsize.saturating_sub(info.len() + 2);
+
+                let (color, name) = match entry.is_dir {
+                    true => (term::highlight_color(), format!("{}/", entry.name)),
+                    false => (term::normal_color(), entry.name.clone())
+                };
+
+                format!("{}{}{}  {}",
+                        color,
+                        term::sized_string_u(&name, name_len),
+                        term::normal_color(),
+                        info)
+            })
+            .collect()
+    }
+
+    fn on_refresh(&mut self) -> HResult<()> {
+        self.core.set_dirty();
+        Ok(())
+    }
+}
+
+
+// Listings are cached on disk, keyed by backend and path
+
+fn cache_file(name: &str, path: &Path) -> HResult<PathBuf> {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    name.hash(&mut hasher);
+    path.hash(&mut hasher);
+
+    let dir = crate::paths::cache_path()?.join("listings");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{:016x}", hasher.finish())))
+}
+
+// One entry per line: "d" or "f", size, name and info, separated by tabs
+fn write_cache(name: &str, path: &Path, entries: &[RemoteEntry]) -> HResult<()> {
+    let lines = entries.iter()
+        .filter(|entry| !entry.name.contains('\t') && !entry.name.contains('\n'))
+        .map(|entry| format!("{}\t{}\t{}\t{}\n",
+                             if entry.is_dir { "d" } else { "f" },
+                             entry.size.map(|s| s.to_string()).unwrap_or_default(),
+                             entry.name,
+                             entry.info))
+        .collect::<String>();
+
+    std::fs::write(cache_file(name, path)?, lines)?;
+    Ok(())
+}
+
+fn read_cache(name: &str, path: &Path) -> Option<(Vec<RemoteEntry>, std::time::Duration)> {
+    let file = cache_file(name, path).ok()?;
+    let age = file.metadata().ok()?.modified().ok()?.elapsed().unwrap_or_default();
+    let content = std::fs::read_to_string(file).ok()?;
+
+    let entries = content.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let kind = fields.next()?;
+            let size = fields.next()?.parse().ok();
+            let name = fields.next()?;
+            let info = fields.next().unwrap_or("");
+
+            let entry = match kind {
+                "d" => RemoteEntry::dir(name),
+                _ => RemoteEntry::file(name, size)
+            };
+            Some(entry.with_info(info))
+        })
+        .collect();
+
+    Some((entries, age))
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connection {
+    Connected,
+    Reconnecting { attempt: u32, retry_at: Instant }
+}
+
+// Waits 2, 4, 8... seconds, but never more than a minute
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs((1u64 << attempt.min(6)).min(60))
+}
+
+
+// Listing is done in the background, since it can easily take a while.
+// When the connection is lost, that keeps trying again until it's back.
+#[derive(Debug)]
+pub struct RemoteBrowser {
+    core: WidgetCore,
+    list: ListView<Vec<RemoteEntry>>,
+    backend: SharedBackend,
+    name: String,
+    path: PathBuf,
+    cwd: File,
+    pending: Arc<Mutex<Option<HResult<Vec<RemoteEntry>>>>>,
+    // Selected again after going up
+    select_after: Option<String>,
+    // Files selected in the file browser, for uploading
+    uploads: Vec<PathBuf>,
+    loading: bool,
+    error: Option<String>,
+    // Set when showing a listing from the cache
+    cache_age: Option<Duration>,
+    // When the listing on screen came from the server
+    listed_at: Option<Instant>,
+    connection: Arc<Mutex<Connection>>,
+    pub jobs: Vec<Cmd>,
+    // Asks for a password after closing, since popups can't be nested
+    pub login: bool,
+}
+
+impl RemoteBrowser {
+    pub fn new(core: &WidgetCore, backend: Box<dyn Backend>, cwd: &File) -> RemoteBrowser {
+        let root = backend.root();
+        let mut browser = RemoteBrowser {
+            core: core.clone(),
+            list: ListView::new(core, vec![]),
+            name: backend.name(),
+            backend: Arc::new(Mutex::new(backend)),
+            path: root.clone(),
+            cwd: cwd.clone(),
+            pending: Arc::new(Mutex::new(None)),
+            select_after: None,
+            uploads: vec![],
+            loading: false,
+            error: None,
+            cache_age: None,
+            listed_at: None,
+            connection: Arc::new(Mutex::new(Connection::Connected)),
+            jobs: vec![],
+            login: false
+        };
+
+        browser.load(root);
+        browser
+    }
+
+    pub fn with_uploads(mut self, uploads: Vec<PathBuf>) -> RemoteBrowser {
+        self.uploads = uploads;
+        self
+    }
+
+    pub fn credentials_key(&self) -> Option<String> {
+        self.backend.lock().credentials_key()
+    }
+
+    pub fn set_secret(&mut self, secret: Option<String>) {
+        self.backend.lock().set_secret(secret);
+        self.login = false;
+
+        let path = self.path.clone();
+        self.load(path);
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        self.load_with(path, false);
+    }
+
+    // Cached listings are shown right away, fresh enough ones aren't
+    // even loaded again, unless forced to
+    fn load_with(&mut self, path: PathBuf, force: bool) {
+        let pending = Arc::new(Mutex::new(None));
+        let backend = self.backend.clone();
+        let sender = self.core.get_sender();
+        let name = self.name.clone();
+        let ttl = self.core.config().remote_cache_ttl;
+
+        self.pending = pending.clone();
+        self.path = path.clone();
+        self.error = None;
+        self.list.content.clear();
+        self.list.set_selection(0);
+
+        self.cache_age = match read_cache(&name, &path) {
+            Some((entries, age)) => {
+                self.show_entries(entries);
+                Some(age)
+            }
+            None => None
+        };
+        self.listed_at = self.cache_age
+            .and_then(|age| Instant::now().checked_sub(age));
+
+        if !force && self.cache_age.map(|age| age.as_secs() < ttl).unwrap_or(false) {
+            self.loading = false;
+            self.select_after = None;
+            return;
+        }
+
+        self.loading = true;
+        let connection = self.connection.clone();
+
+        std::thread::spawn(move || {
+            let mut attempt = 0;
+
+            let entries = loop {
+                match backend.lock().list(&path) {
+                    // Only while the browser still wants this listing
+                    Err(HError::ConnectionError(_)) if Arc::strong_count(&pending) > 1 => {
+                        attempt += 1;
+                        let pause = backoff(attempt);
+                        *connection.lock() = Connection::Reconnecting {
+                            attempt: attempt,
+                            retry_at: Instant::now() + pause
+                        };
+                        sender.send(Events::WidgetReady).ok();
+                        std::thread::sleep(pause);
+                    }
+                    result => break result
+                }
+            };
+
+            if let Ok(entries) = &entries {
+                *connection.lock() = Connection::Connected;
+                write_cache(&name, &path, entries).log();
+            }
+
+            *pending.lock() = Some(entries);
+            sender.send(Events::WidgetReady).ok();
+        });
+    }
+
+    fn offline(&self) -> bool {
+        *self.connection.lock() != Connection::Connected
+    }
+
+    fn show_entries(&mut self, mut entries: Vec<RemoteEntry>) {
+        entries.sort_by(|a, b| {
+            b.is_dir.cmp(&a.is_dir).then_with(|| natord::compare(&a.name, &b.name))
+        });
+
+        // Stay on the same entry when a cached listing is replaced
+        let selected = self.selected()
+            .map(|entry| entry.name.clone())
+            .or_else(|| self.select_after.clone());
+
+        self.list.content = entries;
+
+        let selection = selected
+            .and_then(|name| {
+                self.list.content.iter().position(|e| e.name == name)
+            })
+            .unwrap_or(0);
+        self.list.set_selection(selection);
+    }
+
+    fn poll(&mut self) {
+        let result = match self.pending.lock().take() {
+            Some(result) => result,
+            None => return
+        };
+
+        self.loading = false;
+
+        match result {
+            Ok(entries) => {
+                self.cache_age = None;
+                self.listed_at = Some(Instant::now());
+                self.show_entries(entries);
+                self.select_after = None;
+            }
+            Err(err) => self.error = Some(format!("{}", err))
+        }
+    }
+
+    fn selected(&self) -> Option<&RemoteEntry> {
+        self.list.content.get(self.list.get_selection())
+    }
+
+    fn enter(&mut self) -> HResult<()> {
+        let entry = self.selected()?.clone();
+
+        match entry.is_dir {
+            true => {
+                let path = self.path.join(&entry.name);
+                self.load(path);
+            }
+            false => self.fetch()?
+        }
+
+        Ok(())
+    }
+
+    fn go_up(&mut self) {
+        let name = self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        let parent = match self.path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return
+        };
+
+        self.select_after = name;
+        self.load(parent);
+    }
+
+    fn fetch(&mut self) -> HResult<()> {
+        let entry = self.selected()?.clone();
+        let path = self.path.join(&entry.name);
+        let target = self.cwd.path.join(&entry.name);
+
+        if let Some(reason) = crate::stats::write_blocker(&self.cwd.path) {
+            let status = format!("{}Can't fetch here: {}", term::color_red(), reason);
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        if target.symlink_metadata().is_ok() {
+            let status = format!("{}{} already exists!", term::color_red(), entry.name);
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        let cmd = self.backend.lock().fetch(&path,
+                                            entry.is_dir,
+                                            &target,
+                                            &self.cwd)?;
+        let retries = self.core.config().transfer_retries;
+        self.jobs.push(retrying(cmd, retries));
+
+        let status = format!("Will fetch {} when closed{}",
+                             entry.name,
+                             self.queued_hint());
+        self.core.show_status(&status).log();
+        Ok(())
+    }
+
+    // Transfers retry on their own, so they can be queued while offline
+    fn queued_hint(&self) -> &'static str {
+        match self.offline() {
+            true => ", once the connection is back",
+            false => ""
+        }
+    }
+
+    fn upload(&mut self) -> HResult<()> {
+        if self.uploads.is_empty() {
+            let status = format!("{}Nothing to upload, select some files first!",
+                                 term::color_red());
+            self.core.show_status(&status).log();
+            return Ok(());
+        }
+
+        let uploads = std::mem::replace(&mut self.uploads, vec![]);
+        let cmd = self.backend.lock().upload(&uploads, &self.path, &self.cwd);
+
+        match cmd {
+            Ok(cmd) => {
+                let retries = self.core.config().transfer_retries;
+                self.jobs.push(retrying(cmd, retries));
+                let status = format!("Will upload {} files to {} when closed{}",
+                                     uploads.len(),
+                                     self.path.to_string_lossy(),
+                                     self.queued_hint());
+                self.core.show_status(&status).log();
+            }
+            Err(HError::Error(msg)) => {
+                self.uploads = uploads;
+                let status = format!("{}{}", term::color_red(), msg);
+                self.core.show_status(&status).log();
+            }
+            Err(err) => {
+                self.uploads = uploads;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Widget for RemoteBrowser {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.list.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        // Cached listings always show their age, fresh ones once they're stale
+        let stale_after = self.core.config().stale_after * 60;
+        let age = self.listed_at.map(|listed_at| listed_at.elapsed().as_secs());
+        let stale = age.map(|age| stale_after > 0 && age >= stale_after)
+            .unwrap_or(false);
+        let cached = match (age, self.cache_age.is_some(), stale) {
+            (Some(age), _, true) => format!(", {}listed {}m ago{}",
+                                            term::color_red(),
+                                            age / 60,
+                                            term::normal_color()),
+            (Some(age), true, false) => format!(", cached {}m ago", age / 60),
+            _ => String::new()
+        };
+
+        let connection = match *self.connection.lock() {
+            Connection::Connected => String::from("connected"),
+            Connection::Reconnecting { attempt, retry_at } => {
+                let wait = retry_at.saturating_duration_since(Instant::now());
+                format!("{}offline, reconnecting in {}s (attempt {}){}",
+                        term::color_red(),
+                        wait.as_secs(),
+                        attempt,
+                        term::normal_color())
+            }
+        };
+
+        let state = match (&self.error, self.loading) {
+            (Some(err), _) => format!("{}{}{}", term::color_red(), err, term::normal_color()),
+            (None, true) => format!("{}{}, loading...", connection, cached),
+            (None, false) => format!("{}{}", connection, cached)
+        };
+
+        Ok(format!("{}: {}  [{}]", self.name, self.path.to_string_lossy(), state))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = format!("Enter: open/fetch  f: fetch  u: upload  L: login  \
+                            Esc: close  {} queued",
+                           self.jobs.len());
+        Ok(term::sized_string_u(&hint, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        self.poll();
+        self.list.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.list.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, RemoteAction, Movement};
+
+impl Acting for RemoteBrowser {
+    type Action = RemoteAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.remote
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        match movement {
+            Up(n) => { for _ in 0..*n { self.list.move_up(); } }
+            Down(n) => { for _ in 0..*n { self.list.move_down(); } }
+            PageUp => self.list.page_up(),
+            PageDown => self.list.page_down(),
+            Top => self.list.move_top(),
+            Bottom => self.list.move_bottom(),
+            Right => self.enter()?,
+            Left => self.go_up()
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use RemoteAction::*;
+
+        match action {
+            Close => HError::popup_finnished()?,
+            Enter => self.enter()?,
+            Fetch => self.fetch()?,
+            Upload => self.upload()?,
+            Login => {
+                if self.credentials_key().is_none() {
+                    self.core.show_status("No login needed here").log();
+                    return Ok(());
+                }
+                self.login = true;
+                HError::popup_finnished()?
+            }
+            Reload => {
+                let path = self.path.clone();
+                self.load_with(path, true);
+            }
+        }
+
+        Ok(())
+    }
+}
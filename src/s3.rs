@@ -0,0 +1,187 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf, Component};
+use std::process::Command;
+
+use crate::remote::{Backend, RemoteEntry, split_fields};
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError};
+
+
+// S3 and compatible stores like MinIO, through the aws cli. That already
+// knows where to find credentials (~/.aws, AWS_* variables). For anything
+// that isn't AWS, AWS_ENDPOINT_URL has to point at the server.
+// The top level lists the buckets, prefixes inside them are directories.
+
+#[derive(Debug)]
+pub struct S3Backend {
+    start: PathBuf,
+}
+
+fn aws_args() -> Vec<OsString> {
+    match std::env::var_os("AWS_ENDPOINT_URL") {
+        Some(endpoint) => vec![OsString::from("--endpoint-url"), endpoint],
+        None => vec![]
+    }
+}
+
+// "/bucket/some/prefix" -> "s3://bucket/some/prefix"
+fn s3_url(path: &Path) -> String {
+    let path = path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    format!("s3://{}", path)
+}
+
+impl S3Backend {
+    // Takes "s3://bucket/prefix", or nothing to start with the buckets
+    pub fn new(location: &str) -> S3Backend {
+        let path = location.trim_start_matches("s3://").trim_matches('/');
+
+        S3Backend {
+            start: PathBuf::from("/").join(path)
+        }
+    }
+}
+
+// Buckets: "2024-01-01 12:00:00 bucket"
+// Objects: "2024-01-01 12:00:00       1234 name" or "PRE prefix/"
+fn parse_ls_line(line: &str, buckets: bool) -> Option<RemoteEntry> {
+    if let Some((_, prefix)) = split_fields(line, 1)
+        .filter(|(fields, _)| fields[0] == "PRE") {
+        return Some(RemoteEntry::dir(prefix.trim_end_matches('/')));
+    }
+
+    if buckets {
+        let (fields, name) = split_fields(line, 2)?;
+        let info = format!("{} {}", fields[0], fields[1]);
+        return Some(RemoteEntry::dir(name).with_info(info));
+    }
+
+    let (fields, name) = split_fields(line, 3)?;
+    let size = fields[2].parse().ok();
+    let info = format!("{} {}", fields[0], fields[1].get(..5).unwrap_or(fields[1]));
+
+    // Placeholder objects for "directories" have an empty name
+    if name.is_empty() { return None; }
+
+    Some(RemoteEntry::file(name, size).with_info(info))
+}
+
+fn aws_cmd(args: Vec<OsString>, short_cmd: String, cwd: &File) -> Cmd {
+    let mut all_args = vec![OsString::from("s3")];
+    all_args.extend(aws_args());
+    all_args.extend(args);
+
+    Cmd {
+        cmd: OsString::from("aws"),
+        args: Some(all_args),
+        vars: None,
+        short_cmd: Some(short_cmd),
+        cwd: cwd.clone(),
+        cwd_files: None,
+        tab_files: None,
+        tab_paths: None
+    }
+}
+
+impl Backend for S3Backend {
+    fn name(&self) -> String {
+        String::from("s3")
+    }
+
+    fn root(&self) -> PathBuf {
+        self.start.clone()
+    }
+
+    fn list(&mut self, path: &Path) -> HResult<Vec<RemoteEntry>> {
+        let buckets = path.components().count() <= 1;
+        let mut url = s3_url(path);
+        if !buckets { url.push('/'); }
+
+        let output = Command::new("aws")
+            .arg("s3")
+            .args(aws_args())
+            .arg("ls")
+            .arg(&url)
+            .stdin(std::process::Stdio::null())
+            .output()?;
+
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+            // The aws cli only tells by the message
+            let connection_lost = ["Could not connect", "Connect timeout", "Read timeout"]
+                .iter()
+                .any(|msg| err.contains(msg));
+
+            return match connection_lost {
+                true => Err(HError::ConnectionError(err)),
+                false => Err(HError::Error(err))
+            };
+        }
+
+        let listing = String::from_utf8_lossy(&output.stdout);
+        Ok(listing.lines().filter_map(|line| parse_ls_line(line, buckets)).collect())
+    }
+
+    fn fetch(&mut self,
+             path: &Path,
+             is_dir: bool,
+             target: &Path,
+             cwd: &File) -> HResult<Cmd> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+
+        let mut args = vec![OsString::from("cp")];
+        if is_dir { args.push(OsString::from("--recursive")); }
+        args.push(OsString::from(s3_url(path)));
+        args.push(target.as_os_str().to_os_string());
+
+        Ok(aws_cmd(args, format!("download {}", name), cwd))
+    }
+
+    fn upload(&mut self,
+              sources: &[PathBuf],
+              path: &Path,
+              cwd: &File) -> HResult<Cmd> {
+        if path.components().count() <= 1 {
+            return Err(HError::Error(String::from("Can't upload outside of a bucket")));
+        }
+
+        // One cp per file, so this runs as a little script
+        let script = r#"
+target="$1"; shift
+for src in "$@"; do
+    if [ -d "$src" ]; then
+        aws s3 ${AWS_ENDPOINT_URL:+--endpoint-url "$AWS_ENDPOINT_URL"} \
+            cp --recursive "$src" "$target/$(basename "$src")/"
+    else
+        aws s3 ${AWS_ENDPOINT_URL:+--endpoint-url "$AWS_ENDPOINT_URL"} \
+            cp "$src" "$target/"
+    fi
+done
+"#;
+
+        let mut args = vec![OsString::from("-c"),
+                            OsString::from(script),
+                            OsString::from("sh"),
+                            OsString::from(s3_url(path))];
+        args.extend(sources.iter().map(|s| s.as_os_str().to_os_string()));
+
+        Ok(Cmd {
+            cmd: OsString::from("sh"),
+            args: Some(args),
+            vars: None,
+            short_cmd: Some(format!("upload {} files to {}", sources.len(), s3_url(path))),
+            cwd: cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        })
+    }
+}
@@ -0,0 +1,51 @@
+use std::ffi::{CString, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+
+// SELinux labels files with a security context like
+// "unconfined_u:object_r:user_home_t:s0", kept in an extended attribute.
+// AppArmor has no labels on files, so there's nothing to show for it.
+
+lazy_static! {
+    static ref ENABLED: bool = Path::new("/sys/fs/selinux/enforce").exists();
+}
+
+pub fn enabled() -> bool {
+    *ENABLED
+}
+
+pub fn context(path: &Path) -> Option<String> {
+    if !enabled() { return None; }
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let name = b"security.selinux\0";
+    let mut buf = vec![0u8; 256];
+
+    // Symlinks have their own label
+    let len = unsafe {
+        libc::lgetxattr(path.as_ptr(),
+                        name.as_ptr() as *const libc::c_char,
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len())
+    };
+    if len <= 0 { return None; }
+
+    buf.truncate(len as usize);
+    let context = String::from_utf8_lossy(&buf);
+    Some(context.trim_end_matches('\0').to_string())
+}
+
+// An empty context puts back the default one from the policy
+pub fn relabel_args(paths: &[PathBuf], context: &str, recursive: bool) -> Vec<OsString> {
+    let mut args = match context {
+        "" => vec![OsString::from("restorecon")],
+        _ => vec![OsString::from("chcon")]
+    };
+
+    if recursive { args.push(OsString::from("-R")); }
+    if !context.is_empty() { args.push(OsString::from(context)); }
+    args.push(OsString::from("--"));
+    args.extend(paths.iter().map(|path| path.as_os_str().to_os_string()));
+    args
+}
@@ -0,0 +1,233 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::imgview::ImgView;
+use crate::widget::{Widget, WidgetCore, Events};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError};
+use crate::term;
+
+
+// Shows the images in a directory one after another, using the whole
+// screen. A timer thread says when it's time for the next one, which by
+// then should already be encoded in the background, so switching is just
+// drawing it. An interval of 0 means it only moves on with keys.
+
+pub struct Slideshow {
+    core: WidgetCore,
+    images: Vec<PathBuf>,
+    pos: usize,
+    current: Option<ImgView>,
+    // Position and size it was made for, and the image data
+    preloaded: Arc<Mutex<Option<(usize, Coordinates, Vec<String>)>>>,
+    interval: Duration,
+    deadline: Arc<Mutex<Instant>>,
+    paused: Arc<AtomicBool>,
+    due: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Slideshow {
+    pub fn new(core: &WidgetCore, images: Vec<PathBuf>, start: usize) -> HResult<Slideshow> {
+        let interval = Duration::from_secs(core.config().slideshow_interval);
+
+        let mut slideshow = Slideshow {
+            core: core.clone(),
+            images,
+            pos: start,
+            current: None,
+            preloaded: Arc::new(Mutex::new(None)),
+            interval,
+            deadline: Arc::new(Mutex::new(Instant::now() + interval)),
+            paused: Arc::new(AtomicBool::new(false)),
+            due: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+
+        slideshow.show(start)?;
+
+        if interval.as_secs() > 0 {
+            slideshow.start_timer();
+        }
+
+        Ok(slideshow)
+    }
+
+    fn start_timer(&self) {
+        let sender = self.core.get_sender();
+        let interval = self.interval;
+        let deadline = self.deadline.clone();
+        let paused = self.paused.clone();
+        let due = self.due.clone();
+        let stop = self.stop.clone();
+
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(100));
+
+                if paused.load(Ordering::Relaxed) { continue; }
+
+                let mut deadline = deadline.lock();
+                if Instant::now() >= *deadline {
+                    *deadline = Instant::now() + interval;
+                    due.store(true, Ordering::Relaxed);
+                    sender.send(Events::WidgetReady).ok();
+                }
+            }
+        });
+    }
+
+    // Called whenever the popup comes back with RefreshParent, which isn't
+    // always the timer
+    pub fn tick(&mut self) -> HResult<()> {
+        match self.due.swap(false, Ordering::Relaxed) {
+            true => self.next(),
+            false => Ok(())
+        }
+    }
+
+    fn show(&mut self, pos: usize) -> HResult<()> {
+        let path = self.images.get(pos)?.clone();
+        let coords = self.core.coordinates.clone();
+
+        self.pos = pos;
+        *self.deadline.lock() = Instant::now() + self.interval;
+
+        // Old one goes first, with kitty it would take the new one with it
+        self.current = None;
+
+        let preloaded = self.preloaded.lock().take();
+        let view = match preloaded {
+            Some((ppos, pcoords, buffer)) if ppos == pos && pcoords == coords => {
+                ImgView {
+                    core: self.core.clone(),
+                    buffer,
+                    file: Some(path)
+                }
+            }
+            _ => ImgView::new_from_file(self.core.clone(), &path)?
+        };
+
+        self.current = Some(view);
+        self.preload((pos + 1) % self.images.len());
+
+        Ok(())
+    }
+
+    fn preload(&self, pos: usize) {
+        // It would show up right away
+        if self.core.config().graphics == "ueberzug" { return; }
+        if pos == self.pos { return; }
+
+        let path = match self.images.get(pos) {
+            Some(path) => path.clone(),
+            None => return
+        };
+        let core = self.core.clone();
+        let preloaded = self.preloaded.clone();
+
+        std::thread::spawn(move || {
+            if let Ok(buffer) = ImgView::encode(&core, &path) {
+                *preloaded.lock() = Some((pos, core.coordinates.clone(), buffer));
+            }
+        });
+    }
+
+    fn next(&mut self) -> HResult<()> {
+        let pos = (self.pos + 1) % self.images.len();
+        self.show(pos)
+    }
+
+    fn prev(&mut self) -> HResult<()> {
+        let pos = match self.pos {
+            0 => self.images.len() - 1,
+            pos => pos - 1
+        };
+        self.show(pos)
+    }
+
+    fn toggle_pause(&mut self) -> HResult<()> {
+        let paused = !self.paused.load(Ordering::Relaxed);
+        self.paused.store(paused, Ordering::Relaxed);
+        // Full time for the current one after resuming
+        *self.deadline.lock() = Instant::now() + self.interval;
+        Ok(())
+    }
+}
+
+impl Drop for Slideshow {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Widget for Slideshow {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        if &self.core.coordinates == coordinates { return Ok(()); }
+
+        self.core.coordinates = coordinates.clone();
+        self.show(self.pos)
+    }
+    fn render_header(&self) -> HResult<String> {
+        let name = self.images
+            .get(self.pos)?
+            .file_name()?
+            .to_string_lossy()
+            .to_string();
+
+        let paused = match self.paused.load(Ordering::Relaxed) {
+            true => " [paused]",
+            false => ""
+        };
+
+        Ok(format!("{} ({}/{}){}",
+                   name,
+                   self.pos + 1,
+                   self.images.len(),
+                   paused))
+    }
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = "space: pause/resume  n/p: next/previous  q: quit";
+        Ok(term::sized_string_u(hint, xsize))
+    }
+    fn refresh(&mut self) -> HResult<()> {
+        Ok(())
+    }
+    fn get_drawlist(&self) -> HResult<String> {
+        match &self.current {
+            Some(view) => view.get_drawlist(),
+            None => Ok(String::new())
+        }
+    }
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        use crate::keybind::FileBrowserAction;
+
+        let bindings = self.core.config().keybinds.filebrowser;
+
+        match (key, bindings.get(key)) {
+            (Key::Esc, _) |
+            (Key::Char('q'), _) |
+            (_, Some(FileBrowserAction::Slideshow)) => HError::popup_finnished(),
+            (Key::Char(' '), _) => self.toggle_pause(),
+            (Key::Char('n'), _) |
+            (Key::Char('l'), _) |
+            (Key::Right, _) => self.next(),
+            (Key::Char('p'), _) |
+            (Key::Char('h'), _) |
+            (Key::Left, _) => self.prev(),
+            _ => Ok(())
+        }
+    }
+}
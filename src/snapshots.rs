@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+// Earlier versions of files kept in filesystem snapshots. ZFS has them in
+// .zfs/snapshot/<name>/ at the root of each dataset, snapper puts btrfs
+// snapshots in .snapshots/<n>/snapshot/ and other tools just use
+// .snapshots/<name>/. Inside, the file is at the same place relative to
+// the directory holding the snapshots.
+
+#[derive(Clone, Debug)]
+pub struct Version {
+    pub snapshot: String,
+    pub path: PathBuf,
+    pub mtime: SystemTime,
+    pub size: u64,
+}
+
+// Snapshot directories above path, with the directory they're snapshots of
+fn snapshot_dirs(path: &Path) -> Vec<(PathBuf, PathBuf)> {
+    path.ancestors()
+        .skip(1)
+        .flat_map(|dir| {
+            [".zfs/snapshot", ".snapshots"]
+                .iter()
+                .map(move |snapshots| (dir.join(snapshots), dir.to_path_buf()))
+        })
+        .filter(|(snapshots, _)| snapshots.is_dir())
+        .collect()
+}
+
+pub fn versions(path: &Path) -> Vec<Version> {
+    let mut versions = vec![];
+
+    for (snapshots, base) in snapshot_dirs(path) {
+        let relative = match path.strip_prefix(&base) {
+            Ok(relative) => relative,
+            Err(_) => continue
+        };
+        let entries = match std::fs::read_dir(&snapshots) {
+            Ok(entries) => entries,
+            Err(_) => continue
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let root = entry.path();
+            // That's how snapper does it
+            let root = match root.join("snapshot").is_dir() {
+                true => root.join("snapshot"),
+                false => root
+            };
+
+            let old = root.join(relative);
+            if let Ok(meta) = std::fs::symlink_metadata(&old) {
+                versions.push(Version {
+                    snapshot: entry.file_name().to_string_lossy().to_string(),
+                    path: old,
+                    mtime: meta.modified().unwrap_or(UNIX_EPOCH),
+                    size: meta.len()
+                });
+            }
+        }
+    }
+
+    // Newest first, and each version only once even if it's in many snapshots
+    versions.sort_by(|a, b| b.mtime.cmp(&a.mtime).then(a.snapshot.cmp(&b.snapshot)));
+    versions.dedup_by(|a, b| a.mtime == b.mtime && a.size == b.size);
+    versions
+}
+
+pub fn diff(old: &Path, new: &Path) -> Vec<String> {
+    let output = std::process::Command::new("diff")
+        .arg("-ru")
+        .arg("--")
+        .arg(old)
+        .arg(new)
+        .output();
+
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect(),
+        Err(err) => vec![format!("Couldn't run diff: {}", err)]
+    }
+}
@@ -0,0 +1,251 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::remote::{Backend, RemoteEntry, split_fields};
+use crate::files::File;
+use crate::proclist::Cmd;
+use crate::fail::{HResult, HError};
+
+
+// For hosts without SFTP. Everything goes through one multiplexed ssh
+// connection that stays open for a while, directories are listed by
+// parsing the output of ls. There's no terminal ssh could ask for a
+// password on, so that comes from SSH_ASKPASS if there is one.
+
+#[derive(Debug)]
+pub struct SshBackend {
+    host: String,
+    start: PathBuf,
+    secret: Option<String>,
+}
+
+// Wraps a string in single quotes for the remote shell
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", r"'\''"))
+}
+
+impl SshBackend {
+    // Takes "host" or "user@host:/some/dir" like scp
+    pub fn new(location: &str) -> SshBackend {
+        let (host, start) = match location.find(':') {
+            Some(pos) => (&location[..pos], &location[pos+1..]),
+            None => (location, "/")
+        };
+
+        let start = match start {
+            "" => "/",
+            start => start
+        };
+
+        SshBackend {
+            host: host.to_string(),
+            start: PathBuf::from(start),
+            secret: None
+        }
+    }
+
+    fn ssh_args(&self) -> Vec<OsString> {
+        let control_path = crate::paths::cache_path()
+            .map(|path| path.join("ssh-%C"))
+            .unwrap_or_else(|_| PathBuf::from("/tmp/hunter-ssh-%C"));
+        let mut control = OsString::from("ControlPath=");
+        control.push(control_path);
+
+        let batch_mode = match self.secret {
+            Some(_) => "BatchMode=no",
+            None => "BatchMode=yes"
+        };
+
+        vec![OsString::from("-o"), OsString::from(batch_mode),
+             OsString::from("-o"), OsString::from("ControlMaster=auto"),
+             OsString::from("-o"), OsString::from("ControlPersist=300"),
+             OsString::from("-o"), control,
+             OsString::from("--"),
+             OsString::from(&self.host)]
+    }
+
+    fn ssh_env(&self) -> Vec<(OsString, OsString)> {
+        let secret = match &self.secret {
+            Some(secret) => secret,
+            None => return vec![]
+        };
+        let askpass = match crate::credentials::askpass_path() {
+            Ok(askpass) => askpass,
+            Err(_) => return vec![]
+        };
+
+        // Older versions only use SSH_ASKPASS with DISPLAY set
+        let display = std::env::var_os("DISPLAY").unwrap_or(OsString::from(":0"));
+
+        vec![(OsString::from("SSH_ASKPASS"), askpass.into_os_string()),
+             (OsString::from("SSH_ASKPASS_REQUIRE"), OsString::from("force")),
+             (OsString::from("DISPLAY"), display),
+             (OsString::from("HUNTER_SECRET"), OsString::from(secret))]
+    }
+
+    fn run(&self, remote_cmd: &str) -> HResult<String> {
+        let output = Command::new("ssh")
+            .args(self.ssh_args())
+            .envs(self.ssh_env())
+            .arg(remote_cmd)
+            .stdin(std::process::Stdio::null())
+            .output()?;
+
+        if !output.status.success() && output.stdout.is_empty() {
+            let err = String::from_utf8_lossy(&output.stderr);
+            let err = err.lines().last().unwrap_or("ssh failed").to_string();
+
+            // ssh itself fails with 255, a wrong password isn't worth retrying
+            return match output.status.code() {
+                Some(255) if !err.contains("Permission denied") => {
+                    Err(HError::ConnectionError(err))
+                }
+                _ => Err(HError::Error(err))
+            };
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+// Both "-rw-r--r-- 1 user group 42 2024-01-01 12:00:00.000 +0100 name" with
+// --time-style=full-iso and "-rw-r--r-- 1 user group 42 Jan  1 12:00 name"
+// from ls without it have three date fields
+fn parse_ls_line(line: &str) -> Option<RemoteEntry> {
+    let (fields, name) = split_fields(line, 8)?;
+
+    let mode = fields[0];
+    let size = fields[4].parse().ok()?;
+
+    if name == "." || name == ".." { return None; }
+
+    let date = match fields[5].contains('-') {
+        true => format!("{} {}", fields[5], fields[6].get(..5).unwrap_or(fields[6])),
+        false => format!("{} {} {}", fields[5], fields[6], fields[7])
+    };
+    let info = format!("{} {}:{} {}", mode, fields[2], fields[3], date);
+
+    let entry = match mode.starts_with('d') {
+        true => RemoteEntry::dir(name),
+        false => RemoteEntry::file(name, Some(size))
+    };
+
+    Some(entry.with_info(info))
+}
+
+impl Backend for SshBackend {
+    fn name(&self) -> String {
+        format!("ssh {}", self.host)
+    }
+
+    fn root(&self) -> PathBuf {
+        self.start.clone()
+    }
+
+    fn credentials_key(&self) -> Option<String> {
+        Some(format!("ssh:{}", self.host))
+    }
+
+    fn set_secret(&mut self, secret: Option<String>) {
+        self.secret = secret;
+    }
+
+    fn list(&mut self, path: &Path) -> HResult<Vec<RemoteEntry>> {
+        // -L shows where symlinks lead, so linked directories can be entered
+        let path = shell_quote(&path.to_string_lossy());
+        let remote_cmd = format!("LC_ALL=C ls -laL --time-style=full-iso -- {path} \
+                                  2>/dev/null || LC_ALL=C ls -laL -- {path}",
+                                 path = path);
+
+        let listing = self.run(&remote_cmd)?;
+        Ok(listing.lines().filter_map(parse_ls_line).collect())
+    }
+
+    fn fetch(&mut self,
+             path: &Path,
+             is_dir: bool,
+             target: &Path,
+             cwd: &File) -> HResult<Cmd> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let parent = path.parent()?.to_string_lossy().to_string();
+
+        // Directories come over as tar stream, files with tail, so that
+        // partial downloads can be continued
+        let (remote_cmd, is_dir) = match is_dir {
+            true => (format!("tar -cf - -C {} -- {}",
+                             shell_quote(&parent),
+                             shell_quote(&name)),
+                     "dir"),
+            false => (shell_quote(&path.to_string_lossy()), "file")
+        };
+
+        // The tar stream holds the remote name, so it's unpacked next to
+        // the target first and then renamed
+        let script = r#"
+target="$1"; remote_cmd="$2"; is_dir="$3"; name="$4"; shift 4
+if [ "$is_dir" = dir ]; then
+    tmp=$(mktemp -d "$(dirname "$target")/.hunter-fetch.XXXXXX") || exit 1
+    ssh "$@" "$remote_cmd" | tar -xvf - -C "$tmp" && mv -n "$tmp/$name" "$target"
+    status=$?
+    rm -rf "$tmp"
+    exit $status
+else
+    # Continues where an earlier attempt stopped
+    offset=$(($(wc -c < "$target" 2>/dev/null || echo 0) + 1))
+    ssh "$@" "tail -c +$offset -- $remote_cmd" >> "$target" && echo "$target"
+fi
+"#;
+
+        let mut args = vec![OsString::from("-c"),
+                            OsString::from(script),
+                            OsString::from("sh"),
+                            target.as_os_str().to_os_string(),
+                            OsString::from(remote_cmd),
+                            OsString::from(is_dir),
+                            OsString::from(&name)];
+        args.extend(self.ssh_args());
+
+        Ok(Cmd {
+            cmd: OsString::from("sh"),
+            args: Some(args),
+            vars: Some(self.ssh_env()),
+            short_cmd: Some(format!("fetch {} from {}", name, self.host)),
+            cwd: cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ls_line_with_full_iso_dates() {
+        let line = "-rw-r--r-- 1 user group 42 2024-01-01 12:00:00.000000000 +0100 my notes.txt";
+
+        assert_eq!(parse_ls_line(line),
+                   Some(RemoteEntry::file("my notes.txt", Some(42))
+                        .with_info("-rw-r--r-- user:group 2024-01-01 12:00")));
+    }
+
+    #[test]
+    fn ls_line_with_plain_dates() {
+        let line = "drwxr-xr-x 2 user group 4096 Jan  1 12:00 photos";
+
+        assert_eq!(parse_ls_line(line),
+                   Some(RemoteEntry::dir("photos")
+                        .with_info("drwxr-xr-x user:group Jan 1 12:00")));
+    }
+
+    #[test]
+    fn ls_lines_left_out() {
+        assert_eq!(parse_ls_line("total 12"), None);
+        assert_eq!(parse_ls_line("drwxr-xr-x 2 user group 4096 Jan  1 12:00 ."), None);
+        assert_eq!(parse_ls_line("drwxr-xr-x 2 user group 4096 Jan  1 12:00 .."), None);
+        assert_eq!(parse_ls_line("-rw-r--r-- 1 user group big Jan  1 12:00 file"), None);
+    }
+}
@@ -85,3 +85,93 @@ impl FsExt for Filesystem {
 
 
 }
+
+// What unprivileged users can still write on the filesystem a path is on.
+// The path doesn't have to exist yet, then its nearest parent counts.
+// inotify only sees changes made on this machine
+pub fn is_network_fs(path: &Path) -> bool {
+    let network = ["nfs", "nfs4", "cifs", "smb3", "smbfs", "fuse.sshfs",
+                   "9p", "afs", "ceph", "glusterfs"];
+
+    FsStat::new()
+        .ok()
+        .and_then(|stat| stat.find_fs(path)
+                  .map(|fs| network.contains(&fs.fs_type.as_str()))
+                  .ok())
+        .unwrap_or(false)
+}
+
+pub fn available_space(path: &Path) -> HResult<u64> {
+    let existing = path.ancestors().find(|path| path.exists())?;
+    let stat = nix::sys::statvfs::statvfs(existing)?;
+
+    Ok(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+// Why nothing can be written in a directory, if that's the case. Read-only
+// mounts are told apart, since no amount of permissions will help there.
+pub fn write_blocker(dir: &Path) -> Option<&'static str> {
+    use nix::sys::statvfs::{statvfs, FsFlags};
+    use nix::unistd::{access, AccessFlags};
+
+    let existing = dir.ancestors().find(|path| path.exists())?;
+
+    if statvfs(existing).ok()?.flags().contains(FsFlags::ST_RDONLY) {
+        return Some("read-only filesystem");
+    }
+
+    match access(existing, AccessFlags::W_OK) {
+        Ok(_) => None,
+        Err(_) => Some("not writable")
+    }
+}
+
+// How much an archive will take up once extracted. Formats that record the
+// original size are asked, for the others typical compression is assumed.
+pub fn unpacked_size(archive: &Path) -> u64 {
+    let packed = archive.metadata()
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let name = archive.to_string_lossy().to_lowercase();
+
+    let recorded = |cmd: &str, args: &[&str]| -> Option<String> {
+        let output = std::process::Command::new(cmd)
+            .args(args)
+            .arg(archive)
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        match output.status.success() {
+            true => Some(String::from_utf8_lossy(&output.stdout).to_string()),
+            false => None
+        }
+    };
+
+    let size = if name.ends_with(".zip") {
+        // "3 files, 12345 bytes uncompressed, 2345 bytes compressed:  81.0%"
+        recorded("unzip", &["-Zt"])
+            .and_then(|out| out.split(" bytes uncompressed").next()?
+                      .split_whitespace().last()?
+                      .parse().ok())
+    } else if name.ends_with(".xz") || name.ends_with(".txz") {
+        // "totals" line of --robot, uncompressed size is the fifth field
+        recorded("xz", &["--robot", "-l"])
+            .and_then(|out| out.lines()
+                      .find(|line| line.starts_with("totals"))?
+                      .split('\t').nth(4)?
+                      .parse().ok())
+    } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+        // gzip only keeps the size modulo 4GB, so that's only a lower bound
+        recorded("gzip", &["-l"])
+            .and_then(|out| out.lines().nth(1)?
+                      .split_whitespace().nth(1)?
+                      .parse().ok())
+            .filter(|&size: &u64| size >= packed)
+    } else if name.ends_with(".tar") {
+        Some(packed)
+    } else {
+        None
+    };
+
+    size.unwrap_or(packed * 3)
+}
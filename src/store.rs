@@ -0,0 +1,78 @@
+use nix::fcntl::{flock, FlockArg};
+
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::fail::{HResult, HError};
+
+
+// Tags and bookmarks are shared by all running hunters. Instead of each one
+// writing out what it had in memory (and throwing away what the others
+// wrote in the meantime), changes are applied to what's on disk while
+// holding a lock. The new content goes to a temporary file that's renamed
+// over the old one, so a crash halfway leaves the old file intact.
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+// Released when the file is closed
+fn lock(path: &Path, exclusive: bool) -> HResult<std::fs::File> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(lock_path(path))?;
+
+    let arg = match exclusive {
+        true => FlockArg::LockExclusive,
+        false => FlockArg::LockShared
+    };
+
+    flock(file.as_raw_fd(), arg)
+        .map_err(|e| HError::Error(format!("Couldn't lock {}: {}",
+                                           path.to_string_lossy(),
+                                           e)))?;
+    Ok(file)
+}
+
+pub fn read(path: &Path) -> HResult<Vec<u8>> {
+    let _lock = lock(path, false)?;
+
+    match std::fs::read(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into())
+    }
+}
+
+// Gives back what was written, which includes changes by other instances
+pub fn update<F>(path: &Path, change: F) -> HResult<Vec<u8>>
+where F: FnOnce(&[u8]) -> Vec<u8> {
+    let _lock = lock(path, true)?;
+
+    let old = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e.into())
+    };
+
+    let new = change(&old);
+
+    let mut tmp_name = path.file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut tmp = std::fs::File::create(&tmp_path)?;
+    tmp.write_all(&new)?;
+    tmp.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(new)
+}
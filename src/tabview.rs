@@ -1,4 +1,6 @@
-use termion::event::Key;
+use termion::event::{Key, MouseEvent};
+
+use std::path::Path;
 
 use crate::widget::{Widget, WidgetCore};
 use crate::fail::{HResult, HError, ErrorLog};
@@ -23,7 +25,15 @@ pub trait Tabbable {
     }
     fn on_refresh(&mut self) -> HResult<()> { Ok(()) }
     fn on_config_loaded(&mut self) -> HResult<()> { Ok(()) }
+    fn on_reveal(&mut self, _path: &Path) -> HResult<()> { Ok(()) }
+    fn save_workspace(&mut self) -> HResult<()> { Ok(()) }
+    fn switch_workspace(&mut self) -> HResult<()> { Ok(()) }
     fn on_new(&mut self) -> HResult<()> { Ok(()) }
+    // More keys after TabPrefix, besides gt/gT, with what they do
+    fn prefix_keys(&self) -> Vec<(String, String)> { vec![] }
+    fn on_prefix_key(&mut self, key: Key) -> HResult<()> {
+        HError::undefined_key(key)
+    }
 
 }
 
@@ -32,7 +42,10 @@ pub trait Tabbable {
 pub struct TabView<T> where T: Widget, TabView<T>: Tabbable {
     pub widgets: Vec<T>,
     pub active: usize,
-    pub core: WidgetCore
+    pub core: WidgetCore,
+    // Set by TabPrefix, the next key is gt/gT like in vim, or one of the
+    // prefix_keys
+    prefix: bool
 }
 
 impl<T> TabView<T> where T: Widget, TabView<T>: Tabbable {
@@ -40,7 +53,8 @@ impl<T> TabView<T> where T: Widget, TabView<T>: Tabbable {
         let mut tabview = TabView {
             widgets: vec![],
             active: 0,
-            core: core.clone()
+            core: core.clone(),
+            prefix: false
         };
 
         Tabbable::on_new(&mut tabview).log();
@@ -124,6 +138,14 @@ impl<T> Widget for TabView<T> where T: Widget, TabView<T>: Tabbable {
         self.on_config_loaded()
     }
 
+    fn on_mouse(&mut self, event: MouseEvent) -> HResult<()> {
+        self.active_tab_mut().on_mouse(event)
+    }
+
+    fn reveal(&mut self, path: &Path) -> HResult<()> {
+        self.on_reveal(path)
+    }
+
     fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
         self.core.coordinates = coordinates.clone();
         for widget in &mut self.widgets {
@@ -174,10 +196,30 @@ impl<T> Widget for TabView<T> where T: Widget, TabView<T>: Tabbable {
     }
 
     fn get_drawlist(&self) -> HResult<String> {
-        self.active_tab_().get_drawlist()
+        // Goes over everything else
+        Ok(self.active_tab_().get_drawlist()? + &crate::whichkey::drawlist())
     }
 
     fn on_key(&mut self, key: Key) -> HResult<()> {
+        if self.prefix {
+            self.prefix = false;
+            self.core.clear_status().log();
+            crate::whichkey::done(&self.core);
+
+            match key {
+                Key::Char('t') => return self.next_tab(),
+                Key::Char('T') => return self.prev_tab(),
+                // Anything else would run whatever it's bound to on its own
+                _ => match self.on_prefix_key(key) {
+                    Err(HError::WidgetUndefinedKeyError{..}) => {
+                        let status = format!("g{} isn't bound", AnyKey::from(key));
+                        return self.core.show_status(&status);
+                    }
+                    result => return result
+                }
+            }
+        }
+
         match self.do_key(key) {
             Err(HError::WidgetUndefinedKeyError{..}) => Tabbable::on_key(self, key)?,
             e @ _ => e?
@@ -205,6 +247,17 @@ impl<T: Widget> Acting for TabView<T> where TabView<T>: Tabbable {
             CloseTab => self.close_tab()?,
             NextTab => self.next_tab()?,
             PrevTab => self.prev_tab()?,
+            SaveWorkspace => self.save_workspace()?,
+            SwitchWorkspace => self.switch_workspace()?,
+            TabPrefix => {
+                self.prefix = true;
+                self.core.show_status("g-").log();
+
+                let mut keys = vec![(String::from("t"), NextTab.to_string()),
+                                    (String::from("T"), PrevTab.to_string())];
+                keys.extend(self.prefix_keys());
+                crate::whichkey::start(&self.core, "g", keys);
+            }
         }
 
         Ok(())
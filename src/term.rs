@@ -2,7 +2,6 @@ use std::io::{Stdout, Write, BufWriter, BufRead};
 use std::sync::Arc;
 
 use termion;
-use termion::screen::AlternateScreen;
 use termion::raw::{IntoRawMode, RawTerminal};
 
 use parse_ansi::parse_bytes;
@@ -12,26 +11,47 @@ use parking_lot::{Mutex, RwLock};
 use crate::fail::{HResult, ErrorLog};
 use crate::trait_ext::ExtractResult;
 
-pub type TermMode = AlternateScreen<RawTerminal<BufWriter<Stdout>>>;
+pub type TermMode = RawTerminal<BufWriter<Stdout>>;
+
+// Clicks and the wheel, reported in the SGR format so it works everywhere
+const MOUSE_ON: &str = "\x1b[?1000h\x1b[?1006h";
+const MOUSE_OFF: &str = "\x1b[?1006l\x1b[?1000l";
 
 #[derive(Clone)]
 pub struct Screen {
     screen: Arc<Mutex<TermMode>>,
     size: Arc<RwLock<Option<(usize, usize)>>>,
-    terminal: String
+    terminal: String,
+    // Off means drawing right over the terminal, keeping its scrollback
+    alternate: bool,
+    mouse: bool
 }
 
 impl Screen {
-    pub fn new() -> HResult<Screen> {
-        let screen = BufWriter::new(std::io::stdout()).into_raw_mode()?;
-        let mut screen = AlternateScreen::from(screen);
+    pub fn new(alternate: bool, mouse: bool) -> HResult<Screen> {
+        let mut screen = BufWriter::new(std::io::stdout()).into_raw_mode()?;
         let terminal = std::env::var("TERM").unwrap_or("xterm".into());
 
+        match alternate {
+            true => write!(screen, "{}", termion::screen::ToAlternateScreen)?,
+            // Scrolls whatever is on the terminal up into the scrollback
+            false => {
+                let (_, ysize) = termion::terminal_size()?;
+                write!(screen, "{}", "\n".repeat(ysize as usize))?;
+            }
+        }
+
+        if mouse {
+            write!(screen, "{}", MOUSE_ON)?;
+        }
+
         screen.cursor_hide()?;
         Ok(Screen {
             screen: Arc::new(Mutex::new(screen)),
             size: Arc::new(RwLock::new(None)),
-            terminal: terminal
+            terminal: terminal,
+            alternate: alternate,
+            mouse: mouse
         })
     }
 
@@ -66,6 +86,11 @@ impl Screen {
         }
         Ok(())
     }
+
+    pub fn report_cwd(&mut self, path: &std::path::Path) -> HResult<()> {
+        write!(self, "{}", osc7_cwd(path))?;
+        Ok(())
+    }
 }
 
 impl Write for Screen {
@@ -85,6 +110,7 @@ pub trait ScreenExt: Write {
     fn suspend_raw_mode(&mut self) -> HResult<()>;
     fn activate_raw_mode(&mut self) -> HResult<()>;
     fn suspend(&mut self) -> HResult<()> {
+        self.reset().log();
         self.cursor_show().log();
         self.suspend_raw_mode().log();
         self.to_main_screen()
@@ -161,6 +187,33 @@ impl ScreenExt for Screen {
             .lock()
             .activate_raw_mode()
     }
+
+    fn to_main_screen(&mut self) -> HResult<()> {
+        // Whatever runs in the meantime doesn't want clicks it didn't ask for
+        if self.mouse {
+            write!(self, "{}", MOUSE_OFF)?;
+        }
+        match self.alternate {
+            true => write!(self, "{}", termion::screen::ToMainScreen)?,
+            // There is no other screen, so just clean up after ourselves
+            false => write!(self, "{}{}",
+                            termion::clear::All,
+                            termion::cursor::Goto(1, 1))?
+        }
+        self.flush()?;
+        Ok(())
+    }
+
+    fn to_alternate_screen(&mut self) -> HResult<()> {
+        if self.alternate {
+            write!(self, "{}", termion::screen::ToAlternateScreen)?;
+        }
+        if self.mouse {
+            write!(self, "{}", MOUSE_ON)?;
+        }
+        self.flush()?;
+        Ok(())
+    }
 }
 
 impl ScreenExt for TermMode {
@@ -173,6 +226,115 @@ impl ScreenExt for TermMode {
     }
 }
 
+lazy_static! {
+    static ref IN_TMUX: bool = std::env::var_os("TMUX").is_some();
+}
+
+pub fn in_tmux() -> bool {
+    *IN_TMUX
+}
+
+// tmux eats escape sequences it doesn't know, like graphics and OSC 52,
+// unless they're wrapped in "ESC Ptmux; ... ESC \" with every ESC inside
+// doubled. Newer tmux also needs "set -g allow-passthrough on" for that.
+pub fn passthrough(seq: &str) -> String {
+    if !in_tmux() { return seq.to_string(); }
+
+    format!("\x1bPtmux;{}\x1b\\", seq.replace("\x1b", "\x1b\x1b"))
+}
+
+// OSC 52, puts text into the clipboard of the terminal, even over ssh
+pub fn osc52_copy(text: &[u8]) -> String {
+    let seq = format!("\x1b]52;c;{}\x07", base64::encode(text));
+    passthrough(&seq)
+}
+
+// OSC 7, tells the terminal where we are, so new windows open there
+pub fn osc7_cwd(path: &std::path::Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut buf = [0u8; 256];
+    let host = nix::unistd::gethostname(&mut buf)
+        .map(|host| host.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let path = path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' |
+            b'/' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b)
+        })
+        .collect::<String>();
+
+    passthrough(&format!("\x1b]7;file://{}{}\x1b\\", host, path))
+}
+
+lazy_static! {
+    // What the terminal said it can draw, asked once at startup
+    static ref PROBED_GRAPHICS: RwLock<Option<&'static str>> = RwLock::new(None);
+}
+
+pub fn probed_graphics() -> Option<&'static str> {
+    *PROBED_GRAPHICS.read()
+}
+
+// The answer to DA1 looks like "ESC [ ? 62 ; 4 ; 22 c"
+fn da1_attributes(response: &str) -> Option<Vec<&str>> {
+    let start = response.find("\x1b[?")? + 3;
+    let da1 = &response[start..];
+    let end = da1.find('c')?;
+    Some(da1[..end].split(';').collect())
+}
+
+fn read_stdin_timeout(timeout: std::time::Duration) -> Option<Vec<u8>> {
+    let mut fds = [libc::pollfd { fd: 0, events: libc::POLLIN, revents: 0 }];
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), 1, timeout.as_millis() as i32) };
+    if ready <= 0 { return None; }
+
+    let mut buf = [0u8; 256];
+    let len = unsafe {
+        libc::read(0, buf.as_mut_ptr() as *mut libc::c_void, buf.len())
+    };
+    if len <= 0 { return None; }
+
+    Some(buf[..len as usize].to_vec())
+}
+
+// Asks if kitty's graphics protocol works, then for the device attributes,
+// where a 4 means sixel. Every terminal answers the second one, so only
+// really broken ones make this wait for the timeout. Has to run before
+// anything else reads from the terminal.
+pub fn probe_graphics(screen: &mut impl Write) -> Option<&'static str> {
+    use std::time::{Duration, Instant};
+
+    let query = passthrough("\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\");
+    write!(screen, "{}\x1b[c", query).ok()?;
+    screen.flush().ok()?;
+
+    let started = Instant::now();
+    let timeout = Duration::from_millis(500);
+    let mut response = String::new();
+
+    while da1_attributes(&response).is_none() {
+        let left = timeout.checked_sub(started.elapsed())?;
+        let input = read_stdin_timeout(left)?;
+        response += &String::from_utf8_lossy(&input);
+    }
+
+    let graphics = if response.contains("\x1b_Gi=31;OK") {
+        Some("kitty")
+    } else if da1_attributes(&response)?.contains(&"4") {
+        Some("sixel")
+    } else {
+        None
+    };
+
+    *PROBED_GRAPHICS.write() = graphics;
+    graphics
+}
+
 pub fn flush_stdin() {
     let stdin = std::io::stdin();
     let mut stdin = stdin.lock();
@@ -196,6 +358,28 @@ pub fn ysize() -> u16 {
     ysize
 }
 
+// Less than that and the columns end up with negative widths
+pub const MIN_XSIZE: u16 = 20;
+pub const MIN_YSIZE: u16 = 5;
+
+pub fn too_small() -> bool {
+    match termion::terminal_size() {
+        Ok((xsize, ysize)) => xsize < MIN_XSIZE || ysize < MIN_YSIZE,
+        Err(_) => false
+    }
+}
+
+// Drawn instead of everything else until there's enough room again
+pub fn too_small_drawlist() -> String {
+    let (xsize, ysize) = termion::terminal_size().unwrap_or((MIN_XSIZE, MIN_YSIZE));
+
+    format!("{}{}{}{}",
+            reset(),
+            termion::clear::All,
+            goto_xy(1, ((ysize + 1) / 2).max(1)),
+            sized_string("Terminal too small", xsize))
+}
+
 pub fn size() -> HResult<(usize, usize)> {
     let (xsize, ysize) = termion::terminal_size()?;
     Ok(((xsize-1) as usize, (ysize-1) as usize))
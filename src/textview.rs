@@ -152,6 +152,13 @@ impl TextView {
         self.offset = 0;
     }
 
+    pub fn goto_line(&mut self, line: usize) {
+        let line = line.saturating_sub(1) as isize;
+        self.offset = 0;
+        self.scroll(line);
+        self.core.set_dirty();
+    }
+
     pub fn scroll_bottom(&mut self) {
         let len = self.lines.len() as isize;
         self.scroll(len);
@@ -176,6 +183,7 @@ impl Widget for TextView {
 
         if self.core.is_dirty() {
             self.core.set_clean();
+            crate::redraw::mark("TextView", "content changed");
         }
         Ok(())
     }
@@ -0,0 +1,285 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::imgview::ImgView;
+use crate::widget::{Widget, WidgetCore, Events};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+
+
+// Stills from evenly spaced points of a video, made with ffmpeg, to skim
+// through it without playing it. They're kept in the cache, keyed by path,
+// size and modification time, so each video only goes through ffmpeg once.
+
+fn cache_dir(file: &Path) -> HResult<PathBuf> {
+    use std::hash::{Hash, Hasher};
+
+    let meta = file.metadata()?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    file.hash(&mut hasher);
+    meta.len().hash(&mut hasher);
+    meta.modified()?.hash(&mut hasher);
+
+    let dir = crate::paths::cache_path()?
+        .join("thumbnails")
+        .join(format!("{:016x}", hasher.finish()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn thumb_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("{:03}.jpg", n))
+}
+
+fn duration(file: &Path) -> HResult<f64> {
+    let output = Command::new("ffprobe")
+        .args(&["-v", "error",
+                "-show_entries", "format=duration",
+                "-of", "csv=p=0"])
+        .arg(file)
+        .stdin(Stdio::null())
+        .output()?;
+
+    let duration = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| HError::Error(format!("Can't tell how long {} is",
+                                           file.to_string_lossy())))?;
+    Ok(duration)
+}
+
+// Middle of each of the count parts
+fn timestamp(duration: f64, n: usize, count: usize) -> f64 {
+    duration * (n as f64 + 0.5) / count as f64
+}
+
+fn extract(file: &Path, at: f64, target: &Path) -> HResult<()> {
+    // Written under another name first, or it could be shown half done
+    let partial = target.with_extension("part.jpg");
+
+    let status = Command::new("ffmpeg")
+        .args(&["-v", "error", "-ss"])
+        .arg(format!("{:.2}", at))
+        .arg("-i")
+        .arg(file)
+        .args(&["-frames:v", "1", "-vf", "scale=640:-2", "-y"])
+        .arg(&partial)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+
+    if !status.success() {
+        std::fs::remove_file(&partial).ok();
+        return Err(HError::Error(format!("ffmpeg failed on {}",
+                                         file.to_string_lossy())));
+    }
+
+    Ok(std::fs::rename(partial, target)?)
+}
+
+
+pub struct ThumbStrip {
+    core: WidgetCore,
+    file: PathBuf,
+    dir: PathBuf,
+    count: usize,
+    pos: usize,
+    duration: Arc<Mutex<Option<f64>>>,
+    view: Option<ImgView>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ThumbStrip {
+    pub fn new(core: &WidgetCore, file: &Path) -> HResult<ThumbStrip> {
+        let strip = ThumbStrip {
+            core: core.clone(),
+            file: file.to_path_buf(),
+            dir: cache_dir(file)?,
+            count: core.config().thumbnail_count.max(1),
+            pos: 0,
+            duration: Arc::new(Mutex::new(None)),
+            view: None,
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+
+        strip.generate();
+        Ok(strip)
+    }
+
+    // The one being looked at comes first, then the rest in order
+    fn generate(&self) {
+        let file = self.file.clone();
+        let dir = self.dir.clone();
+        let count = self.count;
+        let duration = self.duration.clone();
+        let stop = self.stop.clone();
+        let sender = self.core.get_sender();
+        let core = self.core.clone();
+
+        std::thread::spawn(move || {
+            let length = match self::duration(&file) {
+                Ok(length) => length,
+                Err(err) => {
+                    core.show_status(&format!("{}", err)).log();
+                    return;
+                }
+            };
+            *duration.lock() = Some(length);
+            sender.send(Events::WidgetReady).ok();
+
+            for n in 0..count {
+                if stop.load(Ordering::Relaxed) { return; }
+
+                let target = thumb_path(&dir, n);
+                if target.exists() { continue; }
+
+                extract(&file, timestamp(length, n, count), &target).log();
+                sender.send(Events::WidgetReady).ok();
+            }
+        });
+    }
+
+    fn image_coordinates(&self) -> Coordinates {
+        let mut coords = self.core.coordinates.clone();
+        let (xsize, ysize) = coords.size_u();
+        // Room for the position line and the strip
+        coords.set_size_u(xsize, ysize.saturating_sub(2));
+        coords
+    }
+
+    // Shows the current one, once it's there
+    fn load(&mut self) -> HResult<()> {
+        let path = thumb_path(&self.dir, self.pos);
+        let current = self.view.as_ref().and_then(|view| view.file.clone());
+
+        if current.as_ref() == Some(&path) || !path.exists() {
+            return Ok(());
+        }
+
+        // Old one goes first, kitty would delete the new one with it
+        self.view = None;
+
+        let mut core = self.core.clone();
+        core.coordinates = self.image_coordinates();
+        self.view = Some(ImgView::new_from_file(core, &path)?);
+        Ok(())
+    }
+
+    fn scrub(&mut self, step: isize) -> HResult<()> {
+        let pos = self.pos as isize + step;
+        self.pos = pos.max(0).min(self.count as isize - 1) as usize;
+        self.core.clear().log();
+        self.load()
+    }
+
+    fn format_secs(secs: f64) -> String {
+        let secs = secs as usize;
+        format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    }
+
+    // One mark per thumbnail, the current one highlighted
+    fn strip(&self) -> String {
+        (0..self.count)
+            .map(|n| {
+                let mark = match thumb_path(&self.dir, n).exists() {
+                    true => "■",
+                    false => "□"
+                };
+                match n == self.pos {
+                    true => format!("{}{}{}",
+                                    term::highlight_color(),
+                                    mark,
+                                    term::normal_color()),
+                    false => mark.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Drop for ThumbStrip {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Widget for ThumbStrip {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        if &self.core.coordinates == coordinates { return Ok(()); }
+
+        self.core.coordinates = coordinates.clone();
+        // Has to be encoded again in the new size
+        self.view = None;
+        self.load()
+    }
+    fn render_header(&self) -> HResult<String> {
+        Ok(self.file.to_string_lossy().to_string())
+    }
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let hint = "Left/Right: scrub  Esc: back";
+        Ok(term::sized_string_u(hint, xsize))
+    }
+    fn refresh(&mut self) -> HResult<()> {
+        self.load()
+    }
+    fn get_drawlist(&self) -> HResult<String> {
+        let (xpos, ypos) = self.core.coordinates.position_u();
+        let (xsize, ysize) = self.core.coordinates.size_u();
+
+        let mut draw = match &self.view {
+            Some(view) => view.get_drawlist()?,
+            None => format!("{}generating thumbnails...",
+                            term::goto_xy_u(xpos, ypos))
+        };
+
+        let position = match *self.duration.lock() {
+            Some(length) => format!("{} / {}  ({}/{})",
+                                    ThumbStrip::format_secs(timestamp(length,
+                                                                      self.pos,
+                                                                      self.count)),
+                                    ThumbStrip::format_secs(length),
+                                    self.pos + 1,
+                                    self.count),
+            None => String::new()
+        };
+
+        draw += &term::goto_xy_u(xpos, ypos + ysize.saturating_sub(1));
+        draw += &term::sized_string_u(&position, xsize);
+        draw += &term::goto_xy_u(xpos, ypos + ysize);
+        draw += &term::sized_string_u(&self.strip(), xsize);
+        draw += &term::reset();
+
+        Ok(draw)
+    }
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        use crate::keybind::FileBrowserAction;
+
+        let bindings = self.core.config().keybinds.filebrowser;
+
+        match (key, bindings.get(key)) {
+            (Key::Esc, _) |
+            (Key::Char('q'), _) |
+            (_, Some(FileBrowserAction::ScrubVideo)) => HError::popup_finnished(),
+            (Key::Left, _) | (Key::Char('h'), _) => self.scrub(-1),
+            (Key::Right, _) | (Key::Char('l'), _) => self.scrub(1),
+            (Key::Home, _) => self.scrub(-(self.count as isize)),
+            (Key::End, _) => self.scrub(self.count as isize),
+            _ => Ok(())
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::hash::Hasher;
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+
+use crate::fail::{HResult, ErrorLog};
+use crate::files::File;
+use crate::proclist::Cmd;
+
+
+// What fileops needs to know before copying: whether a file is already
+// at the target with the same content and which names have to change for
+// the target's filesystem.
+
+fn file_hash(path: &Path) -> HResult<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    let mut buffer = vec![0; 1 << 16];
+
+    loop {
+        let len = file.read(&mut buffer)?;
+        if len == 0 { break; }
+        hasher.write(&buffer[..len]);
+    }
+
+    Ok(hasher.finish())
+}
+
+pub fn identical(source: &Path, target: &Path) -> bool {
+    let (source_meta, target_meta) = match (source.symlink_metadata(),
+                                            target.symlink_metadata()) {
+        (Ok(source_meta), Ok(target_meta)) => (source_meta, target_meta),
+        _ => return false
+    };
+
+    if !source_meta.is_file() ||
+       !target_meta.is_file() ||
+       source_meta.len() != target_meta.len() {
+        return false;
+    }
+
+    // Copied anyway if it can't be read, the copy will say why
+    match (file_hash(source), file_hash(target)) {
+        (Ok(source_hash), Ok(target_hash)) => source_hash == target_hash,
+        (Err(err), _) | (_, Err(err)) => {
+            err.log();
+            false
+        }
+    }
+}
+
+// Windows and Apple filesystems don't keep "a" and "A" apart, so one
+// silently replaces the other. The Windows ones also refuse some
+// characters and names ending in dots or spaces.
+#[derive(Debug, Clone, Copy)]
+pub struct NameRules {
+    pub ignore_case: bool,
+    pub windows_chars: bool,
+}
+
+impl NameRules {
+    // ntfs-3g shows up as fuseblk
+    pub fn for_fs(fs_type: &str) -> Option<NameRules> {
+        match fs_type {
+            "vfat" | "msdos" | "exfat" | "ntfs" | "ntfs3" | "fuseblk" => {
+                Some(NameRules { ignore_case: true, windows_chars: true })
+            }
+            "hfs" | "hfsplus" | "apfs" => {
+                Some(NameRules { ignore_case: true, windows_chars: false })
+            }
+            _ => None
+        }
+    }
+
+    fn is_bad_char(c: char) -> bool {
+        c < ' ' || "<>:\"\\|?*".contains(c)
+    }
+
+    fn invalid(&self, name: &str) -> bool {
+        self.windows_chars &&
+            (name.chars().any(NameRules::is_bad_char) ||
+             name.ends_with('.') ||
+             name.ends_with(' '))
+    }
+
+    fn sanitize(&self, name: &str) -> String {
+        let name = name.chars()
+            .map(|c| if NameRules::is_bad_char(c) { '_' } else { c })
+            .collect::<String>();
+
+        match name.trim_end_matches(|c| c == '.' || c == ' ') {
+            "" => String::from("_"),
+            name => name.to_string()
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        match self.ignore_case {
+            true => name.to_lowercase(),
+            false => name.to_string()
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NameFixes {
+    pub collisions: usize,
+    pub invalid: usize,
+}
+
+// "name.txt" -> "name (2).txt"
+fn numbered(name: &str, n: usize) -> String {
+    match name.rfind('.').filter(|&pos| pos > 0) {
+        Some(pos) => format!("{} ({}){}", &name[..pos], n, &name[pos..]),
+        None => format!("{} ({})", name, n)
+    }
+}
+
+// Renames destinations so they're valid and don't clash on the target,
+// with each other or with what's already there. The pairs have to be in
+// the order CopyPlan walks them in, directories before their contents.
+pub fn fix_names(pairs: &mut [(PathBuf, PathBuf)], rules: NameRules) -> NameFixes {
+    let mut fixes = NameFixes::default();
+    let mut moved_dirs: HashMap<PathBuf, PathBuf> = HashMap::new();
+    // Names in use in each target directory by their key
+    let mut taken: HashMap<PathBuf, HashMap<String, String>> = HashMap::new();
+
+    for (_, dest) in pairs.iter_mut() {
+        let (parent, name) = match (dest.parent(), dest.file_name()) {
+            (Some(parent), Some(name)) => (parent, name.to_string_lossy().to_string()),
+            _ => continue
+        };
+        let parent = moved_dirs.get(parent)
+            .cloned()
+            .unwrap_or_else(|| parent.to_path_buf());
+
+        let names = taken.entry(parent.clone()).or_insert_with(|| {
+            std::fs::read_dir(&parent)
+                .map(|entries| {
+                    entries.flatten()
+                        .map(|entry| entry.file_name().to_string_lossy().to_string())
+                        .map(|name| (rules.key(&name), name))
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+
+        let mut new_name = name.clone();
+
+        if rules.invalid(&name) {
+            fixes.invalid += 1;
+            new_name = rules.sanitize(&name);
+        }
+
+        // The exact same name just means copying over it, as usual
+        let clashes = |names: &HashMap<String, String>, name: &str| {
+            names.get(&rules.key(name))
+                .map(|existing| existing != name)
+                .unwrap_or(false)
+        };
+
+        if clashes(names, &new_name) {
+            fixes.collisions += 1;
+            let base = new_name.clone();
+            new_name = (2..)
+                .map(|n| numbered(&base, n))
+                .find(|name| !names.contains_key(&rules.key(name)))
+                .unwrap_or(base);
+        }
+
+        names.insert(rules.key(&new_name), new_name.clone());
+
+        let new_dest = parent.join(&new_name);
+        if new_dest != *dest {
+            moved_dirs.insert(dest.clone(), new_dest.clone());
+            *dest = new_dest;
+        }
+    }
+
+    fixes
+}
+
+// Every file that would be copied, along with where it would go.
+// Directories come before their contents.
+fn walk_all(sources: &[PathBuf], target: &Path) -> HResult<Vec<(PathBuf, PathBuf)>> {
+    fn walk(source: &Path,
+            target: &Path,
+            pairs: &mut Vec<(PathBuf, PathBuf)>) -> HResult<()> {
+        pairs.push((source.to_path_buf(), target.to_path_buf()));
+
+        if source.symlink_metadata()?.is_dir() {
+            for entry in std::fs::read_dir(source)? {
+                let entry = entry?;
+                walk(&entry.path(), &target.join(entry.file_name()), pairs)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut pairs = vec![];
+
+    for source in sources {
+        let name = source.file_name()?;
+        walk(source, &target.join(name), &mut pairs)?;
+    }
+
+    Ok(pairs)
+}
+
+// Goes through everything that would be copied to see what fix_names
+// would have to change
+pub fn check_names(sources: &[PathBuf],
+                   target: &Path,
+                   rules: NameRules) -> HResult<NameFixes> {
+    let mut pairs = walk_all(sources, target)?;
+    Ok(fix_names(&mut pairs, rules))
+}
+
+// Just the names fix_names changed, by source, for copying file by file
+pub fn fixed_names(sources: &[PathBuf],
+                   target: &Path,
+                   rules: NameRules) -> HResult<HashMap<PathBuf, OsString>> {
+    let mut pairs = walk_all(sources, target)?;
+    fix_names(&mut pairs, rules);
+
+    Ok(pairs.into_iter()
+       .filter_map(|(source, dest)| {
+           let name = dest.file_name()?.to_os_string();
+           match Some(name.as_os_str()) != source.file_name() {
+               true => Some((source, name)),
+               false => None
+           }
+       })
+       .collect())
+}
+
+
+pub fn plain_cmd(cmd: &str,
+                 flags: &str,
+                 sources: Vec<PathBuf>,
+                 target: PathBuf,
+                 cwd: &File) -> Cmd {
+    let short_cmd = format!("{} {} files", cmd, sources.len());
+
+    let mut args = vec![OsString::from(flags)];
+    args.extend(sources.into_iter().map(|s| s.into_os_string()));
+    args.push(target.into_os_string());
+
+    Cmd {
+        cmd: OsString::from(cmd),
+        args: Some(args),
+        vars: None,
+        short_cmd: Some(short_cmd),
+        cwd: cwd.clone(),
+        cwd_files: None,
+        tab_files: None,
+        tab_paths: None
+    }
+}
+
+// Moves within one filesystem are just renames, anything else has to be
+// copied first and needs the space for it
+pub fn crosses_device(sources: &[PathBuf], target: &Path) -> bool {
+    let target_dev = match target.metadata() {
+        Ok(meta) => meta.dev(),
+        Err(_) => return false
+    };
+
+    sources.iter()
+        .filter_map(|source| source.symlink_metadata().ok())
+        .any(|meta| meta.dev() != target_dev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAT: NameRules = NameRules { ignore_case: true, windows_chars: true };
+
+    // A fresh directory with these files in it
+    fn scratch(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("hunter-test-{}-transfer-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for file in files {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+
+        dir
+    }
+
+    fn fixed(target: &Path, names: &[&str]) -> (Vec<String>, NameFixes) {
+        let mut pairs = names.iter()
+            .map(|name| (PathBuf::from("/src").join(name), target.join(name)))
+            .collect::<Vec<_>>();
+
+        let fixes = fix_names(&mut pairs, FAT);
+        let names = pairs.into_iter()
+            .map(|(_, dest)| dest.strip_prefix(target).unwrap().to_string_lossy().to_string())
+            .collect();
+
+        (names, fixes)
+    }
+
+    #[test]
+    fn rules_by_filesystem() {
+        let fat = NameRules::for_fs("vfat").unwrap();
+        assert!(fat.ignore_case && fat.windows_chars);
+
+        let apple = NameRules::for_fs("apfs").unwrap();
+        assert!(apple.ignore_case && !apple.windows_chars);
+
+        assert!(NameRules::for_fs("ext4").is_none());
+    }
+
+    #[test]
+    fn invalid_names_get_fixed() {
+        let target = scratch("invalid", &[]);
+
+        let (names, fixes) = fixed(&target, &["a:b?.", "fine.txt", "..."]);
+
+        assert_eq!(names, vec!["a_b_", "fine.txt", "_"]);
+        assert_eq!(fixes.invalid, 2);
+        assert_eq!(fixes.collisions, 0);
+        std::fs::remove_dir_all(&target).ok();
+    }
+
+    #[test]
+    fn case_collisions_get_numbered() {
+        let target = scratch("case", &["readme.txt"]);
+
+        let (names, fixes) = fixed(&target, &["README.txt", "readme.txt", "Notes", "NOTES"]);
+
+        assert_eq!(names, vec!["README (2).txt", "readme.txt", "Notes", "NOTES (2)"]);
+        assert_eq!(fixes.collisions, 2);
+        std::fs::remove_dir_all(&target).ok();
+    }
+
+    #[test]
+    fn renamed_dirs_take_their_contents_along() {
+        let target = scratch("dirs", &["dir"]);
+
+        let (names, _) = fixed(&target, &["Dir", "Dir/file"]);
+
+        assert_eq!(names, vec!["Dir (2)", "Dir (2)/file"]);
+        std::fs::remove_dir_all(&target).ok();
+    }
+
+    #[test]
+    fn numbered_names() {
+        assert_eq!(numbered("name.txt", 2), "name (2).txt");
+        assert_eq!(numbered("name", 3), "name (3)");
+        assert_eq!(numbered(".bashrc", 2), ".bashrc (2)");
+    }
+}
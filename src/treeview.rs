@@ -0,0 +1,342 @@
+use termion::event::Key;
+
+use std::path::{Path, PathBuf};
+
+use crate::listview::{Listable, ListView};
+use crate::widget::{Widget, WidgetCore};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+
+
+// The directory hierarchy as one indented list, with directories that open
+// and close in place instead of getting their own column. Going left at the
+// top moves the root up a level. Whatever is selected when it's closed gets
+// selected in the columns.
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TreeNode {
+    pub path: PathBuf,
+    name: String,
+    depth: usize,
+    is_dir: bool,
+    expanded: bool,
+    color: Option<String>,
+}
+
+impl TreeNode {
+    fn render(&self, xsize: usize) -> String {
+        let marker = match (self.is_dir, self.expanded) {
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+            _ => "  "
+        };
+
+        let color = self.color
+            .clone()
+            .unwrap_or_else(term::normal_color);
+
+        let line = format!("{}{}{}{}{}",
+                           "  ".repeat(self.depth),
+                           marker,
+                           color,
+                           self.name,
+                           term::normal_color());
+
+        term::sized_string_u(&line, xsize)
+    }
+}
+
+// Directories first, like in the columns
+fn children(dir: &Path, depth: usize, show_hidden: bool) -> Vec<TreeNode> {
+    let mut nodes = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if !show_hidden && name.starts_with('.') { return None; }
+
+                    let path = entry.path();
+                    let meta = path.symlink_metadata().ok()?;
+
+                    Some(TreeNode {
+                        color: crate::files::color_for_path(&path, &meta),
+                        // Follows symlinks, so linked directories open too
+                        is_dir: path.is_dir(),
+                        path,
+                        name,
+                        depth,
+                        expanded: false
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    nodes.sort_by(|a, b| {
+        b.is_dir.cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    nodes
+}
+
+
+impl Listable for ListView<Vec<TreeNode>> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let xsize = self.get_coordinates().unwrap().xsize_u();
+
+        self.content
+            .iter()
+            .map(|node| node.render(xsize))
+            .collect()
+    }
+}
+
+
+#[derive(Debug)]
+pub struct TreeView {
+    core: WidgetCore,
+    list: ListView<Vec<TreeNode>>,
+    root: PathBuf,
+}
+
+impl TreeView {
+    pub fn new(core: &WidgetCore, root: &Path, selected: &Path) -> TreeView {
+        let show_hidden = core.config().show_hidden();
+        let nodes = children(root, 0, show_hidden);
+
+        let mut view = TreeView {
+            core: core.clone(),
+            list: ListView::new(core, nodes),
+            root: root.to_path_buf()
+        };
+
+        view.select_path(selected);
+        view
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.selected_node().map(|node| node.path.clone())
+    }
+
+    fn selected_node(&self) -> Option<&TreeNode> {
+        self.list.content.get(self.list.get_selection())
+    }
+
+    // Opens everything on the way down to path
+    fn select_path(&mut self, path: &Path) {
+        let mut index = 0;
+        let mut selection = 0;
+
+        while index < self.list.content.len() {
+            let found = self.list.content[index..]
+                .iter()
+                .position(|node| path.starts_with(&node.path));
+
+            index += match found {
+                Some(found) => found,
+                None => break
+            };
+            selection = index;
+
+            if self.list.content[index].path == path { break; }
+
+            self.expand(index);
+            index += 1;
+        }
+
+        self.list.set_selection(selection);
+    }
+
+    fn expand(&mut self, index: usize) {
+        let (path, depth) = match self.list.content.get_mut(index) {
+            Some(node) if node.is_dir && !node.expanded => {
+                node.expanded = true;
+                (node.path.clone(), node.depth)
+            }
+            _ => return
+        };
+
+        let show_hidden = self.core.config().show_hidden();
+        let nodes = children(&path, depth + 1, show_hidden);
+        self.list.content.splice(index+1..index+1, nodes);
+    }
+
+    fn collapse(&mut self, index: usize) {
+        let depth = match self.list.content.get_mut(index) {
+            Some(node) if node.expanded => {
+                node.expanded = false;
+                node.depth
+            }
+            _ => return
+        };
+
+        let end = self.list.content[index+1..]
+            .iter()
+            .position(|node| node.depth <= depth)
+            .map(|pos| index + 1 + pos)
+            .unwrap_or(self.list.content.len());
+
+        self.list.content.drain(index+1..end);
+    }
+
+    fn parent_index(&self, index: usize) -> Option<usize> {
+        let depth = self.list.content.get(index)?.depth;
+        if depth == 0 { return None; }
+
+        self.list.content[..index]
+            .iter()
+            .rposition(|node| node.depth == depth - 1)
+    }
+
+    fn toggle(&mut self) -> HResult<()> {
+        let index = self.list.get_selection();
+
+        match self.selected_node()?.expanded {
+            true => self.collapse(index),
+            false => self.expand(index)
+        }
+
+        self.core.clear()
+    }
+
+    fn go_left(&mut self) -> HResult<()> {
+        let index = self.list.get_selection();
+
+        if self.selected_node()?.expanded {
+            self.collapse(index);
+        } else if let Some(parent) = self.parent_index(index) {
+            self.collapse(parent);
+            self.list.set_selection(parent);
+        } else {
+            self.root_up()?;
+        }
+
+        self.core.clear()
+    }
+
+    fn go_right(&mut self) -> HResult<()> {
+        let index = self.list.get_selection();
+        let node = self.selected_node()?;
+        if !node.is_dir { return Ok(()); }
+
+        match node.expanded {
+            true => self.list.move_down(),
+            false => self.expand(index)
+        }
+
+        self.core.clear()
+    }
+
+    fn root_up(&mut self) -> HResult<()> {
+        let selected = self.selected_path().unwrap_or_else(|| self.root.clone());
+        let parent = self.root.parent()?.to_path_buf();
+        let show_hidden = self.core.config().show_hidden();
+
+        self.list.content = children(&parent, 0, show_hidden);
+        self.root = parent;
+        self.select_path(&selected);
+        Ok(())
+    }
+
+    fn collapse_all(&mut self) -> HResult<()> {
+        let selected = self.selected_path()?;
+        let show_hidden = self.core.config().show_hidden();
+
+        self.list.content = children(&self.root, 0, show_hidden);
+
+        let top = self.list.content
+            .iter()
+            .position(|node| selected.starts_with(&node.path))
+            .unwrap_or(0);
+        self.list.set_selection(top);
+
+        self.core.clear()
+    }
+}
+
+impl Widget for TreeView {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.list.set_coordinates(coordinates)
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        Ok(format!("Tree: {}", self.root.to_string_lossy()))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        let xsize = self.core.coordinates.xsize_u();
+        let path = self.selected_path()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(term::sized_string_u(&path, xsize))
+    }
+
+    fn refresh(&mut self) -> HResult<()> {
+        self.list.refresh()
+    }
+
+    fn get_drawlist(&self) -> HResult<String> {
+        self.list.get_drawlist()
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw().log();
+        Ok(())
+    }
+}
+
+
+use crate::keybind::{Acting, Bindings, TreeAction, Movement};
+
+impl Acting for TreeView {
+    type Action = TreeAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.tree
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        match movement {
+            Up(n) => { for _ in 0..*n { self.list.move_up(); } }
+            Down(n) => { for _ in 0..*n { self.list.move_down(); } }
+            PageUp => self.list.page_up(),
+            PageDown => self.list.page_down(),
+            Top => self.list.move_top(),
+            Bottom => self.list.move_bottom(),
+            Left => self.go_left()?,
+            Right => self.go_right()?
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use TreeAction::*;
+
+        match action {
+            Close => HError::popup_finnished()?,
+            Toggle => self.toggle()?,
+            CollapseAll => self.collapse_all()?
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use parking_lot::Mutex;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+
+// With graphics=ueberzug images are drawn by ueberzugpp in a window on top
+// of the terminal, for terminals that can't show images on their own. One
+// instance is started on first use and gets told where to put the image
+// each time it changes or moves.
+
+struct Ueberzug {
+    process: Child,
+    showing: Option<PathBuf>,
+}
+
+lazy_static! {
+    static ref UEBERZUG: Mutex<Option<Ueberzug>> = Mutex::new(None);
+}
+
+fn json_str(s: &str) -> String {
+    let escaped = s.chars().map(|c| match c {
+        '"' => String::from("\\\""),
+        '\\' => String::from("\\\\"),
+        c if c.is_control() => format!("\\u{:04x}", c as u32),
+        c => c.to_string()
+    }).collect::<String>();
+
+    format!("\"{}\"", escaped)
+}
+
+fn start() -> std::io::Result<Ueberzug> {
+    let process = Command::new("ueberzugpp")
+        .arg("layer")
+        .arg("--silent")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    Ok(Ueberzug {
+        process,
+        showing: None
+    })
+}
+
+fn send(ueberzug: &mut Ueberzug, cmd: &str) -> std::io::Result<()> {
+    let stdin = ueberzug.process.stdin.as_mut()
+        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+    writeln!(stdin, "{}", cmd)?;
+    stdin.flush()
+}
+
+// Position and size are in cells
+pub fn show(path: &Path, x: usize, y: usize, xsize: usize, ysize: usize) -> std::io::Result<()> {
+    let mut ueberzug = UEBERZUG.lock();
+
+    if ueberzug.is_none() {
+        *ueberzug = Some(start()?);
+    }
+
+    let cmd = format!("{{\"action\":\"add\",\"identifier\":\"hunter\",\
+                       \"x\":{},\"y\":{},\"max_width\":{},\"max_height\":{},\
+                       \"path\":{}}}",
+                      x, y, xsize, ysize,
+                      json_str(&path.to_string_lossy()));
+
+    let result = send(ueberzug.as_mut().unwrap(), &cmd);
+
+    match result {
+        Ok(()) => ueberzug.as_mut().unwrap().showing = Some(path.to_path_buf()),
+        // Died somehow, it's started again next time
+        Err(_) => *ueberzug = None
+    }
+
+    result
+}
+
+// Only if it's still this image, another one might have replaced it
+pub fn clear(path: &Path) {
+    let mut ueberzug = UEBERZUG.lock();
+
+    if let Some(ueberzug) = ueberzug.as_mut() {
+        if ueberzug.showing.as_ref().map(|p| p.as_path()) != Some(path) { return; }
+
+        send(ueberzug, "{\"action\":\"remove\",\"identifier\":\"hunter\"}").ok();
+        ueberzug.showing = None;
+    }
+}
+
+pub fn quit() {
+    if let Some(mut ueberzug) = UEBERZUG.lock().take() {
+        // Closing stdin makes it quit
+        drop(ueberzug.process.stdin.take());
+        ueberzug.process.wait().ok();
+    }
+}
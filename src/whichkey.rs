@@ -0,0 +1,135 @@
+use parking_lot::Mutex;
+
+use std::time::{Duration, Instant};
+
+use crate::widget::{WidgetCore, Events};
+use crate::fail::ErrorLog;
+use crate::term;
+
+
+// After a prefix key like g, the keys that can follow and what they do show
+// up in a little box in the bottom left corner. Only if the next key doesn't
+// come quickly, so it stays out of the way once they're known by heart. The
+// tab view draws it on top of everything else.
+
+struct Pending {
+    prefix: String,
+    keys: Vec<(String, String)>,
+    since: Instant,
+    delay: Duration,
+}
+
+impl Pending {
+    fn due(&self) -> bool {
+        self.since.elapsed() >= self.delay
+    }
+
+    fn width(&self) -> usize {
+        let key_width = self.keys
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or(0);
+
+        self.keys
+            .iter()
+            .map(|(_, action)| key_width + 2 + action.len())
+            .chain(std::iter::once(self.prefix.len() + 1))
+            .max()
+            .unwrap_or(0)
+            .min(term::xsize() as usize - 2)
+    }
+
+    // Top line, above the footer and one more line for the prefix itself
+    fn ypos(&self) -> u16 {
+        term::ysize().saturating_sub(self.keys.len() as u16 + 1).max(2)
+    }
+}
+
+lazy_static! {
+    static ref PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+}
+
+pub fn start(core: &WidgetCore, prefix: &str, keys: Vec<(String, String)>) {
+    let delay = match core.config().which_key_delay {
+        Some(delay) => Duration::from_millis(delay),
+        None => return
+    };
+
+    let since = Instant::now();
+    *PENDING.lock() = Some(Pending {
+        prefix: prefix.to_string(),
+        keys,
+        since,
+        delay
+    });
+
+    let sender = core.get_sender();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+
+        // Only if it's still the same prefix waiting for its key
+        let waiting = PENDING.lock()
+            .as_ref()
+            .map(|pending| pending.since == since)
+            .unwrap_or(false);
+        if waiting {
+            sender.send(Events::WidgetReady).ok();
+        }
+    });
+}
+
+pub fn done(core: &WidgetCore) {
+    let pending = match PENDING.lock().take() {
+        Some(pending) => pending,
+        None => return
+    };
+    if !pending.due() { return; }
+
+    // Whatever was below it is drawn again with the next frame
+    let width = pending.width() + 2;
+    let ypos = pending.ypos();
+    let clear = (0..=pending.keys.len() as u16)
+        .map(|line| format!("{}{}{:width$}",
+                            term::reset(),
+                            term::goto_xy(1, ypos + line),
+                            " ",
+                            width = width))
+        .collect::<String>();
+
+    core.write_to_screen(&clear).log();
+}
+
+pub fn drawlist() -> String {
+    let pending = PENDING.lock();
+    let pending = match pending.as_ref() {
+        Some(pending) if pending.due() => pending,
+        _ => return String::new()
+    };
+
+    let width = pending.width();
+    let ypos = pending.ypos();
+    let key_width = pending.keys
+        .iter()
+        .map(|(key, _)| key.len())
+        .max()
+        .unwrap_or(0);
+
+    let line = |line: u16, content: String| {
+        format!("{}{}{} {} ",
+                term::reset(),
+                term::goto_xy(1, ypos + line),
+                term::invert(),
+                term::sized_string_u(&format!("{:width$}", content, width = width),
+                                     width - 1))
+    };
+
+    let mut draw = line(0, format!("{}-", pending.prefix));
+
+    for (i, (key, action)) in pending.keys.iter().enumerate() {
+        let content = format!("{:key_width$}  {}", key, action, key_width = key_width);
+        draw += &line(i as u16 + 1, content);
+    }
+
+    draw + &term::reset()
+}
@@ -1,6 +1,8 @@
 use std::sync::Arc;
 use std::sync::mpsc::{Sender, Receiver, channel};
 use std::io::{Write, stdin};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use termion::event::{Event, Key, MouseEvent};
 use termion::input::TermRead;
@@ -10,7 +12,7 @@ use parking_lot::{Mutex, RwLock};
 
 use crate::coordinates::{Coordinates, Position, Size};
 use crate::fail::{HResult, HError, ErrorLog};
-use crate::minibuffer::MiniBuffer;
+use crate::minibuffer::{MiniBuffer, Validator};
 use crate::term;
 use crate::term::{Screen, ScreenExt};
 use crate::dirty::{Dirtyable, DirtyBit};
@@ -30,6 +32,8 @@ pub enum Events {
     RequestInput,
     Status(String),
     ConfigLoaded,
+    // From another instance through the socket
+    Reveal(PathBuf),
 }
 
 impl PartialEq for WidgetCore {
@@ -63,10 +67,14 @@ pub struct WidgetCore {
 
 impl WidgetCore {
     pub fn new() -> HResult<WidgetCore> {
-        let screen = Screen::new()?;
+        let mut screen = Screen::new(Config::alternate_screen(), Config::mouse())?;
+        if Config::probe_graphics() {
+            term::probe_graphics(&mut screen);
+        }
         let (xsize, ysize) = screen.size()?;
-        let coords = Coordinates::new_at(term::xsize(),
-                                         term::ysize() - 2,
+        // Laid out for the smallest usable size when started in a tiny terminal
+        let coords = Coordinates::new_at(term::xsize().max(term::MIN_XSIZE),
+                                         term::ysize().max(term::MIN_YSIZE) - 2,
                                          1,
                                          2);
         let (sender, receiver) = channel();
@@ -101,6 +109,8 @@ impl WidgetCore {
     }
 
     pub fn draw_status(&self) -> HResult<()> {
+        if term::too_small() { return Ok(()); }
+
         let xsize = term::xsize_u();
         let status = match self.status_bar_content.lock().as_ref() {
             Some(status) => status.to_string(),
@@ -155,6 +165,76 @@ impl WidgetCore {
         answer
     }
 
+    pub fn minibuffer_prefilled(&self, query: &str, input: &str) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_prefilled(query, input);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    pub fn minibuffer_prefilled_at(&self,
+                                   query: &str,
+                                   input: &str,
+                                   position: usize) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_prefilled_at(query, input, position);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    pub fn minibuffer_validated(&self,
+                                query: &str,
+                                input: &str,
+                                validator: Validator) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_validated(query, input, validator);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    // Tab completes from words, nothing else is taken
+    pub fn minibuffer_words(&self,
+                            query: &str,
+                            input: &str,
+                            words: Vec<String>) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_words(query, input, words);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    pub fn minibuffer_secret(&self, query: &str) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_secret(query);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    pub fn minibuffer_number(&self,
+                             query: &str,
+                             min: Option<isize>,
+                             max: Option<isize>) -> HResult<isize> {
+        let answer = self.minibuffer_validated(query,
+                                               "",
+                                               Validator::Integer(min, max))?;
+        Ok(answer.trim().parse::<isize>()?)
+    }
+
     pub fn minibuffer_continuous(&self, query: &str) -> HResult<String> {
         let answer = self.minibuffer
             .lock()
@@ -170,6 +250,12 @@ impl WidgetCore {
     }
 
     pub fn clear(&self) -> HResult<()> {
+        if crate::redraw::tracing() {
+            let (xpos, ypos) = self.coordinates.u16position();
+            let (xsize, ysize) = self.coordinates.u16size();
+            crate::redraw::clear(format!("{}x{} at {},{}", xsize, ysize, xpos, ypos));
+        }
+
         let clearlist = self.get_clearlist()?;
         self.write_to_screen(&clearlist)
     }
@@ -193,6 +279,7 @@ impl WidgetCore {
     }
 
     pub fn write_to_screen(&self, s: &str) -> HResult<()> {
+        crate::redraw::written(s.len());
         let mut screen = self.screen()?;
         screen.write_str(s)
     }
@@ -209,6 +296,13 @@ impl WidgetCore {
                        .clone();
         Ok(conf)
     }
+
+    // For the few things that can be changed while running
+    pub fn change_config(&self, change: impl FnOnce(&mut Config)) -> HResult<()> {
+        let mut config = self.config.write();
+        change(config.get_mut()?);
+        Ok(())
+    }
 }
 
 impl Dirtyable for WidgetCore {
@@ -239,6 +333,7 @@ pub trait Widget {
         if &core.coordinates != coordinates {
             core.coordinates = coordinates.clone();
             core.set_dirty();
+            crate::redraw::mark(std::any::type_name::<Self>(), "moved or resized");
         }
         Ok(())
     }
@@ -252,6 +347,7 @@ pub trait Widget {
     fn get_drawlist(&self) -> HResult<String>;
     fn after_draw(&self) -> HResult<()> { Ok(()) }
     fn config_loaded(&mut self) -> HResult<()> { Ok(()) }
+    fn reveal(&mut self, _path: &Path) -> HResult<()> { Ok(()) }
 
 
 
@@ -341,7 +437,8 @@ pub trait Widget {
         // Image will draw over widget otherwise
         if self.get_core()?.config().graphics == "kitty" {
             let ypos = self.get_coordinates()?.ypos();
-            print!("\x1b_Ga=d,d=y,y={}\x1b\\", ypos+1);
+            let delete = format!("\x1b_Ga=d,d=y,y={}\x1b\\", ypos+1);
+            print!("{}", crate::term::passthrough(&delete));
         }
         let result = self.run_widget();
         match result {
@@ -465,6 +562,12 @@ pub trait Widget {
     }
 
     fn draw(&mut self) -> HResult<()> {
+        if crate::term::too_small() {
+            self.get_core()?.write_to_screen(&crate::term::too_small_drawlist()).log();
+            self.get_core()?.screen()?.flush().ok();
+            return Ok(());
+        }
+
         let output =
             self.get_drawlist().unwrap_or("".to_string()) +
             &self.get_header_drawlist().unwrap_or("".to_string()) +
@@ -480,43 +583,90 @@ pub trait Widget {
 
         dispatch_events(tx_internal_event, rx_global_event, self.get_core()?.screen()?);
 
-        for event in rx_internal_event.iter() {
-            match event {
-                Events::InputEvent(event) => {
-                    match self.on_event(event) {
-                        Err(HError::Quit) => { HError::quit()?; },
-                        _ => {}
+        let mut last_draw: Option<Instant> = None;
+
+        while let Ok(event) = rx_internal_event.recv() {
+            self.handle_event(event)?;
+
+            // Holding down a key sends them faster than a slow directory can
+            // be drawn, so everything coming in until the next frame is due
+            // gets handled first and only the result is drawn
+            let max_fps = self.get_core()?.config().max_fps as u64;
+            if let (Some(last_draw), true) = (last_draw, max_fps > 0) {
+                let frame = Duration::from_millis(1000 / max_fps);
+                while let Some(wait) = frame.checked_sub(last_draw.elapsed()) {
+                    match rx_internal_event.recv_timeout(wait) {
+                        Ok(event) => self.handle_event(event)?,
+                        Err(_) => break
                     }
-                    self.get_core()?.get_sender().send(Events::RequestInput)?;
-                }
-                Events::Status(status) => {
-                    self.get_core()?.show_status(&status).log();
                 }
-                Events::TerminalResized => {
-                    self.get_core()?.screen()?.clear().log();
-                }
-                Events::ConfigLoaded => {
-                    self.get_core_mut()?
-                        .config
-                        .write()
-                        .pull_async()
-                        .ok();
-                    self.config_loaded().log();
-                }
-                _ => {}
             }
+
+            let started = Instant::now();
             self.resize().log();
             if self.get_core()?.screen()?.is_resized()? {
                 self.get_core()?.screen()?.take_size().ok();
             }
             self.refresh().ok();
+            let refreshed = Instant::now();
             self.draw().ok();
+            last_draw = Some(Instant::now());
+
+            crate::redraw::frame_done(refreshed - started, refreshed.elapsed());
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Events) -> HResult<()> {
+        if crate::redraw::tracing() {
+            let trigger = match &event {
+                Events::InputEvent(event) => format!("{:?}", event),
+                Events::WidgetReady => String::from("widget ready"),
+                Events::TerminalResized => String::from("terminal resized"),
+                Events::Status(_) => String::from("status"),
+                Events::ConfigLoaded => String::from("config loaded"),
+                Events::Reveal(_) => String::from("reveal"),
+                _ => String::from("other event")
+            };
+            crate::redraw::trigger(trigger);
+        }
+
+        match event {
+            Events::InputEvent(event) => {
+                match self.on_event(event) {
+                    Err(HError::Quit) => { HError::quit()?; },
+                    _ => {}
+                }
+                self.get_core()?.get_sender().send(Events::RequestInput)?;
+            }
+            Events::Status(status) => {
+                self.get_core()?.show_status(&status).log();
+            }
+            Events::TerminalResized => {
+                self.get_core()?.screen()?.clear().log();
+            }
+            Events::ConfigLoaded => {
+                self.get_core_mut()?
+                    .config
+                    .write()
+                    .pull_async()
+                    .ok();
+                self.config_loaded().log();
+            }
+            Events::Reveal(path) => {
+                self.reveal(&path).log();
+            }
+            _ => {}
         }
         Ok(())
     }
 
     fn resize(&mut self) -> HResult<()> {
         if let Ok(true) = self.get_core()?.screen()?.is_resized() {
+            // The old layout stays until it's big enough again, draw() shows
+            // a placeholder in the meantime
+            if crate::term::too_small() { return Ok(()); }
+
             let (xsize, ysize) = self.get_core()?.screen()?.get_size()?;
             let mut coords = self.get_core()?.coordinates.clone();
             coords.set_size_u(xsize, ysize-2);
@@ -534,7 +684,7 @@ fn dispatch_events(tx_internal: Sender<Events>,
 
     input_thread(tx_event.clone(), rx_input_req);
     event_thread(rx_global, tx_event.clone());
-    signal_thread(tx_event.clone());
+    signal_thread(tx_event.clone(), screen.clone());
 
     std::thread::spawn(move || {
         let mut tx_exclusive_event: Option<Sender<Events>> = None;
@@ -594,11 +744,43 @@ fn input_thread(tx: Sender<Events>, rx_input_request: Receiver<()>) {
     });
 }
 
-fn signal_thread(tx: Sender<Events>) {
+fn signal_thread(tx: Sender<Events>, mut screen: Screen) {
     std::thread::spawn(move || {
-        let rx = notify(&[Signal::WINCH]);
-        for _ in rx.iter() {
-            tx.send(Events::TerminalResized).unwrap();
+        let rx = notify(&[Signal::WINCH,
+                          Signal::TSTP,
+                          Signal::CONT,
+                          Signal::TERM,
+                          Signal::HUP,
+                          Signal::INT]);
+        for signal in rx.iter() {
+            let code = match signal {
+                Signal::WINCH => {
+                    tx.send(Events::TerminalResized).unwrap();
+                    continue;
+                }
+                // The shell gets its terminal back before we really stop
+                Signal::TSTP => {
+                    screen.suspend().log();
+                    nix::sys::signal::raise(nix::sys::signal::SIGSTOP).ok();
+                    continue;
+                }
+                // Everything has to be drawn again after "fg"
+                Signal::CONT => {
+                    screen.activate().log();
+                    tx.send(Events::TerminalResized).unwrap();
+                    continue;
+                }
+                Signal::HUP => 129,
+                Signal::INT => 130,
+                _ => 143
+            };
+
+            // Killed, but the terminal should still be usable afterwards
+            screen.suspend().log();
+            crate::mounts::release_all();
+            crate::ueberzug::quit();
+            crate::ipc::stop_listening();
+            std::process::exit(code);
         }
     });
 }
@@ -0,0 +1,103 @@
+use parking_lot::Mutex;
+
+use std::path::PathBuf;
+
+use crate::fail::{HResult, HError};
+
+
+// Named sets of tabs, like "work" or "photos". They're kept in their own
+// file in the config dir:
+//
+// [work:1]
+// /home/user/src/hunter
+// /home/user/notes
+//
+// The number after the name is the tab that was active.
+
+lazy_static! {
+    // The workspace that was switched to last, saved again when leaving it
+    static ref CURRENT: Mutex<Option<String>> = Mutex::new(None);
+}
+
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    pub name: String,
+    pub active: usize,
+    pub tabs: Vec<PathBuf>,
+}
+
+pub fn current() -> Option<String> {
+    CURRENT.lock().clone()
+}
+
+pub fn set_current(name: &str) {
+    *CURRENT.lock() = Some(name.to_string());
+}
+
+pub fn load() -> HResult<Vec<Workspace>> {
+    let path = crate::paths::workspaces_path()?;
+    if !path.exists() { return Ok(vec![]); }
+
+    let content = std::fs::read_to_string(path)?;
+    let mut workspaces: Vec<Workspace> = vec![];
+
+    for line in content.lines().map(|line| line.trim_end()) {
+        if line.starts_with('[') && line.ends_with(']') {
+            let header = &line[1..line.len()-1];
+            let (name, active) = match header.rfind(':') {
+                Some(pos) => (&header[..pos],
+                              header[pos+1..].parse().unwrap_or(0)),
+                None => (header, 0)
+            };
+
+            workspaces.push(Workspace {
+                name: name.to_string(),
+                active,
+                tabs: vec![]
+            });
+        } else if !line.is_empty() {
+            if let Some(workspace) = workspaces.last_mut() {
+                workspace.tabs.push(PathBuf::from(line));
+            }
+        }
+    }
+
+    Ok(workspaces)
+}
+
+fn write(workspaces: &[Workspace]) -> HResult<()> {
+    let path = crate::paths::workspaces_path()?;
+    let content = workspaces.iter().map(|workspace| {
+        let tabs = workspace.tabs
+            .iter()
+            .map(|tab| format!("{}\n", tab.to_string_lossy()))
+            .collect::<String>();
+        format!("[{}:{}]\n{}\n", workspace.name, workspace.active, tabs)
+    }).collect::<String>();
+
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+// Replaces one with the same name
+pub fn save(workspace: Workspace) -> HResult<()> {
+    if workspace.name.is_empty() || workspace.name.contains(|c| c == ']' || c == '\n') {
+        return Err(HError::Error(format!("Invalid workspace name: {}", workspace.name)));
+    }
+
+    let mut workspaces = load()?;
+
+    match workspaces.iter_mut().find(|w| w.name == workspace.name) {
+        Some(old) => *old = workspace,
+        None => workspaces.push(workspace)
+    }
+
+    write(&workspaces)
+}
+
+pub fn get(name: &str) -> HResult<Workspace> {
+    load()?
+        .into_iter()
+        .find(|workspace| workspace.name == name)
+        .ok_or_else(|| HError::Error(format!("No workspace named {}", name)))
+}